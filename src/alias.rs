@@ -0,0 +1,176 @@
+//! Messages structurally identical across packages.
+//!
+//! Packages often copy messages of other packages, which generate the same structs twice
+//! and require conversions between them.
+//! `-W identical-definitions` reports such messages, and `alias` of the configuration file
+//! generates a message as a re-export of the identical one instead of another struct.
+//!
+//! Messages are identical if their fields, constants and annotations changing the generated code are the same
+//! except comments. Fields of messages referring to identical messages of different packages are identical too.
+
+use crate::parser::{ArrayInfo, Expr, TypeName, ValueType};
+use std::collections::BTreeMap;
+
+/// `(pkg, type)` of a message.
+pub type Key = (String, String);
+
+/// Parsed messages compared.
+pub type Messages = BTreeMap<Key, Vec<Expr>>;
+
+/// Classes of structurally identical messages, in which identical messages have the same number.
+///
+/// All messages are in one class at first, and classes are refined by the fields
+/// until references to messages of different classes distinguish all non-identical messages.
+pub fn classes(messages: &Messages) -> BTreeMap<Key, usize> {
+    let mut classes: BTreeMap<Key, usize> = messages.keys().map(|key| (key.clone(), 0)).collect();
+    let mut num_classes = 1;
+    loop {
+        let mut ids: BTreeMap<String, usize> = BTreeMap::new();
+        let refined = messages
+            .iter()
+            .map(|(key, exprs)| {
+                let next_id = ids.len();
+                let id = *ids
+                    .entry(signature(&key.0, exprs, &classes))
+                    .or_insert(next_id);
+                (key.clone(), id)
+            })
+            .collect();
+
+        // each refinement splits classes, so the same number of classes means no split
+        let stable = ids.len() == num_classes;
+        num_classes = ids.len();
+        classes = refined;
+        if stable {
+            return classes;
+        }
+    }
+}
+
+/// Groups of identical messages of two or more packages.
+pub fn identical_groups(classes: &BTreeMap<Key, usize>) -> Vec<Vec<&Key>> {
+    let mut groups: BTreeMap<usize, Vec<&Key>> = BTreeMap::new();
+    for (key, class) in classes.iter() {
+        groups.entry(*class).or_default().push(key);
+    }
+
+    groups
+        .into_values()
+        .filter(|keys| keys.iter().any(|(pkg, _)| *pkg != keys[0].0))
+        .collect()
+}
+
+/// Text of `exprs` of `pkg` except comments,
+/// where messages are referred by their classes if they are in `classes`.
+fn signature(pkg: &str, exprs: &[Expr], classes: &BTreeMap<Key, usize>) -> String {
+    let mut result = String::new();
+    for expr in exprs.iter() {
+        let Expr::Variable {
+            type_name,
+            var_name,
+            value,
+            ros_name,
+            key,
+            range,
+            default,
+            ..
+        } = expr
+        else {
+            continue;
+        };
+
+        result.push_str(&type_signature(pkg, type_name, classes));
+        result.push(' ');
+        result.push_str(var_name);
+        match value {
+            Some(ValueType::Const(v)) => result.push_str(&format!("={v}")),
+            Some(ValueType::Default(v)) => result.push_str(&format!(" {v}")),
+            None => (),
+        }
+        if let Some(ros_name) = ros_name {
+            result.push_str(&format!(" @rust_name({ros_name})"));
+        }
+        if *key {
+            result.push_str(" @key");
+        }
+        if let Some((min, max)) = range {
+            result.push_str(&format!(" @range({min}, {max})"));
+        }
+        if let Some(default) = default {
+            result.push_str(&format!(" @default({default})"));
+        }
+        result.push('\n');
+    }
+    result
+}
+
+/// Text of `type_name` of a field of `pkg`, where messages are referred by their classes if they are in `classes`.
+pub fn type_signature(pkg: &str, type_name: &TypeName, classes: &BTreeMap<Key, usize>) -> String {
+    let reference = |scope: &str, type_name: &str| {
+        let key = (scope.to_string(), type_name.to_string());
+        match classes.get(&key) {
+            Some(class) => format!("#{class}"),
+            None => format!("{scope}/{type_name}"),
+        }
+    };
+
+    let (base, array_info) = match type_name {
+        TypeName::Type {
+            type_name,
+            array_info,
+        } => match crate::generator::gen_primitives(type_name) {
+            Some(_) => (type_name.clone(), array_info),
+            None => (reference(pkg, type_name), array_info),
+        },
+        TypeName::ScopedType {
+            scope,
+            type_name,
+            array_info,
+        } => (reference(scope, type_name), array_info),
+        TypeName::LimitedString { size, array_info } => (format!("string<={size}"), array_info),
+        TypeName::String(array_info) => ("string".to_string(), array_info),
+    };
+
+    match array_info {
+        ArrayInfo::NotArray => base,
+        ArrayInfo::Dynamic => format!("{base}[]"),
+        ArrayInfo::Static(n) => format!("{base}[{n}]"),
+        ArrayInfo::Limited(n) => format!("{base}[<={n}]"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classes, identical_groups, Key};
+    use crate::parser;
+    use nom::Finish;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_identical_groups() {
+        let mut messages = BTreeMap::new();
+        for (pkg, type_name, def) in [
+            ("a", "Point", "float64 x # x\nfloat64 y\n"),
+            ("b", "Point", "float64 x\nfloat64 y # y\n"),
+            ("c", "Point", "float64 x\nfloat32 y\n"),
+            ("a", "Path", "Point[] points\n"),
+            ("b", "Path", "Point[] points\n"),
+            ("c", "Path", "Point[] points\n"),
+            ("a", "Vector", "float64 x\nfloat64 y\n"),
+        ] {
+            let (_, exprs) = parser::parse_msg(def).finish().unwrap();
+            messages.insert((pkg.to_string(), type_name.to_string()), exprs);
+        }
+
+        let classes = classes(&messages);
+        let key = |pkg: &str, type_name: &str| (pkg.to_string(), type_name.to_string());
+        let groups: Vec<Vec<&Key>> = identical_groups(&classes);
+        assert_eq!(
+            groups,
+            vec![
+                vec![&key("a", "Path"), &key("b", "Path")],
+                vec![&key("a", "Point"), &key("a", "Vector"), &key("b", "Point")],
+            ]
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Read .msg and .srv files from archives without unpacking.
+//!
+//! `.tar`, `.tar.gz`, `.tgz` and `.zip` are supported.
+//! Released tarballs usually have a top-level directory, such as `common_interfaces-4.2.3/`,
+//! so the parents of `msg`, `srv` and `action` directories are regarded as packages wherever they are,
+//! and a version of a package directory like `std_msgs-4.2.3/` is removed.
+
+use flate2::read::GzDecoder;
+use std::{
+    error::Error,
+    fs::File,
+    io::{prelude::*, BufReader},
+    path::{Component, Path, PathBuf},
+};
+
+/// Return true if `path` is a supported archive.
+pub fn is_archive(path: &Path) -> bool {
+    archive_stem(path).is_some()
+}
+
+/// File name of `path` without the archive extension,
+/// e.g. `common_interfaces` of `common_interfaces.tar.gz`.
+pub fn archive_stem(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?;
+    [".tar.gz", ".tgz", ".tar", ".zip"]
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext))
+}
+
+/// Read .msg and .srv files in the archive.
+/// Returned paths are relative to the input directory in the archive.
+pub fn read_archive(path: &Path) -> Result<Vec<(PathBuf, String)>, Box<dyn Error>> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+
+    let mut files = if name.ends_with(".zip") {
+        read_zip(path)?
+    } else if name.ends_with(".tar") {
+        read_tar(File::open(path)?)?
+    } else {
+        read_tar(GzDecoder::new(BufReader::new(File::open(path)?)))?
+    };
+
+    strip_top_dirs(&mut files);
+
+    Ok(files)
+}
+
+fn read_tar<R: Read>(reader: R) -> Result<Vec<(PathBuf, String)>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?.to_path_buf();
+        if !is_definition(&path) {
+            continue;
+        }
+
+        let contents = read_definition(&mut entry)?;
+        files.push((path, contents));
+    }
+
+    Ok(files)
+}
+
+fn read_zip(path: &Path) -> Result<Vec<(PathBuf, String)>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path)?))?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        if !entry.is_file() {
+            continue;
+        }
+
+        let Some(path) = entry.enclosed_name() else {
+            continue;
+        };
+        if !is_definition(&path) {
+            continue;
+        }
+
+        let contents = read_definition(&mut entry)?;
+        files.push((path, contents));
+    }
+
+    Ok(files)
+}
+
+/// Read a definition. Invalid UTF-8, which may be found in comments, is replaced by `U+FFFD`.
+pub fn read_definition<R: Read>(mut reader: R) -> std::io::Result<String> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Whether `path` is a .msg, .srv or .action file, or an .idl file.
+pub fn is_definition(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("msg" | "srv" | "action" | "idl")
+    )
+}
+
+/// Make paths relative to the input directory, whose children are packages.
+/// The package of a file is the parent of its `msg`, `srv` or `action` directory,
+/// so directories above it, such as `common_interfaces-4.2.3/`, are removed,
+/// and a package directory with a version, such as `std_msgs-4.2.3/`, is named after the package.
+fn strip_top_dirs(files: &mut [(PathBuf, String)]) {
+    for (path, _) in files.iter_mut() {
+        let components: Vec<_> = path.components().collect();
+        let Some(n) = (1..components.len()).find(|&n| {
+            matches!(
+                components[n].as_os_str().to_str(),
+                Some("msg" | "srv" | "action")
+            )
+        }) else {
+            continue;
+        };
+        let Component::Normal(package) = components[n - 1] else {
+            continue;
+        };
+        let Some(package) = package.to_str() else {
+            continue;
+        };
+
+        let mut stripped = PathBuf::from(package_name(package));
+        stripped.extend(&components[n..]);
+        *path = stripped;
+    }
+}
+
+/// Name of a package directory, e.g. `std_msgs` of `std_msgs-4.2.3`.
+fn package_name(dir: &str) -> &str {
+    let is_valid = |name: &str| crate::validate_name("package", name, Path::new(dir)).is_ok();
+    match dir.rsplit_once('-') {
+        Some((name, version))
+            if !is_valid(dir)
+                && is_valid(name)
+                && version.starts_with(|c: char| c.is_ascii_digit()) =>
+        {
+            name
+        }
+        _ => dir,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_top_dirs;
+    use std::path::PathBuf;
+
+    fn strip(paths: &[&str]) -> Vec<PathBuf> {
+        let mut files: Vec<_> = paths
+            .iter()
+            .map(|path| (PathBuf::from(path), String::new()))
+            .collect();
+        strip_top_dirs(&mut files);
+        files.into_iter().map(|(path, _)| path).collect()
+    }
+
+    #[test]
+    fn test_strip_top_dirs() {
+        // a repository
+        assert_eq!(
+            strip(&[
+                "common_interfaces-4.2.3/std_msgs/msg/Header.msg",
+                "common_interfaces-4.2.3/std_srvs/srv/Empty.srv",
+            ]),
+            [
+                PathBuf::from("std_msgs/msg/Header.msg"),
+                PathBuf::from("std_srvs/srv/Empty.srv"),
+            ]
+        );
+
+        // a package
+        assert_eq!(
+            strip(&["pkg-1.0/msg/X.msg", "pkg-1.0/action/Y.action"]),
+            [
+                PathBuf::from("pkg/msg/X.msg"),
+                PathBuf::from("pkg/action/Y.action"),
+            ]
+        );
+
+        // packages at the top
+        assert_eq!(
+            strip(&["pkg/msg/X.msg", "./other/msg/detail/Y.idl"]),
+            [
+                PathBuf::from("pkg/msg/X.msg"),
+                PathBuf::from("other/msg/detail/Y.idl"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! Interactive browser of interface packages.
+//!
+//! `browse` lists types of the inputs, and shows the expanded definition of the selected type
+//! and the Rust code generated from it side by side.
+//! Pages are rendered when their types are selected first, so that large trees open quickly.
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    widgets::{Block, List, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+use std::{collections::BTreeMap, error::Error};
+
+const HELP: &str = " ↑↓ select  PgUp/PgDn scroll  q quit ";
+
+/// Lines scrolled by PgUp and PgDn.
+const PAGE: u16 = 20;
+
+/// Expanded definition and generated code of a type.
+pub struct Page {
+    pub definition: String,
+    pub generated: String,
+}
+
+/// Browse `types`, which are `pkg/(msg|srv)/Type`, until `q` is pressed.
+/// `render(i)` renders the page of `types[i]`.
+pub fn run(types: &[String], render: &impl Fn(usize) -> Page) -> Result<(), Box<dyn Error>> {
+    if types.is_empty() {
+        return Err("no .msg or .srv is found".into());
+    }
+
+    let mut terminal = ratatui::init();
+    let result = Browser::new().event_loop(&mut terminal, types, render);
+    ratatui::restore();
+    result
+}
+
+struct Browser {
+    list: ListState,
+    scroll: u16,
+    pages: BTreeMap<usize, Page>,
+}
+
+impl Browser {
+    fn new() -> Self {
+        Self {
+            list: ListState::default().with_selected(Some(0)),
+            scroll: 0,
+            pages: BTreeMap::new(),
+        }
+    }
+
+    fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        types: &[String],
+        render: &impl Fn(usize) -> Page,
+    ) -> Result<(), Box<dyn Error>> {
+        loop {
+            let selected = self.selected();
+            self.pages
+                .entry(selected)
+                .or_insert_with(|| render(selected));
+            terminal.draw(|frame| self.draw(frame, types))?;
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key.code, types.len()) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn selected(&self) -> usize {
+        self.list.selected().unwrap_or(0)
+    }
+
+    /// Move the selection or scroll the page. Return `false` to quit.
+    fn handle_key(&mut self, code: KeyCode, len: usize) -> bool {
+        let selected = self.selected();
+        let next = match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => (selected + 1).min(len - 1),
+            KeyCode::Home | KeyCode::Char('g') => 0,
+            KeyCode::End | KeyCode::Char('G') => len - 1,
+            KeyCode::PageUp => {
+                self.scroll = self.scroll.saturating_sub(PAGE);
+                return true;
+            }
+            KeyCode::PageDown | KeyCode::Char(' ') => {
+                self.scroll = self.scroll.saturating_add(PAGE);
+                return true;
+            }
+            _ => return true,
+        };
+
+        if next != selected {
+            self.list.select(Some(next));
+            self.scroll = 0;
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame, types: &[String]) {
+        let [left, definition, generated] = Layout::horizontal([
+            Constraint::Percentage(24),
+            Constraint::Percentage(38),
+            Constraint::Percentage(38),
+        ])
+        .areas(frame.area());
+
+        let list = List::new(types.iter().map(String::as_str))
+            .block(
+                Block::bordered()
+                    .title(format!(" Types ({}) ", types.len()))
+                    .title_bottom(HELP),
+            )
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, left, &mut self.list);
+
+        let page = &self.pages[&self.selected()];
+        for (text, title, area) in [
+            (&page.definition, " Definition ", definition),
+            (&page.generated, " Generated ", generated),
+        ] {
+            let paragraph = Paragraph::new(text.as_str())
+                .block(Block::bordered().title(title))
+                .scroll((self.scroll, 0));
+            frame.render_widget(paragraph, area);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Browser;
+    use ratatui::crossterm::event::KeyCode;
+
+    #[test]
+    fn test_handle_key() {
+        let mut browser = Browser::new();
+
+        assert!(browser.handle_key(KeyCode::Up, 3));
+        assert_eq!(browser.selected(), 0);
+
+        assert!(browser.handle_key(KeyCode::PageDown, 3));
+        assert_eq!(browser.scroll, super::PAGE);
+
+        // selecting another type scrolls back to the top
+        assert!(browser.handle_key(KeyCode::Char('j'), 3));
+        assert_eq!((browser.selected(), browser.scroll), (1, 0));
+
+        assert!(browser.handle_key(KeyCode::End, 3));
+        assert!(browser.handle_key(KeyCode::Down, 3));
+        assert_eq!(browser.selected(), 2);
+
+        assert!(!browser.handle_key(KeyCode::Char('q'), 3));
+    }
+}
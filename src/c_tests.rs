@@ -0,0 +1,326 @@
+//! Tests comparing generated structs with the C structs of rosidl.
+//!
+//! `--emit-c-tests` generates `{package}/c_tests/c_tests.c` and `{package}/tests/c_tests.rs`
+//! enabled by the feature `c-tests` of the package crate.
+//! For each message, including requests and responses, the test checks that the size, the alignment
+//! and the offsets of fields are the same in Rust and C, and passes a message initialized by Rust to C,
+//! which compares it with a message initialized by C using `__are_equal()` and `memcmp()` of their serialized forms.
+//!
+//! ```text
+//! $ cd generated/my_module
+//! $ cargo test --features c-tests
+//! ```
+
+use crate::{
+    generator::FallibleApi,
+    parser::{Expr, ValueType},
+};
+use std::path::{Path, PathBuf};
+
+/// A message compared with its C struct.
+pub struct CTestType {
+    /// Path to the header, such as `my_module/msg/example.h`.
+    header: String,
+
+    /// C struct, such as `my_module__msg__Example`.
+    c_name: String,
+
+    /// Arguments of `ROSIDL_GET_MSG_TYPE_SUPPORT`, such as `my_module, msg, Example`.
+    type_support: String,
+
+    /// Path of the Rust struct relative to the package, such as `msg::Example`.
+    rust_path: String,
+
+    /// Names of fields in C and Rust.
+    fields: Vec<(String, String)>,
+}
+
+/// Messages of `pkg/ext/type_name`, whose `parts` are the message, the request and the response,
+/// or the goal, the result and the feedback.
+/// Messages wrapping the parts of actions are not compared, because they are defined by rosidl.
+/// `rust_type_name` is the name of the generated struct, which may be renamed.
+pub fn c_test_types(
+    pkg: &str,
+    ext: &str,
+    type_name: &str,
+    rust_type_name: &str,
+    parts: &[Vec<Expr>],
+) -> Vec<CTestType> {
+    let header = format!("{pkg}/{ext}/{}.h", rosidl_snake_case(type_name));
+    let new_type = |suffix: &str, rust_suffix: &str, exprs: &[Expr]| CTestType {
+        header: header.clone(),
+        c_name: format!("{pkg}__{ext}__{type_name}{suffix}"),
+        type_support: format!("{pkg}, {ext}, {type_name}{suffix}"),
+        rust_path: format!("{ext}::{rust_type_name}{rust_suffix}"),
+        fields: fields(exprs),
+    };
+
+    let suffixes: &[(&str, &str)] = match ext {
+        "msg" => &[("", "")],
+        "srv" => &[("_Request", "Request"), ("_Response", "Response")],
+        _ => &[
+            ("_Goal", "Goal"),
+            ("_Result", "Result"),
+            ("_Feedback", "Feedback"),
+        ],
+    };
+    suffixes
+        .iter()
+        .zip(parts)
+        .map(|((suffix, rust_suffix), exprs)| new_type(suffix, rust_suffix, exprs))
+        .collect()
+}
+
+/// Names of fields in C and Rust.
+/// Empty messages have no fields to compare, because the member of the C struct is private in Rust.
+fn fields(exprs: &[Expr]) -> Vec<(String, String)> {
+    exprs
+        .iter()
+        .filter_map(|expr| match expr {
+            Expr::Variable {
+                var_name,
+                value: None | Some(ValueType::Default(_)),
+                ros_name,
+                ..
+            } => Some((
+                ros_name.as_deref().unwrap_or(var_name).to_string(),
+                crate::mangle(var_name).to_string(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// File name of the header generated by rosidl, such as `point_cloud2` of `PointCloud2`.
+fn rosidl_snake_case(type_name: &str) -> String {
+    let chars: Vec<char> = type_name.chars().collect();
+    let mut result = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_ascii_lowercase());
+            // `aB`, `1B` and `ABc`
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_is_lower)
+            {
+                result.push('_');
+            }
+        }
+        result.push(c.to_ascii_lowercase());
+    }
+    result
+}
+
+/// Generate `{pkg_dir}/c_tests/c_tests.c` and `{pkg_dir}/tests/c_tests.rs` of `types`.
+/// `krate` is the name of the package crate.
+pub fn gen_c_tests(
+    pkg_dir: &Path,
+    krate: &str,
+    types: &[CTestType],
+    fallible_api: FallibleApi,
+) -> Vec<(PathBuf, String)> {
+    let mut headers: Vec<&str> = types.iter().map(|t| t.header.as_str()).collect();
+    headers.dedup();
+
+    let mut c = String::from(
+        "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+// Functions comparing Rust structs with C structs, which are called by tests/c_tests.rs.
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <string.h>
+
+#include <rcutils/allocator.h>
+#include <rmw/rmw.h>
+#include <rmw/serialized_message.h>
+#include <rosidl_runtime_c/message_type_support_struct.h>
+",
+    );
+    for header in headers {
+        c.push_str(&format!("#include <{header}>\n"));
+    }
+    c.push_str(C_SERIALIZED_EQUAL);
+
+    let mut rs = String::from(
+        "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+//! Tests comparing generated structs with the C structs of rosidl.
+#![cfg(feature = \"c-tests\")]
+",
+    );
+
+    let to_option = fallible_api.to_option();
+    for t in types.iter() {
+        let c_name = &t.c_name;
+        let n = t.fields.len();
+
+        let offsets: String = t
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, (c_field, _))| format!("\n    offsets[{i}] = offsetof({c_name}, {c_field});"))
+            .collect();
+        c.push_str(&format!(
+            "
+void {c_name}__c_tests_layout(size_t *size, size_t *align, size_t *offsets) {{
+    *size = sizeof({c_name});
+    *align = _Alignof({c_name});{offsets}
+}}
+
+bool {c_name}__c_tests_are_equal(const {c_name} *rust_msg) {{
+    {c_name} c_msg;
+    if (!{c_name}__init(&c_msg)) {{
+        return false;
+    }}
+    bool result = {c_name}__are_equal(&c_msg, rust_msg);
+    {c_name}__fini(&c_msg);
+    return result;
+}}
+
+bool {c_name}__c_tests_serialized_equal(const {c_name} *rust_msg) {{
+    {c_name} c_msg;
+    if (!{c_name}__init(&c_msg)) {{
+        return false;
+    }}
+    bool result = serialized_equal(&c_msg, rust_msg, ROSIDL_GET_MSG_TYPE_SUPPORT({}));
+    {c_name}__fini(&c_msg);
+    return result;
+}}
+",
+            t.type_support
+        ));
+
+        let ty = format!("{krate}::{}", t.rust_path);
+        let rust_offsets: Vec<String> = t
+            .fields
+            .iter()
+            .map(|(_, field)| format!("core::mem::offset_of!({ty}, {field})"))
+            .collect();
+        // `[]` compared with `[usize; 0]` cannot be inferred
+        let assert_offsets = if rust_offsets.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\n    assert_eq!([{}], offsets, \"offsets of fields\");",
+                rust_offsets.join(", ")
+            )
+        };
+        let test_name = t.rust_path.replace("::", "_").to_lowercase();
+        rs.push_str(&format!(
+            "
+#[test]
+fn {test_name}() {{
+    extern \"C\" {{
+        fn {c_name}__c_tests_layout(size: *mut usize, align: *mut usize, offsets: *mut usize);
+        fn {c_name}__c_tests_are_equal(rust_msg: *const {ty}) -> bool;
+        fn {c_name}__c_tests_serialized_equal(rust_msg: *const {ty}) -> bool;
+    }}
+
+    let (mut size, mut align, mut offsets) = (0, 0, [0usize; {n}]);
+    unsafe {{ {c_name}__c_tests_layout(&mut size, &mut align, offsets.as_mut_ptr()) }};
+    assert_eq!(core::mem::size_of::<{ty}>(), size, \"size\");
+    assert_eq!(core::mem::align_of::<{ty}>(), align, \"alignment\");{assert_offsets}
+
+    let msg = {ty}::new(){to_option}.unwrap();
+    assert!(unsafe {{ {c_name}__c_tests_are_equal(&msg) }}, \"__are_equal\");
+    assert!(unsafe {{ {c_name}__c_tests_serialized_equal(&msg) }}, \"serialized forms\");
+}}
+"
+        ));
+    }
+
+    vec![
+        (pkg_dir.join("c_tests").join("c_tests.c"), c),
+        (pkg_dir.join("tests").join("c_tests.rs"), rs),
+    ]
+}
+
+/// Build script compiling `c_tests/c_tests.c` if the feature `c-tests` is enabled.
+pub fn gen_build_c_tests(pkg: &str) -> String {
+    format!(
+        "
+    #[cfg(feature = \"c-tests\")]
+    {{
+        let mut build = cc::Build::new();
+        if let Some(paths) = std::env::var_os(\"AMENT_PREFIX_PATH\") {{
+            for path in std::env::split_paths(&paths) {{
+                // headers are installed in include/{{package}} since Humble
+                let include = path.join(\"include\");
+                if let Ok(dirs) = std::fs::read_dir(&include) {{
+                    for dir in dirs.flatten() {{
+                        build.include(dir.path());
+                    }}
+                }}
+                build.include(include);
+            }}
+        }}
+        build.file(\"c_tests/c_tests.c\").compile(\"{pkg}_c_tests\");
+
+        println!(\"cargo:rustc-link-lib=rmw_implementation\");
+        println!(\"cargo:rustc-link-lib=rmw\");
+        println!(\"cargo:rustc-link-lib=rcutils\");
+    }}
+"
+    )
+}
+
+const C_SERIALIZED_EQUAL: &str = "
+static bool serialized_equal(
+    const void *a, const void *b, const rosidl_message_type_support_t *type_support) {
+    rcutils_allocator_t allocator = rcutils_get_default_allocator();
+    rmw_serialized_message_t serialized_a = rmw_get_zero_initialized_serialized_message();
+    rmw_serialized_message_t serialized_b = rmw_get_zero_initialized_serialized_message();
+    bool result = rmw_serialized_message_init(&serialized_a, 0, &allocator) == RMW_RET_OK &&
+        rmw_serialized_message_init(&serialized_b, 0, &allocator) == RMW_RET_OK &&
+        rmw_serialize(a, type_support, &serialized_a) == RMW_RET_OK &&
+        rmw_serialize(b, type_support, &serialized_b) == RMW_RET_OK &&
+        serialized_a.buffer_length == serialized_b.buffer_length &&
+        memcmp(serialized_a.buffer, serialized_b.buffer, serialized_a.buffer_length) == 0;
+    rmw_serialized_message_fini(&serialized_a);
+    rmw_serialized_message_fini(&serialized_b);
+    return result;
+}
+";
+
+#[cfg(test)]
+mod tests {
+    use super::{c_test_types, gen_c_tests, rosidl_snake_case};
+    use crate::{generator::FallibleApi, parser};
+    use nom::Finish;
+    use std::path::Path;
+
+    #[test]
+    fn test_rosidl_snake_case() {
+        assert_eq!(rosidl_snake_case("Example"), "example");
+        assert_eq!(rosidl_snake_case("PointCloud2"), "point_cloud2");
+        assert_eq!(rosidl_snake_case("IMUData"), "imu_data");
+        assert_eq!(
+            rosidl_snake_case("Float32MultiArray"),
+            "float32_multi_array"
+        );
+    }
+
+    #[test]
+    fn test_gen_c_tests() {
+        let (_, (req, resp)) =
+            parser::parse_srv("int32 type # @rust_name kind\n---\nstring name\n")
+                .finish()
+                .unwrap();
+        let types = c_test_types("my_module", "srv", "GetMap", "GetMap", &[req, resp]);
+        let files = gen_c_tests(
+            Path::new("my_module"),
+            "my_module",
+            &types,
+            FallibleApi::Option,
+        );
+        let (c, rs) = (&files[0].1, &files[1].1);
+
+        assert!(c.contains("#include <my_module/srv/get_map.h>\n"));
+        assert!(c.contains("    offsets[0] = offsetof(my_module__srv__GetMap_Request, type);"));
+        assert!(c.contains("ROSIDL_GET_MSG_TYPE_SUPPORT(my_module, srv, GetMap_Response)"));
+        assert!(rs.contains(
+            "assert_eq!([core::mem::offset_of!(my_module::srv::GetMapRequest, kind)], offsets, \"offsets of fields\");"
+        ));
+        assert!(rs.contains("let msg = my_module::srv::GetMapResponse::new().unwrap();"));
+    }
+}
@@ -0,0 +1,475 @@
+//! Wire helpers of CDR.
+//!
+//! `--cdr` generates `{package}/cdr.rs` having `Reader` and `Writer`,
+//! which read and write primitives in the explicit byte order instead of the native one.
+//! `Reader::from_payload()` dispatches by the encapsulation header of the serialized payload,
+//! so payloads of any DDS vendor are parsed by the same code regardless of their byte order.
+//!
+//! Payloads can come from the network, so `Reader` checks bounds before every read and returns errors
+//! instead of panicking, and lengths of sequences are checked against the remaining bytes before allocation.
+//!
+//! XCDR2 has DHEADERs, which are sizes of the following data, before sequences and arrays of non-primitive elements,
+//! and before every struct if the encapsulation is D_CDR2.
+//! `read_struct()`, `read_collection()` and their counterparts of `Writer` handle them,
+//! so that `read_cdr()` and `write_cdr()` of messages are the same code for XCDR1 and XCDR2.
+//!
+//! Each message also has `encode_cdr()` and `decode_cdr()` serializing it with the encapsulation header,
+//! so that messages can be recorded, replayed and sent by non-ROS transports without rmw.
+//! Messages having fields of types of common_interfaces of safe_drive do not have them,
+//! because the layouts of the types are unknown.
+
+use crate::{
+    generator::{gen_primitives, FallibleApi},
+    parser::{ArrayInfo, TypeName},
+};
+use std::{borrow::Cow, collections::VecDeque};
+
+/// Field of a message serialized by `write_cdr()`.
+pub struct CdrField<'a> {
+    /// Name of the field in the definition.
+    pub var_name: &'a str,
+
+    pub type_name: &'a TypeName,
+
+    /// Rust type of the field.
+    pub ty: Cow<'a, str>,
+}
+
+/// Kind of elements of a field.
+enum Kind<'a> {
+    Primitive(&'a str),
+
+    /// String whose bytes are bounded, or unbounded if 0.
+    String(usize),
+
+    /// `builtin_interfaces/Time` or `Duration`, which are `sec` and `nanosec`.
+    Time,
+
+    Message,
+}
+
+impl Kind<'_> {
+    /// Least size of an element, which bounds lengths of sequences.
+    fn min_size(&self) -> usize {
+        match self {
+            Kind::Primitive(ty) => match *ty {
+                "bool" | "i8" | "u8" => 1,
+                "i16" | "u16" => 2,
+                "i32" | "u32" | "f32" => 4,
+                _ => 8,
+            },
+            // the length and the null terminator
+            Kind::String(_) => 5,
+            Kind::Time => 8,
+            Kind::Message => 1,
+        }
+    }
+
+    /// Statement writing the element `value`.
+    fn write(&self, value: &str) -> String {
+        match self {
+            Kind::Primitive(ty) => format!("writer.write_{ty}({value});"),
+            Kind::String(_) => format!("writer.write_string({value}.get_string().as_bytes());"),
+            Kind::Time => format!(
+                "writer.write_struct(|writer| {{
+    writer.write_i32({value}.sec);
+    writer.write_u32({value}.nanosec);
+}});"
+            ),
+            Kind::Message => format!("{value}.write_cdr(writer);"),
+        }
+    }
+
+    /// Statement reading the element `place`.
+    fn read(&self, place: &str) -> String {
+        match self {
+            Kind::Primitive(ty) => format!("{place} = reader.read_{ty}()?;"),
+            Kind::String(max) => format!(
+                "if !{place}.assign(reader.read_str({max})?) {{
+    return Err(Error::Allocation);
+}}"
+            ),
+            Kind::Time => format!(
+                "reader.read_struct(|reader| {{
+    {place}.sec = reader.read_i32()?;
+    {place}.nanosec = reader.read_u32()?;
+    Ok(())
+}})?;"
+            ),
+            Kind::Message => format!("{place}.read_cdr(reader)?;"),
+        }
+    }
+}
+
+/// Indent every line of `code` by `n` spaces.
+fn indent(code: &str, n: usize) -> String {
+    code.lines()
+        .map(|line| format!("{:n$}{line}\n", ""))
+        .collect()
+}
+
+/// Generate `write_cdr()`, `read_cdr()`, `encode_cdr()` and `decode_cdr()` of `struct_name`.
+pub fn gen_cdr_methods(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    struct_name: &str,
+    fields: &[CdrField<'_>],
+    no_std: bool,
+    fallible_api: FallibleApi,
+) {
+    let alloc = if no_std { "alloc" } else { "std" };
+    let to_option = fallible_api.to_option();
+
+    let mut writes = String::new();
+    let mut reads = String::new();
+    for CdrField {
+        var_name,
+        type_name,
+        ty,
+    } in fields.iter()
+    {
+        let field = crate::mangle(var_name);
+        let (kind, array_info) = match type_name {
+            TypeName::Type {
+                type_name,
+                array_info,
+            } => match gen_primitives(type_name) {
+                Some(primitive) => (Kind::Primitive(primitive), array_info),
+                None => (Kind::Message, array_info),
+            },
+            TypeName::ScopedType {
+                scope, array_info, ..
+            } if scope == "builtin_interfaces" => (Kind::Time, array_info),
+            TypeName::ScopedType { array_info, .. } => (Kind::Message, array_info),
+            TypeName::String(array_info) => (Kind::String(0), array_info),
+            TypeName::LimitedString { size, array_info } => (Kind::String(*size), array_info),
+        };
+
+        // elements are `*v` if they are primitives, or `v` by auto-dereference
+        let element = match kind {
+            Kind::Primitive(_) => "*v",
+            _ => "v",
+        };
+        let (write, read) = match array_info {
+            ArrayInfo::NotArray => {
+                let field = format!("self.{field}");
+                (kind.write(&field), kind.read(&field))
+            }
+            ArrayInfo::Static(_) => (
+                format!(
+                    "for v in self.{field}.iter() {{\n{}}}",
+                    indent(&kind.write(element), 4)
+                ),
+                format!(
+                    "for v in self.{field}.iter_mut() {{\n{}}}",
+                    indent(&kind.read(element), 4)
+                ),
+            ),
+            ArrayInfo::Dynamic | ArrayInfo::Limited(_) => {
+                let len = match array_info {
+                    ArrayInfo::Limited(max) => {
+                        format!("reader.read_bounded_len({}, {max})?", kind.min_size())
+                    }
+                    _ => format!("reader.read_len({})?", kind.min_size()),
+                };
+                // sequences of messages are fallible as messages are
+                let to_option = if matches!(kind, Kind::Message) {
+                    to_option
+                } else {
+                    ""
+                };
+                (
+                    format!(
+                        "writer.write_len(self.{field}.as_slice().len());
+for v in self.{field}.as_slice() {{\n{}}}",
+                        indent(&kind.write(element), 4)
+                    ),
+                    format!(
+                        "let len = {len};
+self.{field} = <{ty}>::new(len){to_option}.ok_or(Error::Allocation)?;
+for v in self.{field}.as_slice_mut() {{\n{}}}",
+                        indent(&kind.read(element), 4)
+                    ),
+                )
+            }
+        };
+
+        // XCDR2 has DHEADERs before sequences and arrays of non-primitive elements
+        let (write, read) = match (&kind, array_info) {
+            (Kind::Primitive(_), _) | (_, ArrayInfo::NotArray) => (write, read),
+            _ => (
+                format!(
+                    "writer.write_collection(|writer| {{\n{}}});",
+                    indent(&write, 4)
+                ),
+                format!(
+                    "reader.read_collection(|reader| {{\n{}    Ok(())\n}})?;",
+                    indent(&read, 4)
+                ),
+            ),
+        };
+        writes.push_str(&indent(&write, 12));
+        reads.push_str(&indent(&read, 12));
+    }
+
+    // empty messages have a byte of `structure_needs_at_least_one_member`
+    if fields.is_empty() {
+        writes.push_str("            writer.write_u8(0);\n");
+        reads.push_str("            reader.read_u8()?;\n");
+    }
+
+    let import = if reads.contains("Error::") {
+        "        use super::super::cdr::Error;\n\n"
+    } else {
+        ""
+    };
+
+    lines.push_back(
+        format!(
+            "
+impl {struct_name} {{
+    /// Serialize the message after the encapsulation header of `encapsulation`,
+    /// which is XCDR1 of `CdrLe` and `CdrBe`, or XCDR2 of `Cdr2Le`, `Cdr2Be`, `DCdr2Le` and `DCdr2Be`.
+    pub fn encode_cdr(
+        &self,
+        encapsulation: super::super::cdr::Encapsulation,
+    ) -> Result<{alloc}::vec::Vec<u8>, super::super::cdr::Error> {{
+        super::super::cdr::encode(encapsulation, |writer| self.write_cdr(writer))
+    }}
+
+    /// Deserialize a message from `payload`, whose byte order and version are given by the encapsulation header.
+    pub fn decode_cdr(payload: &[u8]) -> Result<Self, super::super::cdr::Error> {{
+        let mut msg = Self::new(){to_option}.ok_or(super::super::cdr::Error::Allocation)?;
+        super::super::cdr::decode(payload, |reader| msg.read_cdr(reader))?;
+        Ok(msg)
+    }}
+
+    /// Write the fields to `writer`.
+    pub fn write_cdr(&self, writer: &mut super::super::cdr::Writer) {{
+        writer.write_struct(|writer| {{
+{writes}        }});
+    }}
+
+    /// Read the fields from `reader`, where strings and sequences are reallocated.
+    pub fn read_cdr(
+        &mut self,
+        reader: &mut super::super::cdr::Reader<'_>,
+    ) -> Result<(), super::super::cdr::Error> {{
+{import}        reader.read_struct(|reader| {{
+{reads}            Ok(())
+        }})
+    }}
+}}"
+        )
+        .into(),
+    );
+}
+
+/// Generate the content of `cdr.rs`.
+/// `alloc::vec::Vec` is imported if `no_std` is true.
+pub fn gen_cdr_module(no_std: bool) -> String {
+    let import = if no_std {
+        "\nuse alloc::vec::Vec;\n"
+    } else {
+        ""
+    };
+
+    format!(
+        "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+//! Endianness-explicit helpers to read and write CDR.
+{import}
+{CDR_MODULE}"
+    )
+}
+
+/// Content of `cdr.rs`, which is a file to be compiled by tests.
+const CDR_MODULE: &str = include_str!("cdr/module.rs");
+
+#[cfg(test)]
+mod tests {
+    use super::{gen_cdr_methods, CdrField};
+    use crate::{
+        generator::FallibleApi,
+        parser::{self, Expr},
+    };
+    use nom::Finish;
+    use std::collections::VecDeque;
+
+    /// `cdr.rs` of generated packages.
+    #[allow(dead_code)]
+    mod cdr {
+        include!("cdr/module.rs");
+    }
+
+    use cdr::{decode, encode, Encapsulation, Error, Reader, Writer};
+
+    /// `{ x: u8, y: f64, name: string, points: sequence<{ x: i32 }> }`
+    fn write_sample(writer: &mut Writer) {
+        writer.write_struct(|writer| {
+            writer.write_u8(1);
+            writer.write_f64(2.5);
+            writer.write_string(b"abc");
+            writer.write_collection(|writer| {
+                writer.write_len(2);
+                for x in [3, -4] {
+                    writer.write_struct(|writer| writer.write_i32(x));
+                }
+            });
+        });
+    }
+
+    fn read_sample(reader: &mut Reader<'_>) -> Result<(u8, f64, String, Vec<i32>), Error> {
+        reader.read_struct(|reader| {
+            let x = reader.read_u8()?;
+            let y = reader.read_f64()?;
+            let name = reader.read_str(0)?.to_string();
+            let points = reader.read_collection(|reader| {
+                let len = reader.read_len(4)?;
+                (0..len)
+                    .map(|_| reader.read_struct(|reader| reader.read_i32()))
+                    .collect()
+            })?;
+            Ok((x, y, name, points))
+        })
+    }
+
+    #[test]
+    fn test_cdr_module() {
+        let expected = (1, 2.5, "abc".to_string(), vec![3, -4]);
+
+        // the header, u8, padding, f64, string and sequence
+        for (encapsulation, len) in [
+            (Encapsulation::CdrLe, 4 + 8 + 8 + 8 + 4 + 8),
+            (Encapsulation::CdrBe, 4 + 8 + 8 + 8 + 4 + 8),
+            (Encapsulation::Cdr2Le, 4 + 4 + 8 + 8 + 4 + 4 + 8),
+            (Encapsulation::DCdr2Be, 4 + 4 + 4 + 8 + 8 + 4 + 4 + 16),
+        ] {
+            let payload = encode(encapsulation, write_sample).unwrap();
+            assert_eq!(payload.len(), len, "{encapsulation:?}");
+            assert_eq!(
+                u16::from_be_bytes([payload[0], payload[1]]),
+                encapsulation.id()
+            );
+            assert_eq!(decode(&payload, read_sample), Ok(expected.clone()));
+
+            // every truncated payload is an error rather than a panic
+            for n in 0..payload.len() {
+                assert!(
+                    matches!(
+                        decode(&payload[..n], read_sample),
+                        Err(Error::Truncated { .. } | Error::InvalidLength { .. })
+                    ),
+                    "{encapsulation:?}: {n}"
+                );
+            }
+        }
+
+        let payload = encode(Encapsulation::CdrLe, write_sample).unwrap();
+        assert!(matches!(
+            decode(&payload[..14], read_sample),
+            Err(Error::Truncated {
+                offset: 8,
+                needed: 8,
+                remaining: 2
+            })
+        ));
+
+        assert_eq!(
+            encode(Encapsulation::PlCdrLe, write_sample),
+            Err(Error::UnsupportedEncapsulation(0x0003))
+        );
+    }
+
+    #[test]
+    fn test_cdr_read_len() {
+        // a length exceeding the remaining bytes is refused before allocation
+        let mut writer = Writer::with_encapsulation(Encapsulation::CdrLe);
+        writer.write_len(u32::MAX as usize);
+        writer.write_i32(0);
+        let payload = writer.into_inner();
+
+        let (mut reader, _) = Reader::from_payload(&payload).unwrap();
+        assert_eq!(
+            reader.read_len(4),
+            Err(Error::InvalidLength {
+                offset: 0,
+                len: u32::MAX as usize
+            })
+        );
+
+        // the number of elements times their size is checked
+        let (mut reader, _) = Reader::from_payload(&payload).unwrap();
+        assert!(reader.read_len(0).is_ok());
+        let (mut reader, _) = Reader::from_payload(&payload).unwrap();
+        assert_eq!(
+            reader.read_bounded_len(0, 8),
+            Err(Error::InvalidLength {
+                offset: 0,
+                len: u32::MAX as usize
+            })
+        );
+    }
+
+    #[test]
+    fn test_cdr_methods() {
+        let input =
+            "float64 x\nint32[] xs\nstring<=4 name\nPoint[2] ps\nbuiltin_interfaces/Time stamp\n";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let types = [
+            "f64",
+            "I32Seq<0>",
+            "RosString<4>",
+            "[Point; 2]",
+            "UnsafeTime",
+        ];
+        let fields: Vec<CdrField> = exprs
+            .iter()
+            .zip(types)
+            .map(|(expr, ty)| {
+                let Expr::Variable {
+                    type_name,
+                    var_name,
+                    ..
+                } = expr
+                else {
+                    panic!();
+                };
+                CdrField {
+                    var_name,
+                    type_name,
+                    ty: ty.into(),
+                }
+            })
+            .collect();
+
+        let mut lines = VecDeque::new();
+        gen_cdr_methods(&mut lines, "Example", &fields, true, FallibleApi::Result);
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains(") -> Result<alloc::vec::Vec<u8>, super::super::cdr::Error> {\n"));
+        assert!(code.contains(
+            "        let mut msg = Self::new().ok().ok_or(super::super::cdr::Error::Allocation)?;\n"
+        ));
+        assert!(code.contains("            writer.write_f64(self.x);\n"));
+        assert!(code.contains(
+            "            let len = reader.read_len(4)?;
+            self.xs = <I32Seq<0>>::new(len).ok_or(Error::Allocation)?;
+"
+        ));
+        assert!(code.contains("            if !self.name.assign(reader.read_str(4)?) {\n"));
+        assert!(code.contains(
+            "            reader.read_collection(|reader| {
+                for v in self.ps.iter_mut() {
+                    v.read_cdr(reader)?;
+                }
+                Ok(())
+            })?;
+"
+        ));
+        assert!(code.contains("                self.stamp.nanosec = reader.read_u32()?;\n"));
+
+        // empty messages have a byte
+        let mut lines = VecDeque::new();
+        gen_cdr_methods(&mut lines, "Empty", &[], false, FallibleApi::Option);
+        assert!(lines[0].contains("            writer.write_u8(0);\n"));
+        assert!(!lines[0].contains("use super::super::cdr::Error;"));
+    }
+}
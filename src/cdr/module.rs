@@ -0,0 +1,543 @@
+/// Byte order of serialized data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+impl Endian {
+    /// Byte order of the running target.
+    pub const fn native() -> Self {
+        if cfg!(target_endian = "big") {
+            Endian::Big
+        } else {
+            Endian::Little
+        }
+    }
+}
+
+/// Representation identifier of the encapsulation header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encapsulation {
+    CdrBe,
+    CdrLe,
+    PlCdrBe,
+    PlCdrLe,
+    Cdr2Be,
+    Cdr2Le,
+    DCdr2Be,
+    DCdr2Le,
+    PlCdr2Be,
+    PlCdr2Le,
+}
+
+impl Encapsulation {
+    pub const fn from_id(id: u16) -> Option<Self> {
+        let encapsulation = match id {
+            0x0000 => Encapsulation::CdrBe,
+            0x0001 => Encapsulation::CdrLe,
+            0x0002 => Encapsulation::PlCdrBe,
+            0x0003 => Encapsulation::PlCdrLe,
+            0x0006 => Encapsulation::Cdr2Be,
+            0x0007 => Encapsulation::Cdr2Le,
+            0x0008 => Encapsulation::DCdr2Be,
+            0x0009 => Encapsulation::DCdr2Le,
+            0x000a => Encapsulation::PlCdr2Be,
+            0x000b => Encapsulation::PlCdr2Le,
+            _ => return None,
+        };
+        Some(encapsulation)
+    }
+
+    pub const fn id(self) -> u16 {
+        match self {
+            Encapsulation::CdrBe => 0x0000,
+            Encapsulation::CdrLe => 0x0001,
+            Encapsulation::PlCdrBe => 0x0002,
+            Encapsulation::PlCdrLe => 0x0003,
+            Encapsulation::Cdr2Be => 0x0006,
+            Encapsulation::Cdr2Le => 0x0007,
+            Encapsulation::DCdr2Be => 0x0008,
+            Encapsulation::DCdr2Le => 0x0009,
+            Encapsulation::PlCdr2Be => 0x000a,
+            Encapsulation::PlCdr2Le => 0x000b,
+        }
+    }
+
+    /// Byte order, which is the lowest bit of the identifier.
+    pub const fn endian(self) -> Endian {
+        if self.id() & 1 == 0 {
+            Endian::Big
+        } else {
+            Endian::Little
+        }
+    }
+
+    /// XCDR2 aligns 8-byte primitives to 4 bytes.
+    pub const fn is_xcdr2(self) -> bool {
+        self.id() >= 0x0006
+    }
+
+    /// D_CDR2 has a DHEADER before every struct, whose members can be appended by newer definitions.
+    pub const fn is_delimited(self) -> bool {
+        matches!(self, Encapsulation::DCdr2Be | Encapsulation::DCdr2Le)
+    }
+
+    /// PL_CDR and PL_CDR2 serialize members as parameter lists, which messages do not support.
+    pub const fn is_parameter_list(self) -> bool {
+        matches!(
+            self,
+            Encapsulation::PlCdrBe
+                | Encapsulation::PlCdrLe
+                | Encapsulation::PlCdr2Be
+                | Encapsulation::PlCdr2Le
+        )
+    }
+
+    const fn max_align(self) -> usize {
+        if self.is_xcdr2() {
+            4
+        } else {
+            8
+        }
+    }
+}
+
+/// Errors of reading CDR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// `needed` bytes are required at `offset`, but only `remaining` bytes are left.
+    Truncated {
+        offset: usize,
+        needed: usize,
+        remaining: usize,
+    },
+
+    /// The encapsulation header has an unknown representation identifier.
+    UnknownEncapsulation(u16),
+
+    /// The length at `offset` exceeds the remaining bytes or the bound of the field.
+    InvalidLength { offset: usize, len: usize },
+
+    /// A boolean is neither 0 nor 1.
+    InvalidBool { offset: usize, value: u8 },
+
+    /// A string is not terminated by the null character.
+    InvalidString { offset: usize },
+
+    /// A string is not valid UTF-8.
+    InvalidUtf8 { offset: usize },
+
+    /// The encapsulation is known but not supported by messages.
+    UnsupportedEncapsulation(u16),
+
+    /// Memory of a message, a string or a sequence cannot be allocated.
+    Allocation,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Truncated {
+                offset,
+                needed,
+                remaining,
+            } => write!(
+                f,
+                "truncated at {offset}: {needed} bytes are needed, but {remaining} bytes remain"
+            ),
+            Error::UnknownEncapsulation(id) => write!(f, "unknown encapsulation: {id:#06x}"),
+            Error::InvalidLength { offset, len } => write!(f, "invalid length at {offset}: {len}"),
+            Error::InvalidBool { offset, value } => write!(f, "invalid bool at {offset}: {value}"),
+            Error::InvalidString { offset } => {
+                write!(f, "string at {offset} is not terminated by null")
+            }
+            Error::InvalidUtf8 { offset } => write!(f, "string at {offset} is not UTF-8"),
+            Error::UnsupportedEncapsulation(id) => {
+                write!(f, "unsupported encapsulation: {id:#06x}")
+            }
+            Error::Allocation => write!(f, "failed to allocate memory"),
+        }
+    }
+}
+
+macro_rules! read_primitive {
+    ($name:ident, $ty:ty) => {
+        pub fn $name(&mut self) -> Result<$ty, Error> {
+            const SIZE: usize = core::mem::size_of::<$ty>();
+            self.align(SIZE)?;
+            let bytes: [u8; SIZE] = self.read_bytes(SIZE)?.try_into().unwrap();
+            Ok(match self.endian {
+                Endian::Big => <$ty>::from_be_bytes(bytes),
+                Endian::Little => <$ty>::from_le_bytes(bytes),
+            })
+        }
+    };
+}
+
+macro_rules! write_primitive {
+    ($name:ident, $ty:ty) => {
+        pub fn $name(&mut self, value: $ty) {
+            self.align(core::mem::size_of::<$ty>());
+            match self.endian {
+                Endian::Big => self.buf.extend_from_slice(&value.to_be_bytes()),
+                Endian::Little => self.buf.extend_from_slice(&value.to_le_bytes()),
+            }
+        }
+    };
+}
+
+/// Reader of CDR in the explicit byte order.
+/// Alignment is relative to the beginning of `buf`, which must not include the encapsulation header.
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    endian: Endian,
+    max_align: usize,
+    xcdr2: bool,
+    delimited: bool,
+}
+
+impl<'a> Reader<'a> {
+    /// Reader of XCDR1 without the encapsulation header.
+    #[must_use]
+    pub fn new(buf: &'a [u8], endian: Endian) -> Self {
+        Reader {
+            buf,
+            pos: 0,
+            endian,
+            max_align: 8,
+            xcdr2: false,
+            delimited: false,
+        }
+    }
+
+    /// Read the encapsulation header of `payload` and return a reader of the rest
+    /// in the byte order and the alignment of the header.
+    pub fn from_payload(payload: &'a [u8]) -> Result<(Self, Encapsulation), Error> {
+        let Some((header, body)) = payload.split_first_chunk::<4>() else {
+            return Err(Error::Truncated {
+                offset: 0,
+                needed: 4,
+                remaining: payload.len(),
+            });
+        };
+
+        // the identifier is always big endian, and the options are ignored
+        let id = u16::from_be_bytes([header[0], header[1]]);
+        let encapsulation = Encapsulation::from_id(id).ok_or(Error::UnknownEncapsulation(id))?;
+
+        let reader = Reader {
+            buf: body,
+            pos: 0,
+            endian: encapsulation.endian(),
+            max_align: encapsulation.max_align(),
+            xcdr2: encapsulation.is_xcdr2(),
+            delimited: encapsulation.is_delimited(),
+        };
+        Ok((reader, encapsulation))
+    }
+
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    /// Skip padding to align the position to `n` bytes.
+    pub fn align(&mut self, n: usize) -> Result<(), Error> {
+        let n = n.min(self.max_align);
+        let padding = (n - self.pos % n) % n;
+        self.read_bytes(padding).map(|_| ())
+    }
+
+    pub fn read_bytes(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if n > self.remaining() {
+            return Err(Error::Truncated {
+                offset: self.pos,
+                needed: n,
+                remaining: self.remaining(),
+            });
+        }
+
+        let bytes = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_i8(&mut self) -> Result<i8, Error> {
+        Ok(self.read_u8()? as i8)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, Error> {
+        let offset = self.pos;
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(Error::InvalidBool { offset, value }),
+        }
+    }
+
+    read_primitive!(read_u16, u16);
+    read_primitive!(read_i16, i16);
+    read_primitive!(read_u32, u32);
+    read_primitive!(read_i32, i32);
+    read_primitive!(read_u64, u64);
+    read_primitive!(read_i64, i64);
+    read_primitive!(read_f32, f32);
+    read_primitive!(read_f64, f64);
+
+    /// Read the length of a sequence whose elements have at least `elem_size` bytes.
+    /// The length is checked against the remaining bytes, so that corrupted lengths do not cause huge allocations.
+    pub fn read_len(&mut self, elem_size: usize) -> Result<usize, Error> {
+        let offset = self.pos;
+        let len = self.read_u32()? as usize;
+        match len.checked_mul(elem_size) {
+            Some(size) if size <= self.remaining() => Ok(len),
+            _ => Err(Error::InvalidLength { offset, len }),
+        }
+    }
+
+    /// Read a string and return its bytes without the null terminator.
+    pub fn read_string(&mut self) -> Result<&'a [u8], Error> {
+        let offset = self.pos;
+        let len = self.read_len(1)?;
+        match self.read_bytes(len)? {
+            [bytes @ .., 0] => Ok(bytes),
+            _ => Err(Error::InvalidString { offset }),
+        }
+    }
+
+    /// Read the length of a sequence like `read_len()`, which must not exceed `max`.
+    pub fn read_bounded_len(&mut self, elem_size: usize, max: usize) -> Result<usize, Error> {
+        let offset = self.pos;
+        match self.read_len(elem_size)? {
+            len if len > max => Err(Error::InvalidLength { offset, len }),
+            len => Ok(len),
+        }
+    }
+
+    /// Read a UTF-8 string, whose bytes must not exceed `max` unless `max` is 0.
+    pub fn read_str(&mut self, max: usize) -> Result<&'a str, Error> {
+        let offset = self.pos;
+        let bytes = self.read_string()?;
+        if max != 0 && bytes.len() > max {
+            return Err(Error::InvalidLength {
+                offset,
+                len: bytes.len(),
+            });
+        }
+        core::str::from_utf8(bytes).map_err(|_| Error::InvalidUtf8 { offset })
+    }
+
+    /// Read a struct by `f`, which is preceded by a DHEADER if the encapsulation is D_CDR2.
+    pub fn read_struct<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        if self.delimited {
+            self.read_delimited(f)
+        } else {
+            f(self)
+        }
+    }
+
+    /// Read a sequence or an array of non-primitive elements by `f`,
+    /// which is preceded by a DHEADER if the encapsulation is XCDR2.
+    pub fn read_collection<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        if self.xcdr2 {
+            self.read_delimited(f)
+        } else {
+            f(self)
+        }
+    }
+
+    /// Read a DHEADER and the data by `f`, which cannot read beyond the size of the header.
+    /// Unread bytes of the data, such as members appended by newer definitions, are skipped.
+    fn read_delimited<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let offset = self.pos;
+        let size = self.read_u32()? as usize;
+        if size > self.remaining() {
+            return Err(Error::InvalidLength { offset, len: size });
+        }
+
+        let end = self.pos + size;
+        let mut reader = Reader {
+            buf: &self.buf[..end],
+            pos: self.pos,
+            endian: self.endian,
+            max_align: self.max_align,
+            xcdr2: self.xcdr2,
+            delimited: self.delimited,
+        };
+        let value = f(&mut reader)?;
+        self.pos = end;
+        Ok(value)
+    }
+}
+
+/// Writer of CDR in the explicit byte order.
+pub struct Writer {
+    buf: Vec<u8>,
+    origin: usize,
+    endian: Endian,
+    max_align: usize,
+    xcdr2: bool,
+    delimited: bool,
+}
+
+impl Writer {
+    /// Writer of XCDR1 without the encapsulation header.
+    #[must_use]
+    pub fn new(endian: Endian) -> Self {
+        Writer {
+            buf: Vec::new(),
+            origin: 0,
+            endian,
+            max_align: 8,
+            xcdr2: false,
+            delimited: false,
+        }
+    }
+
+    /// Writer starting with the encapsulation header.
+    pub fn with_encapsulation(encapsulation: Encapsulation) -> Self {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&encapsulation.id().to_be_bytes());
+        buf.extend_from_slice(&[0, 0]);
+
+        Writer {
+            buf,
+            origin: 4,
+            endian: encapsulation.endian(),
+            max_align: encapsulation.max_align(),
+            xcdr2: encapsulation.is_xcdr2(),
+            delimited: encapsulation.is_delimited(),
+        }
+    }
+
+    pub fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// Pad zeros to align the position to `n` bytes.
+    pub fn align(&mut self, n: usize) {
+        let n = n.min(self.max_align);
+        let padding = (n - (self.buf.len() - self.origin) % n) % n;
+        self.buf.resize(self.buf.len() + padding, 0);
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn write_i8(&mut self, value: i8) {
+        self.buf.push(value as u8);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.buf.push(value as u8);
+    }
+
+    write_primitive!(write_u16, u16);
+    write_primitive!(write_i16, i16);
+    write_primitive!(write_u32, u32);
+    write_primitive!(write_i32, i32);
+    write_primitive!(write_u64, u64);
+    write_primitive!(write_i64, i64);
+    write_primitive!(write_f32, f32);
+    write_primitive!(write_f64, f64);
+
+    pub fn write_len(&mut self, len: usize) {
+        self.write_u32(len as u32);
+    }
+
+    /// Write a string followed by the null terminator.
+    pub fn write_string(&mut self, bytes: &[u8]) {
+        self.write_len(bytes.len() + 1);
+        self.buf.extend_from_slice(bytes);
+        self.buf.push(0);
+    }
+
+    /// Write a struct by `f`, which is preceded by a DHEADER if the encapsulation is D_CDR2.
+    pub fn write_struct(&mut self, f: impl FnOnce(&mut Self)) {
+        if self.delimited {
+            self.write_delimited(f);
+        } else {
+            f(self);
+        }
+    }
+
+    /// Write a sequence or an array of non-primitive elements by `f`,
+    /// which is preceded by a DHEADER if the encapsulation is XCDR2.
+    pub fn write_collection(&mut self, f: impl FnOnce(&mut Self)) {
+        if self.xcdr2 {
+            self.write_delimited(f);
+        } else {
+            f(self);
+        }
+    }
+
+    /// Write a DHEADER, which is filled with the size of the data written by `f` afterward.
+    fn write_delimited(&mut self, f: impl FnOnce(&mut Self)) {
+        self.align(4);
+        let header = self.buf.len();
+        self.buf.extend_from_slice(&[0; 4]);
+        f(self);
+
+        let size = (self.buf.len() - header - 4) as u32;
+        let bytes = match self.endian {
+            Endian::Big => size.to_be_bytes(),
+            Endian::Little => size.to_le_bytes(),
+        };
+        self.buf[header..header + 4].copy_from_slice(&bytes);
+    }
+
+    /// Serialized bytes including the encapsulation header if any.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Serialize data written by `f` after the encapsulation header of `encapsulation`.
+pub fn encode(encapsulation: Encapsulation, f: impl FnOnce(&mut Writer)) -> Result<Vec<u8>, Error> {
+    if encapsulation.is_parameter_list() {
+        return Err(Error::UnsupportedEncapsulation(encapsulation.id()));
+    }
+
+    let mut writer = Writer::with_encapsulation(encapsulation);
+    f(&mut writer);
+    Ok(writer.into_inner())
+}
+
+/// Deserialize `payload` having the encapsulation header by `f`.
+pub fn decode<T>(
+    payload: &[u8],
+    f: impl FnOnce(&mut Reader<'_>) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let (mut reader, encapsulation) = Reader::from_payload(payload)?;
+    if encapsulation.is_parameter_list() {
+        return Err(Error::UnsupportedEncapsulation(encapsulation.id()));
+    }
+    f(&mut reader)
+}
@@ -0,0 +1,260 @@
+//! Profiles of command line options defined by the configuration file.
+//!
+//! ```json
+//! {
+//!   "profiles": {
+//!     "dev": ["--partial-eq", "rust", "--serde"],
+//!     "release": ["--backend", "safe-drive", "--emit-crates"]
+//!   }
+//! }
+//! ```
+//!
+//! `--profile dev` is expanded to the options of the profile in place,
+//! so options after `--profile` override the options of the profile.
+//!
+//! `skip` lists types which are not generated, such as `pkg/msg/Type`, `pkg/srv/Type` or `pkg/Type`.
+//! `rename` maps packages to modules and types to Rust types.
+//!
+//! ```json
+//! {
+//!   "skip": ["my_pkg/msg/Provided", "my_pkg/srv/Unsupported"],
+//!   "rename": {
+//!     "packages": { "nav_msgs": "third_party_nav_msgs" },
+//!     "types": { "my_pkg/msg/String": "Label" }
+//!   }
+//! }
+//! ```
+//!
+//! `layout` overrides `#[repr(C)]` of messages by `align` or `packed` for DMA buffers or vendor SDKs.
+//! The layouts differ from the C structs of rosidl, so a warning `layout-overrides` is reported for each of them.
+//!
+//! ```json
+//! {
+//!   "layout": {
+//!     "my_pkg/msg/Frame": { "align": 64 },
+//!     "my_pkg/msg/Register": { "packed": 1 }
+//!   }
+//! }
+//! ```
+//!
+//! `alias` generates messages as re-exports of structurally identical messages of other packages,
+//! which are reported by `-W identical-definitions`, so that they need no conversions.
+//! An alias has the type support of the aliased message, so it is published as the type of the aliased message,
+//! such as `my_pkg/msg/Point` in the following example.
+//!
+//! ```json
+//! {
+//!   "alias": {
+//!     "other_pkg/msg/Point": "my_pkg/msg/Point"
+//!   }
+//! }
+//! ```
+//!
+//! `topic` specifies topics of messages by convention, which override `# @topic` of the .msg files.
+//!
+//! ```json
+//! {
+//!   "topic": {
+//!     "sensor_msgs/msg/Imu": "/sensor/imu"
+//!   }
+//! }
+//! ```
+//!
+//! `qos` specifies QoS recommended for messages and services, which override `# @qos(...)` of the files.
+//! The keys are the same as `@qos`.
+//!
+//! ```json
+//! {
+//!   "qos": {
+//!     "sensor_msgs/msg/Imu": { "reliability": "best_effort", "depth": 5 },
+//!     "my_pkg/srv/Reset": { "reliability": "reliable" }
+//!   }
+//! }
+//! ```
+
+use crate::qos::Qos;
+use serde::Deserialize;
+use std::{collections::BTreeMap, error::Error, ffi::OsString, fs::read_to_string, io::ErrorKind};
+
+/// Default path to the configuration file.
+pub const DEFAULT_CONFIG: &str = "ros2msg_to_rs.json";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    profiles: BTreeMap<String, Vec<String>>,
+
+    /// Types which are not generated.
+    #[serde(default)]
+    pub skip: Vec<String>,
+
+    #[serde(default)]
+    pub rename: Rename,
+
+    /// `pkg/msg/Type`, `pkg/srv/Type` or `pkg/Type` -> layout overriding `#[repr(C)]`
+    #[serde(default)]
+    pub layout: BTreeMap<String, Layout>,
+
+    /// `pkg/msg/Type` or `pkg/Type` -> the identical message it re-exports
+    #[serde(default)]
+    pub alias: BTreeMap<String, String>,
+
+    /// `pkg/msg/Type` or `pkg/Type` -> the topic of the message
+    #[serde(default)]
+    pub topic: BTreeMap<String, String>,
+
+    /// `pkg/msg/Type`, `pkg/srv/Type` or `pkg/Type` -> the recommended QoS
+    #[serde(default)]
+    pub qos: BTreeMap<String, Qos>,
+}
+
+/// Packages and types renamed in the generated code.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rename {
+    /// `pkg` -> `module`
+    #[serde(default)]
+    pub packages: BTreeMap<String, String>,
+
+    /// `pkg/msg/Type`, `pkg/srv/Type` or `pkg/Type` -> `NewType`
+    #[serde(default)]
+    pub types: BTreeMap<String, String>,
+}
+
+/// Alignment or packing of a message in bytes, which must be a power of two.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Layout {
+    pub align: Option<usize>,
+    pub packed: Option<usize>,
+}
+
+impl Layout {
+    /// Return `align(N)` or `packed(N)` appended to `#[repr(C, ...)]`.
+    pub fn repr(&self) -> Result<String, Box<dyn Error>> {
+        let (name, n) = match (self.align, self.packed) {
+            (Some(n), None) => ("align", n),
+            (None, Some(n)) => ("packed", n),
+            (Some(_), Some(_)) => return Err("`align` and `packed` cannot be used together".into()),
+            (None, None) => return Err("`align` or `packed` is required".into()),
+        };
+
+        if !n.is_power_of_two() {
+            return Err(format!("`{name}` must be a power of two, but it is {n}").into());
+        }
+        Ok(format!("{name}({n})"))
+    }
+}
+
+/// Load the configuration file of `path`.
+/// The configuration file is optional, so the default is returned if it does not exist.
+pub fn load(path: &str) -> Result<Config, Box<dyn Error>> {
+    let contents = match read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+        Err(e) => return Err(format!("{path}: {e}").into()),
+    };
+    Ok(serde_json::from_str(&contents).map_err(|e| format!("{path}: {e}"))?)
+}
+
+/// Insert the options of the profile selected by `--profile` into `args`.
+/// The configuration file is read only if `--profile` is specified.
+pub fn expand_profile(args: Vec<OsString>) -> Result<Vec<OsString>, Box<dyn Error>> {
+    let mut config_path = None;
+    let mut profile = None;
+
+    let mut iter = args.iter().enumerate();
+    while let Some((i, arg)) = iter.next() {
+        let Some(arg) = arg.to_str() else {
+            continue;
+        };
+        if arg == "--" {
+            break;
+        }
+
+        if let Some(path) = arg.strip_prefix("--config=") {
+            config_path = Some(path.to_string());
+        } else if arg == "--config" {
+            config_path = iter.next().and_then(|(_, v)| v.to_str()).map(String::from);
+        } else if let Some(name) = arg.strip_prefix("--profile=") {
+            profile = Some((name.to_string(), i + 1));
+        } else if arg == "--profile" {
+            if let Some((j, name)) = iter.next() {
+                let name = name.to_str().ok_or("--profile is not UTF-8")?;
+                profile = Some((name.to_string(), j + 1));
+            }
+        }
+    }
+
+    let Some((name, pos)) = profile else {
+        return Ok(args);
+    };
+
+    let path = config_path.as_deref().unwrap_or(DEFAULT_CONFIG);
+    let contents = read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let profile_args = select(&contents, &name).map_err(|e| format!("{path}: {e}"))?;
+
+    let mut result = args;
+    result.splice(pos..pos, profile_args.into_iter().map(OsString::from));
+    Ok(result)
+}
+
+/// Options of the profile `name` in the configuration file of `contents`.
+fn select(contents: &str, name: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut config: Config = serde_json::from_str(contents)?;
+    let Some(args) = config.profiles.remove(name) else {
+        let names: Vec<&str> = config.profiles.keys().map(|k| k.as_str()).collect();
+        return Err(format!(
+            "profile `{name}` is not defined, available profiles: [{}]",
+            names.join(", ")
+        )
+        .into());
+    };
+
+    if args
+        .iter()
+        .any(|arg| arg == "--profile" || arg.starts_with("--profile="))
+    {
+        return Err(format!("profile `{name}` cannot contain --profile").into());
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{select, Config, Layout};
+
+    #[test]
+    fn test_select() {
+        let contents = r#"{"profiles": {"dev": ["--serde"], "bad": ["--profile", "dev"]}}"#;
+        assert_eq!(select(contents, "dev").unwrap(), ["--serde"]);
+        assert!(select(contents, "release").is_err());
+        assert!(select(contents, "bad").is_err());
+        assert!(select(r#"{"profile": {}}"#, "dev").is_err());
+        assert!(select(r#"{"skip": ["pkg/Type"], "profiles": {"dev": []}}"#, "dev").is_ok());
+        assert!(select(
+            r#"{"rename": {"packages": {"a": "b"}}, "profiles": {"dev": []}}"#,
+            "dev"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_layout() {
+        let config: Config =
+            serde_json::from_str(r#"{"layout": {"pkg/Frame": {"align": 64}}}"#).unwrap();
+        assert_eq!(config.layout["pkg/Frame"].repr().unwrap(), "align(64)");
+
+        let packed = Layout {
+            align: None,
+            packed: Some(1),
+        };
+        assert_eq!(packed.repr().unwrap(), "packed(1)");
+
+        for (align, packed) in [(Some(3), None), (Some(8), Some(8)), (None, None)] {
+            assert!(Layout { align, packed }.repr().is_err());
+        }
+    }
+}
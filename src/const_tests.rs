@@ -0,0 +1,123 @@
+//! Tests comparing each generated constant with the value written in the definition.
+//!
+//! Expected values are written as bytes of strings and bits of floating point numbers,
+//! not as literals formatted in the same way as the constants,
+//! so that the tests detect regressions of the generator for consumers vendoring generated code.
+
+use crate::{
+    generator::gen_primitives,
+    parser::{ArrayInfo, Expr, TypeName, Value, ValueType},
+};
+use std::{borrow::Cow, collections::VecDeque};
+
+/// Generate a test of constants of `exprs`.
+/// Each item of `scopes` is a path prefix of constants, such as `""` and `"ExampleRequest::"`, and their definitions.
+pub fn gen_const_tests(lines: &mut VecDeque<Cow<'_, str>>, scopes: &[(String, &[Expr])]) {
+    let mut asserts = String::new();
+
+    for (prefix, exprs) in scopes.iter() {
+        for expr in exprs.iter() {
+            let Expr::Variable {
+                type_name,
+                var_name,
+                value: Some(ValueType::Const(value)),
+                ..
+            } = expr
+            else {
+                continue;
+            };
+
+            let name = format!("{prefix}{}", crate::mangle(var_name));
+            let (elem_type, array_info) = match type_name {
+                TypeName::Type {
+                    type_name,
+                    array_info,
+                }
+                | TypeName::ScopedType {
+                    type_name,
+                    array_info,
+                    ..
+                } => (gen_primitives(type_name), array_info),
+                TypeName::String(array_info) | TypeName::LimitedString { array_info, .. } => {
+                    (Some("&[u8]"), array_info)
+                }
+            };
+            let Some(elem_type) = elem_type else {
+                continue;
+            };
+
+            match value {
+                Value::Array(values) if !matches!(array_info, ArrayInfo::NotArray) => {
+                    asserts.push_str(&format!(
+                        "        assert_eq!({name}.len(), {});\n",
+                        values.len()
+                    ));
+                    for (i, value) in values.iter().enumerate() {
+                        push_assert(&mut asserts, &format!("{name}[{i}]"), elem_type, value);
+                    }
+                }
+                _ => push_assert(&mut asserts, &name, elem_type, value),
+            }
+        }
+    }
+
+    if asserts.is_empty() {
+        return;
+    }
+
+    lines.push_back(
+        format!(
+            "
+#[cfg(test)]
+mod const_tests {{
+    use super::*;
+
+    #[test]
+    fn constants() {{
+{asserts}    }}
+}}"
+        )
+        .into(),
+    );
+}
+
+/// Assert `expr` of `ty` is `value`.
+fn push_assert(asserts: &mut String, expr: &str, ty: &str, value: &Value) {
+    let number = match value {
+        Value::Int(n) => Some(*n as f64),
+        Value::Uint(n) => Some(*n as f64),
+        Value::Float(n) => Some(*n),
+        _ => None,
+    };
+
+    let assert = match (ty, value) {
+        ("&[u8]", Value::String(s)) => {
+            let bytes: Vec<String> = s.bytes().chain([0]).map(|b| b.to_string()).collect();
+            format!("assert_eq!({expr}, [{}].as_slice());", bytes.join(", "))
+        }
+        ("u8" | "i8", Value::String(s)) => {
+            // a character like `char C = 'a'`
+            let [b] = s.as_bytes()[..] else { return };
+            let n = if ty == "i8" {
+                b as i8 as i128
+            } else {
+                b as i128
+            };
+            format!("assert_eq!({expr} as i128, {n});")
+        }
+        ("bool", Value::Bool(b)) => format!("assert_eq!(u8::from({expr}), {});", u8::from(*b)),
+        ("f32", _) => {
+            let Some(n) = number else { return };
+            format!("assert_eq!({expr}.to_bits(), {:#x});", (n as f32).to_bits())
+        }
+        ("f64", _) => {
+            let Some(n) = number else { return };
+            format!("assert_eq!({expr}.to_bits(), {:#x});", n.to_bits())
+        }
+        (_, Value::Int(n)) => format!("assert_eq!({expr} as i128, {n});"),
+        (_, Value::Uint(n)) => format!("assert_eq!({expr} as i128, {n});"),
+        _ => return,
+    };
+
+    asserts.push_str(&format!("        {assert}\n"));
+}
@@ -0,0 +1,154 @@
+//! Lookup of constants by their names at runtime.
+//!
+//! `--constants-by-name` generates `{package}/constants.rs` having `ConstantValue`,
+//! and `constant_by_name()` of each message, so that tools like parameter UIs and validators
+//! can resolve symbolic names written in configurations.
+//!
+//! ```text
+//! uint8 MODE_AUTO = 1
+//! string FRAME = "map"
+//! ```
+//!
+//! `Example::constant_by_name("MODE_AUTO")` returns `Some(ConstantValue::Int(1))`.
+//! Names are the ones of the definitions, even if constants are renamed by `@rust_name`,
+//! and arrays of constants are not listed.
+
+use crate::{
+    generator::gen_primitives,
+    parser::{ArrayInfo, Expr, TypeName, Value, ValueType},
+};
+use std::{borrow::Cow, collections::VecDeque};
+
+/// Generate the content of `constants.rs`.
+pub fn gen_constants_module() -> String {
+    format!(
+        "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+//! Values of constants looked up by `constant_by_name()` of messages.
+{CONSTANTS_MODULE}"
+    )
+}
+
+const CONSTANTS_MODULE: &str = r#"
+/// Value of a constant, where integers of any width are `Int`, and `float32` is converted to `f64`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstantValue {
+    Int(i128),
+    Float(f64),
+    Bool(bool),
+    Str(&'static str),
+}
+"#;
+
+/// Generate `constant_by_name()` of `struct_name` looking up the constants of `exprs`.
+/// `prefix` is the path to the constants, which is `"Self::"` for associated constants.
+pub fn gen_constant_by_name(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    struct_name: &str,
+    prefix: &str,
+    exprs: &[Expr],
+) {
+    let mut arms = String::new();
+    for expr in exprs.iter() {
+        let Expr::Variable {
+            type_name,
+            var_name,
+            value: Some(ValueType::Const(value)),
+            ros_name,
+            ..
+        } = expr
+        else {
+            continue;
+        };
+
+        let constant = format!("{prefix}{}", crate::mangle(var_name));
+        let value = match (type_name, value) {
+            (
+                TypeName::String(ArrayInfo::NotArray)
+                | TypeName::LimitedString {
+                    array_info: ArrayInfo::NotArray,
+                    ..
+                },
+                Value::String(s),
+            ) => format!("Str({s:?})"),
+            (
+                TypeName::Type {
+                    type_name,
+                    array_info: ArrayInfo::NotArray,
+                },
+                _,
+            ) => match gen_primitives(type_name) {
+                Some("bool") => format!("Bool({constant})"),
+                Some("f64") => format!("Float({constant})"),
+                Some("f32") => format!("Float(f64::from({constant}))"),
+                Some(_) => format!("Int(i128::from({constant}))"),
+                None => continue,
+            },
+            _ => continue,
+        };
+        let name = ros_name.as_deref().unwrap_or(var_name);
+        arms.push_str(&format!(
+            "            {name:?} => Some(ConstantValue::{value}),\n"
+        ));
+    }
+
+    let (param, body) = if arms.is_empty() {
+        ("_name", "        None\n".to_string())
+    } else {
+        (
+            "name",
+            format!(
+                "        use super::super::constants::ConstantValue;
+        match name {{
+{arms}            _ => None,
+        }}
+"
+            ),
+        )
+    };
+    lines.push_back(
+        format!(
+            "
+impl {struct_name} {{
+    /// Value of the constant named `name` in the definition.
+    pub fn constant_by_name({param}: &str) -> Option<super::super::constants::ConstantValue> {{
+{body}    }}
+}}"
+        )
+        .into(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::gen_constant_by_name;
+    use crate::parser;
+    use nom::Finish;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_constant_by_name() {
+        let input = "int32 x\nuint64 MAX = 18446744073709551615\nfloat32 HALF = 0.5\nfloat64 PI = 3.14\nbool FLAG = true\nstring FRAME = \"map\"\nchar C = 'a'\nint32[] XS = [1, 2]\n";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let mut lines = VecDeque::new();
+        gen_constant_by_name(&mut lines, "Example", "", &exprs);
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("    pub fn constant_by_name(name: &str) -> Option<super::super::constants::ConstantValue> {\n"));
+        assert!(
+            code.contains("            \"MAX\" => Some(ConstantValue::Int(i128::from(MAX))),\n")
+        );
+        assert!(
+            code.contains("            \"HALF\" => Some(ConstantValue::Float(f64::from(HALF))),\n")
+        );
+        assert!(code.contains("            \"PI\" => Some(ConstantValue::Float(PI)),\n"));
+        assert!(code.contains("            \"FLAG\" => Some(ConstantValue::Bool(FLAG)),\n"));
+        assert!(code.contains("            \"FRAME\" => Some(ConstantValue::Str(\"map\")),\n"));
+        assert!(code.contains("            \"C\" => Some(ConstantValue::Int(i128::from(C))),\n"));
+        assert!(!code.contains("XS"));
+
+        let (_, exprs) = parser::parse_msg("int32 x\n").finish().unwrap();
+        let mut lines = VecDeque::new();
+        gen_constant_by_name(&mut lines, "Example", "Self::", &exprs);
+        assert!(lines[0].contains("pub fn constant_by_name(_name: &str)"));
+    }
+}
@@ -0,0 +1,85 @@
+//! Settings of a generation, i.e. levels of warnings, limits of the parser and the format of messages.
+//!
+//! `generate_workspace()` owns a `Context` and makes it current on its thread while generating,
+//! so generations on different threads do not share settings or counts of denied warnings,
+//! and a nested generation restores the outer context when it returns.
+//! Without a current context, the default settings are used.
+
+use crate::{
+    diagnostics::MessageFormat,
+    parser::Limits,
+    warnings::{Level, Warning},
+};
+use std::{cell::RefCell, collections::BTreeMap};
+
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    /// Levels of warnings overriding the default ones.
+    pub levels: BTreeMap<Warning, Level>,
+
+    pub limits: Limits,
+
+    pub message_format: MessageFormat,
+
+    /// The number of denied warnings reported.
+    pub denied: usize,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Context> = RefCell::new(Context::default());
+}
+
+impl Context {
+    /// Run `f` with this context as the current one, and restore the previous one after `f` returns or panics.
+    pub fn scope<T>(self, f: impl FnOnce() -> T) -> T {
+        struct Restore(Option<Context>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                if let Some(previous) = self.0.take() {
+                    CURRENT.with(|current| *current.borrow_mut() = previous);
+                }
+            }
+        }
+
+        let previous = CURRENT.with(|current| current.replace(self));
+        let _restore = Restore(Some(previous));
+        f()
+    }
+}
+
+/// Call `f` with the current context.
+pub fn with<T>(f: impl FnOnce(&mut Context) -> T) -> T {
+    CURRENT.with(|current| f(&mut current.borrow_mut()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{with, Context};
+    use crate::warnings::{Level, Warning};
+
+    #[test]
+    fn test_scope() {
+        let outer = Context {
+            levels: [(Warning::NamingConvention, Level::Deny)].into(),
+            ..Default::default()
+        };
+        outer.scope(|| {
+            with(|c| c.denied += 1);
+
+            Context::default().scope(|| {
+                assert!(with(|c| c.levels.is_empty() && c.denied == 0));
+            });
+
+            // the outer context is restored
+            assert!(with(|c| c.levels.len() == 1 && c.denied == 1));
+
+            // other threads have their own contexts
+            std::thread::spawn(|| assert!(with(|c| c.levels.is_empty())))
+                .join()
+                .unwrap();
+        });
+
+        assert!(with(|c| c.levels.is_empty() && c.denied == 0));
+    }
+}
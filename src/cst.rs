@@ -0,0 +1,194 @@
+//! Lossless concrete syntax tree of .msg and .srv files.
+//!
+//! While [`crate::parser`] keeps only definitions, the CST keeps every line
+//! with its whitespaces, comments and line ending, so that `cst.to_string()` reproduces the input exactly.
+//! Each definition also holds [`Expr`] parsed by [`crate::parser`],
+//! and the index of a line plus one is its line number in the file.
+
+use crate::parser::{
+    is_separator, parse_immutable_var, parse_mutable_var, parse_typename, parse_variable, Expr,
+};
+use nom::{branch::alt, character::complete::space1, error::VerboseError, Finish};
+use std::{error::Error, fmt::Display};
+
+/// Lines of a .msg or .srv file.
+#[derive(Debug)]
+pub struct Cst<'a> {
+    pub lines: Vec<Line<'a>>,
+}
+
+/// A line of a .msg or .srv file.
+#[derive(Debug)]
+pub struct Line<'a> {
+    /// Leading whitespaces.
+    pub indent: &'a str,
+
+    pub kind: LineKind<'a>,
+
+    /// Whitespaces between the definition or the separator and the comment or the line ending.
+    pub padding: &'a str,
+
+    /// Comment including `#`.
+    pub comment: Option<&'a str>,
+
+    /// `\n`, `\r\n`, or empty at the end of the file.
+    pub line_ending: &'a str,
+}
+
+#[derive(Debug)]
+pub enum LineKind<'a> {
+    /// Empty or comment-only line.
+    Blank,
+
+    /// `---` of .srv files.
+    Separator,
+
+    /// A field or a constant.
+    Definition(Box<Definition<'a>>),
+}
+
+/// A field or a constant as written in the file.
+#[derive(Debug)]
+pub struct Definition<'a> {
+    /// Type such as `int32`, `geometry_msgs/Point[<=3]` or `string<=10`.
+    pub type_name: &'a str,
+
+    /// Whitespaces between the type and the name.
+    pub space: &'a str,
+
+    pub name: &'a str,
+
+    /// ` = 1` of constants or ` 1` of default values. This is empty for fields without default values.
+    pub value: &'a str,
+
+    pub expr: Expr,
+}
+
+/// Parse a .msg or .srv file into the CST.
+/// Separators are accepted in any file, and errors have the line number.
+pub fn parse_cst(input: &str) -> Result<Cst<'_>, Box<dyn Error>> {
+    let mut lines = Vec::new();
+    for (n, text) in input.split_inclusive('\n').enumerate() {
+        let line = parse_line(text)
+            .ok_or_else(|| format!("line {}: invalid definition: {}", n + 1, text.trim_end()))?;
+        lines.push(line);
+    }
+
+    Ok(Cst { lines })
+}
+
+fn parse_line(text: &str) -> Option<Line<'_>> {
+    let content = text.trim_end_matches(['\r', '\n']);
+    let line_ending = &text[content.len()..];
+
+    let body = content.trim_start_matches([' ', '\t']);
+    let indent = &content[..content.len() - body.len()];
+
+    let (kind, rest) = if body.is_empty() || body.starts_with('#') {
+        (LineKind::Blank, body)
+    } else if is_separator(body) {
+        (LineKind::Separator, &body["---".len()..])
+    } else {
+        let (_, expr) = parse_variable(body).finish().ok()?;
+        let (definition, rest) = parse_definition(body, expr)?;
+        (LineKind::Definition(Box::new(definition)), rest)
+    };
+
+    let comment_start = rest.find('#').unwrap_or(rest.len());
+    let (padding, comment) = rest.split_at(comment_start);
+    if !padding.trim().is_empty() {
+        return None;
+    }
+
+    Some(Line {
+        indent,
+        kind,
+        padding,
+        comment: Some(comment).filter(|c| !c.is_empty()),
+        line_ending,
+    })
+}
+
+/// Split a definition into slices, and return the rest containing the padding and the comment.
+fn parse_definition(body: &str, expr: Expr) -> Option<(Definition<'_>, &str)> {
+    let (rest, _) = parse_typename(body).ok()?;
+    let type_name = &body[..body.len() - rest.len()];
+
+    let (rest, space) = space1::<_, VerboseError<&str>>(rest).ok()?;
+
+    let (after, _) = alt((parse_immutable_var, parse_mutable_var))(rest).ok()?;
+    let name_len = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    let name = &rest[..name_len];
+
+    // trailing whitespaces of the value belong to the padding
+    let value = rest[name_len..rest.len() - after.len()].trim_end_matches([' ', '\t']);
+    let rest = &rest[name_len + value.len()..];
+
+    Some((
+        Definition {
+            type_name,
+            space,
+            name,
+            value,
+            expr,
+        },
+        rest,
+    ))
+}
+
+impl Display for Cst<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in self.lines.iter() {
+            write!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Line<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.indent)?;
+        match &self.kind {
+            LineKind::Blank => (),
+            LineKind::Separator => write!(f, "---")?,
+            LineKind::Definition(d) => {
+                write!(f, "{}{}{}{}", d.type_name, d.space, d.name, d.value)?
+            }
+        }
+        write!(
+            f,
+            "{}{}{}",
+            self.padding,
+            self.comment.unwrap_or(""),
+            self.line_ending
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cst, LineKind};
+
+    #[test]
+    fn test_round_trip() {
+        let input = "# header\r\n  int32  a   # comment\nstring<=5 S = 'x' \n\t\nfloat64[<=3] b [1.0, 2.0]\n --- # sep\nuint8 c";
+        let cst = parse_cst(input).unwrap();
+        assert_eq!(cst.to_string(), input);
+        let definitions = cst
+            .lines
+            .iter()
+            .filter(|line| matches!(line.kind, LineKind::Definition(_)));
+        assert_eq!(definitions.count(), 4);
+        assert!(matches!(cst.lines[5].kind, LineKind::Separator));
+
+        let LineKind::Definition(d) = &cst.lines[1].kind else {
+            unreachable!()
+        };
+        assert_eq!((d.type_name, d.name, d.value), ("int32", "a", ""));
+        assert_eq!(cst.lines[1].comment, Some("# comment"));
+
+        assert!(parse_cst("int32 a\n!!\n").is_err());
+    }
+}
@@ -0,0 +1,232 @@
+//! Default values of fields in definitions.
+//!
+//! ```text
+//! int32 x 42
+//! string s "hello"
+//! float64[2] gains [0.5, 1.0]
+//! ```
+//!
+//! `new()`, and so `Default`, set fields having default values to the values after the C initializer,
+//! which are primitives, strings and static arrays of primitives.
+//! The C initializer of rosidl sets the same values, but they are set again because changing only default values
+//! keeps the layouts, so the linked C library may have been built from another revision of the definitions.
+//! Packed messages have no C initializer, so only these statements set their default values.
+//! Strings are allocated by `assign()`, and `new()` fails if the allocation fails.
+//! Other fields, such as sequences, keep the values set by the C initializer of rosidl,
+//! and so do fields whose values do not match their types, which are reported as `invalid-defaults`.
+//! Values of sequences of primitives are checked too, including their bounds like `int32[<=2] xs [1, 2, 3]`.
+
+use crate::{
+    generator::{gen_char_value, gen_primitives, FallibleApi},
+    parser::{ArrayInfo, Expr, TypeName, Value, ValueType},
+    warnings::{self, Warning},
+};
+use std::error::Error;
+
+/// Statements setting fields of `msg`, whose type is `struct_name`, to their default values.
+/// Values not matching the types are reported as `invalid-defaults`.
+pub fn gen_set_defaults(
+    struct_name: &str,
+    exprs: &[Expr],
+    fallible_api: FallibleApi,
+) -> Vec<String> {
+    let mut statements = Vec::new();
+    for expr in exprs.iter() {
+        let Expr::Variable {
+            type_name,
+            var_name,
+            value: Some(ValueType::Default(value)),
+            ..
+        } = expr
+        else {
+            continue;
+        };
+
+        // failure of allocating a string of the field
+        let allocation = fallible_api.err(&format!(
+            "Allocation {{ field: Some(\"{struct_name}::{var_name}\") }}"
+        ));
+        match gen_set_default(type_name, &crate::mangle(var_name), value, &allocation) {
+            Ok(Some(statement)) => statements.push(statement),
+            Ok(None) => (),
+            Err(e) => warnings::warn(
+                Warning::InvalidDefaults,
+                &format!("{struct_name}::{var_name}: {e}"),
+            ),
+        }
+    }
+    statements
+}
+
+/// Statement setting `field` of `msg` to `value`, or `None` if the field is left to the C initializer.
+/// Statements of strings return `allocation` if the allocation fails.
+fn gen_set_default(
+    type_name: &TypeName,
+    field: &str,
+    value: &Value,
+    allocation: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let statement = match type_name {
+        TypeName::Type {
+            type_name,
+            array_info,
+        } => {
+            let Some(ty) = gen_primitives(type_name) else {
+                return Ok(None);
+            };
+            match (array_info, value) {
+                (ArrayInfo::NotArray, _) => {
+                    let v = gen_primitive_value(type_name, ty, value)?;
+                    format!("msg.{field} = {v};")
+                }
+                (ArrayInfo::Static(n), Value::Array(values)) => {
+                    if values.len() != *n {
+                        return Err(format!("{} elements for [{n}]", values.len()).into());
+                    }
+                    let values = values
+                        .iter()
+                        .map(|v| gen_primitive_value(type_name, ty, v))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    format!("msg.{field} = [{}];", values.join(", "))
+                }
+                // sequences are left to the C initializer, but their values are checked in the same way
+                (ArrayInfo::Limited(n), Value::Array(values)) if values.len() > *n => {
+                    return Err(format!("{} elements exceed [<={n}]", values.len()).into());
+                }
+                (ArrayInfo::Limited(_) | ArrayInfo::Dynamic, Value::Array(values)) => {
+                    for v in values.iter() {
+                        gen_primitive_value(type_name, ty, v)?;
+                    }
+                    return Ok(None);
+                }
+                _ => return Err(format!("{value} is not an array").into()),
+            }
+        }
+        TypeName::String(ArrayInfo::NotArray) => {
+            let Value::String(s) = value else {
+                return Err(format!("{value} is not a string").into());
+            };
+            format!("if !msg.{field}.assign({s:?}) {{ return {allocation}; }}")
+        }
+        TypeName::LimitedString {
+            size,
+            array_info: ArrayInfo::NotArray,
+        } => {
+            let Value::String(s) = value else {
+                return Err(format!("{value} is not a string").into());
+            };
+            if s.len() > *size {
+                return Err(format!("{} bytes exceed string<={size}", s.len()).into());
+            }
+            format!("if !msg.{field}.assign({s:?}) {{ return {allocation}; }}")
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(statement))
+}
+
+/// Literal of `value` typed by the suffix of `ty`, which is the Rust type of the primitive `type_name`.
+pub fn gen_primitive_value(
+    type_name: &str,
+    ty: &str,
+    value: &Value,
+) -> Result<String, Box<dyn Error>> {
+    let (min, max) = match ty {
+        "bool" => {
+            return match value {
+                Value::Bool(b) => Ok(b.to_string()),
+                _ => Err(format!("{value} is not a bool").into()),
+            }
+        }
+        "f32" | "f64" => {
+            return match value {
+                Value::Float(n) => Ok(format!("{n}{ty}")),
+                Value::Int(n) => Ok(format!("{n}{ty}")),
+                Value::Uint(n) => Ok(format!("{n}{ty}")),
+                _ => Err(format!("{value} is not a number").into()),
+            }
+        }
+        "i8" => (i8::MIN as i128, i8::MAX as i128),
+        "u8" => (0, u8::MAX as i128),
+        "i16" => (i16::MIN as i128, i16::MAX as i128),
+        "u16" => (0, u16::MAX as i128),
+        "i32" => (i32::MIN as i128, i32::MAX as i128),
+        "u32" => (0, u32::MAX as i128),
+        "i64" => (i64::MIN as i128, i64::MAX as i128),
+        _ => (0, u64::MAX as i128),
+    };
+
+    let n = match value {
+        Value::Int(n) => *n as i128,
+        Value::Uint(n) => *n as i128,
+        // characters like 'a' of char and byte
+        Value::String(c) if type_name == "char" || type_name == "byte" => {
+            return gen_char_value(type_name, c)
+        }
+        _ => return Err(format!("{value} is not an integer").into()),
+    };
+    if n < min || n > max {
+        return Err(format!("{n} is out of the range of {type_name}").into());
+    }
+    Ok(format!("{n}{ty}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gen_set_default, gen_set_defaults};
+    use crate::{
+        generator::FallibleApi,
+        parser::{self, Expr, ValueType},
+    };
+    use nom::Finish;
+
+    #[test]
+    fn test_set_defaults() {
+        let input = "int32 x 42\nfloat64 gain 1\nstring s \"hello\"\nstring<=3 t abc\nchar c 'a'\nuint8[2] bytes [1, 2]\nint32[] xs [1]\nint32[<=2] ys [1, 2]\nint32 MAX=3\n";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        assert_eq!(
+            gen_set_defaults("Example", &exprs, FallibleApi::Option),
+            [
+                "msg.x = 42i32;",
+                "msg.gain = 1f64;",
+                "if !msg.s.assign(\"hello\") { return None; }",
+                "if !msg.t.assign(\"abc\") { return None; }",
+                "msg.c = b'a' as i8;",
+                "msg.bytes = [1u8, 2u8];",
+            ]
+        );
+
+        for input in [
+            "uint8 x 256\n",
+            "int32 x true\n",
+            "string<=2 s abc\n",
+            "int32[2] xs [1]\n",
+            "int32[<=2] xs [1, 2, 3]\n",
+            "uint8[] xs [1, 256]\n",
+            "int32[<=2] xs 1\n",
+        ] {
+            let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+            let Expr::Variable {
+                type_name,
+                value: Some(ValueType::Default(value)),
+                ..
+            } = &exprs[0]
+            else {
+                panic!("{input}");
+            };
+            assert!(
+                gen_set_default(type_name, "x", value, "None").is_err(),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_allocation_error() {
+        let (_, exprs) = parser::parse_msg("string s \"hello\"\n").finish().unwrap();
+        assert_eq!(
+            gen_set_defaults("Example", &exprs, FallibleApi::Result),
+            ["if !msg.s.assign(\"hello\") { return Err(super::super::error::MsgError::Allocation { field: Some(\"Example::s\") }); }"]
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! Messages printed during generation.
+//!
+//! By default, messages are printed for humans.
+//! `--message-format json` prints one JSON object per line instead, like `cargo --message-format json`,
+//! and each object has `reason` which is one of `diagnostic`, `generated-file`, `fetch`, `link-check`
+//! and `generation-finished`.
+
+use crate::context;
+use serde::Serialize;
+use std::path::Path;
+
+/// Format of messages.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MessageFormat {
+    /// Human readable text.
+    #[default]
+    Human,
+
+    /// A JSON object per line.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum Message<'a> {
+    /// A warning or an error.
+    /// `code` is the name of the warning, and `file` is the definition causing the error.
+    Diagnostic {
+        level: Severity,
+        code: Option<&'a str>,
+        message: &'a str,
+        file: Option<&'a Path>,
+    },
+
+    /// A file is written.
+    GeneratedFile { path: &'a Path },
+
+    /// A git repository is being fetched.
+    Fetch { spec: &'a str },
+
+    /// Result of `--check-links`. `missing` is empty if all libraries are found.
+    LinkCheck {
+        package: &'a str,
+        missing: &'a [String],
+    },
+
+    /// Generation is finished. This is printed only in JSON.
+    GenerationFinished { success: bool },
+}
+
+/// Print the message in the format of the context.
+pub fn report(msg: Message) {
+    print(context::with(|c| c.message_format), msg);
+}
+
+/// Print the message in `format`.
+pub fn print(format: MessageFormat, msg: Message) {
+    if format == MessageFormat::Json {
+        if let Ok(json) = serde_json::to_string(&msg) {
+            println!("{json}");
+        }
+        return;
+    }
+
+    match msg {
+        Message::Diagnostic {
+            level,
+            code: Some(code),
+            message,
+            ..
+        } => match level {
+            Severity::Warning => println!("Warning: {message} [{code}]"),
+            Severity::Error => println!("Error: {message} [{code}]"),
+        },
+        Message::Diagnostic {
+            code: None,
+            message,
+            ..
+        } => eprintln!("{message}"),
+        Message::GeneratedFile { path } => println!("generating: {}", path.display()),
+        Message::Fetch { spec } => println!("fetching: {spec}"),
+        Message::LinkCheck { package, missing } => {
+            if missing.is_empty() {
+                println!("link ok: {package}");
+            } else {
+                println!("link error: {package}: not found {}", missing.join(", "));
+            }
+        }
+        Message::GenerationFinished { .. } => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Message, Severity};
+    use std::path::Path;
+
+    #[test]
+    fn test_json() {
+        let json = |msg: Message| serde_json::to_string(&msg).unwrap();
+
+        assert_eq!(
+            json(Message::Diagnostic {
+                level: Severity::Warning,
+                code: Some("invalid-defaults"),
+                message: "x",
+                file: Some(Path::new("my_pkg/msg/Example.msg")),
+            }),
+            r#"{"reason":"diagnostic","level":"warning","code":"invalid-defaults","message":"x","file":"my_pkg/msg/Example.msg"}"#
+        );
+        assert_eq!(
+            json(Message::GeneratedFile {
+                path: Path::new("generated/mod.rs")
+            }),
+            r#"{"reason":"generated-file","path":"generated/mod.rs"}"#
+        );
+        assert_eq!(
+            json(Message::LinkCheck {
+                package: "my_pkg",
+                missing: &["libmy_pkg__rosidl_generator_c.so".to_string()],
+            }),
+            r#"{"reason":"link-check","package":"my_pkg","missing":["libmy_pkg__rosidl_generator_c.so"]}"#
+        );
+        assert_eq!(
+            json(Message::GenerationFinished { success: false }),
+            r#"{"reason":"generation-finished","success":false}"#
+        );
+    }
+}
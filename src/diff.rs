@@ -0,0 +1,370 @@
+//! Field-by-field differences of messages.
+//!
+//! `--diff` generates `{package}/diff.rs` having `FieldDiff`, and `diff()` of each message,
+//! which reports every field of `self` differing from `other` with the values formatted by `Debug`.
+//! Nested messages and arrays are compared element by element, so that paths are like `points[2].x`,
+//! and differing lengths of sequences are reported as `points.len()`.
+//!
+//! ```ignore
+//! for d in expected.diff(&actual) {
+//!     println!("{d}"); // pose.position.x: 1.0 != 1.5
+//! }
+//! ```
+//!
+//! NaNs equal each other, so that messages do not differ from their copies.
+//! Messages having fields of types of common_interfaces of safe_drive do not have `diff()`,
+//! because the fields of the types are unknown.
+
+use crate::{
+    generator::gen_primitives,
+    parser::{ArrayInfo, TypeName},
+};
+use std::{borrow::Cow, collections::VecDeque};
+
+/// Generate the content of `diff.rs`.
+/// `String`, `Vec` and `format!` are imported from `alloc` if `no_std` is true.
+pub fn gen_diff_module(no_std: bool) -> String {
+    let import = if no_std {
+        "\nuse alloc::{format, string::String, vec::Vec};\n"
+    } else {
+        ""
+    };
+
+    format!(
+        "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+//! Differences of fields reported by `diff()` of messages.
+{import}{DIFF_MODULE}"
+    )
+}
+
+const DIFF_MODULE: &str = r#"
+/// Field differing between two messages, whose values are formatted by `Debug`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    /// Path of the field, such as `pose.position.x`, `points[2].x` and `points.len()`.
+    pub path: String,
+    pub left: String,
+    pub right: String,
+}
+
+impl core::fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}: {} != {}", self.path, self.left, self.right)
+    }
+}
+
+/// Values compared by `diff()`.
+pub trait Comparable: core::fmt::Debug {
+    fn same(&self, other: &Self) -> bool;
+}
+
+macro_rules! impl_comparable {
+    ($($ty:ty),*) => {
+        $(
+            impl Comparable for $ty {
+                fn same(&self, other: &Self) -> bool {
+                    self == other
+                }
+            }
+        )*
+    };
+}
+
+impl_comparable!(bool, i8, u8, i16, u16, i32, u32, i64, u64, String);
+
+impl Comparable for f32 {
+    /// NaNs equal each other.
+    fn same(&self, other: &Self) -> bool {
+        self == other || (self.is_nan() && other.is_nan())
+    }
+}
+
+impl Comparable for f64 {
+    /// NaNs equal each other.
+    fn same(&self, other: &Self) -> bool {
+        self == other || (self.is_nan() && other.is_nan())
+    }
+}
+
+/// Prefix of paths of fields of the nested message `field`.
+pub fn nested(prefix: &str, field: &str) -> String {
+    format!("{prefix}{field}.")
+}
+
+/// Prefix of paths of fields of the element `index` of `field`.
+pub fn nested_element(prefix: &str, field: &str, index: usize) -> String {
+    format!("{prefix}{field}[{index}].")
+}
+
+/// Push `field` to `diffs` if `left` and `right` differ.
+pub fn compare<T: Comparable>(
+    diffs: &mut Vec<FieldDiff>,
+    prefix: &str,
+    field: &str,
+    left: &T,
+    right: &T,
+) {
+    if !left.same(right) {
+        diffs.push(FieldDiff {
+            path: format!("{prefix}{field}"),
+            left: format!("{left:?}"),
+            right: format!("{right:?}"),
+        });
+    }
+}
+
+/// Push the length of `field` to `diffs` if `left` and `right` differ.
+pub fn compare_len(diffs: &mut Vec<FieldDiff>, prefix: &str, field: &str, left: usize, right: usize) {
+    if left != right {
+        diffs.push(FieldDiff {
+            path: format!("{prefix}{field}.len()"),
+            left: format!("{left}"),
+            right: format!("{right}"),
+        });
+    }
+}
+
+/// Push the length of `field` and its elements to `diffs` if they differ.
+/// Elements beyond the shorter one are not compared.
+pub fn compare_elements<T: Comparable>(
+    diffs: &mut Vec<FieldDiff>,
+    prefix: &str,
+    field: &str,
+    left: impl ExactSizeIterator<Item = T>,
+    right: impl ExactSizeIterator<Item = T>,
+) {
+    compare_len(diffs, prefix, field, left.len(), right.len());
+    for (i, (l, r)) in left.zip(right).enumerate() {
+        compare(diffs, prefix, &format!("{field}[{i}]"), &l, &r);
+    }
+}
+"#;
+
+/// Field of a message compared by `diff()`.
+pub struct DiffField<'a> {
+    /// Name of the field, which is also the last component of paths.
+    pub var_name: &'a str,
+
+    pub type_name: &'a TypeName,
+}
+
+/// Kind of elements of a field.
+enum Kind {
+    Primitive,
+    String,
+
+    /// `builtin_interfaces/Time` or `Duration`, which are `sec` and `nanosec`.
+    Time,
+
+    Message,
+}
+
+impl Kind {
+    /// Statement comparing the elements `l` and `r` of nested messages, whose prefix of paths is `nested`.
+    /// `r` is a reference if the elements are messages.
+    fn compare_nested(&self, l: &str, r: &str) -> String {
+        match self {
+            Kind::Time => format!(
+                "diff::compare(diffs, &nested, \"sec\", &{l}.sec, &{r}.sec);
+diff::compare(diffs, &nested, \"nanosec\", &{l}.nanosec, &{r}.nanosec);"
+            ),
+            _ => format!("{l}.diff_into({r}, &nested, diffs);"),
+        }
+    }
+}
+
+/// Indent every line of `code` by `n` spaces.
+fn indent(code: &str, n: usize) -> String {
+    code.lines()
+        .map(|line| format!("{:n$}{line}\n", ""))
+        .collect()
+}
+
+/// Generate `diff()` and `diff_into()` of `struct_name`.
+pub fn gen_diff(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    struct_name: &str,
+    fields: &[DiffField<'_>],
+    no_std: bool,
+) {
+    let alloc = if no_std { "alloc" } else { "std" };
+
+    let mut statements = String::new();
+    for DiffField {
+        var_name,
+        type_name,
+    } in fields.iter()
+    {
+        let field = crate::mangle(var_name);
+        let (kind, array_info) = match type_name {
+            TypeName::Type {
+                type_name,
+                array_info,
+            } => match gen_primitives(type_name) {
+                Some(_) => (Kind::Primitive, array_info),
+                None => (Kind::Message, array_info),
+            },
+            TypeName::ScopedType {
+                scope, array_info, ..
+            } if scope == "builtin_interfaces" => (Kind::Time, array_info),
+            TypeName::ScopedType { array_info, .. } => (Kind::Message, array_info),
+            TypeName::String(array_info) | TypeName::LimitedString { array_info, .. } => {
+                (Kind::String, array_info)
+            }
+        };
+
+        // iterator of elements of `{msg}.{field}`
+        let iter = |msg: &str| match array_info {
+            ArrayInfo::Static(_) => format!("{msg}.{field}.iter()"),
+            _ => format!("{msg}.{field}.as_slice().iter()"),
+        };
+
+        let statement = match (&kind, array_info) {
+            (Kind::Primitive, ArrayInfo::NotArray) => {
+                format!("diff::compare(diffs, prefix, \"{var_name}\", &self.{field}, &other.{field});")
+            }
+            (Kind::String, ArrayInfo::NotArray) => format!(
+                "diff::compare(diffs, prefix, \"{var_name}\", &self.{field}.get_string(), &other.{field}.get_string());"
+            ),
+            (_, ArrayInfo::NotArray) => {
+                let r = match kind {
+                    Kind::Message => format!("&other.{field}"),
+                    _ => format!("other.{field}"),
+                };
+                format!(
+                    "let nested = diff::nested(prefix, \"{var_name}\");\n{}",
+                    kind.compare_nested(&format!("self.{field}"), &r)
+                )
+            }
+            (Kind::Primitive, _) => format!(
+                "diff::compare_elements(diffs, prefix, \"{var_name}\", {}.copied(), {}.copied());",
+                iter("self"),
+                iter("other")
+            ),
+            (Kind::String, _) => format!(
+                "diff::compare_elements(
+    diffs,
+    prefix,
+    \"{var_name}\",
+    {}.map(|s| s.get_string()),
+    {}.map(|s| s.get_string()),
+);",
+                iter("self"),
+                iter("other")
+            ),
+            _ => {
+                let len = match array_info {
+                    ArrayInfo::Static(_) => String::new(),
+                    _ => format!(
+                        "diff::compare_len(diffs, prefix, \"{var_name}\", self.{field}.as_slice().len(), other.{field}.as_slice().len());\n"
+                    ),
+                };
+                format!(
+                    "{len}for (i, (l, r)) in {}.zip({}).enumerate() {{
+    let nested = diff::nested_element(prefix, \"{var_name}\", i);
+{}}}",
+                    iter("self"),
+                    iter("other"),
+                    indent(&kind.compare_nested("l", "r"), 4)
+                )
+            }
+        };
+        statements.push_str(&indent(&statement, 8));
+    }
+
+    let (unused, body) = if fields.is_empty() {
+        ("_", String::new())
+    } else {
+        (
+            "",
+            format!("        use super::super::diff;\n\n{statements}"),
+        )
+    };
+
+    lines.push_back(
+        format!(
+            "
+impl {struct_name} {{
+    /// Fields of `self` differing from `other`, where nested messages and arrays are compared element by element.
+    pub fn diff(&self, other: &Self) -> {alloc}::vec::Vec<super::super::diff::FieldDiff> {{
+        let mut diffs = {alloc}::vec::Vec::new();
+        self.diff_into(other, \"\", &mut diffs);
+        diffs
+    }}
+
+    /// Push fields of `self` differing from `other` to `diffs`, whose paths are prefixed by `prefix`.
+    pub fn diff_into(
+        &self,
+        {unused}other: &Self,
+        {unused}prefix: &str,
+        {unused}diffs: &mut {alloc}::vec::Vec<super::super::diff::FieldDiff>,
+    ) {{
+{body}    }}
+}}"
+        )
+        .into(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gen_diff, DiffField};
+    use crate::parser::{self, Expr};
+    use nom::Finish;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_diff() {
+        let input = "float64 x\nint32[] xs\nstring name\nstring[2] names\nPoint p\nPoint[] ps\nbuiltin_interfaces/Time stamp\n";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let fields: Vec<DiffField> = exprs
+            .iter()
+            .map(|expr| {
+                let Expr::Variable {
+                    type_name,
+                    var_name,
+                    ..
+                } = expr
+                else {
+                    panic!();
+                };
+                DiffField {
+                    var_name,
+                    type_name,
+                }
+            })
+            .collect();
+
+        let mut lines = VecDeque::new();
+        gen_diff(&mut lines, "Example", &fields, false);
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains(
+            "    pub fn diff(&self, other: &Self) -> std::vec::Vec<super::super::diff::FieldDiff> {\n"
+        ));
+        assert!(code.contains("        diff::compare(diffs, prefix, \"x\", &self.x, &other.x);\n"));
+        assert!(code.contains("        diff::compare_elements(diffs, prefix, \"xs\", self.xs.as_slice().iter().copied(), other.xs.as_slice().iter().copied());\n"));
+        assert!(code.contains("        diff::compare(diffs, prefix, \"name\", &self.name.get_string(), &other.name.get_string());\n"));
+        assert!(code.contains("            self.names.iter().map(|s| s.get_string()),\n"));
+        assert!(code.contains(
+            "        let nested = diff::nested(prefix, \"p\");
+        self.p.diff_into(&other.p, &nested, diffs);
+"
+        ));
+        assert!(code.contains(
+            "        diff::compare_len(diffs, prefix, \"ps\", self.ps.as_slice().len(), other.ps.as_slice().len());
+        for (i, (l, r)) in self.ps.as_slice().iter().zip(other.ps.as_slice().iter()).enumerate() {
+            let nested = diff::nested_element(prefix, \"ps\", i);
+            l.diff_into(r, &nested, diffs);
+        }
+"
+        ));
+        assert!(code.contains(
+            "        diff::compare(diffs, &nested, \"nanosec\", &self.stamp.nanosec, &other.stamp.nanosec);\n"
+        ));
+
+        let mut lines = VecDeque::new();
+        gen_diff(&mut lines, "Empty", &[], true);
+        assert!(lines[0]
+            .contains("        _diffs: &mut alloc::vec::Vec<super::super::diff::FieldDiff>,\n"));
+    }
+}
@@ -0,0 +1,186 @@
+//! Example nodes of generated crates.
+//!
+//! `--emit-examples` generates `{package}/examples` having a publisher and a subscriber of each message,
+//! and a server and a client of each service, using safe_drive.
+//! Topics and services are named after the types, so each pair can be run as a smoke test.
+//!
+//! ```text
+//! $ cd generated/my_module
+//! $ cargo run --example example_subscriber &
+//! $ cargo run --example example_publisher
+//! ```
+
+use crate::generator::FallibleApi;
+use convert_case::{Case, Casing};
+use std::path::{Path, PathBuf};
+
+/// Generate examples of `pkg/ext/type_name` in `{pkg_dir}/examples`.
+pub fn gen_examples(
+    pkg_dir: &Path,
+    pkg: &str,
+    ext: &str,
+    type_name: &str,
+    fallible_api: FallibleApi,
+) -> Vec<(PathBuf, String)> {
+    let snake = type_name.to_case(Case::Snake);
+    let dir = pkg_dir.join("examples");
+    let ty = format!("{pkg}::{ext}::{type_name}");
+    let to_option = fallible_api.to_option();
+
+    let examples = if ext == "msg" {
+        [
+            ("publisher", gen_publisher(&snake, &ty, to_option)),
+            ("subscriber", gen_subscriber(&snake, &ty)),
+        ]
+    } else {
+        [
+            ("server", gen_server(&snake, &ty)),
+            ("client", gen_client(&snake, &ty, to_option)),
+        ]
+    };
+
+    examples
+        .into_iter()
+        .map(|(role, body)| {
+            let name = format!("{snake}_{role}");
+            let contents = format!(
+                "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+//! Example of a {role} of `{ty}`.
+
+use safe_drive::{{context::Context, error::DynError}};
+
+fn main() -> Result<(), DynError> {{
+    let ctx = Context::new()?;
+    let node = ctx.create_node(\"{name}\", None, Default::default())?;
+{body}}}
+"
+            );
+            (dir.join(format!("{name}.rs")), contents)
+        })
+        .collect()
+}
+
+/// `to_option` converts the result of `new()` to `Option`.
+fn gen_publisher(snake: &str, ty: &str, to_option: &str) -> String {
+    format!(
+        "    let publisher = node.create_publisher::<{ty}>(\"{snake}\", None)?;
+
+    let msg = {ty}::new(){to_option}.ok_or(\"failed to allocate a message\")?;
+    loop {{
+        publisher.send(&msg)?;
+        println!(\"sent\");
+        std::thread::sleep(std::time::Duration::from_secs(1));
+    }}
+"
+    )
+}
+
+fn gen_subscriber(snake: &str, ty: &str) -> String {
+    format!(
+        "    let subscriber = node.create_subscriber::<{ty}>(\"{snake}\", None)?;
+
+    let mut selector = ctx.create_selector()?;
+    selector.add_subscriber(subscriber, Box::new(|_msg| println!(\"received\")));
+    loop {{
+        selector.wait()?;
+    }}
+"
+    )
+}
+
+fn gen_server(snake: &str, ty: &str) -> String {
+    format!(
+        "    let server = node.create_server::<{ty}>(\"{snake}\", None)?;
+
+    let mut selector = ctx.create_selector()?;
+    selector.add_server(
+        server,
+        Box::new(|_request, _header| {{
+            println!(\"received a request\");
+            {ty}Response::new().unwrap()
+        }}),
+    );
+    loop {{
+        selector.wait()?;
+    }}
+"
+    )
+}
+
+fn gen_client(snake: &str, ty: &str, to_option: &str) -> String {
+    format!(
+        "    let mut client = node.create_client::<{ty}>(\"{snake}\", None)?;
+
+    let mut selector = ctx.create_selector()?;
+    loop {{
+        let request = {ty}Request::new(){to_option}.ok_or(\"failed to allocate a request\")?;
+        let receiver = client.send(&request)?;
+        client = match receiver.recv_timeout(std::time::Duration::from_secs(1), &mut selector) {{
+            safe_drive::RecvResult::Ok((client, _response, _header)) => {{
+                println!(\"received a response\");
+                client
+            }}
+            safe_drive::RecvResult::RetryLater(receiver) => {{
+                println!(\"timed out\");
+                receiver.give_up()
+            }}
+            safe_drive::RecvResult::Err(e) => return Err(e),
+        }};
+    }}
+"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::gen_examples;
+    use crate::generator::FallibleApi;
+    use std::path::Path;
+
+    #[test]
+    fn test_examples() {
+        let files = gen_examples(
+            Path::new("out/my_pkg"),
+            "my_pkg",
+            "msg",
+            "PointCloud",
+            FallibleApi::Result,
+        );
+        let paths: Vec<_> = files.iter().map(|(path, _)| path.as_path()).collect();
+        assert_eq!(
+            paths,
+            [
+                Path::new("out/my_pkg/examples/point_cloud_publisher.rs"),
+                Path::new("out/my_pkg/examples/point_cloud_subscriber.rs"),
+            ]
+        );
+        let publisher = &files[0].1;
+        assert!(publisher.contains(
+            "    let node = ctx.create_node(\"point_cloud_publisher\", None, Default::default())?;\n"
+        ));
+        assert!(publisher.contains(
+            "node.create_publisher::<my_pkg::msg::PointCloud>(\"point_cloud\", None)?;\n"
+        ));
+        assert!(publisher.contains("my_pkg::msg::PointCloud::new().ok().ok_or("));
+        assert!(files[1].1.contains(
+            "node.create_subscriber::<my_pkg::msg::PointCloud>(\"point_cloud\", None)?;\n"
+        ));
+
+        let files = gen_examples(
+            Path::new("out/my_pkg"),
+            "my_pkg",
+            "srv",
+            "AddTwoInts",
+            FallibleApi::Option,
+        );
+        let (server, client) = (&files[0], &files[1]);
+        assert!(server.0.ends_with("examples/add_two_ints_server.rs"));
+        assert!(server
+            .1
+            .contains("node.create_server::<my_pkg::srv::AddTwoInts>(\"add_two_ints\", None)?;\n"));
+        assert!(client.0.ends_with("examples/add_two_ints_client.rs"));
+        assert!(client
+            .1
+            .contains("let request = my_pkg::srv::AddTwoIntsRequest::new().ok_or("));
+    }
+}
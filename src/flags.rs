@@ -0,0 +1,246 @@
+//! Types of bit flags defined by groups of constants.
+//!
+//! ```text
+//! uint8 ERROR_OVERHEAT = 1
+//! uint8 ERROR_LOW_BATTERY = 2
+//! uint8 ERROR_LOST = 4
+//! uint8 errors
+//! ```
+//!
+//! `--bitflags` generates `ExampleErrorFlags` of the constants above,
+//! which are two or more integer constants of the same type and the same prefix before the first `_`,
+//! and whose values are distinct powers of two.
+//! The type has the flags as associated constants like `ExampleErrorFlags::OVERHEAT`,
+//! `from_bits()` rejecting unknown bits, `contains()` and the operators of bits,
+//! so that fields like `errors` are checked by `ExampleErrorFlags::from_bits(msg.errors)`.
+//! The constants are still generated for compatibility.
+
+use crate::{
+    generator::gen_primitives,
+    parser::{ArrayInfo, Expr, TypeName, Value, ValueType},
+};
+use convert_case::{Case, Casing};
+use std::{borrow::Cow, collections::BTreeMap, collections::VecDeque};
+
+/// Constants of a group of flags, which are the Rust type, and the names and values of the flags.
+struct Group<'a> {
+    ty: &'a str,
+    flags: Vec<(&'a str, u64)>,
+}
+
+/// Groups of flags of `exprs` by their prefixes.
+fn flag_groups(exprs: &[Expr]) -> BTreeMap<&str, Group<'_>> {
+    let mut groups: BTreeMap<&str, Option<Group>> = BTreeMap::new();
+    for expr in exprs.iter() {
+        let Expr::Variable {
+            type_name:
+                TypeName::Type {
+                    type_name,
+                    array_info: ArrayInfo::NotArray,
+                },
+            var_name,
+            value: Some(ValueType::Const(value)),
+            ..
+        } = expr
+        else {
+            continue;
+        };
+        let Some((prefix, _)) = var_name.split_once('_') else {
+            continue;
+        };
+        let Some(ty) = gen_primitives(type_name).filter(|ty| ty.starts_with(['i', 'u'])) else {
+            continue;
+        };
+
+        // a group having a constant which is not a flag is not flags
+        let value = match value {
+            Value::Uint(n) => Some(*n),
+            Value::Int(n) => u64::try_from(*n).ok(),
+            _ => None,
+        };
+        let group = groups.entry(prefix).or_insert_with(|| {
+            Some(Group {
+                ty,
+                flags: Vec::new(),
+            })
+        });
+        match (group.as_mut(), value) {
+            (Some(g), Some(n))
+                if g.ty == ty && n.is_power_of_two() && g.flags.iter().all(|(_, m)| *m != n) =>
+            {
+                g.flags.push((var_name, n))
+            }
+            _ => *group = None,
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter_map(|(prefix, group)| Some((prefix, group.filter(|g| g.flags.len() >= 2)?)))
+        .collect()
+}
+
+/// Generate types of bit flags of `struct_name` for groups of constants of `exprs`.
+/// `const_prefix` is the path to the constants, such as `"ExampleRequest::"` for associated constants.
+pub fn gen_bitflags(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    struct_name: &str,
+    const_prefix: &str,
+    exprs: &[Expr],
+) {
+    for (prefix, Group { ty, flags }) in flag_groups(exprs) {
+        let flags_name = format!("{struct_name}{}Flags", prefix.to_case(Case::Pascal));
+
+        let mut consts = String::new();
+        let mut all = Vec::new();
+        for (var_name, _) in flags.iter() {
+            let constant = format!("{const_prefix}{}", crate::mangle(var_name));
+            let name = &var_name[prefix.len() + 1..];
+            // names like `1HZ` are not identifiers
+            let name = if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                name
+            } else {
+                var_name
+            };
+            consts.push_str(&format!(
+                "    /// `{var_name}` of the definition.\n    pub const {name}: Self = Self({constant});\n"
+            ));
+            all.push(constant);
+        }
+        let all = all.join(" | ");
+
+        lines.push_back(
+            format!(
+                "
+/// Bit flags of the constants `{prefix}_*` of `{struct_name}`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct {flags_name}(pub {ty});
+
+impl {flags_name} {{
+{consts}
+    /// All of the flags.
+    pub const ALL: Self = Self({all});
+
+    /// No flag.
+    pub const fn empty() -> Self {{
+        Self(0)
+    }}
+
+    /// Flags of `bits`, or `None` if `bits` has bits which are not the flags.
+    pub const fn from_bits(bits: {ty}) -> Option<Self> {{
+        if bits & !Self::ALL.0 == 0 {{
+            Some(Self(bits))
+        }} else {{
+            None
+        }}
+    }}
+
+    /// Flags of `bits`, where bits which are not the flags are dropped.
+    pub const fn from_bits_truncate(bits: {ty}) -> Self {{
+        Self(bits & Self::ALL.0)
+    }}
+
+    /// Bits of the flags.
+    pub const fn bits(self) -> {ty} {{
+        self.0
+    }}
+
+    /// Whether all of `other` are set.
+    pub const fn contains(self, other: Self) -> bool {{
+        self.0 & other.0 == other.0
+    }}
+
+    /// Whether any of `other` is set.
+    pub const fn intersects(self, other: Self) -> bool {{
+        self.0 & other.0 != 0
+    }}
+
+    /// Whether no flag is set.
+    pub const fn is_empty(self) -> bool {{
+        self.0 == 0
+    }}
+
+    /// Set `other`.
+    pub fn insert(&mut self, other: Self) {{
+        self.0 |= other.0;
+    }}
+
+    /// Clear `other`.
+    pub fn remove(&mut self, other: Self) {{
+        self.0 &= !other.0;
+    }}
+}}
+
+impl core::ops::BitOr for {flags_name} {{
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {{
+        Self(self.0 | rhs.0)
+    }}
+}}
+
+impl core::ops::BitOrAssign for {flags_name} {{
+    fn bitor_assign(&mut self, rhs: Self) {{
+        self.0 |= rhs.0;
+    }}
+}}
+
+impl core::ops::BitAnd for {flags_name} {{
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {{
+        Self(self.0 & rhs.0)
+    }}
+}}
+
+impl core::ops::Not for {flags_name} {{
+    type Output = Self;
+
+    fn not(self) -> Self {{
+        Self(!self.0 & Self::ALL.0)
+    }}
+}}"
+            )
+            .into(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flag_groups, gen_bitflags};
+    use crate::parser;
+    use nom::Finish;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_bitflags() {
+        let input = "
+uint8 ERROR_OVERHEAT = 1
+uint8 ERROR_LOW_BATTERY = 2
+uint8 ERROR_LOST = 0x04
+uint8 MODE_A = 1
+uint8 MODE_B = 2
+uint8 MODE_C = 3
+uint8 RATE_1HZ = 1
+uint8 RATE_2HZ = 2
+int32 LEVEL_LOW = 1
+uint8 ONLY_ONE = 1
+uint8 errors
+";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let groups: Vec<&str> = flag_groups(&exprs).into_keys().collect();
+        assert_eq!(groups, ["ERROR", "RATE"]);
+
+        let mut lines = VecDeque::new();
+        gen_bitflags(&mut lines, "Example", "", &exprs);
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("pub struct ExampleErrorFlags(pub u8);\n"));
+        assert!(code.contains("    pub const LOW_BATTERY: Self = Self(ERROR_LOW_BATTERY);\n"));
+        assert!(code.contains(
+            "    pub const ALL: Self = Self(ERROR_OVERHEAT | ERROR_LOW_BATTERY | ERROR_LOST);\n"
+        ));
+        assert!(code.contains("    pub const RATE_1HZ: Self = Self(RATE_1HZ);\n"));
+    }
+}
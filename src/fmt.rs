@@ -0,0 +1,185 @@
+//! Format .msg and .srv files in the canonical style.
+//!
+//! - types of consecutive definitions are aligned,
+//! - spaces around types, names, `=` and comments are normalized,
+//! - indentation and trailing whitespaces are removed,
+//! - consecutive empty lines are merged, and
+//! - line endings are `\n` and a file ends with `\n`.
+//!
+//! Comments are kept as they are.
+
+use crate::cst::{parse_cst, Cst, Line, LineKind};
+use std::{
+    error::Error,
+    fs::{read_to_string, write},
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+/// Format files or .msg and .srv files in directories.
+/// If `check` is true, files are not modified, and an error is returned if any file is not formatted.
+pub fn run(paths: &[String], check: bool) -> Result<(), Box<dyn Error>> {
+    let mut unformatted = 0;
+
+    for path in files(paths)? {
+        let contents = read_to_string(&path)?;
+        let cst = parse_cst(&contents).map_err(|e| format!("{}: {e}", path.display()))?;
+        let formatted = format(&cst);
+
+        if formatted == contents {
+            continue;
+        }
+
+        if check {
+            println!("not formatted: {}", path.display());
+            unformatted += 1;
+        } else {
+            println!("formatting: {}", path.display());
+            write(&path, formatted)?;
+        }
+    }
+
+    if unformatted > 0 {
+        return Err(format!("{unformatted} file(s) are not formatted").into());
+    }
+
+    Ok(())
+}
+
+fn files(paths: &[String]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    for path in paths.iter() {
+        let path = Path::new(path);
+        if !path.is_dir() {
+            files.push(path.to_path_buf());
+            continue;
+        }
+
+        for entry in WalkDir::new(path).sort_by_file_name() {
+            let entry = entry?;
+            let p = entry.path();
+            if entry.file_type().is_file()
+                && matches!(p.extension(), Some(ext) if ext == "msg" || ext == "srv")
+            {
+                files.push(p.to_path_buf());
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Format the CST in the canonical style.
+pub fn format(cst: &Cst) -> String {
+    let mut result = String::new();
+    let lines = &cst.lines;
+
+    let mut i = 0;
+    let mut empty = false;
+    while i < lines.len() {
+        // consecutive definitions are aligned
+        let block = lines[i..]
+            .iter()
+            .take_while(|line| matches!(line.kind, LineKind::Definition(_)))
+            .count();
+
+        if block > 0 {
+            let width = lines[i..i + block]
+                .iter()
+                .map(|line| type_name(line).len())
+                .max()
+                .unwrap_or(0);
+
+            for line in lines[i..i + block].iter() {
+                format_definition(&mut result, line, width);
+            }
+
+            i += block;
+            empty = false;
+            continue;
+        }
+
+        let line = &lines[i];
+        i += 1;
+
+        match (&line.kind, line.comment) {
+            (LineKind::Separator, comment) => {
+                result.push_str("---");
+                push_comment(&mut result, comment);
+            }
+            (_, Some(comment)) => result.push_str(comment.trim_end()),
+            (_, None) => {
+                // at most one empty line, and no empty line at the beginning
+                if empty || result.is_empty() {
+                    continue;
+                }
+                empty = true;
+                result.push('\n');
+                continue;
+            }
+        }
+
+        empty = false;
+        result.push('\n');
+    }
+
+    // no empty line at the end
+    while result.ends_with("\n\n") {
+        result.pop();
+    }
+
+    result
+}
+
+fn type_name(line: &Line) -> String {
+    match &line.kind {
+        LineKind::Definition(definition) => definition
+            .type_name
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+fn format_definition(result: &mut String, line: &Line, width: usize) {
+    let LineKind::Definition(definition) = &line.kind else {
+        return;
+    };
+
+    result.push_str(&format!("{:width$} {}", type_name(line), definition.name));
+
+    let value = definition.value.trim();
+    if let Some(value) = value.strip_prefix('=') {
+        result.push_str(&format!(" = {}", value.trim_start()));
+    } else if !value.is_empty() {
+        result.push_str(&format!(" {value}"));
+    }
+
+    push_comment(result, line.comment);
+    result.push('\n');
+}
+
+fn push_comment(result: &mut String, comment: Option<&str>) {
+    if let Some(comment) = comment {
+        result.push_str(&format!(" {}", comment.trim_end()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format;
+    use crate::cst::parse_cst;
+
+    #[test]
+    fn test_format() {
+        let input = "\n\n  # header  \r\nint32 a # a\nfloat64 [ <=3 ]  long_name   [1.0, 2.0]\nuint8 C  =  1\n\n\n---   # sep\nstring b\n\n";
+        let expected = "# header\nint32        a # a\nfloat64[<=3] long_name [1.0, 2.0]\nuint8        C = 1\n\n--- # sep\nstring b\n";
+
+        let formatted = format(&parse_cst(input).unwrap());
+        assert_eq!(formatted, expected);
+
+        // formatting is idempotent
+        assert_eq!(format(&parse_cst(&formatted).unwrap()), expected);
+    }
+}
@@ -0,0 +1,137 @@
+//! Fuzz targets of deserializers.
+//!
+//! `--fuzz-targets` generates `{package}/fuzz`, which is a cargo-fuzz project of the package crate.
+//! If `--serde` is specified, each message has a target feeding arbitrary bytes to `serde_json::from_slice()`,
+//! and if `--cdr` is specified, `cdr_reader` feeds them to `cdr::Reader`.
+//!
+//! ```text
+//! $ cd generated/my_module/fuzz
+//! $ cargo +nightly fuzz run json_msg_example
+//! ```
+
+use convert_case::{Case, Casing};
+use std::path::{Path, PathBuf};
+
+/// Generate files of `{pkg_dir}/fuzz`.
+/// `types` are paths of messages relative to the package, such as `msg::Example` and `srv::ExampleRequest`.
+pub fn gen_fuzz_targets(
+    pkg_dir: &Path,
+    pkg: &str,
+    types: &[String],
+    serde: bool,
+    cdr: bool,
+) -> Vec<(PathBuf, String)> {
+    let dir = pkg_dir.join("fuzz");
+    let mut files = Vec::new();
+    let mut bins = String::new();
+
+    let mut add_target = |name: String, body: String| {
+        bins.push_str(&format!(
+            "
+[[bin]]
+name = \"{name}\"
+path = \"fuzz_targets/{name}.rs\"
+test = false
+doc = false
+bench = false
+"
+        ));
+
+        let target = format!(
+            "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {{
+{body}}});
+"
+        );
+        files.push((dir.join("fuzz_targets").join(format!("{name}.rs")), target));
+    };
+
+    if serde {
+        for ty in types.iter() {
+            // msg::Example -> json_msg_example
+            let (ext, type_name) = ty.split_once("::").unwrap_or(("", ty));
+            let name = format!("json_{ext}_{}", type_name.to_case(Case::Snake));
+            add_target(
+                name,
+                format!("    let _ = serde_json::from_slice::<{pkg}::{ty}>(data);\n"),
+            );
+        }
+    }
+
+    if cdr {
+        // every read consumes at least 4 bytes, so the loop terminates
+        add_target(
+            "cdr_reader".to_string(),
+            format!(
+                "    if let Ok((mut reader, _)) = {pkg}::cdr::Reader::from_payload(data) {{
+        while reader.read_string().is_ok() {{}}
+    }}
+"
+            ),
+        );
+    }
+
+    let serde_json = if serde { "serde_json = \"1\"\n" } else { "" };
+
+    // the empty workspace keeps the fuzz crate out of the workspace of generated crates
+    let cargo_toml = format!(
+        "# This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+[package]
+name = \"{pkg}-fuzz\"
+version = \"0.0.0\"
+publish = false
+edition = \"2021\"
+
+[package.metadata]
+cargo-fuzz = true
+
+[dependencies]
+libfuzzer-sys = \"0.4\"
+{serde_json}{pkg} = {{ path = \"..\" }}
+
+[workspace]
+members = [\".\"]
+{bins}"
+    );
+    files.push((dir.join("Cargo.toml"), cargo_toml));
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::gen_fuzz_targets;
+    use std::path::Path;
+
+    #[test]
+    fn test_fuzz_targets() {
+        let types = ["msg::PointCloud".to_string(), "srv::GetRequest".to_string()];
+        let files = gen_fuzz_targets(Path::new("out/my_pkg"), "my_pkg", &types, true, true);
+        let file = |path: &str| {
+            let (_, contents) = files.iter().find(|(p, _)| p == Path::new(path)).unwrap();
+            contents
+        };
+
+        assert!(file("out/my_pkg/fuzz/fuzz_targets/json_msg_point_cloud.rs")
+            .contains("    let _ = serde_json::from_slice::<my_pkg::msg::PointCloud>(data);\n"));
+        assert!(file("out/my_pkg/fuzz/fuzz_targets/json_srv_get_request.rs")
+            .contains("serde_json::from_slice::<my_pkg::srv::GetRequest>(data)"));
+        assert!(file("out/my_pkg/fuzz/fuzz_targets/cdr_reader.rs")
+            .contains("my_pkg::cdr::Reader::from_payload(data)"));
+
+        let cargo_toml = file("out/my_pkg/fuzz/Cargo.toml");
+        assert!(cargo_toml.contains("name = \"my_pkg-fuzz\"\n"));
+        assert!(cargo_toml.contains("serde_json = \"1\"\nmy_pkg = { path = \"..\" }\n"));
+        assert!(cargo_toml.contains("path = \"fuzz_targets/json_msg_point_cloud.rs\"\n"));
+        assert_eq!(files.len(), 4);
+
+        // without deserializers, only the project is generated
+        let files = gen_fuzz_targets(Path::new("out/my_pkg"), "my_pkg", &types, false, false);
+        assert_eq!(files.len(), 1);
+        assert!(!files[0].1.contains("[[bin]]"));
+    }
+}
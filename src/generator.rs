@@ -1,7 +1,30 @@
-use crate::parser::{ArrayInfo, Expr, TypeName, Value, ValueType};
+use crate::{
+    cdr::{gen_cdr_methods, CdrField},
+    const_tests::gen_const_tests,
+    constants::gen_constant_by_name,
+    defaults::gen_set_defaults,
+    diff::{gen_diff, DiffField},
+    flags::gen_bitflags,
+    math::{gen_math_conversions, MathLib},
+    metadata::gen_metadata,
+    micro_ros::{gen_static_pool, type_support_symbol, typesupport},
+    optional::gen_optional_accessors,
+    owned::{gen_owned, OwnedField},
+    parser::{parse_msg, ArrayInfo, Expr, Optional, TypeName, Value, ValueType},
+    point_cloud::{gen_point_cloud_iter, is_point_cloud2},
+    qos::{gen_recommended_qos, Qos},
+    random::{gen_fill_random, RandomField},
+    seq::{gen_impl_seq, gen_typed_seq, SeqImpl},
+    serialize::{gen_derive, gen_round_trip_tests, gen_seq_serde},
+    time::gen_time_conversions,
+    warnings::{self, Warning},
+};
+use convert_case::{Case, Casing};
+use nom::Finish;
 use std::{
     borrow::Cow,
-    collections::{BTreeSet, VecDeque},
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    error::Error,
 };
 
 #[derive(Default)]
@@ -10,6 +33,191 @@ pub struct Generator {
     lib_name: String,
     safe_drive_path: String,
     disable_common_interfaces: bool,
+    partial_eq: PartialEqImpl,
+    uom: bool,
+    math: Vec<MathLib>,
+    chrono: bool,
+    package_resolution: BTreeMap<String, Resolution>,
+    emit_crates: bool,
+    deprecated: Option<String>,
+    repr: Option<String>,
+    topic: Option<String>,
+    qos: Option<Qos>,
+    serde: bool,
+    serde_tests: bool,
+    no_std: bool,
+    backend: Backend,
+    random: bool,
+    const_tests: bool,
+    constants_by_name: bool,
+    bitflags: bool,
+    owned: bool,
+    cdr: bool,
+    diff: bool,
+    aliases: bool,
+    seq_impl: SeqImpl,
+    extern_link: bool,
+    extern_allow: Vec<String>,
+    fallible_api: FallibleApi,
+    send_sync: bool,
+    renames: Renames,
+
+    /// Messages of the package, which are referred as `Type` or `{lib_name}/Type`.
+    /// References are not validated if this is `None`.
+    local_types: Option<BTreeSet<String>>,
+
+    /// Generated messages as `(package, type)` whose fields are known including the fields of nested messages.
+    /// Nested messages are assumed to be known if this is `None`.
+    known_types: Option<BTreeSet<(String, String)>>,
+
+    /// Packages generated together, whose messages are generated types even if common_interfaces are used.
+    input_packages: BTreeSet<String>,
+
+    /// `{safe_drive_path}::msg`
+    msg_path: String,
+
+    /// Cache of Rust types of `pkg/Type`.
+    scoped_types: BTreeMap<(String, String), String>,
+
+    /// Pairs of a message and a builtin_interfaces type which are already warned.
+    warned: BTreeSet<(String, String)>,
+
+    /// Rust types defined in the package for each of "msg" and "srv", and their definitions.
+    defined: BTreeMap<(&'static str, String), String>,
+
+    /// Private `SeqRaw` types defined in the package, and their definitions.
+    defined_raw: BTreeMap<(&'static str, String), String>,
+}
+
+/// Where types of a package referred as `pkg/Type` are defined.
+/// If a package is not specified, types are resolved by glob imports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// `safe_drive::msg::common_interfaces::{pkg}`
+    Common,
+
+    /// Locally generated `{pkg}` in the output directory.
+    Local,
+}
+
+/// Packages and types renamed in the generated code.
+/// C symbols are named after the definitions, so only Rust names are changed.
+#[derive(Debug, Default, Clone)]
+pub struct Renames {
+    /// `pkg` -> `module`
+    pub packages: BTreeMap<String, String>,
+
+    /// `(pkg, "msg" | "srv" | "action", Type)` -> `NewType`
+    pub types: BTreeMap<(String, String, String), String>,
+}
+
+impl Renames {
+    /// Module of `pkg`.
+    pub fn package<'a>(&'a self, pkg: &'a str) -> &'a str {
+        self.packages.get(pkg).map_or(pkg, |module| module.as_str())
+    }
+
+    /// Rust type of `pkg/ext/type_name`.
+    pub fn type_name<'a>(&'a self, pkg: &str, ext: &str, type_name: &'a str) -> &'a str {
+        let key = (pkg.to_string(), ext.to_string(), type_name.to_string());
+        self.types.get(&key).map_or(type_name, |name| name.as_str())
+    }
+}
+
+/// How `PartialEq` of generated messages is implemented.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PartialEqImpl {
+    /// Call `{module}__msg__{type}__are_equal` of the C library.
+    #[default]
+    Ffi,
+
+    /// Compare fields one by one in Rust without any FFI call.
+    Rust,
+}
+
+/// Runtime which generated code is linked with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    /// ROS 2 with `rosidl_typesupport_c`.
+    #[default]
+    SafeDrive,
+
+    /// micro-ROS with `rosidl_typesupport_microxrcedds_c` and static memory pools.
+    MicroRos,
+}
+
+/// Return type of fallible constructors of generated messages and sequences.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FallibleApi {
+    /// `Option<Self>`, which is the same as types of safe_drive.
+    #[default]
+    Option,
+
+    /// `Result<Self, MsgError>`, where `MsgError` is defined in `{package}/error.rs`.
+    Result,
+}
+
+impl FallibleApi {
+    /// Return type of `new()`.
+    fn ret(self) -> &'static str {
+        match self {
+            FallibleApi::Option => "Option<Self>",
+            FallibleApi::Result => "Result<Self, super::super::error::MsgError>",
+        }
+    }
+
+    /// Variant of success, which is also used by patterns.
+    fn ok(self) -> &'static str {
+        match self {
+            FallibleApi::Option => "Some",
+            FallibleApi::Result => "Ok",
+        }
+    }
+
+    /// Failure by `error`, which is a variant of `MsgError`.
+    pub fn err(self, error: &str) -> String {
+        match self {
+            FallibleApi::Option => "None".to_string(),
+            FallibleApi::Result => format!("Err(super::super::error::MsgError::{error})"),
+        }
+    }
+
+    /// Error type of helpers setting fields.
+    pub fn field_error(self) -> &'static str {
+        match self {
+            FallibleApi::Option => "&'static str",
+            FallibleApi::Result => "super::super::error::MsgError",
+        }
+    }
+
+    /// Error of `field`, such as `Example::points`, which cannot have `len` elements but `max` elements.
+    pub fn exceeds_bound(self, field: &str, len: &str, max: usize) -> String {
+        match self {
+            FallibleApi::Option => format!("\"{field} cannot have more than {max} elements\""),
+            FallibleApi::Result => format!(
+                "super::super::error::MsgError::ExceedsBound {{ field: Some(\"{field}\"), len: {len}, max: {max} }}"
+            ),
+        }
+    }
+
+    /// Error of `field`, such as `Example::points`, failing to allocate.
+    pub fn allocation(self, field: &str) -> String {
+        match self {
+            FallibleApi::Option => format!("\"failed to allocate {field}\""),
+            FallibleApi::Result => {
+                format!("super::super::error::MsgError::Allocation {{ field: Some(\"{field}\") }}")
+            }
+        }
+    }
+
+    /// Conversion to `Option` appended to calls of `new()` of generated types,
+    /// so that they are handled in the same way as types of safe_drive.
+    pub fn to_option(self) -> &'static str {
+        match self {
+            FallibleApi::Option => "",
+            FallibleApi::Result => ".ok()",
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -18,14 +226,366 @@ pub enum ExprType {
     Variable(String),
 }
 
+/// Definition of a message, which decides how its constants are generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MsgKind {
+    /// A .msg file, whose constants are in the module.
+    Message,
+
+    /// The goal, the result or the feedback of an .action file, or a message wrapping them,
+    /// whose constants are associated with the struct because the parts may define the same names.
+    ActionPart,
+}
+
 impl Generator {
     pub fn new(lib_name: String, safe_drive_path: String, disable_common_interfaces: bool) -> Self {
+        let msg_path = format!("{safe_drive_path}::msg");
         Self {
             libs: Default::default(),
             lib_name,
             safe_drive_path,
             disable_common_interfaces,
+            partial_eq: PartialEqImpl::Ffi,
+            uom: false,
+            math: Vec::new(),
+            chrono: false,
+            package_resolution: BTreeMap::new(),
+            emit_crates: false,
+            deprecated: None,
+            repr: None,
+            topic: None,
+            qos: None,
+            serde: false,
+            serde_tests: false,
+            no_std: false,
+            backend: Backend::SafeDrive,
+            random: false,
+            const_tests: false,
+            constants_by_name: false,
+            bitflags: false,
+            owned: false,
+            cdr: false,
+            diff: false,
+            aliases: false,
+            seq_impl: SeqImpl::Expanded,
+            extern_link: false,
+            extern_allow: Vec::new(),
+            fallible_api: FallibleApi::Option,
+            send_sync: true,
+            renames: Renames::default(),
+            local_types: None,
+            known_types: None,
+            input_packages: BTreeSet::new(),
+            msg_path,
+            scoped_types: BTreeMap::new(),
+            warned: BTreeSet::new(),
+            defined: BTreeMap::new(),
+            defined_raw: BTreeMap::new(),
+        }
+    }
+
+    pub fn set_partial_eq(&mut self, partial_eq: PartialEqImpl) {
+        self.partial_eq = partial_eq;
+    }
+
+    /// Generate accessors typed by the `uom` crate for fields annotated with units.
+    pub fn set_uom(&mut self, uom: bool) {
+        self.uom = uom;
+    }
+
+    /// Generate conversions between geometry messages and types of math libraries.
+    pub fn set_math(&mut self, math: Vec<MathLib>) {
+        self.math = math;
+    }
+
+    /// Generate conversions between `chrono` and fields of `builtin_interfaces`.
+    pub fn set_chrono(&mut self, chrono: bool) {
+        self.chrono = chrono;
+    }
+
+    /// Specify where types of each package are defined,
+    /// instead of relying on `disable_common_interfaces` for all packages.
+    pub fn set_package_resolution(&mut self, package_resolution: BTreeMap<String, Resolution>) {
+        self.package_resolution = package_resolution;
+    }
+
+    /// Generate each package as a crate. Other packages are referred as external crates.
+    pub fn set_emit_crates(&mut self, emit_crates: bool) {
+        self.emit_crates = emit_crates;
+    }
+
+    /// Deprecate the generated struct by `#[deprecated]`.
+    /// This is annotated by `# @deprecated reason` at the top of the file.
+    pub fn set_deprecated(&mut self, deprecated: Option<String>) {
+        self.deprecated = deprecated;
+    }
+
+    /// Override `#[repr(C)]` of the generated struct by `#[repr(C, {repr})]`, such as `align(64)`.
+    /// This is configured by `layout` of the configuration file for each file.
+    pub fn set_repr(&mut self, repr: Option<String>) {
+        self.repr = repr;
+    }
+
+    /// Generate `DEFAULT_TOPIC` of the generated message and helpers creating publishers and subscribers of it.
+    /// This is annotated by `# @topic name` at the top of the file or configured by `topic` of the configuration file.
+    pub fn set_topic(&mut self, topic: Option<String>) {
+        self.topic = topic;
+    }
+
+    /// Generate `recommended_qos()` of the generated message or service for safe_drive.
+    /// This is annotated by `# @qos(...)` at the top of the file or configured by `qos` of the configuration file.
+    pub fn set_qos(&mut self, qos: Option<Qos>) {
+        self.qos = qos;
+    }
+
+    /// `DEFAULT_TOPIC`, and `create_publisher()` and `create_subscriber()` of it for safe_drive.
+    fn gen_topic(&self, lines: &mut VecDeque<Cow<'_, str>>, type_name: &str, topic: &str) {
+        let mut code = format!(
+            "
+impl {type_name} {{
+    /// Topic of `{type_name}` by convention.
+    pub const DEFAULT_TOPIC: &str = {topic:?};"
+        );
+
+        if self.backend == Backend::SafeDrive {
+            let sd = &self.safe_drive_path;
+            let arc = if self.no_std {
+                "alloc::sync::Arc"
+            } else {
+                "std::sync::Arc"
+            };
+            code.push_str(&format!(
+                "
+
+    /// Create a publisher of [`Self::DEFAULT_TOPIC`].
+    pub fn create_publisher(
+        node: &{arc}<{sd}::node::Node>,
+        qos: Option<{sd}::qos::Profile>,
+    ) -> {sd}::error::RCLResult<{sd}::topic::publisher::Publisher<Self>> {{
+        node.create_publisher(Self::DEFAULT_TOPIC, qos)
+    }}
+
+    /// Create a subscriber of [`Self::DEFAULT_TOPIC`].
+    pub fn create_subscriber(
+        node: &{arc}<{sd}::node::Node>,
+        qos: Option<{sd}::qos::Profile>,
+    ) -> {sd}::error::RCLResult<{sd}::topic::subscriber::Subscriber<Self>> {{
+        node.create_subscriber(Self::DEFAULT_TOPIC, qos)
+    }}"
+            ));
+        }
+
+        code.push_str("\n}");
+        lines.push_back(code.into());
+    }
+
+    /// `recommended_qos()` returning `qos::Profile` of safe_drive, which other backends do not have.
+    fn gen_recommended_qos(&self, lines: &mut VecDeque<Cow<'_, str>>, type_name: &str, qos: &Qos) {
+        if self.backend == Backend::SafeDrive {
+            lines.push_back(gen_recommended_qos(type_name, qos, &self.safe_drive_path).into());
+        }
+    }
+
+    /// Return true if the layout is overridden by `packed(N)`,
+    /// which generates a plain Rust struct without C functions because its layout differs from the C struct.
+    fn is_packed(&self) -> bool {
+        self.repr
+            .as_deref()
+            .is_some_and(|repr| repr.starts_with("packed"))
+    }
+
+    /// Packed structs can have only fields of `Copy`, which are primitive types and their fixed size arrays,
+    /// because derived traits copy their fields instead of referring to unaligned fields.
+    fn gen_repr(&self, exprs: &[Expr]) -> Result<String, Box<dyn Error>> {
+        let Some(repr) = &self.repr else {
+            return Ok("#[repr(C)]".to_string());
+        };
+
+        if repr.starts_with("packed") {
+            for expr in exprs.iter() {
+                let Expr::Variable {
+                    type_name,
+                    var_name,
+                    value: None | Some(ValueType::Default(_)),
+                    ..
+                } = expr
+                else {
+                    continue;
+                };
+
+                let is_copy = matches!(
+                    type_name,
+                    TypeName::Type { type_name, array_info: ArrayInfo::NotArray | ArrayInfo::Static(_) }
+                        if gen_primitives(type_name).is_some()
+                );
+                if !is_copy {
+                    return Err(format!(
+                        "{var_name}: packed messages can have only primitive types and their fixed size arrays"
+                    )
+                    .into());
+                }
+            }
+        }
+
+        Ok(format!("#[repr(C, {repr})]"))
+    }
+
+    /// Derive `serde::Serialize` and `serde::Deserialize` for generated structs.
+    pub fn set_serde(&mut self, serde: bool) {
+        self.serde = serde;
+    }
+
+    /// Generate tests of serialization to JSON and deserialization back, if `serde` is enabled.
+    pub fn set_serde_tests(&mut self, serde_tests: bool) {
+        self.serde_tests = serde_tests;
+    }
+
+    /// Generate code using `core` and `alloc` only.
+    pub fn set_no_std(&mut self, no_std: bool) {
+        self.no_std = no_std;
+    }
+
+    /// Link generated code with the backend.
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    /// Generate `fill_random()` setting every field to a random value by the `rand` crate.
+    pub fn set_random(&mut self, random: bool) {
+        self.random = random;
+    }
+
+    /// Generate owned mirrors of messages having `String` and `Vec`, and conversions between them.
+    pub fn set_owned(&mut self, owned: bool) {
+        self.owned = owned;
+    }
+
+    /// Generate `encode_cdr()` and `decode_cdr()` of messages using `cdr.rs` of the package.
+    pub fn set_cdr(&mut self, cdr: bool) {
+        self.cdr = cdr;
+    }
+
+    /// Generate `diff()` of messages reporting fields which differ, using `diff.rs` of the package.
+    pub fn set_diff(&mut self, diff: bool) {
+        self.diff = diff;
+    }
+
+    /// Generate tests comparing each constant with the value in the definition.
+    pub fn set_const_tests(&mut self, const_tests: bool) {
+        self.const_tests = const_tests;
+    }
+
+    /// Generate `constant_by_name()` looking up constants of each message by their names.
+    pub fn set_constants_by_name(&mut self, constants_by_name: bool) {
+        self.constants_by_name = constants_by_name;
+    }
+
+    /// Generate types of bit flags of groups of constants which are powers of two.
+    pub fn set_bitflags(&mut self, bitflags: bool) {
+        self.bitflags = bitflags;
+    }
+
+    /// Generate type aliases of publishers and subscribers of messages, and clients and servers of services.
+    pub fn set_aliases(&mut self, aliases: bool) {
+        self.aliases = aliases;
+    }
+
+    /// Implement sequences by `impl_seq!` or `TypedSeq` of `{package}/seq.rs` instead of expanding them in each file.
+    pub fn set_seq_impl(&mut self, seq_impl: SeqImpl) {
+        self.seq_impl = seq_impl;
+    }
+
+    /// Add `#[link]` of the C libraries of the package to `extern "C"` blocks,
+    /// so that the generated code is linked without `cargo:rustc-link-lib` of build.rs.
+    pub fn set_extern_link(&mut self, extern_link: bool) {
+        self.extern_link = extern_link;
+    }
+
+    /// Lints allowed on `extern "C"` blocks, such as `improper_ctypes`.
+    pub fn set_extern_allow(&mut self, extern_allow: Vec<String>) {
+        self.extern_allow = extern_allow;
+    }
+
+    /// Return type of `new()` of messages and sequences.
+    pub fn set_fallible_api(&mut self, fallible_api: FallibleApi) {
+        self.fallible_api = fallible_api;
+    }
+
+    /// Implement `Send` and `Sync` of messages whose fields are known to be owned data.
+    pub fn set_send_sync(&mut self, send_sync: bool) {
+        self.send_sync = send_sync;
+    }
+
+    /// Rename packages and types in the generated code.
+    pub fn set_renames(&mut self, renames: Renames) {
+        self.renames = renames;
+    }
+
+    /// Attributes of `extern "C"` blocks of the package `module_name`.
+    fn extern_attrs(&self, module_name: &str) -> Vec<String> {
+        let mut attrs = Vec::new();
+        if self.extern_link {
+            let typesupport = typesupport(self.backend);
+            attrs.push(format!(
+                "#[link(name = \"{module_name}__rosidl_generator_c\")]"
+            ));
+            attrs.push(format!("#[link(name = \"{module_name}__{typesupport}\")]"));
+        }
+        if !self.extern_allow.is_empty() {
+            attrs.push(format!("#[allow({})]", self.extern_allow.join(", ")));
+        }
+        attrs
+    }
+
+    /// Set messages of the package to validate references to them.
+    pub fn set_local_types(&mut self, local_types: BTreeSet<String>) {
+        self.local_types = Some(local_types);
+    }
+
+    /// Set generated messages whose fields are known transitively, which are computed by `referred_types()`,
+    /// so that `Send`, `Sync` and so on are implemented only if nested messages implement them too.
+    pub fn set_known_types(&mut self, known_types: BTreeSet<(String, String)>) {
+        self.known_types = Some(known_types);
+    }
+
+    /// Set packages of the input directories, so that their messages referred by this package are known.
+    pub fn set_input_packages(&mut self, input_packages: BTreeSet<String>) {
+        self.input_packages = input_packages;
+    }
+
+    /// Generated messages referred by the fields of `exprs` as `(package, type)`,
+    /// or `None` if a field has a type which is not generated, such as a type of common_interfaces of safe_drive.
+    pub fn referred_types(&self, exprs: &[Expr]) -> Option<Vec<(String, String)>> {
+        let mut types = Vec::new();
+        for expr in exprs.iter() {
+            let Expr::Variable {
+                type_name,
+                value: None | Some(ValueType::Default(_)),
+                ..
+            } = expr
+            else {
+                continue;
+            };
+
+            match type_name {
+                TypeName::ScopedType { scope, .. } if scope == "builtin_interfaces" => (),
+                TypeName::Type { type_name, .. } if gen_primitives(type_name).is_none() => {
+                    types.push((self.lib_name.clone(), type_name.clone()));
+                }
+                TypeName::ScopedType {
+                    scope,
+                    type_name: nested,
+                    ..
+                } => {
+                    if !self.is_generated_type(type_name) {
+                        return None;
+                    }
+                    types.push((scope.clone(), nested.clone()));
+                }
+                _ => (),
+            }
         }
+        Some(types)
     }
 
     pub fn gen_srv<'a>(
@@ -34,29 +594,71 @@ impl Generator {
         type_name: &'a str,
         exprs_req: &[Expr],
         exprs_resp: &[Expr],
-    ) -> VecDeque<Cow<'a, str>> {
+    ) -> Result<VecDeque<Cow<'a, str>>, Box<dyn Error>> {
+        // C symbols are named after the definition, and Rust types after the renamed one
+        let c_name = format!("{module_name}__srv__{type_name}");
+        let type_name = self
+            .renames
+            .type_name(&self.lib_name, "srv", type_name)
+            .to_string();
+        let type_name = type_name.as_str();
+
+        self.define_types("srv", type_name)?;
+
+        let mut members = self.members(&format!("{type_name}Request"), exprs_req, false);
+        members.extend(self.members(&format!("{type_name}Response"), exprs_resp, false));
+        self.warn_collisions(type_name, &members);
+
         let mut lines = VecDeque::new();
         lines.push_back("use super::super::*;".into());
-        lines.push_back("use super::super::super::*;".into());
+
+        // other packages are crates, not sibling modules, if emitting crates
+        if !self.emit_crates {
+            lines.push_back("use super::super::super::*;".into());
+        }
         lines.push_back(format!("use {}::msg::*;", self.safe_drive_path).into());
         lines.push_back(format!("use {}::rcl;", self.safe_drive_path).into());
-        lines.push_back(format!("use {}::msg::common_interfaces::*;", self.safe_drive_path).into());
+
+        // Vec, String and format! are not in the prelude of no_std
+        if self.no_std {
+            lines.push_back("#[allow(unused_imports)]".into());
+            lines.push_back(gen_alloc_import(&[type_name]).into());
+        }
+
+        if !self.disable_common_interfaces {
+            lines.push_back(
+                format!("use {}::msg::common_interfaces::*;", self.safe_drive_path).into(),
+            );
+        }
+
+        // constants are associated with Request and Response,
+        // and also defined in the module unless both of them define the same name
+        let names_req = const_names(exprs_req);
+        let duplicated: BTreeSet<String> = const_names(exprs_resp)
+            .intersection(&names_req)
+            .cloned()
+            .collect();
 
         let mut const_val = Vec::new();
+        let mut const_req = Vec::new();
+        let mut const_resp = Vec::new();
         let mut var_req = Vec::new();
         let mut var_resp = Vec::new();
 
-        for expr in exprs_req.iter() {
-            match self.gen_expr(expr, type_name) {
-                ExprType::Const(val) => const_val.push(val),
-                ExprType::Variable(val) => var_req.push(val),
-            }
-        }
-
-        for expr in exprs_resp.iter() {
-            match self.gen_expr(expr, type_name) {
-                ExprType::Const(val) => const_val.push(val),
-                ExprType::Variable(val) => var_resp.push(val),
+        for (exprs, consts, vars) in [
+            (exprs_req, &mut const_req, &mut var_req),
+            (exprs_resp, &mut const_resp, &mut var_resp),
+        ] {
+            for expr in exprs.iter() {
+                match self.gen_expr(expr, type_name)? {
+                    ExprType::Const(val) => {
+                        if !const_names(std::slice::from_ref(expr)).is_subset(&duplicated) {
+                            const_val.push(val.clone());
+                        }
+                        consts.push(val);
+                    }
+                    ExprType::Variable(val) => vars.push(val),
+                }
             }
         }
 
@@ -65,17 +667,38 @@ impl Generator {
             lines.push_back(c.into());
         }
 
+        if self.is_packed() {
+            return Err("packed layouts are supported only by messages".into());
+        }
+
         // generate C functions
-        gen_cfun_srv(&mut lines, module_name, type_name);
+        let mut externs = Externs::default();
+        for side in ["Request", "Response"] {
+            externs.add_msg(
+                &format!("{c_name}_{side}"),
+                &format!("{type_name}{side}"),
+                false,
+            );
+        }
+        externs.add_type_support(self.backend, "service", &c_name);
+        for side in ["Request", "Response"] {
+            externs.add_type_support(self.backend, "message", &format!("{c_name}_{side}"));
+        }
+        lines.push_back(externs.gen(&self.extern_attrs(module_name)).into());
 
         // generate struct of request
         lines.push_back("".into());
-        lines.push_back("#[repr(C)]".into());
-        lines.push_back("#[derive(Debug)]".into());
+        gen_example_doc(&mut lines, &format!("{type_name}Request"), exprs_req);
+        lines.push_back(self.gen_repr(exprs_req)?.into());
+        lines.push_back(gen_derive(self.serde).into());
+        if let Some(reason) = &self.deprecated {
+            lines.push_back(gen_deprecated(reason).into());
+        }
         lines.push_back(format!("pub struct {type_name}Request {{").into());
 
-        if var_req.is_empty() {
-            lines.push_back("    _unused: u8".into());
+        let empty = var_req.is_empty();
+        if empty {
+            lines.push_back(EMPTY_MEMBER.into());
         } else {
             for v in var_req {
                 lines.push_back(v.into());
@@ -83,15 +706,23 @@ impl Generator {
         }
 
         lines.push_back("}".into());
+        if empty {
+            self.gen_empty_layout_check(&mut lines, &format!("{type_name}Request"));
+        }
 
         // generate struct of response
         lines.push_back("".into());
-        lines.push_back("#[repr(C)]".into());
-        lines.push_back("#[derive(Debug)]".into());
+        gen_example_doc(&mut lines, &format!("{type_name}Response"), exprs_resp);
+        lines.push_back(self.gen_repr(exprs_resp)?.into());
+        lines.push_back(gen_derive(self.serde).into());
+        if let Some(reason) = &self.deprecated {
+            lines.push_back(gen_deprecated(reason).into());
+        }
         lines.push_back(format!("pub struct {type_name}Response {{").into());
 
-        if var_resp.is_empty() {
-            lines.push_back("    _unused: u8".into());
+        let empty = var_resp.is_empty();
+        if empty {
+            lines.push_back(EMPTY_MEMBER.into());
         } else {
             for v in var_resp {
                 lines.push_back(v.into());
@@ -99,13 +730,180 @@ impl Generator {
         }
 
         lines.push_back("}".into());
+        if empty {
+            self.gen_empty_layout_check(&mut lines, &format!("{type_name}Response"));
+        }
 
         // generate impl {type_name}(Request|Response) and struct {type_name}(Request|Response)Sequence
-        gen_impl_and_seq_srv(&mut lines, module_name, type_name);
+        let defaults_req =
+            gen_set_defaults(&format!("{type_name}Request"), exprs_req, self.fallible_api);
+        let defaults_resp = gen_set_defaults(
+            &format!("{type_name}Response"),
+            exprs_resp,
+            self.fallible_api,
+        );
+        gen_impl_and_seq_srv(
+            &mut lines,
+            &c_name,
+            type_name,
+            [&defaults_req, &defaults_resp],
+            self.backend,
+            self.seq_impl,
+            self.fallible_api,
+        );
+
+        // generate aliases of clients and servers
+        if self.aliases {
+            let sd = &self.safe_drive_path;
+            lines.push_back(
+                format!(
+                    "
+/// Client of `{type_name}`.
+pub type {type_name}Client = {sd}::service::client::Client<{type_name}>;
+
+/// Server of `{type_name}`.
+pub type {type_name}Server = {sd}::service::server::Server<{type_name}>;"
+                )
+                .into(),
+            );
+        }
+
+        // generate the QoS of @qos
+        if let Some(qos) = &self.qos {
+            self.gen_recommended_qos(&mut lines, type_name, qos);
+        }
+
+        // generate serde of sequences
+        if self.serde {
+            gen_seq_serde(
+                &mut lines,
+                &format!("{type_name}Request"),
+                self.fallible_api,
+            );
+            gen_seq_serde(
+                &mut lines,
+                &format!("{type_name}Response"),
+                self.fallible_api,
+            );
+        }
+
+        // generate constants associated with {type_name}(Request|Response)
+        gen_assoc_consts(&mut lines, &format!("{type_name}Request"), &const_req);
+        gen_assoc_consts(&mut lines, &format!("{type_name}Response"), &const_resp);
+
+        // generate lookups of constants by their names
+        if self.constants_by_name {
+            let req = format!("{type_name}Request");
+            let resp = format!("{type_name}Response");
+            gen_constant_by_name(&mut lines, &req, "Self::", exprs_req);
+            gen_constant_by_name(&mut lines, &resp, "Self::", exprs_resp);
+        }
+
+        // generate bit flags of groups of constants
+        if self.bitflags {
+            let req = format!("{type_name}Request");
+            let resp = format!("{type_name}Response");
+            gen_bitflags(&mut lines, &req, &format!("{req}::"), exprs_req);
+            gen_bitflags(&mut lines, &resp, &format!("{resp}::"), exprs_resp);
+        }
+
+        // generate helpers of uint8[] and byte[]
+        self.gen_bytes_helpers(&mut lines, &format!("{type_name}Request"), exprs_req);
+        self.gen_bytes_helpers(&mut lines, &format!("{type_name}Response"), exprs_resp);
+
+        // generate helpers checking bounds of limited sequences
+        self.gen_bound_helpers(&mut lines, &format!("{type_name}Request"), exprs_req)?;
+        self.gen_bound_helpers(&mut lines, &format!("{type_name}Response"), exprs_resp)?;
+
+        // generate Send and Sync of {type_name}(Request|Response)
+        self.gen_send_sync(&mut lines, &format!("{type_name}Request"), exprs_req);
+        self.gen_send_sync(&mut lines, &format!("{type_name}Response"), exprs_resp);
+
+        // generate accessors of fields renamed by @rust_name
+        self.gen_renamed_accessors(&mut lines, &format!("{type_name}Request"), exprs_req)?;
+        self.gen_renamed_accessors(&mut lines, &format!("{type_name}Response"), exprs_resp)?;
+
+        // generate accessors of fields annotated by @optional
+        self.gen_optional_accessors(&mut lines, &format!("{type_name}Request"), exprs_req)?;
+        self.gen_optional_accessors(&mut lines, &format!("{type_name}Response"), exprs_resp)?;
+
+        // generate metadata of @key, @range and @default
+        gen_metadata(&mut lines, &format!("{type_name}Request"), exprs_req)?;
+        gen_metadata(&mut lines, &format!("{type_name}Response"), exprs_resp)?;
+
+        // generate static memory pools for micro-ROS
+        if self.backend == Backend::MicroRos {
+            gen_static_pool(&mut lines, &format!("{type_name}Request"), exprs_req);
+            gen_static_pool(&mut lines, &format!("{type_name}Response"), exprs_resp);
+        }
+
+        // generate accessors typed by uom
+        if self.uom {
+            gen_uom_accessors(&mut lines, &format!("{type_name}Request"), exprs_req);
+            gen_uom_accessors(&mut lines, &format!("{type_name}Response"), exprs_resp);
+        }
+
+        // generate conversions of builtin_interfaces
+        let req = format!("{type_name}Request");
+        let resp = format!("{type_name}Response");
+        gen_time_conversions(&mut lines, &req, exprs_req, self.chrono, self.no_std);
+        gen_time_conversions(&mut lines, &resp, exprs_resp, self.chrono, self.no_std);
+
+        // generate fill_random() by rand
+        if self.random {
+            self.gen_fill_random(&mut lines, &req, exprs_req)?;
+            self.gen_fill_random(&mut lines, &resp, exprs_resp)?;
+        }
+
+        // generate owned mirrors
+        if self.owned {
+            self.gen_owned(&mut lines, &req, exprs_req)?;
+            self.gen_owned(&mut lines, &resp, exprs_resp)?;
+        }
+
+        // generate serialization to CDR
+        if self.cdr {
+            self.gen_cdr_methods(&mut lines, &req, exprs_req)?;
+            self.gen_cdr_methods(&mut lines, &resp, exprs_resp)?;
+        }
+
+        // generate diff()
+        if self.diff {
+            self.gen_diff(&mut lines, &req, exprs_req);
+            self.gen_diff(&mut lines, &resp, exprs_resp);
+        }
+
+        // generate tests of serde
+        if self.serde && self.serde_tests {
+            gen_round_trip_tests(
+                &mut lines,
+                &[],
+                &[
+                    format!("{type_name}Request"),
+                    format!("{type_name}Response"),
+                ],
+            );
+        }
+
+        // generate tests of constants
+        if self.const_tests {
+            gen_const_tests(
+                &mut lines,
+                &[
+                    (format!("{type_name}Request::"), exprs_req),
+                    (format!("{type_name}Response::"), exprs_resp),
+                ],
+            );
+        }
+
+        // generated code refers to deprecated items itself
+        if self.deprecated.is_some() || has_deprecated(exprs_req) || has_deprecated(exprs_resp) {
+            lines.push_front("#![allow(deprecated)]".into());
+        }
 
         lines.push_front("// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).".into());
 
-        lines
+        Ok(lines)
     }
 
     pub fn gen_msg<'a>(
@@ -113,97 +911,594 @@ impl Generator {
         module_name: &str,
         type_name: &'a str,
         exprs: &[Expr],
-    ) -> VecDeque<Cow<'a, str>> {
+    ) -> Result<VecDeque<Cow<'a, str>>, Box<dyn Error>> {
+        // C symbols are named after the definition, and Rust types after the renamed one
+        let c_name = format!("{module_name}__msg__{type_name}");
+        let type_name = self
+            .renames
+            .type_name(&self.lib_name, "msg", type_name)
+            .to_string();
+        let type_name = type_name.as_str();
+
+        self.define_types("msg", type_name)?;
+
         let mut lines = VecDeque::new();
         lines.push_back("use super::*;".into());
-        lines.push_back("use super::super::super::*;".into());
+
+        // other packages are crates, not sibling modules, if emitting crates
+        if !self.emit_crates {
+            lines.push_back("use super::super::super::*;".into());
+        }
         lines.push_back(format!("use {}::msg::*;", self.safe_drive_path).into());
         lines.push_back(format!("use {}::rcl;", self.safe_drive_path).into());
 
+        // Vec, String and format! are not in the prelude of no_std
+        if self.no_std {
+            lines.push_back("#[allow(unused_imports)]".into());
+            lines.push_back(gen_alloc_import(&[type_name]).into());
+        }
+
         if !self.disable_common_interfaces {
             lines.push_back(
                 format!("use {}::msg::common_interfaces::*;", self.safe_drive_path).into(),
             );
         }
 
-        let mut const_val = Vec::new();
-        let mut variables = Vec::new();
-
-        for expr in exprs.iter() {
-            match self.gen_expr(expr, type_name) {
-                ExprType::Const(val) => const_val.push(val),
-                ExprType::Variable(val) => variables.push(val),
-            }
-        }
-
-        // generate constant values
-        for c in const_val {
-            lines.push_back(c.into());
+        self.gen_msg_items(
+            &mut lines,
+            module_name,
+            &c_name,
+            type_name,
+            exprs,
+            MsgKind::Message,
+        )?;
+
+        // generated code refers to deprecated items itself
+        if self.deprecated.is_some() || has_deprecated(exprs) {
+            lines.push_front("#![allow(deprecated)]".into());
         }
 
-        // generate C functions
-        gen_cfun_msg(&mut lines, module_name, type_name);
+        lines.push_front("// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).".into());
 
-        // generate struct
-        lines.push_back("".into());
-        lines.push_back("#[repr(C)]".into());
-        lines.push_back("#[derive(Debug)]".into());
-        lines.push_back(format!("pub struct {type_name} {{").into());
+        Ok(lines)
+    }
 
-        if variables.is_empty() {
-            lines.push_back("    _unused: u8".into());
-        } else {
-            for v in variables {
-                lines.push_back(v.into());
-            }
-        }
+    /// Generate `{type_name}Goal`, `{type_name}Result` and `{type_name}Feedback` of an action,
+    /// and the services and the message wrapping them, which rosidl defines for the protocol of actions.
+    pub fn gen_action<'a>(
+        &mut self,
+        module_name: &str,
+        type_name: &'a str,
+        exprs_goal: &[Expr],
+        exprs_result: &[Expr],
+        exprs_feedback: &[Expr],
+    ) -> Result<VecDeque<Cow<'a, str>>, Box<dyn Error>> {
+        // C symbols are named after the definition, and Rust types after the renamed one
+        let c_name = format!("{module_name}__action__{type_name}");
+        let type_name = self
+            .renames
+            .type_name(&self.lib_name, "action", type_name)
+            .to_string();
+        let type_name = type_name.as_str();
+
+        self.define_types("action", type_name)?;
 
-        lines.push_back("}".into());
+        let mut lines = VecDeque::new();
+        lines.push_back("use super::super::*;".into());
 
-        // generate impl {type_name} and struct {type_name}Sequence
-        gen_impl_and_seq_msg(&mut lines, module_name, type_name);
+        // other packages are crates, not sibling modules, if emitting crates
+        if !self.emit_crates {
+            lines.push_back("use super::super::super::*;".into());
+        }
+        lines.push_back(format!("use {}::msg::*;", self.safe_drive_path).into());
+        lines.push_back(format!("use {}::rcl;", self.safe_drive_path).into());
 
-        lines.push_front("// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).".into());
+        // Vec, String and format! are not in the prelude of no_std
+        if self.no_std {
+            let names: Vec<String> = ACTION_MESSAGES
+                .iter()
+                .map(|part| format!("{type_name}{part}"))
+                .collect();
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            lines.push_back("#[allow(unused_imports)]".into());
+            lines.push_back(gen_alloc_import(&names).into());
+        }
 
-        lines
-    }
+        if !self.disable_common_interfaces {
+            lines.push_back(
+                format!("use {}::msg::common_interfaces::*;", self.safe_drive_path).into(),
+            );
+        }
+
+        for (part, exprs) in [
+            ("Goal", exprs_goal),
+            ("Result", exprs_result),
+            ("Feedback", exprs_feedback),
+        ] {
+            self.gen_msg_items(
+                &mut lines,
+                module_name,
+                &format!("{c_name}_{part}"),
+                &format!("{type_name}{part}"),
+                exprs,
+                MsgKind::ActionPart,
+            )?;
+        }
+
+        // messages wrapping the parts, which refer to the parts as local types
+        let wrappers = [
+            (
+                "SendGoal_Request",
+                "SendGoalRequest",
+                format!("unique_identifier_msgs/UUID goal_id\n{type_name}Goal goal\n"),
+            ),
+            (
+                "SendGoal_Response",
+                "SendGoalResponse",
+                "bool accepted\nbuiltin_interfaces/Time stamp\n".to_string(),
+            ),
+            (
+                "GetResult_Request",
+                "GetResultRequest",
+                "unique_identifier_msgs/UUID goal_id\n".to_string(),
+            ),
+            (
+                "GetResult_Response",
+                "GetResultResponse",
+                format!("int8 status\n{type_name}Result result\n"),
+            ),
+            (
+                "FeedbackMessage",
+                "FeedbackMessage",
+                format!("unique_identifier_msgs/UUID goal_id\n{type_name}Feedback feedback\n"),
+            ),
+        ];
+        let parts = ["Goal", "Result", "Feedback"].map(|part| format!("{type_name}{part}"));
+        if let Some(local_types) = &mut self.local_types {
+            local_types.extend(parts.iter().cloned());
+        }
+
+        // the year-2038 problem of `stamp` is of rosidl, not of the definition
+        self.warned
+            .insert((format!("{type_name}SendGoalResponse"), "Time".to_string()));
+
+        for (c_suffix, suffix, definition) in wrappers.iter() {
+            let (_, exprs) = parse_msg(definition)
+                .finish()
+                .map_err(|e| format!("{c_suffix}: {e:?}"))?;
+            self.gen_msg_items(
+                &mut lines,
+                module_name,
+                &format!("{c_name}_{c_suffix}"),
+                &format!("{type_name}{suffix}"),
+                &exprs,
+                MsgKind::ActionPart,
+            )?;
+        }
+        if let Some(local_types) = &mut self.local_types {
+            for part in parts.iter() {
+                local_types.remove(part);
+            }
+        }
+
+        // generate services and the action
+        let mut externs = Externs::default();
+        for service in ["SendGoal", "GetResult"] {
+            externs.add_type_support(self.backend, "service", &format!("{c_name}_{service}"));
+        }
+        externs.add_type_support(self.backend, "action", &c_name);
+        lines.push_back(externs.gen(&self.extern_attrs(module_name)).into());
+
+        for service in ["SendGoal", "GetResult"] {
+            lines.push_back(
+                gen_service_msg(
+                    &format!("{c_name}_{service}"),
+                    &format!("{type_name}{service}"),
+                    self.backend,
+                )
+                .into(),
+            );
+        }
+        lines.push_back(gen_action_msg(&c_name, type_name, self.backend).into());
+
+        // generated code refers to deprecated items itself
+        if self.deprecated.is_some()
+            || has_deprecated(exprs_goal)
+            || has_deprecated(exprs_result)
+            || has_deprecated(exprs_feedback)
+        {
+            lines.push_front("#![allow(deprecated)]".into());
+        }
+
+        lines.push_front("// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).".into());
+
+        Ok(lines)
+    }
+
+    /// Generate items of the message `type_name` of the C struct `c_name`,
+    /// which are its constants, the struct, its sequence and their implementations.
+    fn gen_msg_items(
+        &mut self,
+        lines: &mut VecDeque<Cow<'_, str>>,
+        module_name: &str,
+        c_name: &str,
+        type_name: &str,
+        exprs: &[Expr],
+        kind: MsgKind,
+    ) -> Result<(), Box<dyn Error>> {
+        let members = self.members(type_name, exprs, kind == MsgKind::Message);
+        self.warn_collisions(type_name, &members);
+
+        let mut const_val = Vec::new();
+        let mut variables = Vec::new();
+
+        for expr in exprs.iter() {
+            match self.gen_expr(expr, type_name)? {
+                ExprType::Const(val) => const_val.push(val),
+                ExprType::Variable(val) => variables.push(val),
+            }
+        }
+
+        // generate constant values
+        if kind == MsgKind::Message {
+            for c in const_val.iter() {
+                lines.push_back(c.clone().into());
+            }
+        }
+
+        // packed messages cannot be passed to C functions, which assume the layouts of the C structs
+        let packed = self.is_packed();
+        if packed && kind != MsgKind::Message {
+            return Err("packed layouts are supported only by messages".into());
+        }
+        if packed && self.topic.is_some() {
+            return Err("packed messages cannot be published, so they cannot have topics".into());
+        }
+
+        // generate C functions
+        if !packed {
+            let mut externs = Externs::default();
+            externs.add_msg(c_name, type_name, self.partial_eq == PartialEqImpl::Ffi);
+            externs.add_type_support(self.backend, "message", c_name);
+            lines.push_back(externs.gen(&self.extern_attrs(module_name)).into());
+        }
+
+        // generate struct
+        lines.push_back("".into());
+        gen_example_doc(lines, type_name, exprs);
+        lines.push_back(self.gen_repr(exprs)?.into());
+        lines.push_back(gen_derive(self.serde).into());
+        if let Some(reason) = &self.deprecated {
+            lines.push_back(gen_deprecated(reason).into());
+        }
+        lines.push_back(format!("pub struct {type_name} {{").into());
+
+        let empty = variables.is_empty();
+        if empty {
+            lines.push_back(EMPTY_MEMBER.into());
+        } else {
+            for v in variables {
+                lines.push_back(v.into());
+            }
+        }
+
+        lines.push_back("}".into());
+        if empty {
+            self.gen_empty_layout_check(lines, type_name);
+        }
+
+        // generate impl {type_name} and struct {type_name}Sequence
+        let defaults = gen_set_defaults(type_name, exprs, self.fallible_api);
+        if packed {
+            gen_impl_packed(lines, type_name, &defaults, self.fallible_api);
+        } else {
+            gen_impl_and_seq_msg(
+                lines,
+                c_name,
+                type_name,
+                &defaults,
+                self.backend,
+                self.seq_impl,
+                self.fallible_api,
+            );
+        }
+
+        // generate aliases of publishers and subscribers
+        if self.aliases && kind == MsgKind::Message && !packed {
+            let sd = &self.safe_drive_path;
+            lines.push_back(
+                format!(
+                    "
+/// Publisher of `{type_name}`.
+pub type {type_name}Publisher = {sd}::topic::publisher::Publisher<{type_name}>;
+
+/// Subscriber of `{type_name}`.
+pub type {type_name}Subscriber = {sd}::topic::subscriber::Subscriber<{type_name}>;"
+                )
+                .into(),
+            );
+        }
+
+        // generate the topic of @topic
+        if let (Some(topic), MsgKind::Message) = (&self.topic, kind) {
+            self.gen_topic(lines, type_name, topic);
+        }
+
+        // generate the QoS of @qos
+        if let (Some(qos), MsgKind::Message) = (&self.qos, kind) {
+            self.gen_recommended_qos(lines, type_name, qos);
+        }
+
+        // generate serde of sequences
+        if self.serde && !packed {
+            gen_seq_serde(lines, type_name, self.fallible_api);
+        }
+
+        // generate constants associated with parts of actions
+        if kind == MsgKind::ActionPart {
+            gen_assoc_consts(lines, type_name, &const_val);
+        }
+
+        // generate the lookup of constants by their names
+        if self.constants_by_name {
+            let prefix = match kind {
+                MsgKind::Message => "",
+                MsgKind::ActionPart => "Self::",
+            };
+            gen_constant_by_name(lines, type_name, prefix, exprs);
+        }
+
+        // generate bit flags of groups of constants
+        if self.bitflags {
+            let prefix = match kind {
+                MsgKind::Message => String::new(),
+                MsgKind::ActionPart => format!("{type_name}::"),
+            };
+            gen_bitflags(lines, type_name, &prefix, exprs);
+        }
+
+        // generate helpers of uint8[] and byte[]
+        self.gen_bytes_helpers(lines, type_name, exprs);
+
+        // generate helpers checking bounds of limited sequences
+        self.gen_bound_helpers(lines, type_name, exprs)?;
+
+        // generate Send and Sync
+        self.gen_send_sync(lines, type_name, exprs);
+
+        // generate accessors of fields renamed by @rust_name
+        self.gen_renamed_accessors(lines, type_name, exprs)?;
+
+        // generate accessors of fields annotated by @optional
+        self.gen_optional_accessors(lines, type_name, exprs)?;
+
+        // generate metadata of @key, @range and @default
+        gen_metadata(lines, type_name, exprs)?;
+
+        // generate a static memory pool for micro-ROS
+        if self.backend == Backend::MicroRos {
+            gen_static_pool(lines, type_name, exprs);
+        }
+
+        // generate accessors typed by uom
+        if self.uom {
+            gen_uom_accessors(lines, type_name, exprs);
+        }
+
+        // generate conversions to math libraries
+        gen_math_conversions(lines, type_name, exprs, &self.math);
+
+        // generate conversions of builtin_interfaces
+        gen_time_conversions(lines, type_name, exprs, self.chrono, self.no_std);
+
+        // generate fill_random() by rand
+        if self.random {
+            self.gen_fill_random(lines, type_name, exprs)?;
+        }
+
+        // generate the owned mirror
+        if self.owned {
+            self.gen_owned(lines, type_name, exprs)?;
+        }
+
+        // generate serialization to CDR
+        if self.cdr {
+            self.gen_cdr_methods(lines, type_name, exprs)?;
+        }
+
+        // generate diff()
+        if self.diff {
+            self.gen_diff(lines, type_name, exprs);
+        }
+
+        // generate point iterators of PointCloud2
+        gen_point_cloud_iter(lines, type_name, exprs);
+
+        // generate impl PartialEq
+        match self.partial_eq {
+            _ if packed => gen_partial_eq_packed(lines, type_name, exprs),
+            PartialEqImpl::Ffi => gen_partial_eq_ffi(lines, c_name, type_name),
+            PartialEqImpl::Rust => gen_partial_eq_rust(lines, type_name, exprs),
+        }
+
+        // generate tests of serde
+        if self.serde && self.serde_tests {
+            gen_round_trip_tests(lines, &[type_name.to_string()], &[]);
+        }
+
+        // generate tests of constants
+        if self.const_tests {
+            let prefix = match kind {
+                MsgKind::Message => String::new(),
+                MsgKind::ActionPart => format!("{type_name}::"),
+            };
+            gen_const_tests(lines, &[(prefix, exprs)]);
+        }
+
+        Ok(())
+    }
+
+    /// Generate `type_name` as a re-export of `target_pkg/msg/target_type`, which is structurally identical.
+    /// Constants of `exprs` are re-exported too, and the C library of `type_name` is not used.
+    pub fn gen_msg_alias<'a>(
+        &mut self,
+        type_name: &str,
+        target_pkg: &str,
+        target_type: &str,
+        exprs: &[Expr],
+    ) -> Result<VecDeque<Cow<'a, str>>, Box<dyn Error>> {
+        let type_name = self
+            .renames
+            .type_name(&self.lib_name, "msg", type_name)
+            .to_string();
+        self.define_types("msg", &type_name)?;
+
+        let path = self.resolve_scoped_type(target_pkg, target_type)?;
+        let (module, target) = path.rsplit_once("::").unwrap();
+
+        let mut lines = VecDeque::new();
+        if !self.emit_crates {
+            lines.push_back("#[allow(unused_imports)]".into());
+            lines.push_back("use super::super::super::*;".into());
+        }
+        lines.push_back("".into());
+        lines.push_back(
+            format!(
+                "/// Alias of [`{target}`] of `{target_pkg}`, which is structurally identical."
+            )
+            .into(),
+        );
+        if target == type_name {
+            lines.push_back(format!("pub use {module}::{{{target}, {target}Seq}};").into());
+        } else {
+            lines.push_back(format!("pub use {module}::{target} as {type_name};").into());
+            lines.push_back(format!("pub use {module}::{target}Seq as {type_name}Seq;").into());
+        }
+        for name in const_names(exprs) {
+            lines.push_back(format!("pub use {module}::{name};").into());
+        }
+
+        lines.push_front("// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).".into());
+
+        Ok(lines)
+    }
+
+    fn gen_expr(&mut self, expr: &Expr, msg_type_name: &str) -> Result<ExprType, Box<dyn Error>> {
+        // field name is added to errors
+        let context = |e: Box<dyn Error>| -> Box<dyn Error> {
+            if let Expr::Variable { var_name, .. } = expr {
+                format!("{msg_type_name}::{var_name}: {e}").into()
+            } else {
+                e
+            }
+        };
 
-    fn gen_expr(&mut self, expr: &Expr, msg_type_name: &str) -> ExprType {
         match expr {
             Expr::Variable {
                 type_name,
                 var_name,
                 value,
                 comment,
+                unit,
+                deprecated,
+                ros_name,
+                optional,
+                ..
             } => {
                 let var_name = crate::mangle(var_name.as_str());
                 match value {
                     Some(ValueType::Const(val)) => {
-                        let ty = self.gen_const_type(type_name, msg_type_name);
-                        let v = gen_value(val);
+                        let ty = self
+                            .gen_const_type(type_name, msg_type_name)
+                            .map_err(context)?;
+                        let v = match (type_name, val) {
+                            (
+                                TypeName::Type {
+                                    type_name: t,
+                                    array_info: ArrayInfo::NotArray,
+                                },
+                                Value::String(c),
+                            ) if t == "char" || t == "byte" => {
+                                gen_char_value(t, c).map_err(context)?
+                            }
+                            _ => gen_value(val),
+                        };
+
+                        // check the length of the string, except the null terminator, at compile time too,
+                        // because definitions without the CST such as .idl are not checked by the generation
+                        let v = if let TypeName::LimitedString {
+                            size,
+                            array_info: ArrayInfo::NotArray,
+                        } = type_name
+                        {
+                            format!("{{ const V: {ty} = {v}; assert!(V.len() - 1 <= {size}, \"{var_name} exceeds string<={size}\"); V }}")
+                        } else {
+                            v
+                        };
                         let result = if let Some(c) = comment {
-                            format!("pub const {var_name}: {ty} = {v}; //{c}",)
+                            format!("pub const {var_name}: {ty} = {v}; {}", gen_comment(c))
                         } else {
                             format!("pub const {var_name}: {ty} = {v};",)
                         };
-                        ExprType::Const(result)
+                        let result = if let Some(reason) = deprecated {
+                            format!("{}\n{result}", gen_deprecated(reason))
+                        } else {
+                            result
+                        };
+                        let result = if let Some(u) = unit {
+                            format!("/// Unit: `{}`\n{result}", sanitize_comment(u))
+                        } else {
+                            result
+                        };
+                        let result = if let Some(n) = ros_name {
+                            format!(
+                                "/// `{n}` in the definition, renamed by `@rust_name`.\n{result}"
+                            )
+                        } else {
+                            result
+                        };
+                        Ok(ExprType::Const(result))
                     }
                     _ => {
-                        let ty = self.gen_type(type_name, msg_type_name);
+                        let ty = self.gen_type(type_name, msg_type_name).map_err(context)?;
                         let result = if let Some(c) = comment {
-                            format!("    pub {var_name}: {ty}, //{c}")
+                            format!("    pub {var_name}: {ty}, {}", gen_comment(c))
                         } else {
                             format!("    pub {var_name}: {ty},")
                         };
-                        ExprType::Variable(result)
+                        let result = if let Some(reason) = deprecated {
+                            format!("    {}\n{result}", gen_deprecated(reason))
+                        } else {
+                            result
+                        };
+                        let result = if let Some(u) = unit {
+                            format!("    /// Unit: `{}`\n{result}", sanitize_comment(u))
+                        } else {
+                            result
+                        };
+                        let result = if let Some(n) = ros_name {
+                            format!("    /// `{n}` in the definition, renamed by `@rust_name`.\n{result}")
+                        } else {
+                            result
+                        };
+                        let result = match optional {
+                            Some(Optional::Flag(flag)) => format!("    /// Present if `{flag}` is true, as annotated by `@optional`.\n{result}"),
+                            Some(Optional::Sentinel(sentinel)) => format!("    /// Absent if it is `{sentinel}`, as annotated by `@optional`.\n{result}"),
+                            None => result,
+                        };
+                        Ok(ExprType::Variable(result))
                     }
                 }
             }
-            _ => unreachable!(),
+            _ => Err(format!("{msg_type_name}: unexpected expression: {expr:?}").into()),
         }
     }
 
-    fn gen_type(&mut self, type_name: &'_ TypeName, msg_type_name: &str) -> Cow<'_, str> {
+    fn gen_type(
+        &mut self,
+        type_name: &'_ TypeName,
+        msg_type_name: &str,
+    ) -> Result<Cow<'_, str>, Box<dyn Error>> {
         match type_name {
             TypeName::Type {
                 type_name,
@@ -212,17 +1507,18 @@ impl Generator {
                 let type_str = if let Some(prim) = gen_primitives(type_name) {
                     prim.to_string()
                 } else {
-                    format!("{type_name}")
+                    self.validate_local_type(type_name)?;
+                    gen_local_type(self.renames.type_name(&self.lib_name, "msg", type_name))
                 };
-                self.gen_array_type(None, type_str.into(), array_info, msg_type_name)
+                self.gen_array_type(None, type_str.into(), array_info)
             }
             TypeName::String(array_info) => {
-                let type_str = format!("{}::msg::RosString<0>", self.safe_drive_path);
-                self.gen_string_array_type(type_str.into(), 0, array_info)
+                let type_str = format!("{}::RosString<0>", self.msg_path);
+                Ok(self.gen_string_array_type(type_str.into(), 0, array_info))
             }
             TypeName::LimitedString { size, array_info } => {
-                let type_str = format!("{}::msg::RosString<{size}>", self.safe_drive_path);
-                self.gen_string_array_type(type_str.into(), *size, array_info)
+                let type_str = format!("{}::RosString<{size}>", self.msg_path);
+                Ok(self.gen_string_array_type(type_str.into(), *size, array_info))
             }
             TypeName::ScopedType {
                 scope,
@@ -230,36 +1526,187 @@ impl Generator {
                 array_info,
             } => {
                 let type_str = if self.lib_name == *scope {
-                    type_name.clone()
+                    self.validate_local_type(type_name)?;
+                    gen_local_type(self.renames.type_name(scope, "msg", type_name))
                 } else {
-                    match scope.as_ref() {
-                        "builtin_interfaces" => {
-                            println!(
-                                "Warning: {}::{msg_type_name} uses builtin_interfaces::{type_name} which causes the year-2038 problem.",
+                    if scope == "builtin_interfaces"
+                        && self
+                            .warned
+                            .insert((msg_type_name.to_string(), type_name.clone()))
+                    {
+                        warnings::warn(
+                            Warning::BuiltinTime2038,
+                            &format!(
+                                "{}::{msg_type_name} uses builtin_interfaces::{type_name} which causes the year-2038 problem.",
                                 self.lib_name
-                            );
-                            match type_name.as_ref() {
-                                "Time" => "builtin_interfaces::UnsafeTime".into(),
-                                "Duration" => "builtin_interfaces::UnsafeDuration".into(),
-                                _ => panic!("unsupported type: builtin_interfaces::{type_name}"),
-                            }
-                        }
-                        _ => {
-                            self.libs.insert(scope.clone());
-                            format!("{scope}::msg::{type_name}")
-                        }
+                            ),
+                        );
+                    }
+
+                    let key = (scope.clone(), type_name.clone());
+                    if let Some(type_str) = self.scoped_types.get(&key) {
+                        type_str.clone()
+                    } else {
+                        let type_str = self.resolve_scoped_type(scope, type_name)?;
+                        self.scoped_types.insert(key, type_str.clone());
+                        type_str
                     }
                 };
 
-                let arr = self.gen_array_type(
-                    Some(scope.as_str()),
-                    type_str.into(),
-                    array_info,
-                    msg_type_name,
-                );
-                arr.into_owned().into()
+                let arr = self.gen_array_type(Some(scope.as_str()), type_str.into(), array_info)?;
+                Ok(arr.into_owned().into())
+            }
+        }
+    }
+
+    /// Return an error if `type_name` is not a message of the package.
+    fn validate_local_type(&self, type_name: &str) -> Result<(), Box<dyn Error>> {
+        match &self.local_types {
+            Some(local_types) if !local_types.contains(type_name) => Err(format!(
+                "{}/{type_name} is not found in the package {}",
+                self.lib_name, self.lib_name
+            )
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rust type of `{scope}/{type_name}` defined in another package.
+    fn resolve_scoped_type(
+        &mut self,
+        scope: &str,
+        type_name: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        if scope == "builtin_interfaces" {
+            return match type_name {
+                "Time" => Ok("builtin_interfaces::UnsafeTime".to_string()),
+                "Duration" => Ok("builtin_interfaces::UnsafeDuration".to_string()),
+                _ => Err(format!("unsupported type: builtin_interfaces::{type_name}").into()),
+            };
+        }
+
+        self.libs.insert(scope.to_string());
+        let module = crate::mangle_module(scope);
+
+        // common_interfaces of safe_drive are not renamed
+        let renamed = crate::mangle_module(self.renames.package(scope));
+        let renamed_type = self.renames.type_name(scope, "msg", type_name);
+
+        let type_str = match self.package_resolution.get(scope) {
+            // {target}/{module}/(msg|srv)/{file}.rs
+            Some(Resolution::Local) if self.emit_crates => {
+                format!("::{renamed}::msg::{renamed_type}")
+            }
+            Some(Resolution::Local) => {
+                format!("super::super::super::{renamed}::msg::{renamed_type}")
+            }
+            Some(Resolution::Common) => format!(
+                "{}::common_interfaces::{module}::msg::{type_name}",
+                self.msg_path
+            ),
+            None => format!("{renamed}::msg::{renamed_type}"),
+        };
+
+        Ok(type_str)
+    }
+
+    /// Record Rust types defined by `type_name` of "msg" or "srv",
+    /// and return an error if they are already defined by another type in the package.
+    ///
+    /// Types of "msg" are glob-imported into "srv", and private `SeqRaw` types shadow glob-imported types,
+    /// so collisions among them are compiled but refer to unexpected types, which are warned.
+    fn define_types(&mut self, ext: &'static str, type_name: &str) -> Result<(), Box<dyn Error>> {
+        let (names, raw_names) = if ext == "msg" {
+            (
+                vec![type_name.to_string(), format!("{type_name}Seq")],
+                vec![format!("{type_name}SeqRaw")],
+            )
+        } else if ext == "action" {
+            let mut names = vec![
+                type_name.to_string(),
+                format!("{type_name}SendGoal"),
+                format!("{type_name}GetResult"),
+            ];
+            let mut raw_names = Vec::new();
+            for part in ACTION_MESSAGES {
+                names.push(format!("{type_name}{part}"));
+                names.push(format!("{type_name}{part}Seq"));
+                raw_names.push(format!("{type_name}{part}SeqRaw"));
+            }
+            (names, raw_names)
+        } else {
+            (
+                vec![
+                    type_name.to_string(),
+                    format!("{type_name}Request"),
+                    format!("{type_name}Response"),
+                    format!("{type_name}RequestSeq"),
+                    format!("{type_name}ResponseSeq"),
+                ],
+                vec![
+                    format!("{type_name}RequestSeqRaw"),
+                    format!("{type_name}ResponseSeqRaw"),
+                ],
+            )
+        };
+
+        let mut names = names;
+        if self.aliases && ext == "msg" {
+            names.push(format!("{type_name}Publisher"));
+            names.push(format!("{type_name}Subscriber"));
+        } else if self.aliases && ext == "srv" {
+            names.push(format!("{type_name}Client"));
+            names.push(format!("{type_name}Server"));
+        }
+
+        for name in names.iter() {
+            if let Some(prev) = self.defined.get(&(ext, name.clone())) {
+                return Err(format!(
+                    "{name} is defined by both {}/{ext}/{prev} and {}/{ext}/{type_name}",
+                    self.lib_name, self.lib_name
+                )
+                .into());
+            }
+        }
+
+        let lib_name = &self.lib_name;
+        let mut collisions = Vec::new();
+        if SHADOWING_TYPES.contains(&type_name) {
+            collisions.push(format!(
+                "{lib_name}/{ext}/{type_name} shadows the well-known type `{type_name}`, so references to it are qualified"
+            ));
+        }
+        for other in ["msg", "srv", "action"] {
+            for name in names.iter().chain(raw_names.iter()) {
+                if other == ext && names.contains(name) {
+                    continue;
+                }
+                if let Some(prev) = self.defined.get(&(other, name.clone())) {
+                    collisions.push(format!(
+                        "{name} of {lib_name}/{ext}/{type_name} collides with {name} of {lib_name}/{other}/{prev}"
+                    ));
+                }
+            }
+            for name in names.iter() {
+                if let Some(prev) = self.defined_raw.get(&(other, name.clone())) {
+                    collisions.push(format!(
+                        "{name} of {lib_name}/{ext}/{type_name} collides with {name} of {lib_name}/{other}/{prev}"
+                    ));
+                }
             }
         }
+        for collision in collisions {
+            warnings::warn(Warning::NameCollisions, &collision);
+        }
+
+        for name in names {
+            self.defined.insert((ext, name), type_name.to_string());
+        }
+        for name in raw_names {
+            self.defined_raw.insert((ext, name), type_name.to_string());
+        }
+
+        Ok(())
     }
 
     fn gen_array_type<'a>(
@@ -267,13 +1714,12 @@ impl Generator {
         scope: Option<&str>,
         type_str: Cow<'a, str>,
         array_info: &ArrayInfo,
-        type_name: &str,
-    ) -> Cow<'a, str> {
+    ) -> Result<Cow<'a, str>, Box<dyn Error>> {
         match array_info {
-            ArrayInfo::Dynamic => self.gen_seq_type(scope, type_str, 0, type_name).into(),
-            ArrayInfo::Limited(n) => self.gen_seq_type(scope, type_str, *n, type_name).into(),
-            ArrayInfo::Static(n) => format!("[{type_str}; {n}]").into(),
-            ArrayInfo::NotArray => type_str.into(),
+            ArrayInfo::Dynamic => self.gen_seq_type(scope, type_str, 0),
+            ArrayInfo::Limited(n) => self.gen_seq_type(scope, type_str, *n),
+            ArrayInfo::Static(n) => Ok(format!("[{type_str}; {n}]").into()),
+            ArrayInfo::NotArray => Ok(type_str),
         }
     }
 
@@ -284,68 +1730,824 @@ impl Generator {
         array_info: &ArrayInfo,
     ) -> Cow<'a, str> {
         match array_info {
-            ArrayInfo::Dynamic => {
-                format!("{}::msg::RosStringSeq<{strlen}, 0>", self.safe_drive_path).into()
-            }
+            ArrayInfo::Dynamic => format!("{}::RosStringSeq<{strlen}, 0>", self.msg_path).into(),
             ArrayInfo::Limited(n) => {
-                format!("{}::msg::RosStringSeq<{strlen}, {n}>", self.safe_drive_path).into()
+                format!("{}::RosStringSeq<{strlen}, {n}>", self.msg_path).into()
             }
             ArrayInfo::Static(n) => format!("[{type_str}; {n}]").into(),
-            ArrayInfo::NotArray => type_str.into(),
+            ArrayInfo::NotArray => type_str,
         }
     }
 
-    fn gen_const_type(&mut self, type_name: &'_ TypeName, msg_type_name: &str) -> Cow<'_, str> {
-        if let TypeName::String(array_info) = type_name {
-            self.gen_array_type(None, "&[u8]".into(), array_info, msg_type_name)
+    fn gen_const_type(
+        &mut self,
+        type_name: &'_ TypeName,
+        msg_type_name: &str,
+    ) -> Result<Cow<'_, str>, Box<dyn Error>> {
+        if let TypeName::String(array_info) | TypeName::LimitedString { array_info, .. } = type_name
+        {
+            self.gen_array_type(None, "&[u8]".into(), array_info)
         } else {
             self.gen_type(type_name, msg_type_name)
         }
     }
 
+    /// Collect items generated for `struct_name` and `{struct_name}Seq` from the definition before rendering,
+    /// as `(owner, kind, name)` where the owner of constants in the module is empty.
+    /// `message` is true for a .msg file, whose constants are in the module and which may have a topic,
+    /// and false for parts of services and actions, whose constants are associated with the structs.
+    fn members(
+        &self,
+        struct_name: &str,
+        exprs: &[Expr],
+        message: bool,
+    ) -> Vec<(String, &'static str, String)> {
+        let mut fields = Vec::new();
+        let mut module_consts = Vec::new();
+        let mut consts = Vec::new();
+        let mut methods = vec!["new".to_string()];
+
+        if message && self.topic.is_some() {
+            consts.push("DEFAULT_TOPIC".to_string());
+            if self.backend == Backend::SafeDrive {
+                methods.push("create_publisher".to_string());
+                methods.push("create_subscriber".to_string());
+            }
+        }
+        if message && self.qos.is_some() {
+            methods.push("recommended_qos".to_string());
+        }
+        if self.constants_by_name {
+            methods.push("constant_by_name".to_string());
+        }
+        if self.backend == Backend::MicroRos {
+            methods.push("with_pool".to_string());
+        }
+        if self.random {
+            methods.push("fill_random".to_string());
+        }
+        if self.cdr {
+            for method in ["write_cdr", "read_cdr", "encode_cdr", "decode_cdr"] {
+                methods.push(method.to_string());
+            }
+        }
+        if self.diff {
+            methods.push("diff".to_string());
+            methods.push("diff_into".to_string());
+        }
+        if is_point_cloud2(exprs) {
+            methods.push("iter_points".to_string());
+            methods.push("iter_points_by_name".to_string());
+        }
+
+        for expr in exprs.iter() {
+            let Expr::Variable {
+                type_name,
+                var_name,
+                value,
+                ros_name,
+                optional,
+                unit,
+                key,
+                range,
+                default,
+                ..
+            } = expr
+            else {
+                continue;
+            };
+
+            if let Some(ValueType::Const(_)) = value {
+                let name = crate::mangle(var_name).into_owned();
+                if message {
+                    module_consts.push(name);
+                } else {
+                    consts.push(name);
+                }
+                continue;
+            }
+
+            let field = crate::mangle(var_name).into_owned();
+            fields.push(field.clone());
+
+            if *key && !consts.iter().any(|c| c == "KEY_FIELDS") {
+                consts.push("KEY_FIELDS".to_string());
+            }
+
+            // gen_renamed_accessors
+            if let Some(ros_name) = ros_name {
+                let snake = ros_name.to_case(Case::Snake);
+                let accessor = crate::mangle(&snake).into_owned();
+                if accessor != field && accessor != "new" {
+                    methods.push(accessor);
+                    methods.push(format!("{snake}_mut"));
+                }
+            }
+
+            // gen_optional_accessors
+            if optional.is_some() {
+                methods.push(format!("{var_name}_opt"));
+                methods.push(format!("set_{var_name}_opt"));
+            }
+
+            let (TypeName::Type { array_info, .. }
+            | TypeName::ScopedType { array_info, .. }
+            | TypeName::LimitedString { array_info, .. }
+            | TypeName::String(array_info)) = type_name;
+
+            // gen_bound_helpers
+            if let ArrayInfo::Limited(_) = array_info {
+                consts.push(format!("{}_MAX_LEN", var_name.to_uppercase()));
+                methods.push(format!("{var_name}_push_checked"));
+                if matches!(type_name, TypeName::Type { type_name, .. } if gen_primitives(type_name).is_some())
+                {
+                    methods.push(format!("{var_name}_try_set"));
+                }
+            }
+
+            match type_name {
+                // gen_bytes_helpers
+                TypeName::Type {
+                    type_name,
+                    array_info: ArrayInfo::Dynamic | ArrayInfo::Limited(_),
+                } if type_name == "uint8" || type_name == "byte" => {
+                    methods.push(format!("{var_name}_as_bytes"));
+                    methods.push(format!("{var_name}_copy_from_slice"));
+                }
+
+                // gen_metadata and gen_uom_accessors
+                TypeName::Type {
+                    type_name,
+                    array_info: ArrayInfo::NotArray,
+                } if gen_primitives(type_name).is_some() => {
+                    if range.is_some() {
+                        methods.push(format!("{var_name}_range"));
+                        methods.push(format!("{var_name}_in_range"));
+                    }
+                    if default.is_some() {
+                        consts.push(format!("{}_DEFAULT", var_name.to_uppercase()));
+                    }
+                    let float = type_name == "float32" || type_name == "float64";
+                    if self.uom && float && unit.as_deref().and_then(uom_unit).is_some() {
+                        methods.push(format!("{var_name}_quantity"));
+                        methods.push(format!("set_{var_name}_quantity"));
+                    }
+                }
+
+                // gen_time_conversions
+                TypeName::ScopedType {
+                    scope,
+                    type_name,
+                    array_info: ArrayInfo::NotArray,
+                } if scope == "builtin_interfaces" => {
+                    let mut conversions = Vec::new();
+                    match type_name.as_str() {
+                        "Time" if !self.no_std => conversions.push("system_time"),
+                        "Duration" => conversions.push("duration"),
+                        _ => (),
+                    }
+                    if self.chrono && (type_name == "Time" || type_name == "Duration") {
+                        conversions.push("chrono");
+                    }
+                    for conversion in conversions {
+                        methods.push(format!("{var_name}_as_{conversion}"));
+                        methods.push(format!("set_{var_name}_from_{conversion}"));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let mut members: Vec<_> = module_consts
+            .into_iter()
+            .map(|name| (String::new(), "constant", name))
+            .chain(
+                fields
+                    .into_iter()
+                    .map(|name| (struct_name.to_string(), "field", name)),
+            )
+            .chain(
+                consts
+                    .into_iter()
+                    .map(|name| (struct_name.to_string(), "associated constant", name)),
+            )
+            .chain(
+                methods
+                    .into_iter()
+                    .map(|name| (struct_name.to_string(), "method", name)),
+            )
+            .collect();
+
+        // `{struct_name}Seq` of impl_seq!, TypedSeq or the expanded one
+        if !self.is_packed() {
+            let seq = format!("{struct_name}Seq");
+            for field in ["data", "size", "capacity"] {
+                members.push((seq.clone(), "field", field.to_string()));
+            }
+            for method in SEQ_METHODS {
+                members.push((seq.clone(), "method", method.to_string()));
+            }
+        }
+
+        members
+    }
+
+    fn warn_collisions(&self, type_name: &str, members: &[(String, &'static str, String)]) {
+        for collision in find_collisions(members) {
+            warnings::warn(
+                Warning::NameCollisions,
+                &format!("{}::{type_name}: {collision}", self.lib_name),
+            );
+        }
+    }
+
+    /// Generate a compile-time check that `struct_name` without fields has the same layout as the C struct,
+    /// which has only `uint8_t structure_needs_at_least_one_member`.
+    /// Layouts overridden by `repr` are not checked.
+    fn gen_empty_layout_check(&self, lines: &mut VecDeque<Cow<'_, str>>, struct_name: &str) {
+        if self.repr.is_none() {
+            lines.push_back(
+                format!(
+                    "const _: () = assert!(
+    core::mem::size_of::<{struct_name}>() == 1 && core::mem::align_of::<{struct_name}>() == 1
+);"
+                )
+                .into(),
+            );
+        }
+    }
+
+    /// Generate accessors named after the original names of fields renamed by `@rust_name`.
+    fn gen_renamed_accessors(
+        &mut self,
+        lines: &mut VecDeque<Cow<'_, str>>,
+        struct_name: &str,
+        exprs: &[Expr],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut methods = String::new();
+        for expr in exprs.iter() {
+            let Expr::Variable {
+                type_name,
+                var_name,
+                value: None | Some(ValueType::Default(_)),
+                ros_name: Some(ros_name),
+                ..
+            } = expr
+            else {
+                continue;
+            };
+
+            let field = crate::mangle(var_name);
+            let snake = ros_name.to_case(Case::Snake);
+            let accessor = crate::mangle(&snake);
+            let accessor_mut = format!("{snake}_mut");
+
+            // `new` is the constructor
+            if accessor == field || accessor == "new" {
+                continue;
+            }
+
+            let ty = self.gen_type(type_name, struct_name)?;
+            methods.push_str(&format!(
+                "
+    /// `{ros_name}` in the definition, which is the field `{field}`.
+    pub fn {accessor}(&self) -> &{ty} {{
+        &self.{field}
+    }}
+
+    /// `{ros_name}` in the definition, which is the field `{field}`.
+    pub fn {accessor_mut}(&mut self) -> &mut {ty} {{
+        &mut self.{field}
+    }}
+"
+            ));
+        }
+
+        if !methods.is_empty() {
+            lines.push_back(format!("\nimpl {struct_name} {{{methods}}}").into());
+        }
+
+        Ok(())
+    }
+
+    /// Generate `{field}_opt()` and `set_{field}_opt()` of fields annotated by `@optional`.
+    fn gen_optional_accessors(
+        &mut self,
+        lines: &mut VecDeque<Cow<'_, str>>,
+        struct_name: &str,
+        exprs: &[Expr],
+    ) -> Result<(), Box<dyn Error>> {
+        gen_optional_accessors(lines, struct_name, exprs, |type_name| {
+            Ok(self.gen_type(type_name, struct_name)?.into_owned())
+        })
+    }
+
+    /// Generate `fill_random()` of `struct_name`.
+    /// Fields of types in other packages are filled only if the packages are generated locally.
+    fn gen_fill_random(
+        &mut self,
+        lines: &mut VecDeque<Cow<'_, str>>,
+        struct_name: &str,
+        exprs: &[Expr],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut fields = Vec::new();
+        for expr in exprs.iter() {
+            let Expr::Variable {
+                type_name,
+                var_name,
+                value: None | Some(ValueType::Default(_)),
+                ..
+            } = expr
+            else {
+                continue;
+            };
+
+            let ty = self.gen_type(type_name, struct_name)?.into_owned();
+            fields.push(RandomField {
+                field: crate::mangle(var_name),
+                type_name,
+                ty: ty.into(),
+                generated: self.is_generated_type(type_name),
+            });
+        }
+
+        gen_fill_random(lines, struct_name, &fields, self.no_std, self.fallible_api);
+        Ok(())
+    }
+
+    /// Generate `{struct_name}Owned` mirroring fields of primitive types, strings and generated messages.
+    fn gen_owned(
+        &mut self,
+        lines: &mut VecDeque<Cow<'_, str>>,
+        struct_name: &str,
+        exprs: &[Expr],
+    ) -> Result<(), Box<dyn Error>> {
+        // types of common_interfaces of safe_drive do not have mirrors
+        if !self.has_known_fields(exprs) {
+            warnings::warn(
+                Warning::UnmirroredMessages,
+                &format!(
+                    "{}::{struct_name} has no owned mirror because it has fields of types not generated by ros2msg_to_rs",
+                    self.lib_name
+                ),
+            );
+            return Ok(());
+        }
+
+        let mut fields = Vec::new();
+        for expr in exprs.iter() {
+            let Expr::Variable {
+                type_name,
+                var_name,
+                value: None | Some(ValueType::Default(_)),
+                ..
+            } = expr
+            else {
+                continue;
+            };
+
+            let ty = self.gen_type(type_name, struct_name)?.into_owned();
+            let element = self
+                .gen_type(&element_type(type_name), struct_name)?
+                .into_owned();
+            fields.push(OwnedField {
+                var_name,
+                type_name,
+                ty: ty.into(),
+                element: element.into(),
+            });
+        }
+
+        gen_owned(lines, struct_name, &fields, self.no_std, self.fallible_api);
+        Ok(())
+    }
+
+    /// Generate `encode_cdr()` and `decode_cdr()` of `struct_name` if every field is known.
+    fn gen_cdr_methods(
+        &mut self,
+        lines: &mut VecDeque<Cow<'_, str>>,
+        struct_name: &str,
+        exprs: &[Expr],
+    ) -> Result<(), Box<dyn Error>> {
+        // layouts of types of common_interfaces of safe_drive are unknown
+        if !self.has_known_fields(exprs) {
+            return Ok(());
+        }
+
+        let mut fields = Vec::new();
+        for expr in exprs.iter() {
+            let Expr::Variable {
+                type_name,
+                var_name,
+                value: None | Some(ValueType::Default(_)),
+                ..
+            } = expr
+            else {
+                continue;
+            };
+
+            let ty = self.gen_type(type_name, struct_name)?.into_owned();
+            fields.push(CdrField {
+                var_name,
+                type_name,
+                ty: ty.into(),
+            });
+        }
+
+        gen_cdr_methods(lines, struct_name, &fields, self.no_std, self.fallible_api);
+        Ok(())
+    }
+
+    /// Generate `diff()` of `struct_name` if every field is known.
+    fn gen_diff(&self, lines: &mut VecDeque<Cow<'_, str>>, struct_name: &str, exprs: &[Expr]) {
+        // fields of types of common_interfaces of safe_drive are unknown
+        if !self.has_known_fields(exprs) {
+            return;
+        }
+
+        let fields: Vec<DiffField> = exprs
+            .iter()
+            .filter_map(|expr| match expr {
+                Expr::Variable {
+                    type_name,
+                    var_name,
+                    value: None | Some(ValueType::Default(_)),
+                    ..
+                } => Some(DiffField {
+                    var_name,
+                    type_name,
+                }),
+                _ => None,
+            })
+            .collect();
+        gen_diff(lines, struct_name, &fields, self.no_std);
+    }
+
+    /// Whether every field is a primitive type, a string, a message generated by this tool or of builtin_interfaces,
+    /// or an array or a sequence of them.
+    /// Nested messages must have known fields too if `known_types` is set.
+    fn has_known_fields(&self, exprs: &[Expr]) -> bool {
+        let Some(types) = self.referred_types(exprs) else {
+            return false;
+        };
+        match &self.known_types {
+            Some(known_types) => types.iter().all(|key| known_types.contains(key)),
+            None => true,
+        }
+    }
+
+    /// Whether the type is a message generated by ros2msg_to_rs, rather than a primitive type, a string,
+    /// or a type of common_interfaces of safe_drive.
+    fn is_generated_type(&self, type_name: &TypeName) -> bool {
+        match type_name {
+            TypeName::Type { type_name, .. } => gen_primitives(type_name).is_none(),
+            TypeName::ScopedType { scope, .. } if *scope != self.lib_name => {
+                match self.package_resolution.get(scope) {
+                    _ if scope == "builtin_interfaces" => false,
+                    Some(Resolution::Local) => true,
+                    Some(Resolution::Common) => false,
+                    None => self.disable_common_interfaces || self.input_packages.contains(scope),
+                }
+            }
+            TypeName::ScopedType { .. } => true,
+            TypeName::String(_) | TypeName::LimitedString { .. } => false,
+        }
+    }
+
+    /// Generate `unsafe impl Send` and `unsafe impl Sync` of `struct_name`
+    /// if every field is a primitive type, a string, a message generated by this tool or of builtin_interfaces,
+    /// or an array or a sequence of them.
+    /// The pointers of strings and sequences are owned by the message and never shared,
+    /// so moving or sharing the message across threads is sound.
+    /// Messages having types of common interfaces, directly or by nested messages, are left to the auto traits,
+    /// because their impls are unknown.
+    fn gen_send_sync(&self, lines: &mut VecDeque<Cow<'_, str>>, struct_name: &str, exprs: &[Expr]) {
+        if !self.send_sync {
+            return;
+        }
+
+        if !self.has_known_fields(exprs) {
+            return;
+        }
+
+        lines.push_back(
+            format!(
+                "
+// SAFETY: strings and sequences of {struct_name} are owned by the message and never shared.
+unsafe impl Send for {struct_name} {{}}
+unsafe impl Sync for {struct_name} {{}}"
+            )
+            .into(),
+        );
+    }
+
+    /// Generate `{FIELD}_MAX_LEN`, `{field}_push_checked()` and, for primitive types, `{field}_try_set()`
+    /// of `T[<=N]` fields, which return errors instead of exceeding the bounds.
+    fn gen_bound_helpers(
+        &mut self,
+        lines: &mut VecDeque<Cow<'_, str>>,
+        struct_name: &str,
+        exprs: &[Expr],
+    ) -> Result<(), Box<dyn Error>> {
+        let mut methods = String::new();
+        for expr in exprs.iter() {
+            let Expr::Variable {
+                type_name,
+                var_name,
+                value: None | Some(ValueType::Default(_)),
+                ..
+            } = expr
+            else {
+                continue;
+            };
+
+            let (TypeName::Type { array_info, .. }
+            | TypeName::ScopedType { array_info, .. }
+            | TypeName::LimitedString { array_info, .. }
+            | TypeName::String(array_info)) = type_name;
+            let ArrayInfo::Limited(n) = array_info else {
+                continue;
+            };
+
+            let field = crate::mangle(var_name);
+            let max_len = format!("{}_MAX_LEN", var_name.to_uppercase());
+            let to_option = if self.is_generated_type(type_name) {
+                self.fallible_api.to_option()
+            } else {
+                ""
+            };
+            let seq = self.gen_type(type_name, struct_name)?.into_owned();
+            let element = self
+                .gen_type(&element_type(type_name), struct_name)?
+                .into_owned();
+
+            // errors have the name of the field
+            let path = format!("{struct_name}::{var_name}");
+            let error = self.fallible_api.field_error();
+            let allocation = self.fallible_api.allocation(&path);
+            methods.push_str(&format!(
+                "
+    /// Maximum number of elements of `{var_name}`.
+    pub const {max_len}: usize = {n};
+
+    /// Append `value` to `{var_name}`, which reallocates the sequence.
+    /// Returns an error if `{var_name}` already has {n} elements or memory allocation fails.
+    pub fn {var_name}_push_checked(&mut self, value: {element}) -> Result<(), {error}> {{
+        let len = self.{field}.as_slice().len();
+        if len >= Self::{max_len} {{
+            return Err({});
+        }}
+
+        let mut seq = <{seq}>::new(len + 1){to_option}.ok_or({allocation})?;
+        for (dst, src) in seq.as_slice_mut().iter_mut().zip(self.{field}.as_slice_mut()) {{
+            core::mem::swap(dst, src);
+        }}
+        seq.as_slice_mut()[len] = value;
+        self.{field} = seq;
+        Ok(())
+    }}",
+                self.fallible_api.exceeds_bound(&path, "len + 1", *n)
+            ));
+
+            // only primitive types can be copied
+            if matches!(type_name, TypeName::Type { type_name, .. } if gen_primitives(type_name).is_some())
+            {
+                methods.push_str(&format!(
+                    "
+
+    /// Replace `{var_name}` by a copy of `values`.
+    /// Returns an error if `values` has more than {n} elements or memory allocation fails.
+    pub fn {var_name}_try_set(&mut self, values: &[{element}]) -> Result<(), {error}> {{
+        if values.len() > Self::{max_len} {{
+            return Err({});
+        }}
+
+        let mut seq = <{seq}>::new(values.len()){to_option}.ok_or({allocation})?;
+        seq.as_slice_mut().copy_from_slice(values);
+        self.{field} = seq;
+        Ok(())
+    }}",
+                    self.fallible_api.exceeds_bound(&path, "values.len()", *n)
+                ));
+            }
+        }
+
+        if !methods.is_empty() {
+            lines.push_back(format!("\nimpl {struct_name} {{{methods}\n}}").into());
+        }
+        Ok(())
+    }
+
+    /// Generate `{field}_as_bytes()` and `{field}_copy_from_slice()` for `uint8[]` and `byte[]` fields,
+    /// and `TryFrom<&[u8]>` if the struct has exactly one such field.
+    fn gen_bytes_helpers(
+        &self,
+        lines: &mut VecDeque<Cow<'_, str>>,
+        struct_name: &str,
+        exprs: &[Expr],
+    ) {
+        let mut fields = Vec::new();
+        for expr in exprs.iter() {
+            if let Expr::Variable {
+                type_name:
+                    TypeName::Type {
+                        type_name,
+                        array_info,
+                    },
+                var_name,
+                value: None | Some(ValueType::Default(_)),
+                ..
+            } = expr
+            {
+                let size = match array_info {
+                    ArrayInfo::Dynamic => 0,
+                    ArrayInfo::Limited(n) => *n,
+                    _ => continue,
+                };
+
+                if type_name == "uint8" || type_name == "byte" {
+                    fields.push((var_name.as_str(), size));
+                }
+            }
+        }
+
+        if fields.is_empty() {
+            return;
+        }
+
+        let mut methods = Vec::new();
+        for (var_name, size) in fields.iter() {
+            let field = crate::mangle(var_name);
+            let seq = format!("{}::U8Seq::<{size}>", self.msg_path);
+            methods.push(format!(
+                "
+    /// `{var_name}` as a byte slice.
+    pub fn {var_name}_as_bytes(&self) -> &[u8] {{
+        self.{field}.as_slice()
+    }}
+
+    /// Replace `{var_name}` by a copy of `src`.
+    /// Returns `false` if memory allocation fails or the length of `src` exceeds the limit.
+    #[must_use = \"the field is not replaced if `false` is returned\"]
+    pub fn {var_name}_copy_from_slice(&mut self, src: &[u8]) -> bool {{
+        if let Some(mut seq) = {seq}::new(src.len()) {{
+            seq.as_slice_mut().copy_from_slice(src);
+            self.{field} = seq;
+            true
+        }} else {{
+            false
+        }}
+    }}"
+            ));
+        }
+
+        let methods = methods.concat();
+        lines.push_back(format!("\nimpl {struct_name} {{{methods}\n}}").into());
+
+        if let [(var_name, size)] = fields.as_slice() {
+            let (error, new, failure) = match self.fallible_api {
+                FallibleApi::Option => (
+                    "()".to_string(),
+                    "Self::new().ok_or(())?",
+                    " else {\n            Err(())\n        }".to_string(),
+                ),
+                FallibleApi::Result => {
+                    // tell which field fails
+                    let path = format!("{struct_name}::{var_name}");
+                    let allocation = format!(
+                        " else {{\n            Err({})\n        }}",
+                        self.fallible_api.allocation(&path)
+                    );
+                    let failure = if *size == 0 {
+                        allocation
+                    } else {
+                        let exceeds = self.fallible_api.exceeds_bound(&path, "value.len()", *size);
+                        format!(" else if value.len() > {size} {{\n            Err({exceeds})\n        }}{allocation}")
+                    };
+                    (
+                        self.fallible_api.field_error().to_string(),
+                        "Self::new()?",
+                        failure,
+                    )
+                }
+            };
+            let try_from = format!(
+                "
+/// Create `{struct_name}` whose `{var_name}` is a copy of the slice.
+/// The other fields are initialized by `{struct_name}::new()`.
+impl TryFrom<&[u8]> for {struct_name} {{
+    type Error = {error};
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {{
+        let mut msg = {new};
+        if msg.{var_name}_copy_from_slice(value) {{
+            Ok(msg)
+        }}{failure}
+    }}
+}}"
+            );
+            lines.push_back(try_from.into());
+        }
+    }
+
     fn gen_seq_type<'a>(
         &self,
         scope: Option<&str>,
         type_str: Cow<'a, str>,
         size: usize,
-        type_name: &str,
-    ) -> Cow<'a, str> {
-        match type_str.as_ref() {
-            "bool" => format!("{}::msg::BoolSeq<{size}>", self.safe_drive_path).into(),
-            "i8" => format!("{}::msg::I8Seq<{size}>", self.safe_drive_path).into(),
-            "i16" => format!("{}::msg::I16Seq<{size}>", self.safe_drive_path).into(),
-            "i32" => format!("{}::msg::I32Seq<{size}>", self.safe_drive_path).into(),
-            "i64" => format!("{}::msg::I64Seq<{size}>", self.safe_drive_path).into(),
-            "u8" => format!("{}::msg::U8Seq<{size}>", self.safe_drive_path).into(),
-            "u16" => format!("{}::msg::U16Seq<{size}>", self.safe_drive_path).into(),
-            "u32" => format!("{}::msg::U32Seq<{size}>", self.safe_drive_path).into(),
-            "u64" => format!("{}::msg::U64Seq<{size}>", self.safe_drive_path).into(),
-            "f32" => format!("{}::msg::F32Seq<{size}>", self.safe_drive_path).into(),
-            "f64" => format!("{}::msg::F64Seq<{size}>", self.safe_drive_path).into(),
+    ) -> Result<Cow<'a, str>, Box<dyn Error>> {
+        let seq = match type_str.as_ref() {
+            "bool" => format!("{}::BoolSeq<{size}>", self.msg_path).into(),
+            "i8" => format!("{}::I8Seq<{size}>", self.msg_path).into(),
+            "i16" => format!("{}::I16Seq<{size}>", self.msg_path).into(),
+            "i32" => format!("{}::I32Seq<{size}>", self.msg_path).into(),
+            "i64" => format!("{}::I64Seq<{size}>", self.msg_path).into(),
+            "u8" => format!("{}::U8Seq<{size}>", self.msg_path).into(),
+            "u16" => format!("{}::U16Seq<{size}>", self.msg_path).into(),
+            "u32" => format!("{}::U32Seq<{size}>", self.msg_path).into(),
+            "u64" => format!("{}::U64Seq<{size}>", self.msg_path).into(),
+            "f32" => format!("{}::F32Seq<{size}>", self.msg_path).into(),
+            "f64" => format!("{}::F64Seq<{size}>", self.msg_path).into(),
             _ => match scope {
                 Some("builtin_interfaces") => {
-                    println!(
-                        "Warning: {}::{type_name} uses builtin_interfaces::{type_str} which causes the year-2038 problem.",
-                        self.lib_name
-                    );
+                    // `type_str` is `builtin_interfaces::UnsafeTime` if the type is in another package
+                    let seq = match type_str.as_ref() {
+                        "Time" | "builtin_interfaces::UnsafeTime" => "UnsafeTimeSeq",
+                        "Duration" | "builtin_interfaces::UnsafeDuration" => "UnsafeDurationSeq",
+                        _ => {
+                            return Err(
+                                format!("unsupported type: builtin_interfaces::{type_str}").into()
+                            )
+                        }
+                    };
 
-                    match type_str.as_ref() {
-                        "Time" => format!(
-                            "{}::msg::builtin_interfaces::UnsafeTimeSeq<{size}>",
-                            self.safe_drive_path
-                        )
-                        .into(),
-                        "Duration" => format!(
-                            "{}::msg::builtin_interfaces::UnsafeDurationSeq<{size}>",
-                            self.safe_drive_path
-                        )
-                        .into(),
-                        _ => panic!("unsupported type: builtin_interfaces::{type_str}"),
-                    }
+                    format!(
+                        "{}::msg::builtin_interfaces::{seq}<{size}>",
+                        self.safe_drive_path
+                    )
+                    .into()
                 }
                 _ => format!("{type_str}Seq<{size}>").into(),
             },
-        }
+        };
+
+        Ok(seq)
+    }
+}
+
+/// Names of messages which conflict with items of the prelude or glob-imported from safe_drive.
+/// Suffixes of messages generated of an action, such as `FibonacciGoal` and `FibonacciFeedbackMessage`.
+const ACTION_MESSAGES: [&str; 8] = [
+    "Goal",
+    "Result",
+    "Feedback",
+    "SendGoalRequest",
+    "SendGoalResponse",
+    "GetResultRequest",
+    "GetResultResponse",
+    "FeedbackMessage",
+];
+
+const SHADOWING_TYPES: &[&str] = &["String", "Time", "Duration", "Header"];
+
+/// Rust type of `type_name` defined in the package, which is qualified if it shadows a well-known type.
+/// `super::super` is the package in both of msg and srv files.
+fn gen_local_type(type_name: &str) -> String {
+    if SHADOWING_TYPES.contains(&type_name) {
+        format!("super::super::msg::{type_name}")
+    } else {
+        type_name.to_string()
+    }
+}
+
+/// `use alloc::{...};` of no_std, except items conflicting with types defined in the file.
+fn gen_alloc_import(defined: &[&str]) -> String {
+    let items: Vec<&str> = ["format", "string::String", "vec::Vec"]
+        .into_iter()
+        .filter(|item| !defined.iter().any(|d| item.rsplit("::").next() == Some(*d)))
+        .collect();
+    format!("use alloc::{{{}}};", items.join(", "))
+}
+
+/// Type of elements of an array type.
+fn element_type(type_name: &TypeName) -> TypeName {
+    match type_name {
+        TypeName::Type { type_name, .. } => TypeName::Type {
+            type_name: type_name.clone(),
+            array_info: ArrayInfo::NotArray,
+        },
+        TypeName::ScopedType {
+            scope, type_name, ..
+        } => TypeName::ScopedType {
+            scope: scope.clone(),
+            type_name: type_name.clone(),
+            array_info: ArrayInfo::NotArray,
+        },
+        TypeName::LimitedString { size, .. } => TypeName::LimitedString {
+            size: *size,
+            array_info: ArrayInfo::NotArray,
+        },
+        TypeName::String(_) => TypeName::String(ArrayInfo::NotArray),
     }
 }
 
@@ -353,7 +2555,25 @@ fn gen_value(value: &Value) -> String {
     format!("{value}")
 }
 
-fn gen_primitives(type_name: &str) -> Option<&str> {
+/// Byte literal of a constant of `char` or `byte` written as a character, like `char C = 'a'`.
+/// The character is escaped for Rust, such as `\n` and `\\`.
+pub fn gen_char_value(type_name: &str, c: &str) -> Result<String, Box<dyn Error>> {
+    let literal = match c.as_bytes() {
+        [b] if b.is_ascii() => b.escape_ascii().to_string(),
+        _ => {
+            let c = c.escape_default();
+            return Err(format!("'{c}' is not a single ASCII character").into());
+        }
+    };
+
+    if type_name == "char" {
+        Ok(format!("b'{literal}' as i8"))
+    } else {
+        Ok(format!("b'{literal}'"))
+    }
+}
+
+pub fn gen_primitives(type_name: &str) -> Option<&str> {
     let t = match type_name {
         "bool" => "bool",
         "int8" => "i8",
@@ -373,61 +2593,384 @@ fn gen_primitives(type_name: &str) -> Option<&str> {
     Some(t)
 }
 
-fn gen_cfun_msg(lines: &mut VecDeque<Cow<'_, str>>, module_name: &str, type_name: &str) {
-    let cfun = format!(
-        "
-extern \"C\" {{
-    fn {module_name}__msg__{type_name}__init(msg: *mut {type_name}) -> bool;
-    fn {module_name}__msg__{type_name}__fini(msg: *mut {type_name});
-    fn {module_name}__msg__{type_name}__are_equal(lhs: *const {type_name}, rhs: *const {type_name}) -> bool;
-    fn {module_name}__msg__{type_name}__Sequence__init(msg: *mut {type_name}SeqRaw, size: usize) -> bool;
-    fn {module_name}__msg__{type_name}__Sequence__fini(msg: *mut {type_name}SeqRaw);
-    fn {module_name}__msg__{type_name}__Sequence__are_equal(lhs: *const {type_name}SeqRaw, rhs: *const {type_name}SeqRaw) -> bool;
-    fn rosidl_typesupport_c__get_message_type_support_handle__{module_name}__msg__{type_name}() -> *const rcl::rosidl_message_type_support_t;
-}}
-"
-    );
-    lines.push_back(cfun.into());
+/// Methods of `{Type}Seq`, which are the same for all the implementations of `SeqImpl`.
+const SEQ_METHODS: [&str; 10] = [
+    "new",
+    "null",
+    "as_slice",
+    "as_slice_mut",
+    "iter",
+    "iter_mut",
+    "len",
+    "is_empty",
+    "resize",
+    "push",
+];
+
+/// Find items defined twice in `members` collected by `Generator::members()`,
+/// such as fields mangled to the same name, and helper methods colliding with each other.
+/// Methods and associated constants share a namespace.
+fn find_collisions(members: &[(String, &'static str, String)]) -> Vec<String> {
+    let mut defined = BTreeMap::new();
+    let mut collisions = Vec::new();
+
+    for (owner, kind, name) in members.iter() {
+        let namespace = if *kind == "field" { "field" } else { "value" };
+        let Some(prev) = defined.insert((owner, namespace, name), *kind) else {
+            continue;
+        };
+
+        let path = if owner.is_empty() {
+            name.clone()
+        } else {
+            format!("{owner}::{name}")
+        };
+        if prev == *kind {
+            collisions.push(format!("{kind} `{path}` is generated twice"));
+        } else {
+            collisions.push(format!("{kind} `{path}` collides with a {prev}"));
+        }
+    }
+
+    collisions
 }
 
-fn gen_cfun_srv(lines: &mut VecDeque<Cow<'_, str>>, module_name: &str, type_name: &str) {
-    let cfun = format!(
-        "
-extern \"C\" {{
-    fn {module_name}__srv__{type_name}_Request__init(msg: *mut {type_name}Request) -> bool;
-    fn {module_name}__srv__{type_name}_Request__fini(msg: *mut {type_name}Request);
-    fn {module_name}__srv__{type_name}_Request__Sequence__init(msg: *mut {type_name}RequestSeqRaw, size: usize) -> bool;
-    fn {module_name}__srv__{type_name}_Request__Sequence__fini(msg: *mut {type_name}RequestSeqRaw);
-    fn {module_name}__srv__{type_name}_Response__init(msg: *mut {type_name}Response) -> bool;
-    fn {module_name}__srv__{type_name}_Response__fini(msg: *mut {type_name}Response);
-    fn {module_name}__srv__{type_name}_Response__Sequence__init(msg: *mut {type_name}ResponseSeqRaw, size: usize) -> bool;
-    fn {module_name}__srv__{type_name}_Response__Sequence__fini(msg: *mut {type_name}ResponseSeqRaw);
-    fn rosidl_typesupport_c__get_service_type_support_handle__{module_name}__srv__{type_name}() -> *const rcl::rosidl_service_type_support_t;
-    fn rosidl_typesupport_c__get_message_type_support_handle__{module_name}__srv__{type_name}_Request() -> *const rcl::rosidl_message_type_support_t;
-    fn rosidl_typesupport_c__get_message_type_support_handle__{module_name}__srv__{type_name}_Response() -> *const rcl::rosidl_message_type_support_t;
-}}
-"
-    );
-    lines.push_back(cfun.into());
+/// Mangled names of the constants.
+fn const_names(exprs: &[Expr]) -> BTreeSet<String> {
+    exprs
+        .iter()
+        .filter_map(|expr| match expr {
+            Expr::Variable {
+                var_name,
+                value: Some(ValueType::Const(_)),
+                ..
+            } => Some(crate::mangle(var_name).into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Generate `impl {struct_name}` having `consts` as associated constants.
+fn gen_assoc_consts(lines: &mut VecDeque<Cow<'_, str>>, struct_name: &str, consts: &[String]) {
+    if consts.is_empty() {
+        return;
+    }
+
+    lines.push_back("".into());
+    lines.push_back(format!("impl {struct_name} {{").into());
+    for c in consts {
+        for line in c.lines() {
+            lines.push_back(format!("    {line}").into());
+        }
+    }
+    lines.push_back("}".into());
+}
+
+/// Generate a doc comment of `struct_name` showing how to construct it and set a couple of fields.
+fn gen_example_doc(lines: &mut VecDeque<Cow<'_, str>>, struct_name: &str, exprs: &[Expr]) {
+    let mut statements = Vec::new();
+    for expr in exprs.iter() {
+        if statements.len() == 2 {
+            break;
+        }
+
+        let Expr::Variable {
+            type_name,
+            var_name,
+            value: None | Some(ValueType::Default(_)),
+            deprecated: None,
+            ..
+        } = expr
+        else {
+            continue;
+        };
+
+        let field = crate::mangle(var_name);
+        let statement = match type_name {
+            TypeName::Type {
+                type_name,
+                array_info: ArrayInfo::NotArray,
+            } => match gen_primitives(type_name) {
+                Some("bool") => format!("msg.{field} = true;"),
+                Some("f32" | "f64") => format!("msg.{field} = 1.0;"),
+                Some(_) => format!("msg.{field} = 1;"),
+                None => continue,
+            },
+            TypeName::String(ArrayInfo::NotArray) => format!("msg.{field}.assign(\"{var_name}\");"),
+            TypeName::LimitedString {
+                size,
+                array_info: ArrayInfo::NotArray,
+            } => {
+                // the name of the field, which does not exceed the bound
+                let value: String = var_name.chars().take(*size).collect();
+                format!("msg.{field}.assign(\"{value}\");")
+            }
+            _ => continue,
+        };
+        statements.push(statement);
+    }
+
+    lines.push_back("/// # Example".into());
+    lines.push_back("///".into());
+    lines.push_back("/// ```ignore".into());
+    if statements.is_empty() {
+        lines.push_back(format!("/// let msg = {struct_name}::new().unwrap();").into());
+    } else {
+        lines.push_back(format!("/// let mut msg = {struct_name}::new().unwrap();").into());
+        for statement in statements {
+            lines.push_back(format!("/// {statement}").into());
+        }
+    }
+    lines.push_back("/// ```".into());
+}
+
+/// `#[deprecated]` attribute. `reason` is the note if it is not empty.
+/// Generate a line comment of `comment` copied from a definition.
+/// A comment starting with `/` or `!` is separated from `//` so that it does not become a doc comment.
+fn gen_comment(comment: &str) -> String {
+    let comment = sanitize_comment(comment);
+    if comment.starts_with(['/', '!']) {
+        format!("// {comment}")
+    } else {
+        format!("//{comment}")
+    }
+}
+
+/// Make `comment` safe to be emitted in comments.
+/// Control characters except tabs are replaced by spaces,
+/// and characters changing the direction of text are escaped because rustc denies them in comments.
+fn sanitize_comment(comment: &str) -> String {
+    let mut result = String::with_capacity(comment.len());
+    for c in comment.chars() {
+        match c {
+            '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}' => {
+                result.extend(c.escape_unicode());
+            }
+            '\t' => result.push(c),
+            c if c.is_control() => result.push(' '),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn gen_deprecated(reason: &str) -> String {
+    if reason.is_empty() {
+        "#[deprecated]".to_string()
+    } else {
+        format!("#[deprecated(note = {reason:?})]")
+    }
+}
+
+fn has_deprecated(exprs: &[Expr]) -> bool {
+    exprs.iter().any(|expr| {
+        matches!(
+            expr,
+            Expr::Variable {
+                deprecated: Some(_),
+                ..
+            }
+        )
+    })
+}
+
+/// Quantity, module and unit of `uom::si` corresponding to a unit annotation.
+fn uom_unit(unit: &str) -> Option<(&'static str, &'static str, &'static str)> {
+    let t = match unit {
+        "m" => ("Length", "length", "meter"),
+        "s" => ("Time", "time", "second"),
+        "kg" => ("Mass", "mass", "kilogram"),
+        "rad" => ("Angle", "angle", "radian"),
+        "deg" => ("Angle", "angle", "degree"),
+        "m/s" => ("Velocity", "velocity", "meter_per_second"),
+        "m/s^2" | "m/s2" => ("Acceleration", "acceleration", "meter_per_second_squared"),
+        "rad/s" => ("AngularVelocity", "angular_velocity", "radian_per_second"),
+        "N" => ("Force", "force", "newton"),
+        "Pa" => ("Pressure", "pressure", "pascal"),
+        "K" => (
+            "ThermodynamicTemperature",
+            "thermodynamic_temperature",
+            "kelvin",
+        ),
+        "Hz" => ("Frequency", "frequency", "hertz"),
+        _ => return None,
+    };
+    Some(t)
+}
+
+/// Generate `{field}_quantity()` and `set_{field}_quantity()` typed by `uom`
+/// for `float32` and `float64` fields annotated with a known unit.
+fn gen_uom_accessors(lines: &mut VecDeque<Cow<'_, str>>, struct_name: &str, exprs: &[Expr]) {
+    let mut methods = Vec::new();
+    for expr in exprs.iter() {
+        if let Expr::Variable {
+            type_name:
+                TypeName::Type {
+                    type_name,
+                    array_info: ArrayInfo::NotArray,
+                },
+            var_name,
+            value: None | Some(ValueType::Default(_)),
+            unit: Some(unit),
+            ..
+        } = expr
+        {
+            let float = match type_name.as_str() {
+                "float32" => "f32",
+                "float64" => "f64",
+                _ => continue,
+            };
+
+            let Some((quantity, module, unit_name)) = uom_unit(unit) else {
+                continue;
+            };
+
+            let field = crate::mangle(var_name);
+            methods.push(format!(
+                "
+    /// `{var_name}` as `uom::si::{float}::{quantity}`. The unit of the field is `{unit}`.
+    pub fn {var_name}_quantity(&self) -> uom::si::{float}::{quantity} {{
+        uom::si::{float}::{quantity}::new::<uom::si::{module}::{unit_name}>(self.{field})
+    }}
+
+    /// Set `{var_name}` by `uom::si::{float}::{quantity}`. The unit of the field is `{unit}`.
+    pub fn set_{var_name}_quantity(&mut self, value: uom::si::{float}::{quantity}) {{
+        self.{field} = value.get::<uom::si::{module}::{unit_name}>();
+    }}"
+            ));
+        }
+    }
+
+    if !methods.is_empty() {
+        let methods = methods.concat();
+        lines.push_back(format!("\nimpl {struct_name} {{{methods}\n}}").into());
+    }
+}
+
+/// Member of messages without fields.
+/// C does not allow empty structs, so rosidl's C struct has this member, which is matched for the same layout.
+/// It is never read, but is not prefixed by `_` to keep the name.
+const EMPTY_MEMBER: &str = "    #[allow(dead_code)]\n    structure_needs_at_least_one_member: u8";
+
+/// `extern "C"` declarations of a generated file, which are emitted as one block.
+/// Declarations are de-duplicated by their symbols.
+/// Functions of messages precede type supports, and each group keeps the order of addition.
+#[derive(Default)]
+struct Externs {
+    functions: Vec<(String, String)>,
+    type_supports: Vec<(String, String)>,
+}
+
+impl Externs {
+    /// Declare `__init`, `__fini` and those of the sequence of the C struct `c_name`, which is `type_name` in Rust.
+    /// `__are_equal` is also declared if `are_equal` is true.
+    fn add_msg(&mut self, c_name: &str, type_name: &str, are_equal: bool) {
+        for (suffix, ty) in [
+            ("", type_name.to_string()),
+            ("__Sequence", format!("{type_name}SeqRaw")),
+        ] {
+            let init_size = if suffix.is_empty() {
+                ""
+            } else {
+                ", size: usize"
+            };
+            self.add_function(
+                format!("{c_name}{suffix}__init"),
+                format!("(msg: *mut {ty}{init_size}) -> bool"),
+            );
+            self.add_function(
+                format!("{c_name}{suffix}__fini"),
+                format!("(msg: *mut {ty})"),
+            );
+            if are_equal {
+                self.add_function(
+                    format!("{c_name}{suffix}__are_equal"),
+                    format!("(lhs: *const {ty}, rhs: *const {ty}) -> bool"),
+                );
+            }
+        }
+    }
+
+    /// Declare the type support of the message or service `c_name`. `kind` is `message` or `service`.
+    fn add_type_support(&mut self, backend: Backend, kind: &str, c_name: &str) {
+        let symbol = type_support_symbol(backend, kind, c_name);
+        if !self.contains(&symbol) {
+            self.type_supports.push((
+                symbol,
+                format!("() -> *const rcl::rosidl_{kind}_type_support_t"),
+            ));
+        }
+    }
+
+    fn add_function(&mut self, symbol: String, signature: String) {
+        if !self.contains(&symbol) {
+            self.functions.push((symbol, signature));
+        }
+    }
+
+    fn contains(&self, symbol: &str) -> bool {
+        self.functions
+            .iter()
+            .chain(self.type_supports.iter())
+            .any(|(s, _)| s == symbol)
+    }
+
+    /// Generate the block with `attrs`, such as `#[link(...)]`, before it.
+    fn gen(&self, attrs: &[String]) -> String {
+        let mut block = String::from("\n");
+        for attr in attrs.iter() {
+            block.push_str(&format!("{attr}\n"));
+        }
+        block.push_str("extern \"C\" {\n");
+        for (symbol, signature) in self.functions.iter().chain(self.type_supports.iter()) {
+            block.push_str(&format!("    fn {symbol}{signature};\n"));
+        }
+        block.push_str("}\n");
+        block
+    }
 }
 
-fn gen_impl_and_seq_msg(lines: &mut VecDeque<Cow<'_, str>>, module_name: &str, type_name: &str) {
+fn gen_impl_and_seq_msg(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    c_name: &str,
+    type_name: &str,
+    defaults: &[String],
+    backend: Backend,
+    seq_impl: SeqImpl,
+    fallible_api: FallibleApi,
+) {
+    let type_support = type_support_symbol(backend, "message", c_name);
     // generate impl and struct of sequence
-    let impl_str = gen_impl(module_name, type_name, "", "", MsgOrSrv::Msg);
+    let impl_str = gen_impl(c_name, type_name, "", "", defaults, seq_impl, fallible_api);
     let impl_trait_str = format!(
         "
 impl TypeSupport for {type_name} {{
     fn type_support() -> *const rcl::rosidl_message_type_support_t {{
-        unsafe {{
-            rosidl_typesupport_c__get_message_type_support_handle__{module_name}__msg__{type_name}()
+        // the pointer is valid during the process, so the address is cached
+        // initializing twice is harmless because the address is the same
+        static TYPE_SUPPORT: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+        let mut type_support = TYPE_SUPPORT.load(core::sync::atomic::Ordering::Relaxed);
+        if type_support == 0 {{
+            type_support = unsafe {{ {type_support}() }} as usize;
+            TYPE_SUPPORT.store(type_support, core::sync::atomic::Ordering::Relaxed);
         }}
+        type_support as *const rcl::rosidl_message_type_support_t
     }}
 }}
+"
+    );
+
+    lines.push_back(impl_str.into());
+    lines.push_back(impl_trait_str.into());
+}
 
+fn gen_partial_eq_ffi(lines: &mut VecDeque<Cow<'_, str>>, c_name: &str, type_name: &str) {
+    let impl_eq = format!(
+        "
 impl PartialEq for {type_name} {{
     fn eq(&self, other: &Self) -> bool {{
         unsafe {{
-            {module_name}__msg__{type_name}__are_equal(self, other)
+            {c_name}__are_equal(self, other)
         }}
     }}
 }}
@@ -437,58 +2980,153 @@ impl<const N: usize> PartialEq for {type_name}Seq<N> {{
         unsafe {{
             let msg1 = {type_name}SeqRaw{{data: self.data, size: self.size, capacity: self.capacity}};
             let msg2 = {type_name}SeqRaw{{data: other.data, size: other.size, capacity: other.capacity}};
-            {module_name}__msg__{type_name}__Sequence__are_equal(&msg1, &msg2)
+            {c_name}__Sequence__are_equal(&msg1, &msg2)
         }}
     }}
 }}
 "
     );
+    lines.push_back(impl_eq.into());
+}
 
-    lines.push_back(impl_str.into());
-    lines.push_back(impl_trait_str.into());
+/// Generate `PartialEq` comparing fields one by one instead of calling `__are_equal`.
+fn gen_partial_eq_rust(lines: &mut VecDeque<Cow<'_, str>>, type_name: &str, exprs: &[Expr]) {
+    let cmp: Vec<String> = variable_names(exprs)
+        .iter()
+        .map(|name| format!("self.{name} == other.{name}"))
+        .collect();
+
+    let (other, cmp) = if cmp.is_empty() {
+        ("_other", "true".to_string())
+    } else {
+        ("other", cmp.join(" &&\n            "))
+    };
+
+    let impl_eq = format!(
+        "
+impl PartialEq for {type_name} {{
+    fn eq(&self, {other}: &Self) -> bool {{
+        {cmp}
+    }}
+}}
+
+impl<const N: usize> PartialEq for {type_name}Seq<N> {{
+    fn eq(&self, other: &Self) -> bool {{
+        self.as_slice() == other.as_slice()
+    }}
+}}
+"
+    );
+    lines.push_back(impl_eq.into());
+}
+
+/// Generate `PartialEq` of a packed message, which copies its fields because they may be unaligned.
+fn gen_partial_eq_packed(lines: &mut VecDeque<Cow<'_, str>>, type_name: &str, exprs: &[Expr]) {
+    let names = variable_names(exprs);
+    let other = if names.is_empty() { "_other" } else { "other" };
+    let cmp: String = names
+        .iter()
+        .map(|name| {
+            format!(
+                "        let (left, right) = (self.{name}, other.{name});
+        if left != right {{
+            return false;
+        }}
+"
+            )
+        })
+        .collect();
+
+    let impl_eq = format!(
+        "
+impl PartialEq for {type_name} {{
+    fn eq(&self, {other}: &Self) -> bool {{
+{cmp}        true
+    }}
+}}
+"
+    );
+    lines.push_back(impl_eq.into());
+}
+
+/// Mangled names of the non-constant fields.
+fn variable_names(exprs: &[Expr]) -> Vec<Cow<'_, str>> {
+    exprs
+        .iter()
+        .filter_map(|expr| match expr {
+            Expr::Variable {
+                var_name,
+                value: None | Some(ValueType::Default(_)),
+                ..
+            } => Some(crate::mangle(var_name)),
+            _ => None,
+        })
+        .collect()
 }
 
-fn gen_impl_and_seq_srv(lines: &mut VecDeque<Cow<'_, str>>, module_name: &str, type_name: &str) {
+fn gen_impl_and_seq_srv(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    c_name: &str,
+    type_name: &str,
+    [defaults_req, defaults_resp]: [&[String]; 2],
+    backend: Backend,
+    seq_impl: SeqImpl,
+    fallible_api: FallibleApi,
+) {
+    let type_support_req = type_support_symbol(backend, "message", &format!("{c_name}_Request"));
+    let type_support_resp = type_support_symbol(backend, "message", &format!("{c_name}_Response"));
+
     // generate impl and struct of sequence
-    let impl_str_req = gen_impl(module_name, type_name, "Request", "_Request", MsgOrSrv::Srv);
+    let impl_str_req = gen_impl(
+        c_name,
+        type_name,
+        "Request",
+        "_Request",
+        defaults_req,
+        seq_impl,
+        fallible_api,
+    );
     let impl_str_resp = gen_impl(
-        module_name,
+        c_name,
         type_name,
         "Response",
         "_Response",
-        MsgOrSrv::Srv,
+        defaults_resp,
+        seq_impl,
+        fallible_api,
     );
 
     lines.push_back(impl_str_req.into());
     lines.push_back(impl_str_resp.into());
 
+    let service_msg = gen_service_msg(c_name, type_name, backend);
     let struct_srv = format!(
-        "
-pub struct {type_name};
-
-impl ServiceMsg for {type_name} {{
-    type Request = {type_name}Request;
-    type Response = {type_name}Response;
-    fn type_support() -> *const rcl::rosidl_service_type_support_t {{
-        unsafe {{
-            rosidl_typesupport_c__get_service_type_support_handle__{module_name}__srv__{type_name}()
-        }}
-    }}
-}}
-
+        "{service_msg}
 impl TypeSupport for {type_name}Request {{
     fn type_support() -> *const rcl::rosidl_message_type_support_t {{
-        unsafe {{
-            rosidl_typesupport_c__get_message_type_support_handle__{module_name}__srv__{type_name}_Request()
+        // the pointer is valid during the process, so the address is cached
+        // initializing twice is harmless because the address is the same
+        static TYPE_SUPPORT: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+        let mut type_support = TYPE_SUPPORT.load(core::sync::atomic::Ordering::Relaxed);
+        if type_support == 0 {{
+            type_support = unsafe {{ {type_support_req}() }} as usize;
+            TYPE_SUPPORT.store(type_support, core::sync::atomic::Ordering::Relaxed);
         }}
+        type_support as *const rcl::rosidl_message_type_support_t
     }}
 }}
 
 impl TypeSupport for {type_name}Response {{
     fn type_support() -> *const rcl::rosidl_message_type_support_t {{
-        unsafe {{
-            rosidl_typesupport_c__get_message_type_support_handle__{module_name}__srv__{type_name}_Response()
+        // the pointer is valid during the process, so the address is cached
+        // initializing twice is harmless because the address is the same
+        static TYPE_SUPPORT: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+        let mut type_support = TYPE_SUPPORT.load(core::sync::atomic::Ordering::Relaxed);
+        if type_support == 0 {{
+            type_support = unsafe {{ {type_support_resp}() }} as usize;
+            TYPE_SUPPORT.store(type_support, core::sync::atomic::Ordering::Relaxed);
         }}
+        type_support as *const rcl::rosidl_message_type_support_t
     }}
 }}
 "
@@ -497,47 +3135,191 @@ impl TypeSupport for {type_name}Response {{
     lines.push_back(struct_srv.into());
 }
 
-#[derive(PartialEq, Eq)]
-enum MsgOrSrv {
-    Msg,
-    Srv,
+/// Generate `pub struct {type_name}` having the type support of the action.
+fn gen_action_msg(c_name: &str, type_name: &str, backend: Backend) -> String {
+    let type_support = type_support_symbol(backend, "action", c_name);
+    format!(
+        "
+/// Action of [`{type_name}Goal`], [`{type_name}Result`] and [`{type_name}Feedback`].
+pub struct {type_name};
+
+impl {type_name} {{
+    pub fn type_support() -> *const rcl::rosidl_action_type_support_t {{
+        // the pointer is valid during the process, so the address is cached
+        // initializing twice is harmless because the address is the same
+        static TYPE_SUPPORT: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+        let mut type_support = TYPE_SUPPORT.load(core::sync::atomic::Ordering::Relaxed);
+        if type_support == 0 {{
+            type_support = unsafe {{ {type_support}() }} as usize;
+            TYPE_SUPPORT.store(type_support, core::sync::atomic::Ordering::Relaxed);
+        }}
+        type_support as *const rcl::rosidl_action_type_support_t
+    }}
+}}
+"
+    )
+}
+
+/// Generate `pub struct {type_name}` implementing `ServiceMsg` of `{type_name}Request` and `{type_name}Response`.
+fn gen_service_msg(c_name: &str, type_name: &str, backend: Backend) -> String {
+    let type_support = type_support_symbol(backend, "service", c_name);
+    format!(
+        "
+pub struct {type_name};
+
+impl ServiceMsg for {type_name} {{
+    type Request = {type_name}Request;
+    type Response = {type_name}Response;
+    fn type_support() -> *const rcl::rosidl_service_type_support_t {{
+        // the pointer is valid during the process, so the address is cached
+        // initializing twice is harmless because the address is the same
+        static TYPE_SUPPORT: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+        let mut type_support = TYPE_SUPPORT.load(core::sync::atomic::Ordering::Relaxed);
+        if type_support == 0 {{
+            type_support = unsafe {{ {type_support}() }} as usize;
+            TYPE_SUPPORT.store(type_support, core::sync::atomic::Ordering::Relaxed);
+        }}
+        type_support as *const rcl::rosidl_service_type_support_t
+    }}
+}}
+"
+    )
 }
 
-fn gen_impl(
-    module_name: &str,
+/// Generate `new()`, `Default` and `Drop` of a message, whose C functions are `{c_name}__init` and so on.
+/// `new()` runs `defaults`, statements setting fields of `msg` to their default values, after the initialization.
+fn gen_impl_msg(
+    c_name: &str,
     type_name: &str,
-    req_resp: &str,
-    c_func_mid: &str,
-    msg_or_srv: MsgOrSrv,
+    defaults: &[String],
+    fallible_api: FallibleApi,
 ) -> String {
-    let mid = if msg_or_srv == MsgOrSrv::Msg {
-        "msg"
+    let ret = fallible_api.ret();
+    let ok = fallible_api.ok();
+    let err = fallible_api.err("Allocation { field: None }");
+    let failure = match fallible_api {
+        FallibleApi::Option => "`None`",
+        FallibleApi::Result => "`MsgError::Allocation`",
+    };
+    let (doc, defaults) = if defaults.is_empty() {
+        ("", String::new())
     } else {
-        "srv"
+        let statements: String = defaults
+            .iter()
+            .map(|s| format!("            {s}\n"))
+            .collect();
+        (
+            "\n    /// Fields are set to the default values of the definition.",
+            statements,
+        )
     };
 
-    let type_name_full = format!("{type_name}{req_resp}");
-
     format!(
         "
-impl {type_name_full} {{
-    pub fn new() -> Option<Self> {{
-        let mut msg: Self = unsafe {{ std::mem::MaybeUninit::zeroed().assume_init() }};
-        if unsafe {{ {module_name}__{mid}__{type_name}{c_func_mid}__init(&mut msg) }} {{
-            Some(msg)
+impl {type_name} {{
+    /// Create a message initialized by the C function.{doc}
+    /// Returns {failure} if the initialization fails.
+    #[must_use]
+    pub fn new() -> {ret} {{
+        let mut msg: Self = unsafe {{ core::mem::MaybeUninit::zeroed().assume_init() }};
+        if unsafe {{ {c_name}__init(&mut msg) }} {{
+{defaults}            {ok}(msg)
         }} else {{
-            None
+            {err}
         }}
     }}
 }}
 
-impl Drop for {type_name_full} {{
+impl Default for {type_name} {{
+    /// Same as `new()` but panics if the initialization fails.
+    fn default() -> Self {{
+        Self::new().expect(\"failed to initialize {type_name}\")
+    }}
+}}
+
+impl Drop for {type_name} {{
     fn drop(&mut self) {{
-        unsafe {{ {module_name}__{mid}__{type_name}{c_func_mid}__fini(self) }};
+        unsafe {{ {c_name}__fini(self) }};
     }}
 }}
 
-#[repr(C)]
+"
+    )
+}
+
+/// Generate `new()` of a packed message, which sets the default values without the C initializer.
+/// Packed messages have neither `Drop`, sequences nor type support because they are not passed to C functions.
+fn gen_impl_packed(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    type_name: &str,
+    defaults: &[String],
+    fallible_api: FallibleApi,
+) {
+    let ret = fallible_api.ret();
+    let ok = fallible_api.ok();
+    let (doc, binding, defaults) = if defaults.is_empty() {
+        ("", "msg", String::new())
+    } else {
+        let statements: String = defaults.iter().map(|s| format!("        {s}\n")).collect();
+        (
+            "\n    /// Fields are set to the default values of the definition.",
+            "mut msg",
+            statements,
+        )
+    };
+
+    lines.push_back(
+        format!(
+            "
+impl {type_name} {{
+    /// Create a message whose fields are zero, which never fails.{doc}
+    /// The C initializer is not called because the layout differs from the C struct.
+    #[must_use]
+    pub fn new() -> {ret} {{
+        let {binding}: Self = unsafe {{ core::mem::MaybeUninit::zeroed().assume_init() }};
+{defaults}        {ok}(msg)
+    }}
+}}
+
+impl Default for {type_name} {{
+    /// Same as `new()`.
+    fn default() -> Self {{
+        Self::new().expect(\"failed to initialize {type_name}\")
+    }}
+}}
+"
+        )
+        .into(),
+    );
+}
+
+fn gen_impl(
+    c_name: &str,
+    type_name: &str,
+    req_resp: &str,
+    c_func_mid: &str,
+    defaults: &[String],
+    seq_impl: SeqImpl,
+    fallible_api: FallibleApi,
+) -> String {
+    let type_name_full = format!("{type_name}{req_resp}");
+    let c_name_full = format!("{c_name}{c_func_mid}");
+    let impl_msg = gen_impl_msg(&c_name_full, &type_name_full, defaults, fallible_api);
+    let ret = fallible_api.ret();
+    let ok = fallible_api.ok();
+    let err_alloc = fallible_api.err("Allocation { field: None }");
+    let err_bound = fallible_api.err("ExceedsBound { field: None, len: size, max: N }");
+    match seq_impl {
+        SeqImpl::Expanded => (),
+        SeqImpl::Macro => return gen_impl_seq(&c_name_full, &type_name_full, defaults),
+        SeqImpl::Typed => {
+            let typed_seq = gen_typed_seq(&c_name_full, &type_name_full);
+            return format!("{impl_msg}{typed_seq}");
+        }
+    }
+
+    format!(
+        "{impl_msg}#[repr(C)]
 #[derive(Debug)]
 struct {type_name_full}SeqRaw {{
     data: *mut {type_name_full},
@@ -560,22 +3342,24 @@ impl<const N: usize> {type_name_full}Seq<N> {{
     /// Create a sequence of.
     /// `N` represents the maximum number of elements.
     /// If `N` is `0`, the sequence is unlimited.
-    pub fn new(size: usize) -> Option<Self> {{
+    #[must_use]
+    pub fn new(size: usize) -> {ret} {{
         if N != 0 && size > N {{
             // the size exceeds in the maximum number
-            return None;
+            return {err_bound};
         }}
 
-        let mut msg: {type_name_full}SeqRaw = unsafe {{ std::mem::MaybeUninit::zeroed().assume_init() }};
-        if unsafe {{ {module_name}__{mid}__{type_name}{c_func_mid}__Sequence__init(&mut msg, size) }} {{
-            Some(Self {{data: msg.data, size: msg.size, capacity: msg.capacity }})
+        let mut msg: {type_name_full}SeqRaw = unsafe {{ core::mem::MaybeUninit::zeroed().assume_init() }};
+        if unsafe {{ {c_name_full}__Sequence__init(&mut msg, size) }} {{
+            {ok}(Self {{data: msg.data, size: msg.size, capacity: msg.capacity }})
         }} else {{
-            None
+            {err_alloc}
         }}
     }}
 
+    #[must_use]
     pub fn null() -> Self {{
-        let msg: {type_name_full}SeqRaw = unsafe {{ std::mem::MaybeUninit::zeroed().assume_init() }};
+        let msg: {type_name_full}SeqRaw = unsafe {{ core::mem::MaybeUninit::zeroed().assume_init() }};
         Self {{data: msg.data, size: msg.size, capacity: msg.capacity }}
     }}
 
@@ -583,7 +3367,7 @@ impl<const N: usize> {type_name_full}Seq<N> {{
         if self.data.is_null() {{
             &[]
         }} else {{
-            let s = unsafe {{ std::slice::from_raw_parts(self.data, self.size as _) }};
+            let s = unsafe {{ core::slice::from_raw_parts(self.data, self.size as _) }};
             s
         }}
     }}
@@ -592,16 +3376,16 @@ impl<const N: usize> {type_name_full}Seq<N> {{
         if self.data.is_null() {{
             &mut []
         }} else {{
-            let s = unsafe {{ std::slice::from_raw_parts_mut(self.data, self.size as _) }};
+            let s = unsafe {{ core::slice::from_raw_parts_mut(self.data, self.size as _) }};
             s
         }}
     }}
 
-    pub fn iter(&self) -> std::slice::Iter<'_, {type_name_full}> {{
+    pub fn iter(&self) -> core::slice::Iter<'_, {type_name_full}> {{
         self.as_slice().iter()
     }}
 
-    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, {type_name_full}> {{
+    pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, {type_name_full}> {{
         self.as_slice_mut().iter_mut()
     }}
 
@@ -612,12 +3396,93 @@ impl<const N: usize> {type_name_full}Seq<N> {{
     pub fn is_empty(&self) -> bool {{
         self.len() == 0
     }}
+
+    /// Resize the sequence.
+    /// New elements are initialized by `{type_name_full}::new()`.
+    /// Return `false` if `size` exceeds `N` or the allocation fails.
+    #[must_use = \"the sequence is not resized if `false` is returned\"]
+    pub fn resize(&mut self, size: usize) -> bool {{
+        if N != 0 && size > N {{
+            // the size exceeds in the maximum number
+            return false;
+        }}
+
+        let len = self.len();
+        if size <= self.capacity as usize {{
+            if size > len {{
+                // elements beyond the size are still initialized, so just reset them
+                let s = unsafe {{ core::slice::from_raw_parts_mut(self.data, size) }};
+                for elem in s[len..].iter_mut() {{
+                    let {ok}(msg) = {type_name_full}::new() else {{
+                        return false;
+                    }};
+                    *elem = msg;
+                }}
+            }}
+            self.size = size as _;
+            return true;
+        }}
+
+        // reallocate with extra capacity to push elements in amortized constant time
+        let mut capacity = size.max(self.capacity as usize * 2);
+        if N != 0 {{
+            capacity = capacity.min(N);
+        }}
+
+        let {ok}(mut seq) = Self::new(capacity) else {{
+            return false;
+        }};
+        for (dst, src) in seq.as_slice_mut().iter_mut().zip(self.as_slice_mut()) {{
+            core::mem::swap(dst, src);
+        }}
+        seq.size = size as _;
+
+        // the old sequence is finalized by drop
+        core::mem::swap(self, &mut seq);
+        true
+    }}
+
+    /// Append an element to the end of the sequence.
+    /// Return the element back if the sequence is full or the allocation fails.
+    pub fn push(&mut self, value: {type_name_full}) -> Result<(), {type_name_full}> {{
+        let len = self.len();
+        if !self.resize(len + 1) {{
+            return Err(value);
+        }}
+        self.as_slice_mut()[len] = value;
+        Ok(())
+    }}
+}}
+
+impl<'a, const N: usize> IntoIterator for &'a {type_name_full}Seq<N> {{
+    type Item = &'a {type_name_full};
+    type IntoIter = core::slice::Iter<'a, {type_name_full}>;
+
+    fn into_iter(self) -> Self::IntoIter {{
+        self.iter()
+    }}
+}}
+
+impl<'a, const N: usize> IntoIterator for &'a mut {type_name_full}Seq<N> {{
+    type Item = &'a mut {type_name_full};
+    type IntoIter = core::slice::IterMut<'a, {type_name_full}>;
+
+    fn into_iter(self) -> Self::IntoIter {{
+        self.iter_mut()
+    }}
+}}
+
+impl<const N: usize> Default for {type_name_full}Seq<N> {{
+    /// Same as `null()`.
+    fn default() -> Self {{
+        Self::null()
+    }}
 }}
 
 impl<const N: usize> Drop for {type_name_full}Seq<N> {{
     fn drop(&mut self) {{
         let mut msg = {type_name_full}SeqRaw{{data: self.data, size: self.size, capacity: self.capacity}};
-        unsafe {{ {module_name}__{mid}__{type_name}{c_func_mid}__Sequence__fini(&mut msg) }};
+        unsafe {{ {c_name_full}__Sequence__fini(&mut msg) }};
     }}
 }}
 
@@ -626,3 +3491,469 @@ unsafe impl<const N: usize> Sync for {type_name_full}Seq<N> {{}}
 "
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{find_collisions, gen_impl, gen_partial_eq_rust, FallibleApi, Generator};
+    use crate::{
+        context::{self, Context},
+        msg_error::gen_error_module,
+        parser,
+        seq::SeqImpl,
+        warnings::{self, Warning},
+    };
+    use nom::Finish;
+    use std::collections::VecDeque;
+
+    fn collisions(input: &str, message: bool) -> Vec<String> {
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        find_collisions(&g.members("Foo", &exprs, message))
+    }
+
+    #[test]
+    fn test_collisions() {
+        // `type` is mangled to `type_`
+        assert_eq!(
+            collisions("int32 type\nint32 type_\n", true),
+            ["field `Foo::type_` is generated twice"]
+        );
+
+        // the accessor of the original name and the range of `speed`
+        assert_eq!(
+            collisions(
+                "float64 speed # @range(min=0, max=1)\nfloat64 speed_range # @rust_name limit\n",
+                true
+            ),
+            ["method `Foo::speed_range` is generated twice"]
+        );
+
+        // constants of parts of services are associated with the structs
+        let input = "int32[<=2] x\nint32 X_MAX_LEN=2\n";
+        assert!(collisions(input, true).is_empty());
+        assert_eq!(
+            collisions(input, false),
+            ["associated constant `Foo::X_MAX_LEN` is generated twice"]
+        );
+
+        // `new` is not an accessor
+        assert!(collisions("int32 new # @rust_name create\n", true).is_empty());
+    }
+
+    /// The number of `name-collisions` reported by defining `types` as `(ext, type_name)` in a package,
+    /// or an error if a type is defined twice.
+    fn type_collisions(
+        types: &[(&'static str, &str)],
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let context = Context {
+            levels: warnings::levels(&[], &[], &[Warning::NameCollisions]),
+            ..Default::default()
+        };
+        context.scope(|| {
+            let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+            for (ext, type_name) in types {
+                g.define_types(ext, type_name)?;
+            }
+            Ok(context::with(|c| c.denied))
+        })
+    }
+
+    #[test]
+    fn test_type_collisions() {
+        assert_eq!(
+            type_collisions(&[("msg", "Foo"), ("srv", "Bar")]).unwrap(),
+            0
+        );
+
+        // types of services and actions shadow messages in either order, including their sequences
+        assert_eq!(
+            type_collisions(&[("msg", "FooRequest"), ("srv", "Foo")]).unwrap(),
+            2
+        );
+        assert_eq!(
+            type_collisions(&[("srv", "Foo"), ("msg", "FooResponseSeq")]).unwrap(),
+            1
+        );
+        assert_eq!(
+            type_collisions(&[("msg", "FooGoal"), ("action", "Foo")]).unwrap(),
+            2
+        );
+
+        // private `SeqRaw` types shadow glob-imported messages
+        assert_eq!(
+            type_collisions(&[("msg", "FooRequestSeqRaw"), ("srv", "Foo")]).unwrap(),
+            1
+        );
+
+        // types of the same kind are not shadowed but defined twice
+        let e = type_collisions(&[("msg", "Foo"), ("msg", "FooSeq")]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "FooSeq is defined by both my_library/msg/Foo and my_library/msg/FooSeq"
+        );
+    }
+
+    #[test]
+    fn test_seq_collisions() {
+        let input = "uint8[] data\nuint32 size\nuint32 capacity\nint32[<=4] push\n";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        let members = g.members("Foo", &exprs, true);
+
+        // fields of `FooSeq` and `Foo` are different
+        for name in ["data", "size", "capacity"] {
+            assert!(members.contains(&("Foo".to_string(), "field", name.to_string())));
+            assert!(members.contains(&("FooSeq".to_string(), "field", name.to_string())));
+        }
+        for name in ["resize", "push"] {
+            assert!(members.contains(&("FooSeq".to_string(), "method", name.to_string())));
+        }
+        assert!(find_collisions(&members).is_empty());
+
+        // a seq method colliding with another one is found
+        let mut members = members;
+        members.push(("FooSeq".to_string(), "method", "resize".to_string()));
+        assert_eq!(
+            find_collisions(&members),
+            ["method `FooSeq::resize` is generated twice"]
+        );
+
+        // packed messages have no sequences
+        g.set_repr(Some("packed".to_string()));
+        let members = g.members("Foo", &exprs, true);
+        assert!(!members.iter().any(|(owner, _, _)| owner == "FooSeq"));
+    }
+
+    /// Mocks of `U8Seq` of safe_drive, and messages having `uint8[<=4] data` or `uint8[] data`.
+    /// `{option}` and `{result}` are replaced by the byte helpers of each fallible API.
+    const BYTES_MOCKS: &str = "
+pub mod error {
+    {error}
+}
+
+pub mod msg {
+    /// Fails if `len` exceeds the bound `N`, or allocation of 13 elements is requested.
+    pub struct U8Seq<const N: usize>(Vec<u8>);
+
+    impl<const N: usize> U8Seq<N> {
+        pub fn new(len: usize) -> Option<Self> {
+            ((N == 0 || len <= N) && len != 13).then(|| Self(vec![0; len]))
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            &self.0
+        }
+
+        pub fn as_slice_mut(&mut self) -> &mut [u8] {
+            &mut self.0
+        }
+    }
+}
+
+pub mod option {
+    pub mod msg {
+        pub struct Bounded {
+            pub data: crate::msg::U8Seq<4>,
+        }
+
+        impl Bounded {
+            pub fn new() -> Option<Self> {
+                Some(Self { data: crate::msg::U8Seq::new(0)? })
+            }
+        }
+
+        {option}
+    }
+}
+
+pub mod result {
+    pub mod msg {
+        use crate::error::MsgError;
+
+        pub struct Bounded {
+            pub data: crate::msg::U8Seq<4>,
+        }
+
+        impl Bounded {
+            pub fn new() -> Result<Self, MsgError> {
+                Ok(Self { data: crate::msg::U8Seq::new(0).unwrap() })
+            }
+        }
+
+        pub struct Unbounded {
+            pub data: crate::msg::U8Seq<0>,
+        }
+
+        impl Unbounded {
+            pub fn new() -> Result<Self, MsgError> {
+                Ok(Self { data: crate::msg::U8Seq::new(0).unwrap() })
+            }
+        }
+
+        {result}
+    }
+}
+";
+
+    const BYTES_TESTS: &str = r#"
+use crate::error::MsgError;
+
+#[test]
+fn test_option() {
+    use crate::option::msg::Bounded;
+
+    let mut msg = Bounded::try_from(&[1, 2][..]).unwrap();
+    assert_eq!(msg.data_as_bytes(), [1, 2]);
+
+    // slices longer than the bound fail and keep the field
+    assert!(Bounded::try_from(&[0; 5][..]).is_err());
+    assert!(!msg.data_copy_from_slice(&[0; 5]));
+    assert_eq!(msg.data_as_bytes(), [1, 2]);
+    assert!(msg.data_copy_from_slice(&[1, 2, 3, 4]));
+    assert_eq!(msg.data_as_bytes(), [1, 2, 3, 4]);
+}
+
+#[test]
+fn test_result() {
+    use crate::result::msg::{Bounded, Unbounded};
+
+    let msg = Bounded::try_from(&[1, 2, 3, 4][..]).unwrap();
+    assert_eq!(msg.data_as_bytes(), [1, 2, 3, 4]);
+
+    // slices longer than the bound are not allocation failures
+    let e = Bounded::try_from(&[0; 5][..]).err().unwrap();
+    let exceeds = MsgError::ExceedsBound { field: Some("Bounded::data"), len: 5, max: 4 };
+    assert_eq!(e, exceeds);
+    assert_eq!(e.to_string(), "5 elements of Bounded::data exceed the bound of 4 elements");
+
+    let msg = Unbounded::try_from(&[0; 100][..]).unwrap();
+    assert_eq!(msg.data_as_bytes().len(), 100);
+
+    // the field failing to allocate is reported
+    let e = Unbounded::try_from(&[0; 13][..]).err().unwrap();
+    assert_eq!(e, MsgError::Allocation { field: Some("Unbounded::data") });
+    assert_eq!(e.field(), Some("Unbounded::data"));
+    assert_eq!(e.to_string(), "failed to allocate Unbounded::data");
+}
+"#;
+
+    #[test]
+    fn test_bytes_helpers() {
+        let gen = |fallible_api, name: &str, input: &str| {
+            let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+            let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+            g.set_fallible_api(fallible_api);
+            let mut lines = VecDeque::new();
+            g.gen_bytes_helpers(&mut lines, name, &exprs);
+            lines.iter().map(|l| format!("{l}\n")).collect::<String>()
+        };
+
+        let option = gen(FallibleApi::Option, "Bounded", "uint8[<=4] data\n");
+        let result = gen(FallibleApi::Result, "Bounded", "uint8[<=4] data\n")
+            + &gen(FallibleApi::Result, "Unbounded", "byte[] data\n");
+        let code = BYTES_MOCKS
+            .replace("{error}", &gen_error_module(false))
+            .replace("{option}", &option)
+            .replace("{result}", &result);
+        crate::tests::run_generated_tests("bytes_helpers", &(code + BYTES_TESTS));
+    }
+
+    /// Mocks of the C functions of `pkg/msg/Elem`, whose sequences allocate as many elements as requested.
+    /// `{seq}` is replaced by the generated `Elem` and `ElemSeq`.
+    const SEQ_MOCKS: &str = "
+use std::cell::Cell;
+
+#[allow(non_camel_case_types)]
+type size_t = usize;
+
+thread_local! {
+    /// The number of sequences allocated.
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct Elem {
+    pub value: i32,
+}
+
+unsafe fn pkg__msg__Elem__init(msg: *mut Elem) -> bool {
+    (*msg).value = 7;
+    true
+}
+
+unsafe fn pkg__msg__Elem__fini(_msg: *mut Elem) {}
+
+unsafe fn pkg__msg__Elem__Sequence__init(seq: *mut ElemSeqRaw, size: usize) -> bool {
+    ALLOCATIONS.with(|n| n.set(n.get() + 1));
+    let elems: Box<[Elem]> = (0..size).map(|_| Elem { value: 7 }).collect();
+    (*seq).data = Box::into_raw(elems) as *mut Elem;
+    (*seq).size = size;
+    (*seq).capacity = size;
+    true
+}
+
+/// Finalize all the elements of the capacity.
+unsafe fn pkg__msg__Elem__Sequence__fini(seq: *mut ElemSeqRaw) {
+    if !(*seq).data.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut((*seq).data, (*seq).capacity)));
+    }
+}
+
+{seq}
+";
+
+    const SEQ_TESTS: &str = "
+#[test]
+fn test_resize() {
+    let values = |seq: &ElemSeq<4>| seq.iter().map(|e| e.value).collect::<Vec<_>>();
+    let allocations = || ALLOCATIONS.with(|n| n.get());
+
+    let mut seq = ElemSeq::<4>::new(2).unwrap();
+    seq.as_slice_mut()[0].value = 1;
+    seq.as_slice_mut()[1].value = 2;
+    let base = allocations();
+
+    // shrinking keeps the capacity
+    assert!(seq.resize(1));
+    assert_eq!(values(&seq), [1]);
+    assert_eq!(seq.capacity, 2);
+
+    // growing within the capacity initializes the elements again
+    assert!(seq.resize(2));
+    assert_eq!(values(&seq), [1, 7]);
+    assert_eq!(allocations(), base);
+
+    // growing beyond the capacity reallocates twice as much, bounded by N
+    assert!(seq.push(Elem { value: 3 }).is_ok());
+    assert_eq!(values(&seq), [1, 7, 3]);
+    assert_eq!(seq.capacity, 4);
+    assert_eq!(allocations(), base + 1);
+    assert!(seq.push(Elem { value: 4 }).is_ok());
+    assert_eq!(allocations(), base + 1);
+
+    // N bounds the size
+    assert!(!seq.resize(5));
+    let Err(elem) = seq.push(Elem { value: 5 }) else {
+        panic!(\"pushed beyond N\");
+    };
+    assert_eq!(elem.value, 5);
+    assert_eq!(values(&seq), [1, 7, 3, 4]);
+
+    // an unlimited sequence grows from null
+    let mut seq = ElemSeq::<0>::null();
+    for (value, capacity) in [(1, 1), (2, 2), (3, 4), (4, 4), (5, 8)] {
+        assert!(seq.push(Elem { value }).is_ok());
+        assert_eq!(seq.capacity, capacity);
+    }
+    assert_eq!(seq.iter().map(|e| e.value).collect::<Vec<_>>(), [1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn test_default() {
+    assert_eq!(Elem::default().value, 7);
+
+    // the default sequence is null, which is not allocated
+    let allocations = ALLOCATIONS.with(|n| n.get());
+    let seq = ElemSeq::<4>::default();
+    assert!(seq.data.is_null());
+    assert_eq!(seq.len(), 0);
+    assert_eq!(ALLOCATIONS.with(|n| n.get()), allocations);
+}
+
+#[test]
+fn test_into_iter() {
+    let mut seq = ElemSeq::<4>::new(3).unwrap();
+    for (i, elem) in (&mut seq).into_iter().enumerate() {
+        elem.value = i as i32;
+    }
+    let mut values = Vec::new();
+    for elem in &seq {
+        values.push(elem.value);
+    }
+    assert_eq!(values, [0, 1, 2]);
+}
+";
+
+    #[test]
+    fn test_seq() {
+        let seq = gen_impl(
+            "pkg__msg__Elem",
+            "Elem",
+            "",
+            "",
+            &[],
+            SeqImpl::Expanded,
+            FallibleApi::Option,
+        );
+        let code = SEQ_MOCKS.replace("{seq}", &seq);
+        crate::tests::run_generated_tests("seq", &(code + SEQ_TESTS));
+    }
+
+    /// Mocks of a message having `a`, `c` and `type` mangled into `type_`, and of a message without fields,
+    /// whose `PartialEq` is replaced with `{eq}`.
+    const PARTIAL_EQ_MOCKS: &str = "
+pub struct Msg {
+    pub a: i32,
+    pub c: String,
+    pub type_: u8,
+}
+
+pub struct MsgSeq<const N: usize>(Vec<Msg>);
+
+impl<const N: usize> MsgSeq<N> {
+    pub fn as_slice(&self) -> &[Msg] {
+        &self.0
+    }
+}
+
+pub struct Empty;
+
+pub struct EmptySeq<const N: usize>(Vec<Empty>);
+
+impl<const N: usize> EmptySeq<N> {
+    pub fn as_slice(&self) -> &[Empty] {
+        &self.0
+    }
+}
+
+{eq}
+";
+
+    const PARTIAL_EQ_TESTS: &str = r#"
+#[test]
+fn test_partial_eq() {
+    let msg = |a, c: &str, type_| Msg { a, c: c.to_string(), type_ };
+
+    // every field is compared, and constants are not fields
+    assert!(msg(1, "x", 2) == msg(1, "x", 2));
+    assert!(msg(1, "x", 2) != msg(0, "x", 2));
+    assert!(msg(1, "x", 2) != msg(1, "y", 2));
+    assert!(msg(1, "x", 2) != msg(1, "x", 0));
+
+    // sequences compare their elements
+    assert!(MsgSeq::<0>(vec![msg(1, "x", 2)]) == MsgSeq::<0>(vec![msg(1, "x", 2)]));
+    assert!(MsgSeq::<0>(vec![msg(1, "x", 2)]) != MsgSeq::<0>(vec![msg(1, "y", 2)]));
+    assert!(MsgSeq::<0>(vec![msg(1, "x", 2)]) != MsgSeq::<0>(vec![]));
+
+    // messages without fields are always equal
+    assert!(Empty == Empty);
+    assert!(EmptySeq::<0>(vec![Empty]) == EmptySeq::<0>(vec![Empty]));
+}
+"#;
+
+    #[test]
+    fn test_partial_eq_rust() {
+        let mut lines = VecDeque::new();
+        let (_, exprs) = parser::parse_msg("int32 a\nint32 B = 10\nstring c\nuint8 type\n")
+            .finish()
+            .unwrap();
+        gen_partial_eq_rust(&mut lines, "Msg", &exprs);
+        let (_, exprs) = parser::parse_msg("# no fields\n").finish().unwrap();
+        gen_partial_eq_rust(&mut lines, "Empty", &exprs);
+
+        let eq: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        let code = PARTIAL_EQ_MOCKS.replace("{eq}", &eq);
+        crate::tests::run_generated_tests("partial_eq_rust", &(code + PARTIAL_EQ_TESTS));
+    }
+}
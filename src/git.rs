@@ -0,0 +1,208 @@
+//! Fetch interface packages from git repositories.
+//!
+//! A repository is specified by `URL[#ref][:subdir]`.
+//! `subdir` is recognized only after `#`, because URLs like `git@github.com:org/repo.git` contain `:`,
+//! so use `URL#:subdir` to specify a subdirectory of the default branch.
+//!
+//! Repositories are shallowly fetched into `$XDG_CACHE_HOME/ros2msg_to_rs/git`
+//! (`~/.cache/ros2msg_to_rs/git` by default) by the `git` command.
+
+use crate::diagnostics::{self, Message};
+use std::{
+    env,
+    error::Error,
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A repository specified by `URL[#ref][:subdir]`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GitSource<'a> {
+    pub url: &'a str,
+    pub reference: Option<&'a str>,
+    pub subdir: Option<&'a str>,
+}
+
+impl<'a> GitSource<'a> {
+    /// Parse `URL[#ref][:subdir]`.
+    ///
+    /// URLs and refs starting with `-` are refused, because `git` would take them as options.
+    pub fn parse(spec: &'a str) -> Result<Self, Box<dyn Error>> {
+        let source = match spec.split_once('#') {
+            None => GitSource {
+                url: spec,
+                reference: None,
+                subdir: None,
+            },
+            Some((url, rest)) => {
+                let (reference, subdir) = match rest.split_once(':') {
+                    Some((reference, subdir)) => (reference, Some(subdir)),
+                    None => (rest, None),
+                };
+
+                GitSource {
+                    url,
+                    reference: Some(reference).filter(|r| !r.is_empty()),
+                    subdir: subdir.filter(|s| !s.is_empty()),
+                }
+            }
+        };
+
+        if source.url.is_empty() {
+            return Err(format!("no URL is specified in {spec}").into());
+        }
+        if source.url.starts_with('-') {
+            return Err(format!("URL must not start with '-': {spec}").into());
+        }
+        if source.reference.is_some_and(|r| r.starts_with('-')) {
+            return Err(format!("ref must not start with '-': {spec}").into());
+        }
+
+        Ok(source)
+    }
+
+    /// Name of the repository, e.g. `common_interfaces` of `https://github.com/ros2/common_interfaces.git`.
+    fn repository_name(&self) -> &'a str {
+        let url = self.url.trim_end_matches('/');
+        let name = url.rsplit(['/', ':']).next().unwrap_or(url);
+        let name = name.strip_suffix(".git").unwrap_or(name);
+        if name.is_empty() {
+            "repository"
+        } else {
+            name
+        }
+    }
+
+    /// Name of the directory in the cache.
+    fn cache_name(&self) -> String {
+        let name = format!("{}#{}", self.url, self.reference.unwrap_or("HEAD"));
+        name.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+}
+
+/// Fetch the repository and return the directory to be used as an input directory.
+pub fn fetch(spec: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let source = GitSource::parse(spec)?;
+    // the last directory is the name of the repository,
+    // which is used as the project name if this is the first input
+    let dir = cache_dir()?
+        .join(source.cache_name())
+        .join(source.repository_name());
+
+    if !dir.join(".git").exists() {
+        create_dir_all(&dir)?;
+        git(&dir, &["init", "--quiet"])?;
+        git(&dir, &["remote", "add", "--", "origin", source.url])?;
+    }
+
+    diagnostics::report(Message::Fetch { spec });
+    let reference = source.reference.unwrap_or("HEAD");
+    git(
+        &dir,
+        &[
+            "fetch", "--quiet", "--depth", "1", "--", "origin", reference,
+        ],
+    )?;
+    git(&dir, &["checkout", "--quiet", "--detach", "FETCH_HEAD"])?;
+
+    let dir = match source.subdir {
+        Some(subdir) => dir.join(subdir),
+        None => dir,
+    };
+
+    if !dir.is_dir() {
+        return Err(format!("{} is not found in {spec}", dir.display()).into());
+    }
+
+    Ok(dir)
+}
+
+fn cache_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let cache = if let Some(dir) = env::var_os("XDG_CACHE_HOME") {
+        PathBuf::from(dir)
+    } else if let Some(home) = env::var_os("HOME") {
+        Path::new(&home).join(".cache")
+    } else {
+        return Err("neither XDG_CACHE_HOME nor HOME is set".into());
+    };
+
+    Ok(cache.join("ros2msg_to_rs").join("git"))
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let status = Command::new("git").arg("-C").arg(dir).args(args).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("failed: git {}", args.join(" ")).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GitSource;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            GitSource::parse("https://github.com/ros2/common_interfaces.git").unwrap(),
+            GitSource {
+                url: "https://github.com/ros2/common_interfaces.git",
+                reference: None,
+                subdir: None,
+            }
+        );
+        assert_eq!(
+            GitSource::parse("https://github.com/ros2/common_interfaces.git#humble:std_msgs")
+                .unwrap(),
+            GitSource {
+                url: "https://github.com/ros2/common_interfaces.git",
+                reference: Some("humble"),
+                subdir: Some("std_msgs"),
+            }
+        );
+        assert_eq!(
+            GitSource::parse("git@github.com:org/repo.git#main").unwrap(),
+            GitSource {
+                url: "git@github.com:org/repo.git",
+                reference: Some("main"),
+                subdir: None,
+            }
+        );
+        assert_eq!(
+            GitSource::parse("git@github.com:org/repo.git#:interfaces").unwrap(),
+            GitSource {
+                url: "git@github.com:org/repo.git",
+                reference: None,
+                subdir: Some("interfaces"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert!(GitSource::parse("--upload-pack=touch /tmp/x").is_err());
+        assert!(GitSource::parse("https://github.com/org/repo.git#--upload-pack=x").is_err());
+        assert!(GitSource::parse("#main").is_err());
+        assert!(GitSource::parse("https://github.com/org/repo.git#main-branch").is_ok());
+    }
+
+    #[test]
+    fn test_repository_name() {
+        let source = GitSource::parse("https://github.com/ros2/common_interfaces.git/").unwrap();
+        assert_eq!(source.repository_name(), "common_interfaces");
+        assert_eq!(
+            source.cache_name(),
+            "https___github.com_ros2_common_interfaces.git__HEAD"
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Static HTML documentation of interface packages.
+//!
+//! `doc` writes `index.html` listing packages and their types, and `{package}/{msg|srv}/{Type}.html` of each type,
+//! which has tables of constants and fields with their comments.
+//! Types of fields link to their pages if they are found in the inputs.
+
+use crate::{
+    cst::{parse_cst, LineKind},
+    generator::gen_primitives,
+    parser::{Expr, TypeName, ValueType},
+};
+use std::error::Error;
+
+const HEADER: &str = "<!-- This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs). -->\n<!DOCTYPE html>\n";
+
+const STYLE: &str = "<style>
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.5em; text-align: left; vertical-align: top; }
+code { font-family: monospace; }
+</style>
+";
+
+/// Render `index.html` of `types`, which are `(package, "msg" or "srv", type name)`.
+pub fn render_index(types: &[(&str, &str, &str)]) -> String {
+    let mut body = String::new();
+    let mut current = None;
+
+    for (pkg, ext, type_name) in types.iter() {
+        if current != Some(pkg) {
+            if current.is_some() {
+                body.push_str("</ul>\n");
+            }
+            body.push_str(&format!("<h2 id=\"{pkg}\">{pkg}</h2>\n<ul>\n"));
+            current = Some(pkg);
+        }
+        body.push_str(&format!(
+            "<li><a href=\"{pkg}/{ext}/{type_name}.html\">{pkg}/{ext}/{type_name}</a></li>\n"
+        ));
+    }
+    if current.is_some() {
+        body.push_str("</ul>\n");
+    }
+
+    page("Interfaces", &format!("<h1>Interfaces</h1>\n{body}"))
+}
+
+/// Render the page of `pkg/ext/type_name` defined by `contents`.
+/// `exists(pkg, type_name)` returns whether the page of `pkg/msg/type_name` is rendered.
+pub fn render_type(
+    pkg: &str,
+    ext: &str,
+    type_name: &str,
+    contents: &str,
+    exists: &impl Fn(&str, &str) -> bool,
+) -> Result<String, Box<dyn Error>> {
+    let cst = parse_cst(contents)?;
+
+    let mut sections = vec![Section::default()];
+    let mut description = String::new();
+    let mut pending = Vec::new();
+
+    for line in cst.lines.iter() {
+        let definition = match &line.kind {
+            LineKind::Blank => {
+                if let Some(comment) = line.comment {
+                    pending.push(comment.trim_start_matches('#').trim().to_string());
+                } else if sections.len() == 1 && sections[0].is_empty() {
+                    // the leading comment separated by an empty line describes the type
+                    push_paragraph(&mut description, &mut pending);
+                }
+                continue;
+            }
+            LineKind::Separator => {
+                pending.clear();
+                sections.push(Section::default());
+                continue;
+            }
+            LineKind::Definition(definition) => definition,
+        };
+
+        let Expr::Variable {
+            type_name: ty,
+            value,
+            ..
+        } = &definition.expr
+        else {
+            continue;
+        };
+
+        if let Some(comment) = line.comment {
+            pending.push(comment.trim_start_matches('#').trim().to_string());
+        }
+        let comment = escape(&pending.join(" "));
+        pending.clear();
+
+        let section = sections.last_mut().unwrap();
+        let link = type_link(pkg, definition.type_name, ty, exists);
+        let name = escape(definition.name);
+        match value {
+            Some(ValueType::Const(_)) => {
+                let value = definition.value.trim().trim_start_matches('=').trim();
+                section.constants.push_str(&format!(
+                    "<tr><td>{link}</td><td><code>{name}</code></td><td><code>{}</code></td><td>{comment}</td></tr>\n",
+                    escape(value)
+                ));
+            }
+            _ => {
+                let default = escape(definition.value.trim());
+                section.fields.push_str(&format!(
+                    "<tr><td>{link}</td><td><code>{name}</code></td><td><code>{default}</code></td><td>{comment}</td></tr>\n"
+                ));
+            }
+        }
+    }
+    if sections.len() == 1 && sections[0].is_empty() {
+        push_paragraph(&mut description, &mut pending);
+    }
+
+    let titles: &[&str] = if ext == "srv" {
+        &["Request", "Response"]
+    } else {
+        &[""]
+    };
+
+    let mut body = format!(
+        "<p><a href=\"../../index.html#{pkg}\">{pkg}</a></p>\n<h1>{pkg}/{ext}/{type_name}</h1>\n{description}"
+    );
+    for (title, section) in titles.iter().zip(sections.iter()) {
+        if !title.is_empty() {
+            body.push_str(&format!("<h2>{title}</h2>\n"));
+        }
+        if !section.constants.is_empty() {
+            body.push_str(&format!(
+                "<h3>Constants</h3>\n<table>\n<tr><th>Type</th><th>Name</th><th>Value</th><th>Comment</th></tr>\n{}</table>\n",
+                section.constants
+            ));
+        }
+        if !section.fields.is_empty() {
+            body.push_str(&format!(
+                "<h3>Fields</h3>\n<table>\n<tr><th>Type</th><th>Name</th><th>Default</th><th>Comment</th></tr>\n{}</table>\n",
+                section.fields
+            ));
+        }
+    }
+
+    Ok(page(&format!("{pkg}/{ext}/{type_name}"), &body))
+}
+
+/// Rows of constants and fields of a message, a request or a response.
+#[derive(Default)]
+struct Section {
+    constants: String,
+    fields: String,
+}
+
+impl Section {
+    fn is_empty(&self) -> bool {
+        self.constants.is_empty() && self.fields.is_empty()
+    }
+}
+
+fn push_paragraph(description: &mut String, pending: &mut Vec<String>) {
+    if !pending.is_empty() {
+        description.push_str(&format!("<p>{}</p>\n", escape(&pending.join(" "))));
+        pending.clear();
+    }
+}
+
+/// The type as written in the file, which links to the page of the type if it exists.
+/// Pages are in `{package}/{msg|srv}`, so links are relative to it.
+fn type_link(
+    pkg: &str,
+    text: &str,
+    type_name: &TypeName,
+    exists: &impl Fn(&str, &str) -> bool,
+) -> String {
+    let (scope, nested) = match type_name {
+        TypeName::Type { type_name, .. } if gen_primitives(type_name).is_none() => {
+            (pkg, type_name.as_str())
+        }
+        TypeName::ScopedType {
+            scope, type_name, ..
+        } => (scope.as_str(), type_name.as_str()),
+        _ => return format!("<code>{}</code>", escape(text)),
+    };
+
+    if exists(scope, nested) {
+        format!(
+            "<a href=\"../../{scope}/msg/{nested}.html\"><code>{}</code></a>",
+            escape(text)
+        )
+    } else {
+        format!("<code>{}</code>", escape(text))
+    }
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "{HEADER}<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n{STYLE}</head>\n<body>\n{body}</body>\n</html>\n"
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_index, render_type};
+
+    #[test]
+    fn test_render_type() {
+        let exists = |pkg: &str, type_name: &str| (pkg, type_name) == ("geometry_msgs", "Point");
+        let contents = "# An example.\n\n# position\ngeometry_msgs/Point p\nstring<=3 s \"a\" # bounded\nint32 C = 1\nother_msgs/Missing m\n";
+        let html = render_type("my_pkg", "msg", "Example", contents, &exists).unwrap();
+
+        assert!(html.starts_with("<!-- This file was automatically generated by ros2msg_to_rs"));
+        assert!(html.contains("<p>An example.</p>"));
+        assert!(html.contains("<tr><td><a href=\"../../geometry_msgs/msg/Point.html\"><code>geometry_msgs/Point</code></a></td><td><code>p</code></td><td><code></code></td><td>position</td></tr>"));
+        assert!(html.contains("<td><code>string&lt;=3</code></td><td><code>s</code></td><td><code>&quot;a&quot;</code></td><td>bounded</td>"));
+        assert!(html.contains("<h3>Constants</h3>"));
+        assert!(html.contains("<td><code>other_msgs/Missing</code></td>"));
+
+        let html = render_type("my_pkg", "srv", "Get", "int32 a\n---\nint32 b\n", &exists).unwrap();
+        assert!(html.contains("<h2>Request</h2>"));
+        assert!(html.contains("<h2>Response</h2>"));
+
+        let html = render_index(&[("a", "msg", "X"), ("a", "srv", "Y"), ("b", "msg", "Z")]);
+        assert!(html.contains("<h2 id=\"a\">a</h2>\n<ul>\n<li><a href=\"a/msg/X.html\">a/msg/X</a></li>\n<li><a href=\"a/srv/Y.html\">a/srv/Y</a></li>\n</ul>\n<h2 id=\"b\">"));
+    }
+}
@@ -0,0 +1,859 @@
+//! Parser of .idl files, which define messages, services and actions in OMG IDL.
+//!
+//! Packages generated by rosidl_adapter may ship only .idl files,
+//! which are parsed into the same `Expr` as .msg, .srv and .action files.
+//! `pkg/msg/Type.idl` defines `struct Type`, `pkg/srv/Type.idl` defines `struct Type_Request` and `struct Type_Response`,
+//! and `pkg/action/Type.idl` defines `struct Type_Goal`, `struct Type_Result` and `struct Type_Feedback`.
+//! Constants of a struct are defined in `module {struct}_Constants`.
+//!
+//! The subset of IDL used by ROS 2 is supported: modules, structs, constants, typedefs,
+//! arrays, sequences, bounded strings and the annotations below. Preprocessor directives such as `#include` are ignored.
+//!
+//! | IDL                                          | .msg                            |
+//! |----------------------------------------------|---------------------------------|
+//! | `@default (value=5) int32 x;`                | `int32 x 5`                     |
+//! | `@verbatim (language="comment", text="...")` | `# ...`                         |
+//! | `@key`                                       | `# @key`                        |
+//! | `@range (min=0, max=10)`                     | `# @range(min=0, max=10)`       |
+//! | `@unit (value="m/s")`                        | `# [m/s]`                       |
+//!
+//! Annotations in the comments, such as `@rust_name` and `@deprecated`, are the same as .msg files,
+//! and other IDL annotations are ignored.
+
+use crate::parser::{self, ArrayInfo, Expr, TypeName, Value, ValueType};
+use std::collections::BTreeMap;
+
+/// Member of rosidl_adapter added to empty structs, which is not a field of the message.
+const PLACEHOLDER: &str = "structure_needs_at_least_one_member";
+
+/// Parse the .idl file of a message.
+pub fn parse_msg(input: &str, type_name: &str) -> Result<Vec<Expr>, String> {
+    let mut idl = parse_idl(input)?;
+    idl.take(type_name)
+}
+
+/// Parse the .idl file of a service into the request and the response.
+pub fn parse_srv(input: &str, type_name: &str) -> Result<(Vec<Expr>, Vec<Expr>), String> {
+    let mut idl = parse_idl(input)?;
+    let request = idl.take(&format!("{type_name}_Request"))?;
+    let response = idl.take(&format!("{type_name}_Response"))?;
+    Ok((request, response))
+}
+
+/// Goal, result and feedback of an action.
+type ActionParts = (Vec<Expr>, Vec<Expr>, Vec<Expr>);
+
+/// Parse the .idl file of an action into the goal, the result and the feedback.
+pub fn parse_action(input: &str, type_name: &str) -> Result<ActionParts, String> {
+    let mut idl = parse_idl(input)?;
+    let goal = idl.take(&format!("{type_name}_Goal"))?;
+    let result = idl.take(&format!("{type_name}_Result"))?;
+    let feedback = idl.take(&format!("{type_name}_Feedback"))?;
+    Ok((goal, result, feedback))
+}
+
+/// Structs of an .idl file.
+#[derive(Debug, Default)]
+struct Idl {
+    structs: BTreeMap<String, Vec<Expr>>,
+
+    /// Constants of `module {struct}_Constants`.
+    constants: BTreeMap<String, Vec<Expr>>,
+}
+
+impl Idl {
+    /// Constants and fields of `struct name`.
+    fn take(&mut self, name: &str) -> Result<Vec<Expr>, String> {
+        let Some(fields) = self.structs.remove(name) else {
+            return Err(format!("struct {name} is not defined"));
+        };
+        let mut result = self.constants.remove(name).unwrap_or_default();
+        result.extend(fields);
+        Ok(result)
+    }
+}
+
+fn parse_idl(input: &str) -> Result<Idl, String> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+        typedefs: BTreeMap::new(),
+        idl: Idl::default(),
+    };
+    parser.parse_definitions(&mut Vec::new())?;
+    if parser.pos < parser.tokens.len() {
+        return Err(parser.error("unexpected }"));
+    }
+    Ok(parser.idl)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+
+    /// Text of a number, whose sign is a separate token.
+    Number(String),
+
+    /// String or character literal, whose escapes are resolved.
+    String(String),
+
+    Punct(char),
+}
+
+/// Tokens and their line numbers.
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => (),
+            '#' => {
+                // preprocessor directive
+                while chars.next_if(|c| *c != '\n').is_some() {}
+            }
+            '/' if chars.next_if_eq(&'/').is_some() => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => loop {
+                match chars.next() {
+                    Some('*') if chars.next_if_eq(&'/').is_some() => break,
+                    Some('\n') => line += 1,
+                    Some(_) => (),
+                    None => return Err(format!("line {line}: unterminated comment")),
+                }
+            },
+            '"' | '\'' => {
+                let start = line;
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some('\\') => s.push(unescape(&mut chars, line)?),
+                        Some('\n') | None => {
+                            return Err(format!("line {start}: unterminated literal"))
+                        }
+                        Some(c) => s.push(c),
+                    }
+                }
+                tokens.push((Token::String(s), start));
+            }
+            c if c.is_ascii_digit() => {
+                let mut n = c.to_string();
+                loop {
+                    let exponent = !n.starts_with("0x") && n.ends_with(['e', 'E']);
+                    match chars.next_if(|c| {
+                        c.is_ascii_alphanumeric() || *c == '.' || (exponent && *c == '-')
+                    }) {
+                        Some(c) => n.push(c),
+                        None => break,
+                    }
+                }
+                tokens.push((Token::Number(n), line));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    ident.push(c);
+                }
+                tokens.push((Token::Ident(ident), line));
+            }
+            '{' | '}' | ';' | '<' | '>' | '(' | ')' | '[' | ']' | ',' | '=' | ':' | '@' | '-' => {
+                tokens.push((Token::Punct(c), line))
+            }
+            c => return Err(format!("line {line}: unexpected character {c:?}")),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Resolve an escape sequence after a backslash.
+fn unescape(
+    chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    line: usize,
+) -> Result<char, String> {
+    let c = match chars.next() {
+        Some('n') => '\n',
+        Some('t') => '\t',
+        Some('r') => '\r',
+        Some('0') => '\0',
+        Some('x') => {
+            let mut digits = String::new();
+            while digits.len() < 2 {
+                match chars.next_if(|c| c.is_ascii_hexdigit()) {
+                    Some(c) => digits.push(c),
+                    None => break,
+                }
+            }
+            u8::from_str_radix(&digits, 16)
+                .map(char::from)
+                .map_err(|_| format!("line {line}: invalid escape \\x{digits}"))?
+        }
+        Some(c @ ('\\' | '"' | '\'' | '?')) => c,
+        Some(c) => return Err(format!("line {line}: invalid escape \\{c}")),
+        None => return Err(format!("line {line}: unterminated literal")),
+    };
+    Ok(c)
+}
+
+/// Annotations of a definition.
+#[derive(Debug, Default)]
+struct Annotations {
+    default: Option<Value>,
+    comment: Option<String>,
+    key: bool,
+    range: Option<(String, String)>,
+    unit: Option<String>,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    typedefs: BTreeMap<String, TypeName>,
+    idl: Idl,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        match self.tokens.get(self.pos) {
+            Some((token, _)) => {
+                self.pos += 1;
+                Ok(token.clone())
+            }
+            None => Err(self.error("unexpected end of file")),
+        }
+    }
+
+    /// Error at the current token.
+    fn error(&self, message: &str) -> String {
+        let line = self
+            .tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(1, |(_, line)| *line);
+        format!("line {line}: {message}")
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_ident(&mut self, ident: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(i)) if i == ident) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {c}")))
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Ident(ident)) => {
+                let ident = ident.clone();
+                self.pos += 1;
+                Ok(ident)
+            }
+            _ => Err(self.error("expected an identifier")),
+        }
+    }
+
+    /// Definitions until `}` of the innermost module of `modules` or the end of the file.
+    fn parse_definitions(&mut self, modules: &mut Vec<String>) -> Result<(), String> {
+        let limits = parser::limits();
+        while !matches!(self.peek(), None | Some(Token::Punct('}'))) {
+            let annotations = self.parse_annotations()?;
+            match self.expect_ident()?.as_str() {
+                "module" => {
+                    if modules.len() == limits.max_nesting {
+                        return Err(self.error("modules are nested too deeply, see --max-nesting"));
+                    }
+                    let name = self.expect_ident()?;
+                    self.expect('{')?;
+                    modules.push(name);
+                    self.parse_definitions(modules)?;
+                    modules.pop();
+                    self.expect('}')?;
+                }
+                "struct" => {
+                    let name = self.expect_ident()?;
+                    self.expect('{')?;
+                    let mut fields = Vec::new();
+                    while !self.eat('}') {
+                        if fields.len() == limits.max_fields {
+                            return Err(self.error("too many fields, see --max-fields"));
+                        }
+                        let annotations = self.parse_annotations()?;
+                        let type_name = self.parse_type()?;
+                        let var_name = self.expect_ident()?;
+                        let type_name = self.parse_array(type_name)?;
+                        self.expect(';')?;
+                        if var_name != PLACEHOLDER {
+                            fields.push(member(type_name, var_name, annotations)?);
+                        }
+                    }
+                    self.idl.structs.insert(name, fields);
+                }
+                "typedef" => {
+                    let type_name = self.parse_type()?;
+                    let name = self.expect_ident()?;
+                    let type_name = self.parse_array(type_name)?;
+                    self.typedefs.insert(name, type_name);
+                }
+                "const" => {
+                    let type_name = self.parse_type()?;
+                    let var_name = self.expect_ident()?;
+                    self.expect('=')?;
+                    let value = self.parse_literal()?;
+                    let Some(name) = modules.last().and_then(|m| m.strip_suffix("_Constants"))
+                    else {
+                        return Err(self.error(&format!(
+                            "{var_name} must be in a module named {{struct}}_Constants"
+                        )));
+                    };
+                    let constant = parser::variable(
+                        type_name,
+                        var_name,
+                        Some(ValueType::Const(value)),
+                        annotations.comment,
+                    );
+                    self.idl
+                        .constants
+                        .entry(name.to_string())
+                        .or_default()
+                        .push(constant);
+                }
+                other => {
+                    self.pos -= 1;
+                    return Err(self.error(&format!(
+                        "unexpected {other}, expected module, struct, typedef or const"
+                    )));
+                }
+            }
+            self.expect(';')?;
+        }
+
+        if self.peek().is_none() && !modules.is_empty() {
+            return Err(self.error("expected }"));
+        }
+        Ok(())
+    }
+
+    fn parse_annotations(&mut self) -> Result<Annotations, String> {
+        let mut annotations = Annotations::default();
+        while self.eat('@') {
+            let name = self.expect_ident()?;
+
+            // `@name (value)` is the same as `@name (value=value)`
+            let mut args = BTreeMap::new();
+            if self.eat('(') {
+                while !self.eat(')') {
+                    if !args.is_empty() {
+                        self.expect(',')?;
+                    }
+                    let key = match self.tokens.get(self.pos..self.pos + 2) {
+                        Some([(Token::Ident(key), _), (Token::Punct('='), _)]) => {
+                            let key = key.clone();
+                            self.pos += 2;
+                            key
+                        }
+                        _ => "value".to_string(),
+                    };
+                    args.insert(key, self.parse_literal()?);
+                }
+            }
+
+            match name.as_str() {
+                "default" => annotations.default = args.remove("value"),
+                "verbatim" => {
+                    let comment =
+                        matches!(args.get("language"), Some(Value::String(l)) if l == "comment");
+                    if let (true, Some(Value::String(text))) = (comment, args.get("text")) {
+                        // comments of .msg files start after `#`
+                        let text: Vec<&str> = text.lines().map(|l| l.trim()).collect();
+                        annotations.comment = Some(format!(" {}", text.join(" ")));
+                    }
+                }
+                "key" => annotations.key = true,
+                "range" => {
+                    if let (Some(min), Some(max)) = (args.get("min"), args.get("max")) {
+                        annotations.range = Some((literal_text(min), literal_text(max)));
+                    }
+                }
+                "unit" => annotations.unit = args.get("value").map(literal_text),
+                _ => (),
+            }
+        }
+        Ok(annotations)
+    }
+
+    fn parse_type(&mut self) -> Result<TypeName, String> {
+        let primitive = |type_name: &str| TypeName::Type {
+            type_name: type_name.to_string(),
+            array_info: ArrayInfo::NotArray,
+        };
+
+        let name = self.expect_ident()?;
+        let type_name = match name.as_str() {
+            "sequence" => {
+                self.expect('<')?;
+                let element = self.parse_type()?;
+                let array_info = if self.eat(',') {
+                    ArrayInfo::Limited(self.parse_size()?)
+                } else {
+                    ArrayInfo::Dynamic
+                };
+                self.expect('>')?;
+                self.with_array(element, array_info)?
+            }
+            "string" if self.eat('<') => {
+                let size = self.parse_size()?;
+                self.expect('>')?;
+                TypeName::LimitedString {
+                    size,
+                    array_info: ArrayInfo::NotArray,
+                }
+            }
+            "string" => TypeName::String(ArrayInfo::NotArray),
+            "boolean" => primitive("bool"),
+            "octet" => primitive("byte"),
+            "float" => primitive("float32"),
+            "double" => primitive("float64"),
+            "short" => primitive("int16"),
+            "long" if self.eat_ident("long") => primitive("int64"),
+            "long" if self.eat_ident("double") => {
+                return Err(self.error("long double is not supported"))
+            }
+            "long" => primitive("int32"),
+            "unsigned" if self.eat_ident("short") => primitive("uint16"),
+            "unsigned" if self.eat_ident("long") => match self.eat_ident("long") {
+                true => primitive("uint64"),
+                false => primitive("uint32"),
+            },
+            "unsigned" => return Err(self.error("expected short or long")),
+            "char" | "int8" | "uint8" | "int16" | "uint16" | "int32" | "uint32" | "int64"
+            | "uint64" => primitive(&name),
+            "wchar" | "wstring" => return Err(self.error(&format!("{name} is not supported"))),
+            _ => {
+                let mut scope = vec![name];
+                while self.eat(':') {
+                    self.expect(':')?;
+                    scope.push(self.expect_ident()?);
+                }
+                match scope.as_slice() {
+                    [name] => match self.typedefs.get(name) {
+                        Some(type_name) => type_name.clone(),
+                        None => primitive(name),
+                    },
+                    [pkg, msg, type_name] if msg == "msg" => TypeName::ScopedType {
+                        scope: pkg.clone(),
+                        type_name: type_name.clone(),
+                        array_info: ArrayInfo::NotArray,
+                    },
+                    _ => {
+                        return Err(self.error(&format!(
+                            "{} is not a message, which must be pkg::msg::Type",
+                            scope.join("::")
+                        )))
+                    }
+                }
+            }
+        };
+        Ok(type_name)
+    }
+
+    /// `[N]` after the name of a member or a typedef.
+    fn parse_array(&mut self, type_name: TypeName) -> Result<TypeName, String> {
+        if !self.eat('[') {
+            return Ok(type_name);
+        }
+        let size = self.parse_size()?;
+        self.expect(']')?;
+        self.with_array(type_name, ArrayInfo::Static(size))
+    }
+
+    fn with_array(&self, type_name: TypeName, array: ArrayInfo) -> Result<TypeName, String> {
+        let array_info = match &type_name {
+            TypeName::Type { array_info, .. }
+            | TypeName::ScopedType { array_info, .. }
+            | TypeName::LimitedString { array_info, .. }
+            | TypeName::String(array_info) => array_info,
+        };
+        if !matches!(array_info, ArrayInfo::NotArray) {
+            return Err(self.error("multi-dimensional arrays are not supported"));
+        }
+
+        let type_name = match type_name {
+            TypeName::Type { type_name, .. } => TypeName::Type {
+                type_name,
+                array_info: array,
+            },
+            TypeName::ScopedType {
+                scope, type_name, ..
+            } => TypeName::ScopedType {
+                scope,
+                type_name,
+                array_info: array,
+            },
+            TypeName::LimitedString { size, .. } => TypeName::LimitedString {
+                size,
+                array_info: array,
+            },
+            TypeName::String(_) => TypeName::String(array),
+        };
+        Ok(type_name)
+    }
+
+    fn parse_size(&mut self) -> Result<usize, String> {
+        match self.next()? {
+            Token::Number(n) => n.parse().map_err(|_| {
+                self.pos -= 1;
+                self.error(&format!("{n} is not a size"))
+            }),
+            _ => {
+                self.pos -= 1;
+                Err(self.error("expected a size"))
+            }
+        }
+    }
+
+    /// Literal of a constant or an argument of an annotation. Adjacent strings are concatenated.
+    fn parse_literal(&mut self) -> Result<Value, String> {
+        let minus = self.eat('-');
+        let value = match self.next()? {
+            Token::Number(n) => parse_number(&n, minus),
+            Token::String(s) if !minus => {
+                let mut s = s;
+                while let Some(Token::String(next)) = self.peek() {
+                    s.push_str(next);
+                    self.pos += 1;
+                }
+                Some(Value::String(s))
+            }
+            Token::Ident(b) if !minus && b == "TRUE" => Some(Value::Bool(true)),
+            Token::Ident(b) if !minus && b == "FALSE" => Some(Value::Bool(false)),
+            _ => None,
+        };
+        value.ok_or_else(|| {
+            self.pos -= 1;
+            self.error("expected a literal")
+        })
+    }
+
+    /// Elements of an array in a string like `"(1, 2, 3)"` or `"[1, 2, 3]"`,
+    /// which rosidl_adapter writes as defaults of arrays.
+    fn parse_elements(&mut self) -> Result<Value, String> {
+        let close = match self.next()? {
+            Token::Punct('(') => ')',
+            Token::Punct('[') => ']',
+            _ => return Err(self.error("expected ( or [")),
+        };
+
+        let limits = parser::limits();
+        let mut elements = Vec::new();
+        while !self.eat(close) {
+            if !elements.is_empty() {
+                self.expect(',')?;
+            }
+            if elements.len() == limits.max_array_elements {
+                return Err(self.error("too many elements of an array, see --max-array-elements"));
+            }
+            elements.push(self.parse_literal()?);
+        }
+        if self.peek().is_some() {
+            return Err(self.error("unexpected tokens after the array"));
+        }
+        Ok(Value::Array(elements))
+    }
+}
+
+fn parse_number(n: &str, minus: bool) -> Option<Value> {
+    let sign = if minus { "-" } else { "" };
+    if let Some(hex) = n.strip_prefix("0x").or(n.strip_prefix("0X")) {
+        let n = u64::from_str_radix(hex, 16).ok()?;
+        return match minus {
+            true => i64::try_from(n).ok().map(|n| Value::Int(-n)),
+            false => Some(Value::Uint(n)),
+        };
+    }
+
+    if n.contains(['.', 'e', 'E']) {
+        format!("{sign}{n}").parse().ok().map(Value::Float)
+    } else if minus {
+        format!("-{n}").parse().ok().map(Value::Int)
+    } else {
+        n.parse().ok().map(Value::Uint)
+    }
+}
+
+/// Text of an argument of an annotation, e.g. `10` of `@range (min=0, max=10)`.
+fn literal_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// Field of a struct, whose annotations are added to the ones in the comment.
+fn member(type_name: TypeName, var_name: String, annotations: Annotations) -> Result<Expr, String> {
+    let is_array = match &type_name {
+        TypeName::Type { array_info, .. }
+        | TypeName::ScopedType { array_info, .. }
+        | TypeName::LimitedString { array_info, .. }
+        | TypeName::String(array_info) => !matches!(array_info, ArrayInfo::NotArray),
+    };
+    let default = match annotations.default {
+        Some(Value::String(s)) if is_array => {
+            let mut parser = Parser {
+                tokens: tokenize(&s).map_err(|e| format!("default of {var_name}: {e}"))?,
+                pos: 0,
+                typedefs: BTreeMap::new(),
+                idl: Idl::default(),
+            };
+            let elements = parser
+                .parse_elements()
+                .map_err(|e| format!("default of {var_name}: {e}"))?;
+            Some(elements)
+        }
+        default => default,
+    };
+
+    let mut expr = parser::variable(
+        type_name,
+        var_name,
+        default.map(ValueType::Default),
+        annotations.comment,
+    );
+    if let Expr::Variable {
+        unit, key, range, ..
+    } = &mut expr
+    {
+        *key |= annotations.key;
+        if annotations.range.is_some() {
+            *range = annotations.range;
+        }
+        if annotations.unit.is_some() {
+            *unit = annotations.unit;
+        }
+    }
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_action, parse_msg, parse_srv};
+    use crate::parser::{ArrayInfo, Expr, TypeName, Value, ValueType};
+
+    const HEADER: &str = r#"
+// generated from rosidl_adapter/resource/msg.idl.em
+// with input from my_msgs/msg/Sample.msg
+
+#include "builtin_interfaces/msg/Time.idl"
+
+module my_msgs {
+  module msg {
+    typedef double double__9[9];
+    module Sample_Constants {
+      const uint8 MODE_AUTO = 1;
+      const string NAME = "sample" "\n";
+    };
+    @verbatim (language="comment", text=
+      "A sample message.")
+    struct Sample {
+      @verbatim (language="comment", text=
+        "Stamp of the sample." "\n"
+        "@rust_name time")
+      builtin_interfaces::msg::Time stamp;
+
+      @default (value=-1.5)
+      @unit (value="m/s")
+      float speed;
+
+      double__9 covariance;
+
+      @default (value="(1, 2, 3)")
+      sequence<int32, 3> ids;
+
+      string<10> name;
+
+      sequence<Inner> inners;
+
+      @key
+      @range (min=0, max=10)
+      unsigned long long id;
+
+      boolean flags[2];
+    };
+  };
+};
+"#;
+
+    #[test]
+    fn test_parse_msg() {
+        let exprs = parse_msg(HEADER, "Sample").unwrap();
+        let vars: Vec<_> = exprs
+            .iter()
+            .map(|expr| match expr {
+                Expr::Variable {
+                    type_name,
+                    var_name,
+                    value,
+                    ..
+                } => (type_name, var_name.as_str(), value),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(vars.len(), 10);
+
+        assert!(
+            matches!(vars[0], (TypeName::Type { type_name, .. }, "MODE_AUTO", Some(ValueType::Const(Value::Uint(1)))) if type_name == "uint8")
+        );
+        assert!(
+            matches!(vars[1], (TypeName::String(ArrayInfo::NotArray), "NAME", Some(ValueType::Const(Value::String(s)))) if s == "sample\n")
+        );
+        assert!(
+            matches!(vars[2], (TypeName::ScopedType { scope, type_name, .. }, "time", None) if scope == "builtin_interfaces" && type_name == "Time")
+        );
+        assert!(
+            matches!(vars[3], (TypeName::Type { type_name, .. }, "speed", Some(ValueType::Default(Value::Float(f)))) if type_name == "float32" && *f == -1.5)
+        );
+        assert!(
+            matches!(vars[4], (TypeName::Type { type_name, array_info: ArrayInfo::Static(9) }, "covariance", None) if type_name == "float64")
+        );
+        assert!(
+            matches!(vars[5], (TypeName::Type { array_info: ArrayInfo::Limited(3), .. }, "ids", Some(ValueType::Default(Value::Array(a)))) if a.len() == 3)
+        );
+        assert!(matches!(
+            vars[6],
+            (
+                TypeName::LimitedString {
+                    size: 10,
+                    array_info: ArrayInfo::NotArray
+                },
+                "name",
+                None
+            )
+        ));
+        assert!(
+            matches!(vars[7], (TypeName::Type { type_name, array_info: ArrayInfo::Dynamic }, "inners", None) if type_name == "Inner")
+        );
+        assert!(
+            matches!(vars[8], (TypeName::Type { type_name, .. }, "id", None) if type_name == "uint64")
+        );
+        assert!(
+            matches!(vars[9], (TypeName::Type { type_name, array_info: ArrayInfo::Static(2) }, "flags", None) if type_name == "bool")
+        );
+
+        let Expr::Variable {
+            comment, ros_name, ..
+        } = &exprs[2]
+        else {
+            unreachable!()
+        };
+        assert_eq!(
+            comment.as_deref(),
+            Some(" Stamp of the sample. @rust_name time")
+        );
+        assert_eq!(ros_name.as_deref(), Some("stamp"));
+
+        let Expr::Variable { unit, .. } = &exprs[3] else {
+            unreachable!()
+        };
+        assert_eq!(unit.as_deref(), Some("m/s"));
+
+        let Expr::Variable { key, range, .. } = &exprs[8] else {
+            unreachable!()
+        };
+        assert!(*key);
+        assert_eq!(range, &Some(("0".to_string(), "10".to_string())));
+    }
+
+    #[test]
+    fn test_parse_srv_action() {
+        let input = "
+module p {
+  module srv {
+    struct Get_Request {
+      uint8 structure_needs_at_least_one_member;
+    };
+    struct Get_Response {
+      int32 value;
+    };
+  };
+};
+";
+        let (request, response) = parse_srv(input, "Get").unwrap();
+        assert!(request.is_empty());
+        assert_eq!(response.len(), 1);
+
+        let input = "
+module p { module action {
+  struct Fib_Goal { int32 order; };
+  struct Fib_Result { sequence<int32> sequence; };
+  /* no feedback */
+}; };
+";
+        assert_eq!(
+            parse_action(input, "Fib").unwrap_err(),
+            "struct Fib_Feedback is not defined"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (input, expected) in [
+            (
+                "module p {\n struct A { int32 x };\n};\n",
+                "line 2: expected ;",
+            ),
+            ("module p {\n", "line 1: expected }"),
+            (
+                "module p { const int32 X = 1; };",
+                "line 1: X must be in a module named {struct}_Constants",
+            ),
+            (
+                "struct A {\n wstring w;\n};",
+                "line 2: wstring is not supported",
+            ),
+            (
+                "struct A {\n pkg::srv::B b;\n};",
+                "line 2: pkg::srv::B is not a message, which must be pkg::msg::Type",
+            ),
+            ("struct A {\n int32 x[2][3];\n};", "line 2: expected ;"),
+            (
+                "typedef int32 a[2];\nstruct A {\n a x[3];\n};",
+                "line 3: multi-dimensional arrays are not supported",
+            ),
+            (
+                "struct A {\n string s = \"a;\n};",
+                "line 2: unterminated literal",
+            ),
+            (
+                "enum E { A };",
+                "line 1: unexpected enum, expected module, struct, typedef or const",
+            ),
+        ] {
+            assert_eq!(parse_msg(input, "A").unwrap_err(), expected, "{input}");
+        }
+    }
+}
@@ -0,0 +1,5298 @@
+//! ros2msg_to_rs generates Rust files from ROS 2's .msg, .srv and .action files.
+//!
+//! # How to use
+//!
+//! ## Step 1. Prepare .msg, .srv and .action files
+//!
+//! ```text
+//! $ mkdir src
+//! $ mkdir src/my_module
+//! $ mkdir src/my_module/msg
+//! $ vi src/my_module/msg/example.msg
+//! $ vi src/my_module/srv/example.srv
+//! $ vi src/my_module/action/example.action
+//! ```
+//!
+//! ## Step 2. Generate
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated
+//! $ ls generated/module
+//! mod.rs    msg.rs    srv.rs
+//! ```
+//!
+//! `-i` is the input directory and `-o` is the output directory.
+//!
+//! An .action file generates its goal, result and feedback like `{Type}Goal`,
+//! the messages and services of the action protocol like `{Type}SendGoalRequest` and `{Type}GetResult`,
+//! and `{Type}` having the action type support.
+//! The protocol messages refer to `unique_identifier_msgs/msg/UUID` and `builtin_interfaces/msg/Time`,
+//! so `unique_identifier_msgs` must be in the input directories.
+//!
+//! .idl files in `msg`, `srv` and `action` directories are read too,
+//! for packages shipping only .idl files, such as the ones generated by rosidl_adapter.
+//! If both `Type.msg` and `Type.idl` exist, which is the case of installed packages, `Type.msg` is used.
+//! Mermaid diagrams and subcommands other than generation read only .msg and .srv files.
+//! ros2msg_to_rs assumess the first first directories are modules.
+//! If there is `src/my_module` and specify `-i src`,
+//! ros2msg_to_rs assumes the `my_module` is a module.
+//!
+//! `-i` also accepts archives, `.tar`, `.tar.gz`, `.tgz` and `.zip`,
+//! which are read without unpacking.
+//! If all files in an archive are in a single top-level directory,
+//! the directory is treated as the input directory.
+//!
+//! `--from-git URL[#ref][:subdir]` fetches a git repository into a cache and uses it as an input directory,
+//! e.g. `--from-git https://github.com/ros2/common_interfaces.git#humble:geometry_msgs`.
+//! Note that `subdir` is recognized only after `#`, so use `URL#:subdir` for the default branch.
+//!
+//! `-i` can be specified multiple times for overlayed workspaces.
+//! If the same type is defined in several input directories,
+//! the last one is used by default, and `--prefer first` changes this behavior.
+//!
+//! `--versioned-input VERSION=DIR` generates packages of another version side by side,
+//! such as `my_pkg_v1` and `my_pkg_v2` from two trees, for rolling migrations of interfaces.
+//! References between packages of a version refer to the same version, and the other references refer to packages of `-i`.
+//! Messages of the previous version, in the order of the options, are converted in both directions
+//! in `{package}_{VERSION}/conversions.rs`, where identical messages are moved by `From`.
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated --versioned-input v1=old/src --versioned-input v2=new/src
+//! ```
+//!
+//! C symbols keep the names of the definitions as renamed packages do, so the types of a version call
+//! `__init`, `__fini` and the type support of the C libraries of `-i`.
+//! Therefore, definitions of a version are generated only if they are structurally identical to the ones of `-i`,
+//! including the messages they refer to, and the others are reported by the warning `versioned-changes`
+//! and not generated. `--deny versioned-changes` refuses such versions instead.
+//! For the same reason, `--versioned-input` cannot be used with `--emit-crates`, `--emit-c-tests` and `--check-links`.
+//!
+//! `--vendor-sources` copies the .msg and .srv files to `{out}/{package}/interfaces`,
+//! and `-i {out}` regenerates the same files without the original workspace.
+//!
+//! Existing files in the output directory are overwritten only if they are generated by ros2msg_to_rs,
+//! which is marked in the first line, and `--force` overwrites any file.
+//! The default output directory is `generated/{name of the first input}`.
+//! Cargo's build directory like `target`, which has `CACHEDIR.TAG` of cargo, is refused without `--force`
+//! because `cargo clean` removes the generated files.
+//! If files generated by the old default `target/{name of the first input}` are found, a warning tells they are no longer updated.
+//! `--post-cmd CMD` runs the command by the shell after successful generation,
+//! and the output directory is given by the environment variable `ROS2MSG_TO_RS_OUT`.
+//! Commands are run in the order specified, and generation fails if any of them fails.
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated --post-cmd 'rustfmt "$ROS2MSG_TO_RS_OUT"/*/mod.rs'
+//! ```
+//!
+//! `--backup` saves the previous version of each modified file as `{file}.bak`, such as `example.rs.bak`.
+//!
+//! Pathological or corrupted definitions fail fast by limits of the parser,
+//! `--max-line-length`, `--max-fields`, `--max-array-elements` and `--max-nesting`,
+//! whose defaults are large enough for real interfaces.
+//!
+//! Input directories are traversed in the order of file names, and modules in generated files are sorted,
+//! so the same inputs generate the same files regardless of file systems and operating systems.
+//!
+//! Comments of fields and constants are copied to the generated code as line comments.
+//! Control characters are replaced by spaces, characters changing the direction of text are escaped like `\u{202e}`,
+//! and invalid UTF-8 is replaced by `U+FFFD`, so any comment keeps the generated code compilable.
+//!
+//! `mod.rs`, `msg.rs` and `srv.rs` of each package have documentation listing the types by intra-doc links,
+//! and `mod.rs` also has the version of the package in `{input}/{package}/package.xml` if it is found.
+//!
+//! # Profiles
+//!
+//! `--profile NAME` selects a named set of options defined in `ros2msg_to_rs.json`,
+//! or in the file specified by `--config`,
+//! so that the same interface tree can be generated in several flavors.
+//!
+//! ```json
+//! {
+//!   "profiles": {
+//!     "dev": ["--partial-eq", "rust", "--serde"],
+//!     "release": ["--backend", "safe-drive", "--emit-crates"]
+//!   }
+//! }
+//! ```
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated/dev --profile dev
+//! $ ros2msg_to_rs -i src -o generated/release --profile release
+//! ```
+//!
+//! The options of the profile are inserted at the position of `--profile`,
+//! so options after `--profile` override the options of the profile.
+//!
+//! Types listed by `skip` of the configuration file are not generated nor listed in `msg.rs` and `srv.rs`,
+//! which is useful for types provided by another crate or not supported.
+//! References to skipped types are reported as `skipped-types`.
+//!
+//! ```json
+//! {
+//!   "skip": ["my_pkg/msg/Provided", "my_pkg/srv/Unsupported"]
+//! }
+//! ```
+//!
+//! `rename` maps packages to modules and types to Rust types,
+//! which is useful when third-party interfaces collide with existing crates or types.
+//! Module names, struct names, file names and references from other types are renamed consistently,
+//! while C symbols and libraries keep the names of the definitions.
+//!
+//! ```json
+//! {
+//!   "rename": {
+//!     "packages": { "nav_msgs": "third_party_nav_msgs" },
+//!     "types": { "my_pkg/msg/String": "Label" }
+//!   }
+//! }
+//! ```
+//!
+//! `layout` overrides `#[repr(C)]` of messages by `#[repr(C, align(N))]` or `#[repr(C, packed(N))]`
+//! for DMA buffers or vendor SDKs imposing alignments. The layouts differ from the C structs of rosidl,
+//! so the warning `layout-overrides` is reported for each of them.
+//! Sequences allocated by C functions have a different stride from aligned messages.
+//! Packed messages are plain Rust structs without C functions, whose `new()` sets the default values by Rust,
+//! and they have neither sequences nor type support, so they cannot be published nor be fields of other messages.
+//! Services and actions cannot be packed.
+//!
+//! ```json
+//! {
+//!   "layout": {
+//!     "my_pkg/msg/Frame": { "align": 64 },
+//!     "my_pkg/msg/Register": { "packed": 1 }
+//!   }
+//! }
+//! ```
+//!
+//! # no_std
+//!
+//! `--no-std` generates code using `core` and `alloc` only, so the crate containing the generated code
+//! requires `extern crate alloc;`, which is added to crates generated by `--emit-crates`.
+//! `--safe-drive` should be a crate providing the same types as safe_drive without `std`.
+//!
+//! # micro-ROS
+//!
+//! `--backend micro-ros` generates code for firmware using micro-ROS.
+//! Type supports are declared by the symbols of `rosidl_typesupport_microxrcedds_c`,
+//! and `{Type}Pool` and `{Type}::with_pool()` are generated for messages having bounded sequences or strings,
+//! so that the messages can be created without dynamic allocation.
+//! `with_pool()` is unsafe and returns `{Type}Pooled`, whose bounded fields are set in place by `{field}_set()`,
+//! because the other methods of messages free or reallocate fields.
+//! Use it with `--no-std` and `--safe-drive` pointing to a crate providing rclc bindings.
+//!
+//! # Messages and sequences
+//!
+//! Messages implement `Default` by the C initializer, and `{Type}Seq<N>` implement `Default` as `null()`.
+//! Because messages implement `Drop`, set fields after `Default::default()` instead of using `..Default::default()`.
+//!
+//! ```ignore
+//! let mut msg = my_module::msg::Example::default();
+//! msg.value = 10;
+//! ```
+//!
+//! Fields having default values in the definition, like `int32 x 42` and `string s "hello"`,
+//! are set to the values by `new()` and so by `Default`.
+//!
+//! Each generated struct has an example of construction in its documentation.
+//! Messages without fields have a private `structure_needs_at_least_one_member: u8`
+//! as the C structs of rosidl do, so their layouts and names of members are the same.
+//!
+//! `{Type}Seq<N>` can grow by `push()` and `resize()`, which reallocate the sequence by the C functions.
+//! Constructors such as `new()` and `null()` are `#[must_use]`, and so are fallible methods returning `bool`
+//! such as `resize()`, so that ignored allocation failures are reported by the compiler.
+//!
+//! `--fallible-api result` makes `new()` of messages and `{Type}Seq<N>` return `Result<Self, MsgError>`
+//! instead of `Option<Self>`, where `MsgError` of `{package}/error.rs` tells `Allocation` from `ExceedsBound`.
+//! Errors of `{field}_push_checked()`, `{field}_try_set()` and `TryFrom<&[u8]>` have the field failing,
+//! such as `Some("Example::points")`, which is also shown by `Display`.
+//! It cannot be used with `--seq-macro` and `--typed-seq`.
+//!
+//! Messages implement `Send` and `Sync` if every field is a primitive type, a string, a generated message
+//! or a message of builtin_interfaces, or an array or a sequence of them, because the message owns its memory.
+//! Messages having other types of common interfaces rely on the auto traits of the types.
+//! Messages of packages in the input directories are generated ones even if common_interfaces of safe_drive are used.
+//! `--no-send-sync` disables the implementations.
+//!
+//! Fields of bounded sequences like `int32[<=3] xs` have `XS_MAX_LEN` and `xs_push_checked()`,
+//! and also `xs_try_set()` copying a slice if the elements are primitive types,
+//! which return errors instead of exceeding the bounds.
+//!
+//! `--seq-macro` generates `{package}/seq.rs` defining `impl_seq!`, and each generated file invokes it
+//! instead of repeating the implementation of `new()`, `Drop` and `{Type}Seq<N>`,
+//! which shrinks the generated code to a fraction.
+//! `--typed-seq` generates `{package}/seq.rs` defining a generic `TypedSeq<T, N>` instead,
+//! and `{Type}Seq<N>` becomes an alias of it, so sequences are compiled once per package.
+//!
+//! `--serde` derives `serde::Serialize` and `serde::Deserialize` for messages and services,
+//! which requires the `serde` feature of safe_drive.
+//! `--serde-tests` additionally generates tests which serialize the default value of each message to JSON
+//! and deserialize it back, so that the generated and safe_drive's implementations of serde are checked.
+//!
+//! Constants of `char` and `byte` can be characters like `char C = 'a'`, which become `b'a' as i8` and `b'a'`,
+//! and integer constants can be hexadecimal like `byte B = 0x41`, as written in legacy definitions.
+//!
+//! `--const-tests` generates a test asserting that each constant equals the value in the definition,
+//! where strings are compared by their bytes and floating point numbers are compared by their bits,
+//! so that consumers vendoring generated code can detect regressions of the generator.
+//!
+//! `--constants-by-name` generates `constant_by_name()` of each message returning `ConstantValue`
+//! of `{package}/constants.rs`, so that tools can resolve names of constants, such as `"MODE_AUTO"`, at runtime.
+//!
+//! `--bitflags` generates a type like `ExampleErrorFlags` for each group of integer constants like `ERROR_*`
+//! whose values are distinct powers of two, having `from_bits()`, `contains()` and the operators of bits,
+//! so that fields of flags are checked as a type instead of loose constants.
+//!
+//! `--emit-aliases` generates type aliases of safe_drive for messages and services.
+//!
+//! ```ignore
+//! pub type ExamplePublisher = safe_drive::topic::publisher::Publisher<Example>;
+//! pub type ExampleSubscriber = safe_drive::topic::subscriber::Subscriber<Example>;
+//! pub type ExampleClient = safe_drive::service::client::Client<Example>;
+//! pub type ExampleServer = safe_drive::service::server::Server<Example>;
+//! ```
+//!
+//! C functions of a generated file are declared in one `extern "C"` block, functions of messages first
+//! and type supports last, and each symbol is declared once.
+//! `--extern-link` adds `#[link]` of `lib{package}__rosidl_generator_c` and the type support library to the block,
+//! so that the code links without build.rs, and `--extern-allow` allows lints on it.
+//!
+//! ```text
+//! $ ros2msg_to_rs -i interfaces --extern-link --extern-allow improper_ctypes
+//! ```
+//!
+//! # Random values
+//!
+//! `--random` generates `fill_random()`, which sets every field to a random value by `rand` 0.9.
+//! Lengths of bounded sequences and strings do not exceed their bounds,
+//! and fields of types in common_interfaces of safe_drive are left as they are.
+//!
+//! ```ignore
+//! let mut msg = my_module::msg::Example::new().unwrap();
+//! msg.fill_random(&mut rand::rng());
+//! ```
+//!
+//! # Owned mirrors
+//!
+//! `--owned` generates `{Type}Owned` of each message, whose strings and sequences are `String` and `Vec`,
+//! with `From<&{Type}>` and `TryFrom<&{Type}Owned>`, which fails if allocation fails or bounds are exceeded.
+//! `builtin_interfaces/Time` and `Duration` are mirrored by `TimeOwned` and `DurationOwned` of the package.
+//! Messages having fields of types in common_interfaces of safe_drive, directly or by nested messages,
+//! are not mirrored, and they are reported by the warning `unmirrored-messages`.
+//!
+//! ```ignore
+//! let owned = my_module::msg::ExampleOwned {
+//!     name: "robot".to_string(),
+//!     points: vec![1.0, 2.0],
+//!     ..Default::default()
+//! };
+//! let msg = my_module::msg::Example::try_from(&owned)?;
+//! ```
+//!
+//! # Differences
+//!
+//! `--diff` generates `diff()` of each message, which returns `FieldDiff` of every field differing from another message.
+//! Nested messages and arrays are compared element by element, and values are formatted by `Debug`,
+//! so that tests and regression tools can show what changed instead of a bare inequality.
+//! Messages having fields of types in common_interfaces of safe_drive do not have `diff()`.
+//!
+//! ```ignore
+//! for d in expected.diff(&actual) {
+//!     println!("{}: {} != {}", d.path, d.left, d.right);
+//! }
+//! ```
+//!
+//! # CDR
+//!
+//! `--cdr` generates `{package}/cdr.rs` having `Reader` and `Writer` of CDR.
+//! They read and write primitives in the byte order given explicitly,
+//! and `Reader::from_payload()` selects the byte order and the alignment by the encapsulation header,
+//! so that payloads of any DDS vendor can be parsed.
+//! Reads are bounds-checked and return `cdr::Error` instead of panicking on truncated or corrupted payloads.
+//!
+//! ```ignore
+//! let (mut reader, _) = my_module::cdr::Reader::from_payload(&payload)?;
+//! let value = reader.read_i32()?;
+//! ```
+//!
+//! Each message also has `encode_cdr()` and `decode_cdr()`, which serialize it to and from XCDR1 or XCDR2
+//! with the encapsulation header, so that messages can be recorded, replayed and sent without rmw.
+//! Messages having fields of types of common_interfaces of safe_drive are not serializable.
+//!
+//! ```ignore
+//! use my_module::cdr::Encapsulation;
+//!
+//! let payload = msg.encode_cdr(Encapsulation::CdrLe)?;
+//! let decoded = my_module::msg::Example::decode_cdr(&payload)?;
+//! ```
+//!
+//! # Fuzzing
+//!
+//! `--fuzz-targets` with `--emit-crates` generates `{package}/fuzz`, a cargo-fuzz project,
+//! which has a target of `serde_json::from_slice()` for each message if `--serde` is specified,
+//! and a target of `cdr::Reader` if `--cdr` is specified.
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated --emit-crates --serde --fuzz-targets
+//! $ cd generated/my_module/fuzz
+//! $ cargo +nightly fuzz run json_msg_example
+//! ```
+//!
+//! # Annotations
+//!
+//! Comments of .msg and .srv files can have annotations.
+//!
+//! ```text
+//! # @deprecated use NewType instead
+//! int32 old_field # @deprecated use new_field instead
+//! int32 new_field
+//! ```
+//!
+//! `@deprecated` at the top of a file deprecates the struct,
+//! and `@deprecated` of a field deprecates the field by `#[deprecated]`.
+//!
+//! ```text
+//! int32 Type # @rust_name type_id
+//! ```
+//!
+//! `@rust_name` renames the field of the generated struct without changing the layout,
+//! and accessors named after the original name, `type_()` and `type_mut()`, are also generated.
+//!
+//! ```text
+//! int32 id # @key
+//! float64 gain # @range(min=0.0, max=1.0) @default(value=0.5)
+//! ```
+//!
+//! `@key`, `@range` and `@default` are the same as annotations of IDL,
+//! and they generate `KEY_FIELDS`, `gain_range()`, `gain_in_range()` and `GAIN_DEFAULT`.
+//!
+//! ```text
+//! float64 altitude # @optional
+//! bool has_altitude
+//! int32 count # @optional(sentinel=-1)
+//! ```
+//!
+//! `@optional` pairs a field with the bool field `has_{field}`, or the field of `@optional(flag=valid)`,
+//! and `@optional(sentinel=-1)` treats the value as absence,
+//! generating `altitude_opt()` returning `Option` and `set_altitude_opt()`.
+//!
+//! ```text
+//! # @topic /sensor/imu
+//! float64 x
+//! ```
+//!
+//! `@topic` at the top of a .msg file generates `DEFAULT_TOPIC`,
+//! and `create_publisher(&node, qos)` and `create_subscriber(&node, qos)` of the topic for safe_drive.
+//! `topic` of the configuration file specifies topics of messages without modifying them.
+//!
+//! ```text
+//! # @qos(reliability=best_effort, history=keep_last, depth=5)
+//! float64 x
+//! ```
+//!
+//! `@qos` at the top of a .msg or .srv file generates `recommended_qos()` returning `qos::Profile` of safe_drive,
+//! and `qos` of the configuration file specifies it without modifying the file.
+//!
+//! # Warnings
+//!
+//! Warnings have names, such as `builtin-time-2038`, `naming-convention`, `duplicate-definitions`,
+//! `name-collisions`, `missing-dependencies` and `skipped-types`, which are printed with the warnings.
+//! `--allow NAME` suppresses the warning, and `--deny NAME` makes it an error,
+//! in which case nothing is written after all files are processed.
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated --allow builtin-time-2038 --deny name-collisions
+//! ```
+//!
+//! Messages named `String`, `Time`, `Duration` or `Header` shadow well-known types,
+//! so they are reported as `name-collisions` and referred by qualified paths in the generated code.
+//! `--deny name-collisions` refuses such packages instead.
+//!
+//! `name-collisions` also reports generated types colliding across messages and services of a package,
+//! such as `FooRequest` of `srv/Foo` and `msg/FooRequest`, because the latter is shadowed in services.
+//!
+//! `identical-definitions` reports messages of different packages which are structurally identical,
+//! and it is allowed by default, so it is enabled by `-W identical-definitions`.
+//! Such messages can be aliased by `alias` of the configuration file, which re-exports one instead of generating another.
+//!
+//! `invalid-defaults` reports default values not matching the types of the fields, such as `uint8 x 256`,
+//! which are not set by `new()`.
+//!
+//! `versioned-changes` reports definitions of `--versioned-input` which are not generated
+//! because they differ from the ones of `-i`.
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated -W identical-definitions
+//! Warning: my_pkg/msg/Point, other_pkg/msg/Point are structurally identical, so they can be aliased by `alias` of the configuration file [identical-definitions]
+//! ```
+//!
+//! `--message-format json` prints warnings, errors and generated files as JSON objects, one per line.
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated --message-format json
+//! {"reason":"diagnostic","level":"warning","code":"builtin-time-2038","message":"...","file":null}
+//! {"reason":"generated-file","path":"generated/my_module/msg/example.rs"}
+//! {"reason":"generation-finished","success":true}
+//! ```
+//!
+//! `--timings FILE` writes durations of walking input directories, parsing, generating and writing,
+//! in total and of each package, as JSON. `--timings-format folded` writes folded stacks for flamegraphs.
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated --timings timings.txt --timings-format folded
+//! $ inferno-flamegraph timings.txt > timings.svg
+//! ```
+//!
+//! # Formatter
+//!
+//! ```text
+//! $ ros2msg_to_rs fmt src
+//! $ ros2msg_to_rs fmt --check src
+//! ```
+//!
+//! `fmt` formats .msg and .srv files in the canonical style, aligning types and normalizing spaces,
+//! and comments are kept. `--check` fails if any file is not formatted without modifying it.
+//!
+//! # New packages
+//!
+//! ```text
+//! $ ros2msg_to_rs new my_module --dir src
+//! $ cd src
+//! $ ros2msg_to_rs --profile default
+//! ```
+//!
+//! `new` creates an interface package having `msg`, `srv` and `action` directories,
+//! `package.xml` and `CMakeLists.txt` for colcon, and a sample message.
+//! `ros2msg_to_rs.json` is also created in the directory if it does not exist,
+//! whose `default` profile generates crates of the packages in the directory to `generated/interfaces`.
+//!
+//! # HTML documentation
+//!
+//! ```text
+//! $ ros2msg_to_rs doc -i src -i common_interfaces -o doc
+//! ```
+//!
+//! `doc` writes `{out}/index.html` and a page of each type having tables of constants and fields with their comments,
+//! and types of fields link to their pages, so that people not using Rust can browse the interfaces.
+//! Comments at the top of a file separated by an empty line are the description of the type,
+//! and comments above or after a definition are the comment of the definition.
+//!
+//! # Expanded definitions
+//!
+//! ```text
+//! $ ros2msg_to_rs show -i src -i common_interfaces my_module/msg/Example
+//! ```
+//!
+//! `show` prints the definition of a type like `ros2 interface show`,
+//! where the definition of the type of each field follows the field with indentation.
+//! Types are resolved across the input directories in the same way as generation.
+//!
+//! # Browsing types
+//!
+//! ```text
+//! $ ros2msg_to_rs browse -i src -i common_interfaces
+//! ```
+//!
+//! `browse` lists types of the inputs in a terminal UI,
+//! and shows the expanded definition of the selected type and the Rust code generated from it side by side.
+//! Use ↑↓ (or `j` and `k`) to select a type, PgUp and PgDn to scroll, and `q` to quit.
+//! Warnings are not reported while browsing.
+//! `browse` requires the `tui` feature, such as `cargo install ros2msg_to_rs --features tui`.
+//!
+//! # Sizes of messages
+//!
+//! ```text
+//! $ ros2msg_to_rs size -i src -i common_interfaces -p my_module
+//! my_module/msg/Example: C size 48 bytes (align 8), max CDR size unbounded (name, points)
+//! ```
+//!
+//! `size` reports the size of the C struct on 64-bit targets and the maximum size of XCDR1 of each message.
+//! The maximum size is not defined if the message has unbounded strings or sequences, which are listed instead.
+//!
+//! # Statistics of packages
+//!
+//! ```text
+//! $ ros2msg_to_rs stats -i src -i common_interfaces -p my_module
+//! my_module: 3 message(s), 1 service(s), max nesting depth 2, 4 unbounded field(s), external: [std_msgs]
+//! ```
+//!
+//! `stats` summarizes each package for interface review.
+//! The nesting depth of a message is 0 if the message has only primitive and string fields.
+//!
+//! # Crates
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated --emit-crates
+//! $ ls generated/my_module
+//! Cargo.toml    build.rs    mod.rs    msg    srv
+//! ```
+//!
+//! `--emit-crates` generates each module as a crate.
+//! Dependencies on safe_drive and the other generated crates are written to Cargo.toml,
+//! and build.rs links the C libraries of the package.
+//!
+//! Packages are divided into stages written to `{out}/build-order.txt`,
+//! where packages of each stage depend only on packages of previous stages, and files are written stage by stage.
+//! `--print-stages` also prints the stages, and `--only-stage N` writes only the crates of the stage `N`,
+//! and the workspace contains the crates of stages up to `N`, so huge interface sets can be built incrementally.
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated --emit-crates --print-stages --only-stage 0
+//! stage 0: my_base
+//! stage 1: my_module
+//! ```
+//!
+//! `--emit-examples` generates `{package}/examples` having a publisher and a subscriber of each message
+//! and a server and a client of each service, which communicate through a topic or a service named after the type.
+//! `--example-type pkg/msg/Type` limits the examples to the type.
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated --emit-crates --emit-examples --example-type my_module/msg/Example
+//! $ cd generated/my_module
+//! $ cargo run --example example_subscriber &
+//! $ cargo run --example example_publisher
+//! ```
+//!
+//! `--emit-c-tests` generates `{package}/c_tests/c_tests.c` and `{package}/tests/c_tests.rs`,
+//! which check that the size, the alignment and the offsets of fields of each message are the same in Rust and C,
+//! and that a message initialized by Rust equals one initialized by C by `__are_equal()` and by their serialized forms.
+//! The C file is compiled by the build script if the feature `c-tests` is enabled.
+//!
+//! ```text
+//! $ ros2msg_to_rs -i src -o generated --emit-crates --emit-c-tests
+//! $ cd generated/my_module
+//! $ cargo test --features c-tests
+//! ```
+//!
+//! `--emit-deps-report deps.json` writes the interface packages referred from each package,
+//! and `external` lists the referred packages which are not generated,
+//! such as packages in common_interfaces.
+//!
+//! `--emit-mermaid` writes `{out}/{package}/classes.mmd`, a class diagram of Mermaid,
+//! where fields of message types are compositions with the multiplicities of arrays,
+//! so that the diagrams can be embedded in design documents.
+//!
+//! `--dep-file out.d` writes a Makefile-style dependency file of generated files,
+//! which can be used by Make or Ninja to regenerate files when .msg or .srv files are modified.
+//!
+//! `--check-links` checks the C libraries of each package, such as
+//! `lib{package}__rosidl_typesupport_c.so`, can be found in `AMENT_PREFIX_PATH` or `LD_LIBRARY_PATH`.
+//! Packages which will fail to link are reported before a long cargo build.
+//!
+//! # common_interfaces of safe_drive
+//!
+//! ```text
+//! $ ros2msg_to_rs common-interfaces -i common_interfaces -o safe_drive/src/msg/common_interfaces
+//! ```
+//!
+//! `common-interfaces` generates common_interfaces bundled with safe_drive
+//! from the source tree of ROS 2's common_interfaces.
+//!
+//! # Library
+//!
+//! ros2msg_to_rs is also a library, so that tools and build scripts can generate code without the binary.
+//! [`generate_workspace()`] does the same as the command line without subcommands,
+//! and [`Args::new()`] parses the options like the command line.
+//!
+//! ```no_run
+//! let args = ros2msg_to_rs::Args::new(["-i", "src", "-o", "generated", "--serde"])?;
+//! ros2msg_to_rs::generate_workspace(&args)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [`parser`] and [`generator`] generate code of a single definition.
+//!
+//! ```
+//! use ros2msg_to_rs::{generator::Generator, parser};
+//!
+//! let mut g = Generator::new("my_module".to_string(), "safe_drive".to_string(), false);
+//! let (_, exprs) = parser::parse_msg("int32 x\nfloat64[<=3] ys\n").unwrap();
+//! let lines = g.gen_msg("my_module", "Example", &exprs).unwrap();
+//! assert!(lines.iter().any(|line| line.contains("pub struct Example")));
+//! ```
+
+use clap::{Parser, Subcommand, ValueEnum};
+use context::Context;
+use convert_case::{Case, Casing};
+use cst::LineKind;
+use diagnostics::{Message, MessageFormat, Severity};
+use generator::{Backend, FallibleApi, Generator, PartialEqImpl, Renames, Resolution};
+use math::MathLib;
+use nom::{error::convert_error, Finish};
+use parser::{ArrayInfo, Expr, Limits, TypeName, Value, ValueType};
+use seq::SeqImpl;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    error::Error,
+    ffi::{OsStr, OsString},
+    fs::{create_dir_all, read_to_string, write, File},
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant},
+};
+use timings::{Timings, TimingsFormat};
+use walkdir::WalkDir;
+use warnings::Warning;
+
+mod alias;
+mod archive;
+#[cfg(feature = "tui")]
+mod browse;
+mod c_tests;
+mod cdr;
+mod config;
+mod const_tests;
+mod constants;
+mod context;
+mod cst;
+mod defaults;
+mod diagnostics;
+mod diff;
+mod examples;
+mod flags;
+mod fmt;
+mod fuzz;
+pub mod generator;
+mod git;
+mod html;
+mod idl;
+mod links;
+mod math;
+mod mermaid;
+mod metadata;
+mod micro_ros;
+mod msg_error;
+mod optional;
+mod owned;
+pub mod parser;
+mod point_cloud;
+mod qos;
+mod random;
+mod scaffold;
+mod seq;
+mod serialize;
+mod show;
+mod size;
+mod stages;
+mod stats;
+mod time;
+mod timings;
+mod versions;
+mod warnings;
+
+/// Rust code generator from .msg and .srv of ROS 2.
+#[derive(Parser, Debug)]
+#[clap(
+    author,
+    version,
+    about,
+    long_about = None,
+    subcommand_negates_reqs = true,
+    args_override_self = true
+)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    #[clap(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate common_interfaces bundled with safe_drive.
+    ///
+    /// `-i` is the source tree of ROS 2's common_interfaces.
+    /// This is equivalent to `--disable-common-interfaces --exclude-package builtin_interfaces`,
+    /// and `--safe-drive` is `crate` unless specified.
+    /// builtin_interfaces is excluded because safe_drive defines it by itself to handle the year-2038 problem.
+    CommonInterfaces(Box<Args>),
+
+    /// Format .msg and .srv files in the canonical style.
+    Fmt(FmtArgs),
+
+    /// Create a new interface package having `msg`, `srv` and `action` directories,
+    /// `package.xml`, `CMakeLists.txt` and a sample message.
+    New(NewArgs),
+
+    /// Render packages into a static HTML site of types with their fields, constants and comments.
+    Doc(DocArgs),
+
+    /// Print the definition of a type, recursively expanding the types of its fields.
+    Show(ShowArgs),
+
+    /// Browse types in a terminal UI showing their expanded definitions and generated code side by side.
+    #[cfg(feature = "tui")]
+    Browse(BrowseArgs),
+
+    /// Report the C struct size and the maximum CDR size of each message, and unbounded fields.
+    Size(InputArgs),
+
+    /// Summarize each package: numbers of messages and services, the maximum nesting depth,
+    /// the number of unbounded fields and external dependencies.
+    Stats(InputArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct ShowArgs {
+    /// Input directory or archive containing .msg and .srv.
+    /// Multiple inputs can be specified for overlayed workspaces.
+    #[clap(short, long, required = true)]
+    input: Vec<String>,
+
+    /// Which definition is used if the same type is defined in several input directories.
+    #[clap(long, value_enum, default_value_t = Prefer::Last)]
+    prefer: Prefer,
+
+    /// Type to be printed, such as `pkg/msg/Type`, `pkg/srv/Type` or `pkg/Type`.
+    type_name: String,
+}
+
+#[cfg(feature = "tui")]
+#[derive(clap::Args, Debug)]
+struct BrowseArgs {
+    /// Input directory or archive containing .msg and .srv.
+    /// Multiple inputs can be specified for overlayed workspaces.
+    #[clap(short, long, required = true)]
+    input: Vec<String>,
+
+    /// Which definition is used if the same type is defined in several input directories.
+    #[clap(long, value_enum, default_value_t = Prefer::Last)]
+    prefer: Prefer,
+
+    /// Path to the safe_drive used by the generated code.
+    #[clap(short, long, default_value_t = String::from("safe_drive"))]
+    safe_drive: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct NewArgs {
+    /// Name of the package.
+    name: String,
+
+    /// Directory where the package is created. The configuration file is also created in it if it does not exist.
+    #[clap(long, default_value_t = String::from("."))]
+    dir: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct DocArgs {
+    /// Input directory or archive containing .msg and .srv.
+    /// Multiple inputs can be specified for overlayed workspaces.
+    #[clap(short, long, required = true)]
+    input: Vec<String>,
+
+    /// Which definition is used if the same type is defined in several input directories.
+    #[clap(long, value_enum, default_value_t = Prefer::Last)]
+    prefer: Prefer,
+
+    /// Render only types of the packages. This option can be specified multiple times.
+    #[clap(short, long)]
+    package: Vec<String>,
+
+    /// Path to the output directory.
+    #[clap(short, long, default_value_t = String::from("doc"))]
+    out: String,
+
+    /// Overwrite existing files even if they are not generated by ros2msg_to_rs.
+    #[clap(long)]
+    force: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct InputArgs {
+    /// Input directory or archive containing .msg and .srv.
+    /// Multiple inputs can be specified for overlayed workspaces.
+    #[clap(short, long, required = true)]
+    input: Vec<String>,
+
+    /// Which definition is used if the same type is defined in several input directories.
+    #[clap(long, value_enum, default_value_t = Prefer::Last)]
+    prefer: Prefer,
+
+    /// Report only messages of the packages. This option can be specified multiple times.
+    #[clap(short, long)]
+    package: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct FmtArgs {
+    /// Files or directories containing .msg and .srv to be formatted.
+    #[clap(required = true)]
+    paths: Vec<String>,
+
+    /// Check files are formatted without modifying them, and fail if not.
+    #[clap(long)]
+    check: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Input directory containing .msg and .srv.
+    /// Multiple directories can be specified for overlayed workspaces.
+    #[clap(short, long, required_unless_present = "from_git")]
+    input: Vec<String>,
+
+    /// Git repository containing .msg and .srv, specified by `URL[#ref][:subdir]`.
+    /// The repository is fetched into a cache and used as an input directory
+    /// before directories specified by `-i`.
+    #[clap(long)]
+    from_git: Vec<String>,
+
+    /// Input directory of a version of packages, specified by `VERSION=DIR` such as `v1=old/src`.
+    /// Packages in the directory are generated as modules `{package}_{VERSION}` besides the packages of `-i`,
+    /// and messages identical to the ones of the previous version are converted by `From`.
+    /// This option can be specified multiple times in the order of versions.
+    #[clap(long)]
+    versioned_input: Vec<String>,
+
+    /// Which definition is used if the same `pkg/Type` is found under multiple input directories.
+    /// `last` follows the order of overlayed workspaces.
+    #[clap(long, value_enum, default_value_t = Prefer::Last)]
+    prefer: Prefer,
+
+    /// Path to the safe_drive. [default: safe_drive]
+    #[clap(short, long)]
+    safe_drive: Option<String>,
+
+    /// Path to the output directory. [default: generated/{name of the first input}]
+    /// Cargo's build directory is refused without `--force`, because `cargo clean` removes it.
+    #[clap(short, long, default_value_t = String::from(DEFAULT_OUT))]
+    out: String,
+
+    /// Disable to use common_interfaces. This option is used to generate common_interfaces used by safe_drive.
+    /// So, do not set this option if you are not of the develeper of safe_drive.
+    #[clap(long)]
+    disable_common_interfaces: bool,
+
+    /// Packages which are not generated. This option can be specified multiple times.
+    #[clap(long)]
+    exclude_package: Vec<String>,
+
+    /// Packages resolved to locally generated modules even if common_interfaces of safe_drive are used.
+    /// This is useful when an overlayed workspace shadows a package of common_interfaces.
+    /// This option can be specified multiple times.
+    #[clap(long)]
+    local_package: Vec<String>,
+
+    /// Packages resolved to common_interfaces of safe_drive even if `--disable-common-interfaces` is specified.
+    /// This option can be specified multiple times.
+    #[clap(long)]
+    common_package: Vec<String>,
+
+    /// Generate each package as a crate having Cargo.toml and build.rs,
+    /// and Cargo.toml of a workspace containing them in the output directory.
+    /// Dependencies between packages are added to Cargo.toml automatically.
+    #[clap(long)]
+    emit_crates: bool,
+
+    /// Version of safe_drive used by crates generated by `--emit-crates`.
+    #[clap(long, default_value_t = String::from("0.4"))]
+    safe_drive_version: String,
+
+    /// Path to the safe_drive crate used by crates generated by `--emit-crates`.
+    /// If this is specified, `--safe-drive-version` is ignored.
+    #[clap(long)]
+    safe_drive_crate_path: Option<String>,
+
+    /// Copy the .msg and .srv files used for generation to `{out}/{package}/interfaces`,
+    /// so that the output can be regenerated by `-i {out}` without the original workspace.
+    #[clap(long)]
+    vendor_sources: bool,
+
+    /// Write a Makefile-style dependency file listing each generated file
+    /// and the .msg and .srv files it depends on.
+    #[clap(long)]
+    dep_file: Option<String>,
+
+    /// Write a class diagram of messages and their compositions in Mermaid to `{out}/{package}/classes.mmd`.
+    #[clap(long)]
+    emit_mermaid: bool,
+
+    /// Write a JSON report of interface packages referred from each package to the file.
+    #[clap(long)]
+    emit_deps_report: Option<String>,
+
+    /// After generation, check whether the C libraries of each generated package are found
+    /// in `AMENT_PREFIX_PATH` or `LD_LIBRARY_PATH`, and report packages which will fail to link.
+    #[clap(long)]
+    check_links: bool,
+
+    /// How to implement PartialEq of messages.
+    /// `rust` compares fields in Rust instead of calling `__are_equal` of the C library.
+    #[clap(long, value_enum, default_value_t = PartialEqImpl::Ffi)]
+    partial_eq: PartialEqImpl,
+
+    /// Generate accessors typed by the `uom` crate for fields whose comments have units like `# [m/s]`.
+    /// The generated code requires `uom` as a dependency.
+    #[clap(long)]
+    uom: bool,
+
+    /// Generate conversions between geometry messages, such as Vector3, Quaternion and Pose,
+    /// and types of the math library. This option can be specified multiple times.
+    #[clap(long, value_enum)]
+    math: Vec<MathLib>,
+
+    /// Generate conversions between `chrono` and fields of `builtin_interfaces/Time` and `Duration`,
+    /// in addition to conversions of `std::time`. The generated code requires `chrono` as a dependency.
+    #[clap(long)]
+    chrono: bool,
+
+    /// Derive `serde::Serialize` and `serde::Deserialize` for messages and services.
+    /// The generated code requires `serde` and the `serde` feature of safe_drive.
+    #[clap(long)]
+    serde: bool,
+
+    /// Generate tests which serialize each message to JSON and deserialize it back.
+    /// The tests require `serde_json` as a dev-dependency.
+    #[clap(long, requires = "serde")]
+    serde_tests: bool,
+
+    /// Generate tests asserting that each constant equals the value in the .msg and .srv files,
+    /// comparing bytes of strings and bits of floating point numbers.
+    #[clap(long)]
+    const_tests: bool,
+
+    /// Generate `constant_by_name()` of each message, returning `ConstantValue` of `{package}/constants.rs`.
+    #[clap(long)]
+    constants_by_name: bool,
+
+    /// Generate a type of bit flags for each group of integer constants sharing a prefix, like `ERROR_*`,
+    /// whose values are distinct powers of two.
+    #[clap(long)]
+    bitflags: bool,
+
+    /// Implement sequences by a macro in `{package}/seq.rs` instead of repeating them in each file.
+    #[clap(long)]
+    seq_macro: bool,
+
+    /// Implement sequences by the generic `TypedSeq` in `{package}/seq.rs`, and make `{Type}Seq` its alias.
+    #[clap(long, conflicts_with = "seq_macro")]
+    typed_seq: bool,
+
+    /// Add `#[link]` of the C libraries of each package to its `extern "C"` blocks,
+    /// so that the generated code links without build.rs.
+    #[clap(long)]
+    extern_link: bool,
+
+    /// Lint allowed on `extern "C"` blocks, such as `improper_ctypes`.
+    /// This option can be specified multiple times.
+    #[clap(long, value_name = "LINT")]
+    extern_allow: Vec<String>,
+
+    /// Return type of `new()` of messages and sequences.
+    /// `result` returns `Result<Self, MsgError>` distinguishing failures of allocation from violations of bounds.
+    #[clap(long, value_enum, default_value_t = FallibleApi::Option, conflicts_with_all = ["seq_macro", "typed_seq"])]
+    fallible_api: FallibleApi,
+
+    /// Do not implement `Send` and `Sync` of messages.
+    /// They are implemented by default if every field is owned data, such as strings and sequences.
+    #[clap(long)]
+    no_send_sync: bool,
+
+    /// Generate type aliases of safe_drive, `{Message}Publisher` and `{Message}Subscriber` of messages,
+    /// and `{Service}Client` and `{Service}Server` of services.
+    #[clap(long)]
+    emit_aliases: bool,
+
+    /// Generate code using `core` and `alloc` only for `#![no_std]` crates.
+    /// Conversions to `std::time::SystemTime` are not generated.
+    #[clap(long)]
+    no_std: bool,
+
+    /// Runtime which the generated code is linked with.
+    /// `micro-ros` declares the type supports of micro-ROS and generates static memory pools.
+    #[clap(long, value_enum, default_value_t = Backend::SafeDrive)]
+    backend: Backend,
+
+    /// Generate `cdr.rs` in each package, which reads and writes CDR in the explicit byte order
+    /// and dispatches payloads by their encapsulation headers,
+    /// and `encode_cdr()` and `decode_cdr()` of each message.
+    #[clap(long)]
+    cdr: bool,
+
+    /// Generate `fill_random()` setting every field of messages to a random value.
+    /// The generated code requires `rand` 0.9 as a dependency.
+    #[clap(long)]
+    random: bool,
+
+    /// Generate `{Type}Owned` of each message, whose strings and sequences are `String` and `Vec`,
+    /// and conversions between messages and them.
+    #[clap(long)]
+    owned: bool,
+
+    /// Generate `diff()` of each message reporting fields which differ from another message,
+    /// with `FieldDiff` of `{package}/diff.rs`.
+    #[clap(long)]
+    diff: bool,
+
+    /// Generate a cargo-fuzz project in each package feeding arbitrary bytes to the deserializers
+    /// enabled by `--serde` and `--cdr`.
+    #[clap(long, requires = "emit_crates")]
+    fuzz_targets: bool,
+
+    /// Generate example nodes using safe_drive in `{out}/{package}/examples`,
+    /// a publisher and a subscriber of each message, and a server and a client of each service.
+    #[clap(long, requires = "emit_crates")]
+    emit_examples: bool,
+
+    /// Generate tests in `{out}/{package}/tests/c_tests.rs` comparing the layouts, `__are_equal()` and
+    /// the serialized forms of messages with the C structs, enabled by the feature `c-tests` of the crates.
+    #[clap(long, requires = "emit_crates")]
+    emit_c_tests: bool,
+
+    /// Generate examples of only the type, such as `pkg/msg/Type`, `pkg/srv/Type` or `pkg/Type`.
+    /// This option can be specified multiple times.
+    #[clap(long, requires = "emit_examples")]
+    example_type: Vec<String>,
+
+    /// Print the stages of packages in the build order, where packages of each stage
+    /// depend only on packages of previous stages. The stages are also written to `{out}/build-order.txt`.
+    #[clap(long, requires = "emit_crates")]
+    print_stages: bool,
+
+    /// Write only the crates of the stage, and add the crates of the stage and previous stages to the workspace.
+    #[clap(long, requires = "emit_crates")]
+    only_stage: Option<usize>,
+
+    /// Suppress the warning. This option can be specified multiple times.
+    #[clap(long, value_enum)]
+    allow: Vec<Warning>,
+
+    /// Print the warning, which is the default. This option can be specified multiple times.
+    #[clap(short = 'W', long, value_enum)]
+    warn: Vec<Warning>,
+
+    /// Treat the warning as an error, and fail after generation.
+    /// This option can be specified multiple times and takes precedence over `--allow` and `--warn`.
+    #[clap(long, value_enum)]
+    deny: Vec<Warning>,
+
+    /// Overwrite existing files even if they are not generated by ros2msg_to_rs.
+    #[clap(long)]
+    force: bool,
+
+    /// Save the previous version of each modified file as `{file}.bak`, such as `example.rs.bak`.
+    #[clap(long)]
+    backup: bool,
+
+    /// Run the command by the shell after successful generation,
+    /// where `ROS2MSG_TO_RS_OUT` is the output directory. This option can be specified multiple times.
+    #[clap(long)]
+    post_cmd: Vec<String>,
+
+    /// Format of messages. `json` prints a JSON object per line for IDEs and scripts.
+    #[clap(long, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+
+    /// Maximum bytes of a line of .msg and .srv files.
+    #[clap(long, default_value_t = Limits::DEFAULT.max_line_length)]
+    max_line_length: usize,
+
+    /// Maximum fields and constants of a message, a request or a response.
+    #[clap(long, default_value_t = Limits::DEFAULT.max_fields)]
+    max_fields: usize,
+
+    /// Maximum elements of an array literal.
+    #[clap(long, default_value_t = Limits::DEFAULT.max_array_elements)]
+    max_array_elements: usize,
+
+    /// Maximum nesting of array literals.
+    #[clap(long, default_value_t = Limits::DEFAULT.max_nesting)]
+    max_nesting: usize,
+
+    /// Write durations of walking, parsing, generating and writing, in total and of each package, to the file.
+    #[clap(long, value_name = "FILE")]
+    timings: Option<String>,
+
+    /// Format of `--timings`. `folded` is folded stacks for flamegraphs.
+    #[clap(long, value_enum, default_value_t = TimingsFormat::Json)]
+    timings_format: TimingsFormat,
+
+    /// Profile of options defined in the configuration file.
+    /// The options of the profile are inserted in place, so options after `--profile` override them.
+    #[clap(long)]
+    profile: Option<String>,
+
+    /// Path to the configuration file defining profiles, skipped types and renames.
+    #[clap(long, default_value_t = String::from(config::DEFAULT_CONFIG))]
+    config: String,
+}
+
+/// Priority of input directories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Prefer {
+    /// The definition in the first input directory is used.
+    First,
+
+    /// The definition in the last input directory is used.
+    Last,
+}
+
+impl Args {
+    /// Options of generation parsed like the command line without the name of the program,
+    /// such as `["-i", "src", "-o", "generated"]`, where `--profile` is expanded too.
+    pub fn new<I, T>(options: I) -> Result<Args, Box<dyn Error>>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let mut argv = vec![OsString::from(env!("CARGO_PKG_NAME"))];
+        argv.extend(options.into_iter().map(Into::into));
+
+        let cli = Cli::try_parse_from(config::expand_profile(argv)?)?;
+        if cli.command.is_some() {
+            return Err("subcommands cannot be options of generation".into());
+        }
+        let mut args = cli.args;
+        args.safe_drive
+            .get_or_insert_with(|| "safe_drive".to_string());
+        Ok(args)
+    }
+}
+
+/// Error of [`run`] which has already been printed, such as an error printed as JSON by `--message-format json`.
+#[derive(Debug)]
+pub struct Reported;
+
+impl std::fmt::Display for Reported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the error has already been reported")
+    }
+}
+
+impl Error for Reported {}
+
+/// Run the command line of ros2msg_to_rs, where `argv` includes the name of the program.
+/// Errors of the command line, including `--help` and `--version`, are returned as `clap::Error`,
+/// and errors printed as JSON are returned as [`Reported`], so the caller decides how to exit.
+pub fn run<I, T>(argv: I) -> Result<(), Box<dyn Error>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let argv = argv.into_iter().map(Into::into).collect();
+    let cli = Cli::try_parse_from(config::expand_profile(argv)?)?;
+
+    let args = match cli.command {
+        Some(Command::CommonInterfaces(mut args)) => {
+            args.disable_common_interfaces = true;
+            args.exclude_package.push("builtin_interfaces".to_string());
+            args.safe_drive.get_or_insert_with(|| "crate".to_string());
+            *args
+        }
+        Some(Command::Fmt(fmt_args)) => return fmt::run(&fmt_args.paths, fmt_args.check),
+        Some(Command::New(new_args)) => return new_package(&new_args),
+        Some(Command::Doc(doc_args)) => return doc(&doc_args),
+        Some(Command::Show(show_args)) => return show(&show_args),
+        #[cfg(feature = "tui")]
+        Some(Command::Browse(browse_args)) => return browse(&browse_args),
+        Some(Command::Size(size_args)) => return size(&size_args),
+        Some(Command::Stats(stats_args)) => return stats(&stats_args),
+        None => {
+            let mut args = cli.args;
+            args.safe_drive
+                .get_or_insert_with(|| "safe_drive".to_string());
+            args
+        }
+    };
+
+    let format = args.message_format;
+    let result = generate_workspace(&args);
+    if format != MessageFormat::Json {
+        return result;
+    }
+
+    // errors are also printed as JSON
+    if let Err(e) = &result {
+        diagnostics::print(
+            format,
+            Message::Diagnostic {
+                level: Severity::Error,
+                code: None,
+                message: &e.to_string(),
+                file: None,
+            },
+        );
+    }
+    diagnostics::print(
+        format,
+        Message::GenerationFinished {
+            success: result.is_ok(),
+        },
+    );
+
+    result.map_err(|_| Reported.into())
+}
+
+/// Generate Rust files from the input directories of `args` to the output directory,
+/// as ros2msg_to_rs does without subcommands.
+///
+/// Levels of warnings, limits of the parser and the message format of `args` apply only to this call,
+/// so generations can run on several threads at the same time.
+pub fn generate_workspace(args: &Args) -> Result<(), Box<dyn Error>> {
+    let context = Context {
+        levels: warnings::levels(&args.allow, &args.warn, &args.deny),
+        limits: Limits {
+            max_line_length: args.max_line_length,
+            max_fields: args.max_fields,
+            max_array_elements: args.max_array_elements,
+            max_nesting: args.max_nesting,
+        },
+        message_format: args.message_format,
+        denied: 0,
+    };
+    context.scope(|| generate(args))
+}
+
+fn generate(args: &Args) -> Result<(), Box<dyn Error>> {
+    if args.fuzz_targets && !args.serde && !args.cdr {
+        return Err("--fuzz-targets requires --serde or --cdr".into());
+    }
+    if args.emit_c_tests && args.backend != Backend::SafeDrive {
+        return Err("--emit-c-tests requires the safe-drive backend".into());
+    }
+    for spec in args.example_type.iter() {
+        parse_type_spec(spec)?;
+    }
+
+    let mut roots = Vec::new();
+    for spec in args.from_git.iter() {
+        roots.push(git::fetch(spec)?.canonicalize()?);
+    }
+    for input in args.input.iter() {
+        roots.push(Path::new(input).canonicalize()?);
+    }
+    let mut versioned_roots = Vec::new();
+    for spec in args.versioned_input.iter() {
+        let Some((version, dir)) = spec.split_once('=') else {
+            return Err(format!("--versioned-input must be VERSION=DIR: {spec}").into());
+        };
+        validate_name("version", version, Path::new(dir))?;
+        if versioned_roots.iter().any(|(v, _)| v == version) {
+            return Err(format!("version {version} is specified twice").into());
+        }
+        versioned_roots.push((version.to_string(), Path::new(dir).canonicalize()?));
+    }
+    if !versioned_roots.is_empty() {
+        // versions of a package have the same C symbols and libraries
+        for (enabled, option) in [
+            (args.emit_crates, "--emit-crates"),
+            (args.emit_c_tests, "--emit-c-tests"),
+            (args.check_links, "--check-links"),
+        ] {
+            if enabled {
+                return Err(format!("--versioned-input cannot be used with {option}").into());
+            }
+        }
+    }
+    let project_name = archive::archive_stem(&roots[0])
+        .map(OsStr::new)
+        .unwrap_or_else(|| roots[0].file_name().unwrap());
+
+    // destination directory
+    let target = if args.out == DEFAULT_OUT {
+        warn_old_default_out(project_name);
+        Path::new(DEFAULT_OUT).join(project_name)
+    } else {
+        Path::new(&args.out).to_path_buf()
+    };
+    if !args.force {
+        if let Some(dir) = cargo_target_dir(&target) {
+            return Err(format!(
+                "refusing to write into cargo's build directory {}, which is removed by `cargo clean`, use --force to write there",
+                dir.display()
+            )
+            .into());
+        }
+    }
+
+    let mut timings = Timings::default();
+    let mut outputs = Outputs::default();
+    let generated = generate_msgs(
+        &mut outputs,
+        &target,
+        &roots,
+        &versioned_roots,
+        args,
+        &mut timings,
+    )?;
+
+    // generation of files other than the types
+    let start = Instant::now();
+    generate_mod_rs(&mut outputs, &target, &generated, args);
+    let stages = if args.emit_crates {
+        stages::stages(&generated.libs)?
+    } else {
+        Vec::new()
+    };
+    if args.emit_crates {
+        generate_crates(&mut outputs, &target, &generated, &stages, args)?;
+        generate_build_order(&mut outputs, &target, &stages, args)?;
+    }
+    if args.fuzz_targets {
+        for (pkg, types) in generated.types.iter() {
+            let files = fuzz::gen_fuzz_targets(&target.join(pkg), pkg, types, args.serde, args.cdr);
+            for (path, contents) in files {
+                outputs.add(path, contents);
+            }
+        }
+    }
+    if args.emit_examples {
+        for (pkg, definitions) in generated.definitions.iter() {
+            for (ext, type_name) in definitions.iter() {
+                // safe_drive has no examples of actions
+                if ext == "action" || generated.packed.contains(&(pkg.clone(), type_name.clone())) {
+                    continue;
+                }
+                let selected = args.example_type.is_empty()
+                    || args.example_type.iter().any(|spec| {
+                        parse_type_spec(spec).is_ok_and(|key| key == (pkg, ext, type_name))
+                    });
+                if !selected {
+                    continue;
+                }
+
+                let files = examples::gen_examples(
+                    &target.join(pkg),
+                    pkg,
+                    ext,
+                    type_name,
+                    args.fallible_api,
+                );
+                for (path, contents) in files {
+                    outputs.add(path, contents);
+                }
+            }
+        }
+    }
+    if args.emit_c_tests {
+        for (pkg, types) in generated.c_tests.iter() {
+            let module = generated.renames.package(pkg);
+            let files = c_tests::gen_c_tests(
+                &target.join(module),
+                &mangle_module(module),
+                types,
+                args.fallible_api,
+            );
+            for (path, contents) in files {
+                outputs.add(path, contents);
+            }
+        }
+    }
+    if let Some(report) = &args.emit_deps_report {
+        generate_deps_report(&mut outputs, Path::new(report), &generated)?;
+    }
+    if let Some(dep_file) = &args.dep_file {
+        generate_dep_file(&mut outputs, Path::new(dep_file), &generated);
+    }
+    timings.add(None, "generate", start.elapsed());
+
+    // crates are written stage by stage
+    if args.emit_crates {
+        let stage_of = |path: &Path| {
+            let pkg = path.strip_prefix(&target).ok()?.components().next()?;
+            let pkg = pkg.as_os_str().to_str()?;
+            stages
+                .iter()
+                .position(|stage| stage.iter().any(|p| p == pkg))
+        };
+        outputs
+            .files
+            .sort_by_key(|(path, _)| stage_of(path).unwrap_or(usize::MAX));
+
+        if let Some(n) = args.only_stage {
+            outputs.retain(|path| stage_of(path).is_none_or(|stage| stage == n));
+        }
+    }
+
+    // nothing is written if denied warnings are reported
+    warnings::check_denied()?;
+    if !args.force {
+        outputs.check_overwrite()?;
+    }
+    outputs.write(args.backup, |path, d| {
+        // files of packages are under `{target}/{package}`
+        let pkg = path
+            .strip_prefix(&target)
+            .ok()
+            .filter(|p| p.components().count() > 1)
+            .and_then(|p| p.components().next())
+            .and_then(|c| c.as_os_str().to_str());
+        timings.add(pkg, "write", d);
+    })?;
+
+    if let Some(path) = &args.timings {
+        write(path, timings.report(args.timings_format))?;
+    }
+
+    if args.check_links {
+        check_links(&generated)?;
+    }
+
+    for cmd in args.post_cmd.iter() {
+        run_post_cmd(cmd, &target)?;
+    }
+
+    Ok(())
+}
+
+/// Default output directory. `target` was the default until cargo's build directory was refused.
+const DEFAULT_OUT: &str = "generated";
+
+/// Warn that files generated in `target/{project_name}` by the old default are no longer updated.
+fn warn_old_default_out(project_name: &OsStr) {
+    let old = Path::new("target").join(project_name);
+    let generated_before = read_to_string(old.join("mod.rs")).is_ok_and(|mod_rs| {
+        mod_rs
+            .lines()
+            .next()
+            .is_some_and(|l| l.contains(PROVENANCE))
+    });
+    if !generated_before {
+        return;
+    }
+
+    let message = format!(
+        "Warning: the default output directory is changed from target to {DEFAULT_OUT}, so {} is not updated. Remove it, or use `-o {} --force` to keep updating it",
+        old.display(),
+        old.display()
+    );
+    diagnostics::report(Message::Diagnostic {
+        level: Severity::Warning,
+        code: None,
+        message: &message,
+        file: None,
+    });
+}
+
+/// Return cargo's build directory containing `path`, which has `CACHEDIR.TAG` created by cargo.
+fn cargo_target_dir(path: &Path) -> Option<PathBuf> {
+    let path = std::env::current_dir().ok()?.join(path);
+    path.ancestors()
+        .find(|dir| {
+            read_to_string(dir.join("CACHEDIR.TAG"))
+                .is_ok_and(|tag| tag.contains("created by cargo"))
+        })
+        .map(Path::to_path_buf)
+}
+
+/// Run `cmd` by the shell with `ROS2MSG_TO_RS_OUT` of the output directory.
+fn run_post_cmd(cmd: &str, target: &Path) -> Result<(), Box<dyn Error>> {
+    let mut command = if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c");
+        command
+    };
+
+    let status = command
+        .arg(cmd)
+        .env("ROS2MSG_TO_RS_OUT", target)
+        .status()
+        .map_err(|e| format!("failed to run `{cmd}`: {e}"))?;
+    if !status.success() {
+        return Err(format!("`{cmd}` failed: {status}").into());
+    }
+
+    Ok(())
+}
+
+/// Report generated packages whose C libraries are not found.
+fn check_links(generated: &Generated) -> Result<(), Box<dyn Error>> {
+    let dirs = links::search_dirs();
+
+    let mut failed = Vec::new();
+    for pkg in generated.libs.keys() {
+        let missing = links::missing_libs(pkg, &dirs);
+        diagnostics::report(Message::LinkCheck {
+            package: pkg,
+            missing: &missing,
+        });
+        if !missing.is_empty() {
+            failed.push(pkg.as_str());
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("failed to find C libraries of {}", failed.join(", ")).into())
+    }
+}
+
+/// Generate `{target}/build-order.txt` having packages of each stage in a line, and print it if `--print-stages`.
+fn generate_build_order(
+    outputs: &mut Outputs,
+    target: &Path,
+    stages: &[Vec<String>],
+    args: &Args,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(n) = args.only_stage {
+        if n >= stages.len() {
+            return Err(
+                format!("--only-stage {n}: there are only {} stage(s)", stages.len()).into(),
+            );
+        }
+    }
+
+    let mut build_order = String::from("# This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).\n# Packages of each stage depend only on packages of previous stages.\n");
+    for (n, stage) in stages.iter().enumerate() {
+        let line = format!("{n}: {}", stage.join(" "));
+        if args.print_stages {
+            println!("stage {line}");
+        }
+        build_order.push_str(&format!("{line}\n"));
+    }
+    outputs.add(target.join("build-order.txt"), build_order);
+
+    Ok(())
+}
+
+/// Generate `{target}/mod.rs` and `{target}/{module}/mod.rs`.
+/// `{target}/mod.rs` is not generated if `--emit-crates` is specified because each package is a crate.
+/// `{target}/{module}/cdr.rs` is also generated by `--cdr`,
+/// `{target}/{module}/seq.rs` if sequences are not expanded in each file,
+/// `{target}/{module}/constants.rs` by `--constants-by-name`,
+/// `{target}/{module}/diff.rs` by `--diff`,
+/// and `{target}/{module}/error.rs` by `--fallible-api result`.
+/// `{target}/{module}/mod.rs` has the documentation listing the types and the version of the package.
+fn generate_mod_rs(outputs: &mut Outputs, target: &Path, generated: &Generated, args: &Args) {
+    let (emit_crates, no_std) = (args.emit_crates, args.no_std);
+    let seq_impl = seq_impl(args);
+
+    // {target}/mod.rs
+    let mut mod_rs = String::from("// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).\n");
+    for (m, val) in generated.mod_dirs.iter() {
+        let module = m.file_name().unwrap().to_str().unwrap();
+        mod_rs.push_str(&format!("pub mod {};\n", mangle_module(module)));
+
+        // {target}/{module}/mod.rs
+        let mut mod_rs_in = String::from("// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).\n");
+        mod_rs_in.push_str(&gen_package_doc(
+            module,
+            generated.versions.get(module).map(String::as_str),
+            generated.definitions.get(module).map_or(&[], Vec::as_slice),
+        ));
+        if emit_crates && no_std {
+            // the module is the root of the crate
+            mod_rs_in.push_str("#![no_std]\nextern crate alloc;\n");
+        }
+        for s in val {
+            mod_rs_in.push_str(&format!("pub mod {s};\n"));
+            if s == "msg" {
+                mod_rs_in.push_str("use msg::*;\n");
+            }
+        }
+        if args.cdr {
+            mod_rs_in.push_str("pub mod cdr;\n");
+            outputs.add(m.join("cdr.rs"), cdr::gen_cdr_module(no_std));
+        }
+        if seq_impl != SeqImpl::Expanded {
+            mod_rs_in.push_str("mod seq;\n");
+            outputs.add(m.join("seq.rs"), seq::gen_seq_module(seq_impl));
+        }
+        if args.constants_by_name {
+            mod_rs_in.push_str("pub mod constants;\npub use constants::ConstantValue;\n");
+            outputs.add(m.join("constants.rs"), constants::gen_constants_module());
+        }
+        if args.owned {
+            mod_rs_in.push_str("pub mod owned;\n");
+            outputs.add(m.join("owned.rs"), owned::gen_owned_module());
+        }
+        if args.diff {
+            mod_rs_in.push_str("pub mod diff;\npub use diff::FieldDiff;\n");
+            outputs.add(m.join("diff.rs"), diff::gen_diff_module(no_std));
+        }
+        if args.fallible_api == FallibleApi::Result {
+            mod_rs_in.push_str("pub mod error;\npub use error::MsgError;\n");
+            outputs.add(m.join("error.rs"), msg_error::gen_error_module(no_std));
+        }
+        outputs.add(m.join("mod.rs"), mod_rs_in);
+    }
+
+    if !emit_crates {
+        outputs.add(target.join("mod.rs"), mod_rs);
+    }
+}
+
+/// Generate `{target}/{module}/Cargo.toml`, `{target}/{module}/build.rs` and `{target}/Cargo.toml`.
+/// Only crates of `--only-stage` and previous stages are members of the workspace.
+fn generate_crates(
+    outputs: &mut Outputs,
+    target: &Path,
+    generated: &Generated,
+    stages: &[Vec<String>],
+    args: &Args,
+) -> Result<(), Box<dyn Error>> {
+    let package_resolution = package_resolution(args)?;
+
+    let safe_drive = if let Some(path) = &args.safe_drive_crate_path {
+        format!("path = \"{path}\"")
+    } else {
+        format!("version = \"{}\"", args.safe_drive_version)
+    };
+    let safe_drive = if args.serde && args.no_std {
+        format!("{{ {safe_drive}, features = [\"serde\"] }}\nserde = {{ version = \"1\", default-features = false, features = [\"derive\", \"alloc\"] }}")
+    } else if args.serde {
+        format!("{{ {safe_drive}, features = [\"serde\"] }}\nserde = {{ version = \"1\", features = [\"derive\"] }}")
+    } else {
+        format!("{{ {safe_drive} }}")
+    };
+    let safe_drive = if args.random && args.no_std {
+        format!("{safe_drive}\nrand = {{ version = \"0.9\", default-features = false, features = [\"alloc\"] }}")
+    } else if args.random {
+        format!("{safe_drive}\nrand = \"0.9\"")
+    } else {
+        safe_drive
+    };
+    let dev_dependencies = if args.serde_tests {
+        "\n[dev-dependencies]\nserde_json = \"1\"\n"
+    } else {
+        ""
+    };
+
+    let c_tests_features = if args.emit_c_tests {
+        "\n[features]\nc-tests = [\"dep:cc\"]\n\n[build-dependencies]\ncc = { version = \"1\", optional = true }\n"
+    } else {
+        ""
+    };
+
+    let typesupport = micro_ros::typesupport(args.backend);
+
+    let mut members = Vec::new();
+    for (pkg, libs) in generated.libs.iter() {
+        // crates are named after renamed modules, and C libraries after packages
+        let name = generated.renames.package(pkg);
+
+        let mut dependencies = format!("safe_drive = {safe_drive}\n");
+        for lib in libs.iter() {
+            if lib == pkg || package_resolution.get(lib) == Some(&Resolution::Common) {
+                continue;
+            }
+
+            if generated.libs.contains_key(lib) {
+                let lib = generated.renames.package(lib);
+                dependencies.push_str(&format!("{lib} = {{ path = \"../{lib}\" }}\n"));
+            } else if args.disable_common_interfaces
+                || package_resolution.get(lib) == Some(&Resolution::Local)
+            {
+                warnings::warn(
+                    Warning::MissingDependencies,
+                    &format!("{pkg} refers to {lib}, but {lib} is not generated."),
+                );
+            }
+        }
+
+        let cargo_toml = format!(
+            "# This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+[package]
+name = \"{name}\"
+version = \"0.1.0\"
+edition = \"2021\"
+
+[lib]
+path = \"mod.rs\"
+
+[dependencies]
+{dependencies}{dev_dependencies}{c_tests_features}"
+        );
+
+        let build_c_tests = if args.emit_c_tests {
+            c_tests::gen_build_c_tests(pkg)
+        } else {
+            String::new()
+        };
+        let build_rs = format!(
+            "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+fn main() {{
+    if let Some(paths) = std::env::var_os(\"AMENT_PREFIX_PATH\") {{
+        for path in std::env::split_paths(&paths) {{
+            println!(\"cargo:rustc-link-search={{}}\", path.join(\"lib\").display());
+        }}
+    }}
+
+    println!(\"cargo:rustc-link-lib={pkg}__{typesupport}\");
+    println!(\"cargo:rustc-link-lib={pkg}__rosidl_generator_c\");
+{build_c_tests}}}
+"
+        );
+
+        let dir = target.join(name);
+        outputs.add(dir.join("Cargo.toml"), cargo_toml);
+        outputs.add(dir.join("build.rs"), build_rs);
+
+        let built = match args.only_stage {
+            Some(n) => stages.iter().take(n + 1).flatten().any(|p| p == pkg),
+            None => true,
+        };
+        if built {
+            members.push(format!("    \"{name}\","));
+        }
+    }
+
+    let workspace = format!(
+        "# This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+[workspace]
+resolver = \"2\"
+members = [
+{}
+]
+",
+        members.join("\n")
+    );
+    outputs.add(target.join("Cargo.toml"), workspace);
+
+    Ok(())
+}
+
+/// Generate a Makefile-style dependency file.
+/// The .rs file of a type depends on its .msg or .srv file,
+/// and the other files, such as mod.rs, depend on all the .msg and .srv files
+/// because they list the types.
+fn generate_dep_file(outputs: &mut Outputs, path: &Path, generated: &Generated) {
+    fn escape(path: &Path) -> String {
+        path.display()
+            .to_string()
+            .replace('$', "$$")
+            .replace('#', "\\#")
+            .replace(' ', "\\ ")
+    }
+
+    let all_sources: BTreeSet<&PathBuf> = generated.sources.values().collect();
+    let all_sources: Vec<String> = all_sources.iter().map(|path| escape(path)).collect();
+    let all_sources = all_sources.join(" ");
+
+    let mut dep_file = String::new();
+    for (file, _) in outputs.files.iter() {
+        if let Some(source) = generated.sources.get(file) {
+            dep_file.push_str(&format!("{}: {}\n", escape(file), escape(source)));
+        } else {
+            dep_file.push_str(&format!("{}: {all_sources}\n", escape(file)));
+        }
+    }
+
+    outputs.add_unmarked(path.to_path_buf(), dep_file);
+}
+
+/// Interface packages referred from generated packages.
+#[derive(Serialize)]
+struct DepsReport<'a> {
+    /// Packages referred from each package, except the package itself.
+    packages: BTreeMap<&'a str, BTreeSet<&'a str>>,
+
+    /// Packages referred from any package, except generated ones.
+    external: BTreeSet<&'a str>,
+}
+
+/// Generate a JSON report of the packages referred from each package.
+fn generate_deps_report(
+    outputs: &mut Outputs,
+    path: &Path,
+    generated: &Generated,
+) -> Result<(), Box<dyn Error>> {
+    let mut report = DepsReport {
+        packages: BTreeMap::new(),
+        external: BTreeSet::new(),
+    };
+
+    for (pkg, libs) in generated.libs.iter() {
+        let libs: BTreeSet<&str> = libs
+            .iter()
+            .map(|lib| lib.as_str())
+            .filter(|lib| lib != pkg)
+            .collect();
+
+        for lib in libs.iter() {
+            if !generated.libs.contains_key(*lib) {
+                report.external.insert(lib);
+            }
+        }
+
+        report.packages.insert(pkg, libs);
+    }
+
+    let mut json = serde_json::to_string_pretty(&report)?;
+    json.push('\n');
+    outputs.add_unmarked(path.to_path_buf(), json);
+
+    Ok(())
+}
+
+/// Generated files.
+/// Files are built in memory and written at once by `Outputs::write`,
+/// which also creates each directory only once.
+///
+/// Existing files are overwritten only if they have `PROVENANCE`,
+/// so that hand-written files are not clobbered by a wrong output directory.
+#[derive(Default)]
+struct Outputs {
+    dirs: BTreeSet<PathBuf>,
+    files: Vec<(PathBuf, String)>,
+
+    /// Files which cannot have `PROVENANCE`, such as JSON and vendored .msg files.
+    unmarked: BTreeSet<PathBuf>,
+}
+
+/// Mark of generated files, which is in the first line of them.
+const PROVENANCE: &str = "This file was automatically generated by ros2msg_to_rs";
+
+impl Outputs {
+    fn add(&mut self, path: PathBuf, contents: String) {
+        if let Some(dir) = path.parent() {
+            self.dirs.insert(dir.to_path_buf());
+        }
+        self.files.push((path, contents));
+    }
+
+    /// Add a file without `PROVENANCE`, which is overwritten without the check.
+    fn add_unmarked(&mut self, path: PathBuf, contents: String) {
+        self.unmarked.insert(path.clone());
+        self.add(path, contents);
+    }
+
+    /// Return an error if any existing file to be overwritten is not generated by ros2msg_to_rs.
+    fn check_overwrite(&self) -> Result<(), Box<dyn Error>> {
+        let mut refused = Vec::new();
+        for (path, _) in self.files.iter() {
+            if self.unmarked.contains(path) {
+                continue;
+            }
+
+            // unreadable files are reported when they are written
+            let Ok(existing) = read_to_string(path) else {
+                continue;
+            };
+            if !existing
+                .lines()
+                .next()
+                .is_some_and(|l| l.contains(PROVENANCE))
+            {
+                refused.push(path.display().to_string());
+            }
+        }
+
+        if refused.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "refusing to overwrite files not generated by ros2msg_to_rs, use --force to overwrite them: {}",
+                refused.join(", ")
+            )
+            .into())
+        }
+    }
+
+    /// Keep only files whose paths satisfy `f`.
+    fn retain(&mut self, f: impl Fn(&Path) -> bool) {
+        self.files.retain(|(path, _)| f(path));
+        self.dirs = self
+            .files
+            .iter()
+            .filter_map(|(path, _)| path.parent().map(Path::to_path_buf))
+            .collect();
+    }
+
+    /// Write files. If `backup` is true, existing files to be modified are saved as `{file}.bak`.
+    /// `written` is called with the path and the duration of writing each file.
+    fn write(
+        &self,
+        backup: bool,
+        mut written: impl FnMut(&Path, Duration),
+    ) -> Result<(), Box<dyn Error>> {
+        for dir in self.dirs.iter() {
+            create_dir_all(dir)?;
+        }
+
+        for (path, contents) in self.files.iter() {
+            // files are copied as they are, even if they are not UTF-8
+            if backup {
+                if let Ok(existing) = std::fs::read(path) {
+                    if existing != contents.as_bytes() {
+                        let mut bak = path.as_os_str().to_os_string();
+                        bak.push(".bak");
+                        std::fs::copy(path, bak)?;
+                    }
+                }
+            }
+
+            diagnostics::report(Message::GeneratedFile { path });
+            let start = Instant::now();
+            write(path, contents)?;
+            written(path, start.elapsed());
+        }
+
+        Ok(())
+    }
+}
+
+/// A .msg, .srv or .action file, or an .idl file in `msg`, `srv` or `action`, found in the input directories.
+struct Source {
+    /// Name of the package, which is the first directory under the input directory.
+    module_name: String,
+
+    /// "msg", "srv" or "action".
+    ext: String,
+
+    type_name: String,
+
+    /// Path of the definition. Paths in archives are shown as `{archive}/{path in the archive}`.
+    path: PathBuf,
+
+    /// File containing the definition, which is the archive for definitions in archives.
+    file: PathBuf,
+
+    contents: String,
+}
+
+impl Source {
+    fn is_idl(&self) -> bool {
+        self.path.extension().is_some_and(|ext| ext == "idl")
+    }
+}
+
+/// (package, "msg", "srv" or "action", type name)
+type SourceKey = (String, String, String);
+
+/// Packages of `-i` or of a version specified by `--versioned-input`.
+struct Tree<'a> {
+    /// Version of `--versioned-input`, which is `None` for the packages of `-i`.
+    version: Option<&'a str>,
+
+    inputs: BTreeMap<SourceKey, Source>,
+
+    /// Renames of packages and types, where packages of a version are renamed to `{module}_{version}`.
+    renames: Renames,
+
+    /// Version of each package in its package.xml.
+    package_versions: BTreeMap<String, String>,
+
+    /// Definitions of a version not generated because they differ from the ones of `-i`.
+    changed: BTreeSet<SourceKey>,
+}
+
+/// Collect .msg, .srv, .action and .idl files from input directories and archives.
+///
+/// If the same `pkg/Type` is defined under several inputs,
+/// only one of them is used according to `prefer` and a warning is printed.
+fn collect_sources(
+    roots: &[PathBuf],
+    prefer: Prefer,
+) -> Result<BTreeMap<SourceKey, Source>, Box<dyn Error>> {
+    let mut sources: BTreeMap<SourceKey, Source> = BTreeMap::new();
+
+    for src in roots {
+        if archive::is_archive(src) {
+            for (path, contents) in archive::read_archive(src)? {
+                // assume children are modules
+                let Some(Component::Normal(mod_name)) = path.components().next() else {
+                    continue;
+                };
+                let mod_name = mod_name.to_os_string();
+                add_source(
+                    &mut sources,
+                    prefer,
+                    &mod_name,
+                    src.join(&path),
+                    src,
+                    contents,
+                )?;
+            }
+            continue;
+        }
+
+        let mut mod_name = OsString::new();
+
+        // traverse directory in the same order on any file system
+        // so that `prefer` selects the same definition
+        for entry in WalkDir::new(src).sort_by_file_name() {
+            let path = entry?;
+
+            // assume children are modules
+            if path.depth() == 1 {
+                mod_name = path.file_name().to_os_string();
+            }
+
+            if !path.file_type().is_file() {
+                continue;
+            }
+
+            let p = path.path();
+            if !archive::is_definition(p) {
+                continue;
+            }
+
+            let contents = archive::read_definition(File::open(p)?)?;
+            add_source(
+                &mut sources,
+                prefer,
+                &mod_name,
+                p.to_path_buf(),
+                p,
+                contents,
+            )?;
+        }
+    }
+
+    Ok(sources)
+}
+
+/// Add a .msg, .srv or .action file to `sources`.
+/// `path` is displayed in messages and `file` is the file on disk containing the definition.
+fn add_source(
+    sources: &mut BTreeMap<SourceKey, Source>,
+    prefer: Prefer,
+    mod_name: &OsStr,
+    path: PathBuf,
+    file: &Path,
+    contents: String,
+) -> Result<(), Box<dyn Error>> {
+    let (Some(mut ext), Some(file_name)) = (path.extension(), path.file_name()) else {
+        return Ok(());
+    };
+
+    // an .idl file defines a message, a service or an action by its directory
+    if ext == "idl" {
+        match path.parent().and_then(|dir| dir.file_name()) {
+            Some(dir) if dir == "msg" || dir == "srv" || dir == "action" => ext = dir,
+            _ => return Ok(()),
+        }
+    }
+
+    let v: Vec<&str> = file_name.to_str().unwrap().split('.').collect();
+    let source = Source {
+        module_name: mod_name.to_str().unwrap().to_string(),
+        ext: ext.to_str().unwrap().to_string(),
+        type_name: v.first().unwrap().to_string(),
+        path: path.clone(),
+        file: file.to_path_buf(),
+        contents,
+    };
+
+    let key = (
+        source.module_name.clone(),
+        source.ext.clone(),
+        source.type_name.clone(),
+    );
+
+    if let Some(prev) = sources.get(&key) {
+        // rosidl installs .idl files generated from .msg, .srv and .action files next to them
+        match (prev.is_idl(), source.is_idl()) {
+            (false, true) => return Ok(()),
+            (true, false) => {
+                sources.insert(key, source);
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        let (used, ignored) = match prefer {
+            Prefer::First => (&prev.path, &source.path),
+            Prefer::Last => (&source.path, &prev.path),
+        };
+        warnings::warn(
+            Warning::DuplicateDefinitions,
+            &format!(
+                "{}/{}/{} is defined twice, {} is used and {} is ignored.",
+                key.0,
+                key.1,
+                key.2,
+                used.display(),
+                ignored.display()
+            ),
+        );
+
+        if prefer == Prefer::First {
+            return Ok(());
+        }
+    }
+
+    sources.insert(key, source);
+
+    Ok(())
+}
+
+/// Packages generated by `generate_msgs`.
+struct Generated {
+    /// `{target}/{module}` and its submodules, which are "msg", "srv" or "action".
+    mod_dirs: BTreeMap<PathBuf, BTreeSet<String>>,
+
+    /// Packages referred from each package.
+    libs: BTreeMap<String, BTreeSet<String>>,
+
+    /// Generated .rs file of each type and its .msg or .srv file.
+    sources: BTreeMap<PathBuf, PathBuf>,
+
+    /// Messages of each package relative to the package, such as `msg::Type` and `srv::TypeRequest`.
+    types: BTreeMap<String, Vec<String>>,
+
+    /// Definitions of each package, "msg", "srv" or "action" and the type name.
+    definitions: BTreeMap<String, Vec<(String, String)>>,
+
+    /// Renames of packages and types, which are applied to `mod_dirs`, `types` and `definitions`.
+    renames: Renames,
+
+    /// Version of each package in its package.xml.
+    versions: BTreeMap<String, String>,
+
+    /// Messages of each package compared with the C structs by `--emit-c-tests`.
+    c_tests: BTreeMap<String, Vec<c_tests::CTestType>>,
+
+    /// Packed messages as `(module, type)`, which have no type support, so they have no examples.
+    packed: BTreeSet<(String, String)>,
+}
+
+fn package_resolution(args: &Args) -> Result<BTreeMap<String, Resolution>, Box<dyn Error>> {
+    let mut package_resolution = BTreeMap::new();
+    for pkg in args.local_package.iter() {
+        package_resolution.insert(pkg.clone(), Resolution::Local);
+    }
+    for pkg in args.common_package.iter() {
+        if package_resolution
+            .insert(pkg.clone(), Resolution::Common)
+            .is_some()
+        {
+            return Err(
+                format!("{pkg} is specified by both --local-package and --common-package").into(),
+            );
+        }
+    }
+
+    Ok(package_resolution)
+}
+
+/// Version of ros2msg_to_rs and options affecting generated code,
+/// written in generated files for reproducibility.
+fn generator_options(args: &Args) -> String {
+    fn value_name<T: ValueEnum>(value: &T) -> String {
+        value
+            .to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default()
+    }
+
+    let math: Vec<String> = args.math.iter().map(value_name).collect();
+
+    let mut options = vec![
+        format!("safe_drive={}", args.safe_drive.as_deref().unwrap_or("")),
+        format!("partial_eq={}", value_name(&args.partial_eq)),
+        format!("uom={}", args.uom),
+        format!("math=[{}]", math.join(",")),
+        format!("chrono={}", args.chrono),
+        format!("serde={}", args.serde),
+        format!("no_std={}", args.no_std),
+        format!("backend={}", value_name(&args.backend)),
+        format!("cdr={}", args.cdr),
+        format!("random={}", args.random),
+        format!("owned={}", args.owned),
+        format!("diff={}", args.diff),
+        format!("const_tests={}", args.const_tests),
+        format!("constants_by_name={}", args.constants_by_name),
+        format!("bitflags={}", args.bitflags),
+        format!("emit_aliases={}", args.emit_aliases),
+        format!("seq_macro={}", args.seq_macro),
+        format!("typed_seq={}", args.typed_seq),
+        format!("extern_link={}", args.extern_link),
+        format!("extern_allow=[{}]", args.extern_allow.join(",")),
+        format!("fallible_api={}", value_name(&args.fallible_api)),
+        format!("no_send_sync={}", args.no_send_sync),
+        format!(
+            "disable_common_interfaces={}",
+            args.disable_common_interfaces
+        ),
+        format!("emit_crates={}", args.emit_crates),
+    ];
+
+    if !args.local_package.is_empty() {
+        options.push(format!("local_package=[{}]", args.local_package.join(",")));
+    }
+    if !args.common_package.is_empty() {
+        options.push(format!(
+            "common_package=[{}]",
+            args.common_package.join(",")
+        ));
+    }
+
+    format!(
+        "ros2msg_to_rs {} ({})",
+        env!("CARGO_PKG_VERSION"),
+        options.join(", ")
+    )
+}
+
+/// Generator of a package configured by `args`.
+fn new_generator(
+    module_name: &str,
+    args: &Args,
+    package_resolution: &BTreeMap<String, Resolution>,
+) -> Generator {
+    let mut g = Generator::new(
+        module_name.to_string(),
+        args.safe_drive.clone().unwrap(),
+        args.disable_common_interfaces,
+    );
+    g.set_partial_eq(args.partial_eq);
+    g.set_uom(args.uom);
+    g.set_math(args.math.clone());
+    g.set_chrono(args.chrono);
+    g.set_package_resolution(package_resolution.clone());
+    g.set_emit_crates(args.emit_crates);
+    g.set_serde(args.serde);
+    g.set_serde_tests(args.serde_tests);
+    g.set_no_std(args.no_std);
+    g.set_backend(args.backend);
+    g.set_random(args.random);
+    g.set_owned(args.owned);
+    g.set_cdr(args.cdr);
+    g.set_diff(args.diff);
+    g.set_const_tests(args.const_tests);
+    g.set_constants_by_name(args.constants_by_name);
+    g.set_bitflags(args.bitflags);
+    g.set_aliases(args.emit_aliases);
+    g.set_seq_impl(seq_impl(args));
+    g.set_extern_link(args.extern_link);
+    g.set_extern_allow(args.extern_allow.clone());
+    g.set_fallible_api(args.fallible_api);
+    g.set_send_sync(!args.no_send_sync);
+    g
+}
+
+fn seq_impl(args: &Args) -> SeqImpl {
+    if args.typed_seq {
+        SeqImpl::Typed
+    } else if args.seq_macro {
+        SeqImpl::Macro
+    } else {
+        SeqImpl::Expanded
+    }
+}
+
+fn generate_msgs(
+    outputs: &mut Outputs,
+    target: &Path,
+    roots: &[PathBuf],
+    versioned_roots: &[(String, PathBuf)],
+    args: &Args,
+    timings: &mut Timings,
+) -> Result<Generated, Box<dyn Error>> {
+    let mut modules_msg = BTreeMap::new();
+    let mut modules_srv = BTreeMap::new();
+    let mut modules_action = BTreeMap::new();
+    let mut mod_dirs: BTreeMap<PathBuf, BTreeSet<String>> = BTreeMap::new();
+    let mut libs = BTreeMap::new();
+    let mut sources = BTreeMap::new();
+    let mut types: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut definitions: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    let mut diagrams: BTreeMap<PathBuf, mermaid::Diagram> = BTreeMap::new();
+    let mut c_tests: BTreeMap<String, Vec<c_tests::CTestType>> = BTreeMap::new();
+    let mut packed = BTreeSet::new();
+    let package_resolution = package_resolution(args)?;
+    let options = generator_options(args);
+
+    let inputs = timings.time(None, "walk", || collect_sources(roots, args.prefer))?;
+    let mut versions = BTreeMap::new();
+
+    let config = config::load(&args.config)?;
+    let mut skipped = BTreeSet::new();
+    for spec in config.skip.iter() {
+        let (pkg, ext, type_name) = parse_type_spec(spec)?;
+        skipped.insert((pkg.to_string(), ext.to_string(), type_name.to_string()));
+    }
+    let renames = renames(&config.rename, Path::new(&args.config))?;
+    let mut layouts = BTreeMap::new();
+    for (spec, layout) in config.layout.iter() {
+        let (pkg, ext, type_name) = parse_type_spec(spec)?;
+        let repr = layout
+            .repr()
+            .map_err(|e| format!("{}: layout of {spec}: {e}", args.config))?;
+        layouts.insert(
+            (pkg.to_string(), ext.to_string(), type_name.to_string()),
+            repr,
+        );
+    }
+    let mut topics = BTreeMap::new();
+    for (spec, topic) in config.topic.iter() {
+        let (pkg, ext, type_name) = parse_type_spec(spec)?;
+        if ext != "msg" {
+            return Err(format!(
+                "{}: topic of {spec}: only messages have topics",
+                args.config
+            )
+            .into());
+        }
+        validate_topic(topic).map_err(|e| format!("{}: topic of {spec}: {e}", args.config))?;
+        topics.insert(
+            (pkg.to_string(), ext.to_string(), type_name.to_string()),
+            topic,
+        );
+    }
+    let mut qos_profiles = BTreeMap::new();
+    for (spec, qos) in config.qos.iter() {
+        let (pkg, ext, type_name) = parse_type_spec(spec)?;
+        if ext == "action" {
+            return Err(format!("{}: qos of {spec}: actions have no QoS", args.config).into());
+        }
+        qos.validate()
+            .map_err(|e| format!("{}: qos of {spec}: {e}", args.config))?;
+        qos_profiles.insert(
+            (pkg.to_string(), ext.to_string(), type_name.to_string()),
+            qos,
+        );
+    }
+    let (aliases, messages) = aliases(&config, &inputs, &skipped, &layouts, args)?;
+
+    // trees of packages, which are the packages of `-i` and the ones of each version
+    let mut trees = vec![Tree {
+        version: None,
+        inputs,
+        renames: renames.clone(),
+        package_versions: package_versions(roots),
+        changed: BTreeSet::new(),
+    }];
+    for (version, root) in versioned_roots.iter() {
+        let root = std::slice::from_ref(root);
+        let inputs = timings.time(None, "walk", || collect_sources(root, args.prefer))?;
+
+        // packages of the version refer to each other
+        let mut version_renames = renames.clone();
+        for (pkg, _, _) in inputs.keys() {
+            let module = format!("{}_{version}", renames.package(pkg));
+            version_renames.packages.insert(pkg.clone(), module);
+        }
+
+        let mut tree = Tree {
+            version: Some(version),
+            inputs,
+            renames: version_renames,
+            package_versions: package_versions(root),
+            changed: BTreeSet::new(),
+        };
+        tree.changed = changed_definitions(&trees[0], &tree);
+        for (pkg, ext, type_name) in tree.changed.iter() {
+            warnings::warn(
+                Warning::VersionedChanges,
+                &format!("{pkg}/{ext}/{type_name} of {version} is not generated because it differs from the one of -i, whose C functions it would call"),
+            );
+        }
+        trees.push(tree);
+    }
+
+    // definitions of versions are identical to the ones of `-i`, so they share the known types
+    let known_types = known_types(&trees[0], &skipped, args, &package_resolution);
+
+    // packages of each module to reject renaming packages into the same module
+    let mut modules: BTreeMap<&str, &str> = BTreeMap::new();
+
+    for tree in trees.iter() {
+        let renames = &tree.renames;
+
+        // messages of each package to validate references in the package
+        let mut local_types: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+        for (pkg, ext, type_name) in tree.inputs.keys() {
+            if ext == "msg" {
+                local_types
+                    .entry(pkg)
+                    .or_default()
+                    .insert(type_name.clone());
+            }
+        }
+
+        // a generator is shared by the files of a package
+        let mut generators: BTreeMap<String, Generator> = BTreeMap::new();
+        let packages = input_packages(tree, args);
+
+        for (key, source) in tree.inputs.iter() {
+            if args.exclude_package.contains(&source.module_name)
+                || skipped.contains(key)
+                || tree.changed.contains(key)
+            {
+                continue;
+            }
+
+            let ext = source.ext.as_str();
+            let module_name = source.module_name.as_str();
+            let type_name = source.type_name.as_str();
+
+            validate_name("package", module_name, &source.path)?;
+            validate_name("type", type_name, &source.path)?;
+            check_naming(source);
+            check_skipped_references(source, &skipped);
+            check_packed_references(source, &layouts)?;
+            check_string_constants(source)?;
+
+            // Rust names of the package and the type
+            let module = renames.package(module_name);
+            let rust_type_name = renames.type_name(module_name, ext, type_name);
+            if let Some(other) = modules.insert(module, module_name) {
+                if other != module_name {
+                    return Err(format!(
+                        "{other} and {module_name} are renamed to the same module {module}"
+                    )
+                    .into());
+                }
+            }
+
+            if let Some(version) = tree.package_versions.get(module_name) {
+                versions.insert(module.to_string(), version.clone());
+            }
+
+            let contents = &source.contents;
+
+            // generate Rust code
+            let g = generators
+                .entry(module_name.to_string())
+                .or_insert_with(|| {
+                    let mut g = new_generator(module_name, args, &package_resolution);
+                    g.set_local_types(local_types.remove(module_name).unwrap_or_default());
+                    g.set_known_types(known_types.clone());
+                    g.set_input_packages(packages.clone());
+                    g.set_renames(renames.clone());
+                    g
+                });
+            g.set_deprecated(parser::parse_file_deprecated(contents));
+
+            let repr = layouts.get(key).cloned();
+            if let Some(repr) = &repr {
+                let consequence = if repr.starts_with("packed") {
+                    packed.insert((module.to_string(), rust_type_name.to_string()));
+                    "the offsets of its fields differ from the C struct, so it is generated without C functions and cannot be published"
+                } else {
+                    "sequences of it allocated by C functions have a different stride"
+                };
+                warnings::warn(
+                    Warning::LayoutOverrides,
+                    &format!("{module_name}/{ext}/{type_name} has #[repr(C, {repr})] by the configuration file, and {consequence}"),
+                );
+            }
+            g.set_repr(repr);
+
+            // the configuration file overrides the annotation
+            let topic = match topics.get(key) {
+                Some(topic) => Some(topic.to_string()),
+                None if ext == "msg" => parser::parse_file_topic(contents),
+                None => None,
+            };
+            if let Some(topic) = &topic {
+                validate_topic(topic).map_err(|e| format!("{}: {e}", source.path.display()))?;
+            }
+            g.set_topic(topic);
+
+            let qos = match qos_profiles.get(key) {
+                Some(qos) => Some((*qos).clone()),
+                None if ext != "action" => parser::parse_file_qos(contents)
+                    .map(|args| qos::Qos::from_args(&args))
+                    .transpose()
+                    .map_err(|e| format!("{}: {e}", source.path.display()))?,
+                None => None,
+            };
+            g.set_qos(qos);
+
+            // aliases are of the packages of `-i`
+            let alias = aliases.get(key).filter(|_| tree.version.is_none());
+            let mut lines = if let Some((target_pkg, target_type)) = alias {
+                let exprs = &messages[&(module_name.to_string(), type_name.to_string())];
+                timings
+                    .time(Some(module_name), "generate", || {
+                        g.gen_msg_alias(type_name, target_pkg, target_type, exprs)
+                    })
+                    .map_err(|e| format!("failed to generate {}: {e}", source.path.display()))?
+            } else {
+                generate_definition(g, source, module_name, type_name, timings)?
+            };
+
+            // provenance next to "This file was automatically generated by ..."
+            let file_ext = if source.is_idl() { "idl" } else { ext };
+            let sha256: String = Sha256::digest(contents.as_bytes())
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect();
+            lines.insert(
+                1,
+                format!(
+                    "// Source: {module_name}/{ext}/{type_name}.{file_ext} (SHA-256: {sha256})"
+                )
+                .into(),
+            );
+            lines.insert(2, format!("// Generator: {options}").into());
+
+            // "{target}/{mod_name}"
+            let mod_dir = target.join(module);
+
+            if let Some(mods) = mod_dirs.get_mut(&mod_dir) {
+                mods.insert(ext.to_string());
+            } else {
+                let mut mods = BTreeSet::new();
+                mods.insert(ext.to_string());
+                mod_dirs.insert(mod_dir.clone(), mods);
+            }
+
+            // module's directory
+            // {target}/{mod_name}/(msg|srv)
+            let target_dir = mod_dir.join(ext);
+
+            // generate {target}/{mod_name}/(msg|srv|action)/{snake_type_name}.rs
+            let sname = rust_type_name.to_case(Case::Snake);
+            let snake_type_name = mangle(&sname);
+
+            let mod_file = format!("{snake_type_name}.rs");
+            let target_file = target_dir.join(mod_file);
+
+            add_modules(
+                match ext {
+                    "msg" => &mut modules_msg,
+                    "srv" => &mut modules_srv,
+                    _ => &mut modules_action,
+                },
+                mod_dir.as_os_str(),
+                snake_type_name.to_string(),
+            );
+
+            let contents: String = lines.iter().map(|line| format!("{line}\n")).collect();
+            sources.insert(target_file.clone(), source.file.clone());
+
+            definitions
+                .entry(module.to_string())
+                .or_default()
+                .push((ext.to_string(), rust_type_name.to_string()));
+
+            let pkg_types = types.entry(module.to_string()).or_default();
+            match ext {
+                "msg" => pkg_types.push(format!("msg::{rust_type_name}")),
+                "srv" => {
+                    pkg_types.push(format!("srv::{rust_type_name}Request"));
+                    pkg_types.push(format!("srv::{rust_type_name}Response"));
+                }
+                _ => {
+                    for part in ["Goal", "Result", "Feedback"] {
+                        pkg_types.push(format!("action::{rust_type_name}{part}"));
+                    }
+                }
+            }
+
+            // diagrams are drawn from the lines of .msg, .srv and .action files
+            if args.emit_mermaid && !source.is_idl() {
+                diagrams
+                    .entry(mod_dir.clone())
+                    .or_default()
+                    .add(module_name, ext, type_name, &source.contents)
+                    .map_err(|e| format!("{}: {e}", source.path.display()))?;
+            }
+
+            if args.emit_c_tests {
+                // the definition is parsed again only if it is tested
+                let parts = parse_definition(source)?;
+                c_tests
+                    .entry(module_name.to_string())
+                    .or_default()
+                    .extend(c_tests::c_test_types(
+                        module_name,
+                        ext,
+                        type_name,
+                        rust_type_name,
+                        &parts,
+                    ));
+            }
+
+            if args.vendor_sources {
+                // {target}/{mod_name}/interfaces/(msg|srv|action)/{type_name}.(msg|srv|action|idl)
+                let vendored = mod_dir
+                    .join("interfaces")
+                    .join(ext)
+                    .join(format!("{type_name}.{file_ext}"));
+                outputs.add_unmarked(vendored, source.contents.clone());
+            }
+            outputs.add(target_file, contents);
+        }
+
+        // C libraries are of the packages of `-i`
+        if tree.version.is_none() {
+            libs = generators
+                .into_iter()
+                .map(|(pkg, g)| (pkg, g.libs))
+                .collect();
+        }
+    }
+
+    // conversions between consecutive versions of each package
+    for pair in trees[1..].windows(2) {
+        let (prev, cur) = (&pair[0], &pair[1]);
+        for (module, code) in conversions(prev, cur, &skipped, args) {
+            let mod_dir = target.join(&module);
+            if let Some(mods) = mod_dirs.get_mut(&mod_dir) {
+                mods.insert("conversions".to_string());
+                outputs.add(mod_dir.join("conversions.rs"), code);
+            }
+        }
+    }
+
+    for (ext, modules) in [
+        ("msg", modules_msg),
+        ("srv", modules_srv),
+        ("action", modules_action),
+    ] {
+        for (k, v) in modules {
+            let dir = Path::new(&k);
+            let module = dir.file_name().unwrap().to_str().unwrap();
+            let kind = match ext {
+                "msg" => "Messages",
+                "srv" => "Services",
+                _ => "Actions",
+            };
+            let doc = format!(
+                "//! {kind} of `{module}`.\n//!\n{}",
+                gen_types_doc(definitions.get(module).map_or(&[], Vec::as_slice), ext, "")
+            );
+            outputs.add(dir.join(format!("{ext}.rs")), generate_msg_srv_rs(&v, &doc));
+        }
+    }
+
+    for (mod_dir, diagram) in diagrams {
+        outputs.add(mod_dir.join("classes.mmd"), diagram.render());
+    }
+
+    Ok(Generated {
+        mod_dirs,
+        libs,
+        sources,
+        types,
+        definitions,
+        renames,
+        versions,
+        c_tests,
+        packed,
+    })
+}
+
+/// `conversions.rs` of each module of `cur` converting identical messages between `prev` and `cur`,
+/// which are versions of packages specified by `--versioned-input`.
+fn conversions(
+    prev: &Tree,
+    cur: &Tree,
+    skipped: &BTreeSet<SourceKey>,
+    args: &Args,
+) -> BTreeMap<String, String> {
+    // messages keyed by their modules, whose references to other packages are replaced by modules too
+    let version_messages = |tree: &Tree| -> alias::Messages {
+        tree.inputs
+            .iter()
+            .filter(|(key, _)| {
+                key.1 == "msg"
+                    && !skipped.contains(key)
+                    && !tree.changed.contains(key)
+                    && !args.exclude_package.contains(&key.0)
+            })
+            .filter_map(|((pkg, _, type_name), source)| {
+                // errors are reported by the generation
+                let mut exprs = parse_definition(source).ok()?.pop()?;
+                versions::rename_scopes(&mut exprs, &tree.renames);
+                let module = tree.renames.package(pkg).to_string();
+                Some(((module, type_name.clone()), exprs))
+            })
+            .collect()
+    };
+    let mut messages = version_messages(prev);
+    let prev_keys: BTreeSet<alias::Key> = messages.keys().cloned().collect();
+    messages.append(&mut version_messages(cur));
+    let classes = alias::classes(&messages);
+
+    let mut conversions = BTreeMap::new();
+    for (pkg, _, type_name) in cur.inputs.keys() {
+        let prev_module = prev.renames.package(pkg);
+        let cur_module = cur.renames.package(pkg);
+        let prev_key = (prev_module.to_string(), type_name.clone());
+        let cur_key = (cur_module.to_string(), type_name.clone());
+        if !prev_keys.contains(&prev_key) || !classes.contains_key(&cur_key) {
+            continue;
+        }
+
+        // changed messages are not generated in versions
+        if classes[&prev_key] != classes[&cur_key] {
+            continue;
+        }
+        let rust_type_name = cur.renames.type_name(pkg, "msg", type_name).to_string();
+        conversions
+            .entry((prev_module, cur_module))
+            .or_insert_with(Vec::new)
+            .push(rust_type_name);
+    }
+
+    conversions
+        .into_iter()
+        .map(|((prev_module, cur_module), conversions)| {
+            let code = versions::gen_conversions(prev_module, cur_module, &conversions);
+            (cur_module.to_string(), code)
+        })
+        .collect()
+}
+
+/// Messages of `tree` as `(package, type)` whose fields are known including the fields of nested messages,
+/// which implement traits generated only for known fields, such as `Send`, `Sync` and `diff()`.
+fn known_types(
+    tree: &Tree,
+    skipped: &BTreeSet<SourceKey>,
+    args: &Args,
+    package_resolution: &BTreeMap<String, Resolution>,
+) -> BTreeSet<(String, String)> {
+    let packages = input_packages(tree, args);
+    let mut generators: BTreeMap<&str, Generator> = BTreeMap::new();
+    let mut referred = BTreeMap::new();
+    for (key @ (pkg, ext, type_name), source) in tree.inputs.iter() {
+        if ext != "msg" || skipped.contains(key) || args.exclude_package.contains(pkg) {
+            continue;
+        }
+
+        // errors are reported by the generation
+        let Some(exprs) = parse_definition(source)
+            .ok()
+            .and_then(|mut parts| parts.pop())
+        else {
+            continue;
+        };
+        let g = generators.entry(pkg).or_insert_with(|| {
+            let mut g = new_generator(pkg, args, package_resolution);
+            g.set_input_packages(packages.clone());
+            g
+        });
+        if let Some(types) = g.referred_types(&exprs) {
+            referred.insert((pkg.clone(), type_name.clone()), types);
+        }
+    }
+
+    // messages referring to unknown messages are unknown too
+    loop {
+        let unknown: Vec<(String, String)> = referred
+            .iter()
+            .filter(|(_, types)| types.iter().any(|key| !referred.contains_key(key)))
+            .map(|(key, _)| key.clone())
+            .collect();
+        if unknown.is_empty() {
+            return referred.into_keys().collect();
+        }
+        for key in unknown.iter() {
+            referred.remove(key);
+        }
+    }
+}
+
+/// Packages of `tree` which are generated, so messages referring to them are known even if common_interfaces are used.
+fn input_packages(tree: &Tree, args: &Args) -> BTreeSet<String> {
+    tree.inputs
+        .keys()
+        .map(|(pkg, _, _)| pkg)
+        .filter(|pkg| !args.exclude_package.contains(pkg))
+        .cloned()
+        .collect()
+}
+
+/// Definitions of `tree`, a version of `--versioned-input`, which are not structurally identical to the ones of `linked`,
+/// the packages of `-i`, or are not defined in it. Messages they refer to are compared too.
+fn changed_definitions(linked: &Tree, tree: &Tree) -> BTreeSet<SourceKey> {
+    // messages and parts of services and actions as `(module, "{type}.{ext}.{index}")`,
+    // which do not collide with the names of messages
+    let parts = |tree: &Tree| -> alias::Messages {
+        let mut messages = BTreeMap::new();
+        for ((pkg, ext, type_name), source) in tree.inputs.iter() {
+            // errors are reported by the generation
+            let Ok(parts) = parse_definition(source) else {
+                continue;
+            };
+            let module = tree.renames.package(pkg);
+            for (i, mut exprs) in parts.into_iter().enumerate() {
+                versions::rename_scopes(&mut exprs, &tree.renames);
+                let name = match ext.as_str() {
+                    "msg" => type_name.clone(),
+                    _ => format!("{type_name}.{ext}.{i}"),
+                };
+                messages.insert((module.to_string(), name), exprs);
+            }
+        }
+        messages
+    };
+    let mut messages = parts(linked);
+    let linked_keys: BTreeSet<alias::Key> = messages.keys().cloned().collect();
+    let mut tree_parts = parts(tree);
+    let tree_keys: BTreeSet<alias::Key> = tree_parts.keys().cloned().collect();
+    messages.append(&mut tree_parts);
+    let classes = alias::classes(&messages);
+
+    tree.inputs
+        .keys()
+        .filter(|(pkg, ext, type_name)| {
+            let (linked_module, module) = (linked.renames.package(pkg), tree.renames.package(pkg));
+            let names: Vec<String> = match ext.as_str() {
+                "msg" => vec![type_name.clone()],
+                "srv" => (0..2).map(|i| format!("{type_name}.{ext}.{i}")).collect(),
+                _ => (0..3).map(|i| format!("{type_name}.{ext}.{i}")).collect(),
+            };
+            // definitions failing to be parsed are reported by the generation
+            names.into_iter().any(|name| {
+                let linked_key = (linked_module.to_string(), name.clone());
+                let key = (module.to_string(), name);
+                tree_keys.contains(&key)
+                    && (!linked_keys.contains(&linked_key) || classes[&linked_key] != classes[&key])
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// Messages re-exporting structurally identical messages by `alias` of the configuration file,
+/// and parsed messages to compare them, which are also reported by `identical-definitions`.
+fn aliases(
+    config: &config::Config,
+    inputs: &BTreeMap<SourceKey, Source>,
+    skipped: &BTreeSet<SourceKey>,
+    layouts: &BTreeMap<SourceKey, String>,
+    args: &Args,
+) -> Result<(BTreeMap<SourceKey, alias::Key>, alias::Messages), Box<dyn Error>> {
+    let generated = |key: &SourceKey| {
+        inputs.contains_key(key) && !skipped.contains(key) && !args.exclude_package.contains(&key.0)
+    };
+
+    let mut aliases = BTreeMap::new();
+    for (spec, target) in config.alias.iter() {
+        let (pkg, ext, type_name) = parse_type_spec(spec)?;
+        let (target_pkg, target_ext, target_type) = parse_type_spec(target)?;
+        if ext != "msg" || target_ext != "msg" {
+            return Err(format!(
+                "{}: alias of {spec}: only messages can be aliased",
+                args.config
+            )
+            .into());
+        }
+        aliases.insert(
+            (pkg.to_string(), ext.to_string(), type_name.to_string()),
+            (target_pkg.to_string(), target_type.to_string()),
+        );
+    }
+
+    // messages are parsed twice only if they are compared
+    if aliases.is_empty() && !warnings::enabled(Warning::IdenticalDefinitions) {
+        return Ok((aliases, BTreeMap::new()));
+    }
+    let messages: alias::Messages = inputs
+        .iter()
+        .filter(|(key, _)| key.1 == "msg" && generated(key))
+        .filter_map(|((pkg, _, type_name), source)| {
+            // errors are reported by the generation
+            let exprs = parse_definition(source).ok()?.pop()?;
+            Some(((pkg.clone(), type_name.clone()), exprs))
+        })
+        .collect();
+
+    let classes = alias::classes(&messages);
+    for group in alias::identical_groups(&classes) {
+        let names: Vec<String> = group
+            .iter()
+            .map(|(pkg, type_name)| format!("{pkg}/msg/{type_name}"))
+            .collect();
+        warnings::warn(
+            Warning::IdenticalDefinitions,
+            &format!(
+                "{} are structurally identical, so they can be aliased by `alias` of the configuration file",
+                names.join(", ")
+            ),
+        );
+    }
+
+    for (key, (target_pkg, target_type)) in aliases.iter() {
+        let (pkg, _, type_name) = key;
+        let context = format!("{}: alias of {pkg}/msg/{type_name}", args.config);
+        let target_key = (target_pkg.clone(), "msg".to_string(), target_type.clone());
+        let target = format!("{target_pkg}/msg/{target_type}");
+
+        if !generated(key) {
+            return Err(format!("{context}: the message is not generated").into());
+        }
+        if !generated(&target_key) {
+            return Err(format!("{context}: {target} is not generated").into());
+        }
+        if pkg == target_pkg {
+            return Err(format!("{context}: {target} is not of another package").into());
+        }
+        if aliases.contains_key(&target_key) {
+            return Err(format!("{context}: {target} is an alias itself").into());
+        }
+        if layouts.contains_key(key) {
+            return Err(format!("{context}: an alias cannot have a layout").into());
+        }
+
+        let class = classes.get(&(pkg.clone(), type_name.clone()));
+        if class.is_none() || class != classes.get(&(target_pkg.clone(), target_type.clone())) {
+            return Err(format!("{context}: {target} is not structurally identical").into());
+        }
+    }
+
+    Ok((aliases, messages))
+}
+
+/// Versions of packages read from `{input}/{package}/package.xml`.
+/// Packages in archives have no version, and later inputs take precedence like overlayed workspaces.
+fn package_versions(roots: &[PathBuf]) -> BTreeMap<String, String> {
+    let mut versions = BTreeMap::new();
+    for root in roots.iter().filter(|root| !archive::is_archive(root)) {
+        let Ok(entries) = std::fs::read_dir(root) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(xml) = read_to_string(entry.path().join("package.xml")) else {
+                continue;
+            };
+            if let Some(version) = parse_package_version(&xml) {
+                let pkg = entry.file_name().to_string_lossy().into_owned();
+                versions.insert(pkg, version);
+            }
+        }
+    }
+    versions
+}
+
+/// Return `1.2.3` of `<version>1.2.3</version>` in package.xml.
+fn parse_package_version(xml: &str) -> Option<String> {
+    let (_, rest) = xml.split_once("<version")?;
+    let (_, rest) = rest.split_once('>')?;
+    let (version, _) = rest.split_once("</version>")?;
+    Some(version.trim().to_string())
+}
+
+/// Generate `//!` of `{module}/mod.rs` having the version and the types of the package.
+fn gen_package_doc(
+    module: &str,
+    version: Option<&str>,
+    definitions: &[(String, String)],
+) -> String {
+    let mut doc = match version {
+        Some(version) => format!("//! Interfaces of `{module}` {version}.\n"),
+        None => format!("//! Interfaces of `{module}`.\n"),
+    };
+    for (ext, title) in [
+        ("msg", "Messages"),
+        ("srv", "Services"),
+        ("action", "Actions"),
+    ] {
+        if definitions.iter().any(|(e, _)| e == ext) {
+            doc.push_str(&format!(
+                "//!\n//! # {title}\n//!\n{}",
+                gen_types_doc(definitions, ext, &format!("{ext}::"))
+            ));
+        }
+    }
+    doc
+}
+
+/// Generate a list of intra-doc links to the types of `ext` in `definitions`.
+/// Links of services are followed by their requests and responses, and links of actions by their parts.
+fn gen_types_doc(definitions: &[(String, String)], ext: &str, prefix: &str) -> String {
+    let mut type_names: Vec<&str> = definitions
+        .iter()
+        .filter(|(e, _)| e == ext)
+        .map(|(_, type_name)| type_name.as_str())
+        .collect();
+    type_names.sort_unstable();
+
+    type_names
+        .into_iter()
+        .map(|t| {
+            match ext {
+                "msg" => format!("//! - [`{prefix}{t}`]\n"),
+                "srv" => format!(
+                    "//! - [`{prefix}{t}`], [`{prefix}{t}Request`] and [`{prefix}{t}Response`]\n"
+                ),
+                _ => format!(
+                    "//! - [`{prefix}{t}`], [`{prefix}{t}Goal`], [`{prefix}{t}Result`] and [`{prefix}{t}Feedback`]\n"
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Renames of packages and types defined by the configuration file of `path`.
+fn renames(rename: &config::Rename, path: &Path) -> Result<Renames, Box<dyn Error>> {
+    let mut renames = Renames::default();
+    for (pkg, module) in rename.packages.iter() {
+        validate_name("package", module, path)?;
+        renames.packages.insert(pkg.clone(), module.clone());
+    }
+    for (spec, new_name) in rename.types.iter() {
+        validate_name("type", new_name, path)?;
+        let (pkg, ext, type_name) = parse_type_spec(spec)?;
+        renames.types.insert(
+            (pkg.to_string(), ext.to_string(), type_name.to_string()),
+            new_name.clone(),
+        );
+    }
+    Ok(renames)
+}
+
+/// Create a new interface package.
+fn new_package(args: &NewArgs) -> Result<(), Box<dyn Error>> {
+    let dir = Path::new(&args.dir);
+    validate_name("package", &args.name, &dir.join(&args.name))?;
+
+    let config_created = scaffold::new_package(dir, &args.name)?;
+    println!("created {}", dir.join(&args.name).display());
+    if config_created {
+        println!(
+            "created {}, generate crates by `ros2msg_to_rs --profile default`",
+            dir.join(config::DEFAULT_CONFIG).display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Write HTML documentation of packages.
+fn doc(args: &DocArgs) -> Result<(), Box<dyn Error>> {
+    let sources = collect_inputs(&args.input, args.prefer)?;
+    let selected = |pkg: &str| args.package.is_empty() || args.package.iter().any(|p| p == pkg);
+    let exists =
+        |pkg: &str, type_name: &str| selected(pkg) && find_msg(&sources, pkg, type_name).is_some();
+
+    let out = Path::new(&args.out);
+    let mut outputs = Outputs::default();
+    let mut types = Vec::new();
+    for ((pkg, ext, type_name), source) in sources.iter() {
+        if !selected(pkg) {
+            continue;
+        }
+
+        let html = html::render_type(pkg, ext, type_name, &source.contents, &exists)
+            .map_err(|e| format!("{}: {e}", source.path.display()))?;
+        outputs.add(
+            out.join(pkg).join(ext).join(format!("{type_name}.html")),
+            html,
+        );
+        types.push((pkg.as_str(), ext.as_str(), type_name.as_str()));
+    }
+    outputs.add(out.join("index.html"), html::render_index(&types));
+
+    if !args.force {
+        outputs.check_overwrite()?;
+    }
+    outputs.write(false, |_, _| ())
+}
+
+/// Split `pkg/msg/Type`, `pkg/srv/Type`, `pkg/action/Type` or `pkg/Type`, which is a message,
+/// into the package, "msg", "srv" or "action" and the type.
+fn parse_type_spec(spec: &str) -> Result<(&str, &str, &str), Box<dyn Error>> {
+    match spec.split('/').collect::<Vec<_>>()[..] {
+        [pkg, ext @ ("msg" | "srv" | "action"), type_name] => Ok((pkg, ext, type_name)),
+        [pkg, type_name] => Ok((pkg, "msg", type_name)),
+        _ => Err(format!(
+            "{spec}: the type must be pkg/msg/Type, pkg/srv/Type, pkg/action/Type or pkg/Type"
+        )
+        .into()),
+    }
+}
+
+/// Print the expanded definition of `pkg/(msg|srv)/Type`, resolving types across the input directories.
+fn show(args: &ShowArgs) -> Result<(), Box<dyn Error>> {
+    let sources = collect_inputs(&args.input, args.prefer)?;
+
+    let (pkg, ext, type_name) = parse_type_spec(&args.type_name)?;
+
+    let key = (pkg.to_string(), ext.to_string(), type_name.to_string());
+    let source = sources
+        .get(&key)
+        .ok_or_else(|| format!("{pkg}/{ext}/{type_name} is not found"))?;
+
+    let find = |pkg: &str, type_name: &str| find_msg(&sources, pkg, type_name);
+    print!("{}", show::expand(pkg, &source.contents, &find)?);
+
+    Ok(())
+}
+
+/// Browse types of the inputs in a terminal UI.
+#[cfg(feature = "tui")]
+fn browse(args: &BrowseArgs) -> Result<(), Box<dyn Error>> {
+    let sources = collect_inputs(&args.input, args.prefer)?;
+    let keys: Vec<_> = sources.keys().collect();
+    let types: Vec<_> = keys
+        .iter()
+        .map(|(pkg, ext, type_name)| format!("{pkg}/{ext}/{type_name}"))
+        .collect();
+
+    // warnings printed to stderr would break the screen
+    let context = Context {
+        levels: warnings::levels(Warning::value_variants(), &[], &[]),
+        ..Default::default()
+    };
+
+    let find = |pkg: &str, type_name: &str| find_msg(&sources, pkg, type_name);
+    let render = |index: usize| {
+        let key = keys[index];
+        let (pkg, ext, type_name) = key;
+        let contents = &sources[key].contents;
+        let definition =
+            show::expand(pkg, contents, &find).unwrap_or_else(|e| format!("error: {e}"));
+        let generated = generate_type(pkg, ext, type_name, contents, &args.safe_drive)
+            .unwrap_or_else(|e| format!("error: {e}"));
+        browse::Page {
+            definition,
+            generated,
+        }
+    };
+
+    context.scope(|| browse::run(&types, &render))
+}
+
+/// Generate the module of `pkg/ext/type_name` alone with the default options.
+#[cfg(feature = "tui")]
+fn generate_type(
+    pkg: &str,
+    ext: &str,
+    type_name: &str,
+    contents: &str,
+    safe_drive: &str,
+) -> Result<String, Box<dyn Error>> {
+    let mut generator = Generator::new(pkg.to_string(), safe_drive.to_string(), false);
+    let lines = if ext == "msg" {
+        let (_, exprs) = parser::parse_msg(contents)
+            .finish()
+            .map_err(|e| convert_error(contents, e))?;
+        generator.gen_msg(pkg, type_name, &exprs)?
+    } else {
+        let (_, (req, resp)) = parser::parse_srv(contents)
+            .finish()
+            .map_err(|e| convert_error(contents, e))?;
+        generator.gen_srv(pkg, type_name, &req, &resp)?
+    };
+
+    let mut result = String::new();
+    for line in lines {
+        result.push_str(&line);
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+/// Print sizes of messages and requests and responses of services.
+fn size(args: &InputArgs) -> Result<(), Box<dyn Error>> {
+    let sources = collect_inputs(&args.input, args.prefer)?;
+    let find = |pkg: &str, type_name: &str| find_msg(&sources, pkg, type_name);
+    let mut failed = 0;
+
+    for ((pkg, ext, type_name), source) in sources.iter() {
+        if !args.package.is_empty() && !args.package.contains(pkg) {
+            continue;
+        }
+
+        let types = if ext == "msg" {
+            let (_, exprs) = parser::parse_msg(&source.contents).finish().map_err(|e| {
+                format!(
+                    "{}: {}",
+                    source.path.display(),
+                    convert_error(source.contents.as_str(), e)
+                )
+            })?;
+            vec![(format!("{pkg}/msg/{type_name}"), exprs)]
+        } else {
+            let (_, (req, resp)) = parser::parse_srv(&source.contents).finish().map_err(|e| {
+                format!(
+                    "{}: {}",
+                    source.path.display(),
+                    convert_error(source.contents.as_str(), e)
+                )
+            })?;
+            vec![
+                (format!("{pkg}/srv/{type_name}_Request"), req),
+                (format!("{pkg}/srv/{type_name}_Response"), resp),
+            ]
+        };
+
+        for (name, exprs) in types {
+            // messages referring to unknown types are reported, and the others are continued
+            let sizes = match size::sizes(pkg, &exprs, &find) {
+                Ok(sizes) => sizes,
+                Err(e) => {
+                    eprintln!("{name}: {e}");
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            let max_cdr_size = match sizes.max_cdr_size {
+                Some(n) => format!("{n} bytes"),
+                None => format!("unbounded ({})", sizes.unbounded.join(", ")),
+            };
+            println!(
+                "{name}: C size {} bytes (align {}), max CDR size {max_cdr_size}",
+                sizes.c_size, sizes.c_align
+            );
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("sizes of {failed} type(s) are not computed").into());
+    }
+
+    Ok(())
+}
+
+/// Print statistics of each package.
+fn stats(args: &InputArgs) -> Result<(), Box<dyn Error>> {
+    let sources = collect_inputs(&args.input, args.prefer)?;
+    let find = |pkg: &str, type_name: &str| find_msg(&sources, pkg, type_name);
+
+    let mut packages: BTreeMap<&str, stats::Stats> = BTreeMap::new();
+    for ((pkg, ext, _), source) in sources.iter() {
+        if !args.package.is_empty() && !args.package.contains(pkg) {
+            continue;
+        }
+
+        let stats = packages.entry(pkg).or_default();
+        stats::add(stats, pkg, ext, &source.contents, &find)
+            .map_err(|e| format!("{}: {e}", source.path.display()))?;
+    }
+
+    for (pkg, stats) in packages.iter() {
+        let external: Vec<&str> = stats.external.iter().map(|s| s.as_str()).collect();
+        println!(
+            "{pkg}: {} message(s), {} service(s), max nesting depth {}, {} unbounded field(s), external: [{}]",
+            stats.messages,
+            stats.services,
+            stats.max_depth,
+            stats.unbounded,
+            external.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Collect .msg and .srv files from `inputs` for subcommands.
+/// .action and .idl files are only generated, so they are excluded.
+fn collect_inputs(
+    inputs: &[String],
+    prefer: Prefer,
+) -> Result<BTreeMap<SourceKey, Source>, Box<dyn Error>> {
+    let mut roots = Vec::new();
+    for input in inputs.iter() {
+        roots.push(Path::new(input).canonicalize()?);
+    }
+    let mut sources = collect_sources(&roots, prefer)?;
+    sources.retain(|(_, ext, _), source| ext != "action" && !source.is_idl());
+    Ok(sources)
+}
+
+/// Contents of `pkg/msg/type_name` in `sources`.
+fn find_msg<'a>(
+    sources: &'a BTreeMap<SourceKey, Source>,
+    pkg: &str,
+    type_name: &str,
+) -> Option<&'a str> {
+    let key = (pkg.to_string(), "msg".to_string(), type_name.to_string());
+    sources.get(&key).map(|source| source.contents.as_str())
+}
+
+/// Warn names which do not follow the naming convention of ROS 2 with their line numbers.
+/// Names are checked as written in the file, even if they are renamed by `@rust_name`.
+fn check_naming(source: &Source) {
+    let path = source.path.display();
+    if let Some(msg) = warnings::check_naming("type", &source.type_name) {
+        warnings::warn(Warning::NamingConvention, &format!("{msg}: {path}"));
+    }
+
+    // syntax errors are reported by the parser
+    let Ok(cst) = cst::parse_cst(&source.contents) else {
+        return;
+    };
+
+    for (n, line) in cst.lines.iter().enumerate() {
+        let LineKind::Definition(definition) = &line.kind else {
+            continue;
+        };
+
+        let kind = if let Expr::Variable {
+            value: Some(ValueType::Const(_)),
+            ..
+        } = &definition.expr
+        {
+            "constant"
+        } else {
+            "field"
+        };
+
+        if let Some(msg) = warnings::check_naming(kind, definition.name) {
+            warnings::warn(
+                Warning::NamingConvention,
+                &format!("{msg}: {path}:{}", n + 1),
+            );
+        }
+    }
+}
+
+/// Messages referred by the fields of `source` with their line numbers.
+fn referred_messages(source: &Source) -> Vec<(usize, SourceKey)> {
+    // syntax errors are reported by the parser
+    let Ok(cst) = cst::parse_cst(&source.contents) else {
+        return Vec::new();
+    };
+
+    let mut result = Vec::new();
+    for (n, line) in cst.lines.iter().enumerate() {
+        let LineKind::Definition(definition) = &line.kind else {
+            continue;
+        };
+        let Expr::Variable { type_name, .. } = &definition.expr else {
+            continue;
+        };
+
+        let (scope, nested) = match type_name {
+            TypeName::Type { type_name, .. } => (source.module_name.as_str(), type_name),
+            TypeName::ScopedType {
+                scope, type_name, ..
+            } => (scope.as_str(), type_name),
+            _ => continue,
+        };
+
+        let key = (scope.to_string(), "msg".to_string(), nested.clone());
+        result.push((n + 1, key));
+    }
+    result
+}
+
+/// Warn references to types skipped by the configuration file with their line numbers.
+fn check_skipped_references(source: &Source, skipped: &BTreeSet<SourceKey>) {
+    if skipped.is_empty() {
+        return;
+    }
+
+    for (line, key) in referred_messages(source) {
+        if skipped.contains(&key) {
+            let (scope, _, nested) = key;
+            warnings::warn(
+                Warning::SkippedTypes,
+                &format!(
+                    "{scope}/msg/{nested} is skipped by the configuration file: {}:{line}",
+                    source.path.display(),
+                ),
+            );
+        }
+    }
+}
+
+/// Refuse fields of packed messages, whose layouts differ from the C structs the C functions of `source` assume.
+fn check_packed_references(
+    source: &Source,
+    layouts: &BTreeMap<SourceKey, String>,
+) -> Result<(), String> {
+    if !layouts.values().any(|repr| repr.starts_with("packed")) {
+        return Ok(());
+    }
+
+    for (line, key) in referred_messages(source) {
+        if layouts
+            .get(&key)
+            .is_some_and(|repr| repr.starts_with("packed"))
+        {
+            let (scope, _, nested) = key;
+            return Err(format!(
+                "{}:{line}: {scope}/msg/{nested} is packed by the configuration file, so it cannot be a field",
+                source.path.display(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Refuse string constants longer than their bounds, such as `string<=1 T = "ab"`,
+/// which the generated code also asserts at compile time.
+fn check_string_constants(source: &Source) -> Result<(), String> {
+    // syntax errors are reported by the parser
+    let Ok(cst) = cst::parse_cst(&source.contents) else {
+        return Ok(());
+    };
+
+    for (n, line) in cst.lines.iter().enumerate() {
+        let LineKind::Definition(definition) = &line.kind else {
+            continue;
+        };
+        if let Expr::Variable {
+            type_name:
+                TypeName::LimitedString {
+                    size,
+                    array_info: ArrayInfo::NotArray,
+                },
+            var_name,
+            value: Some(ValueType::Const(Value::String(value))),
+            ..
+        } = &definition.expr
+        {
+            if value.len() > *size {
+                return Err(format!(
+                    "{}:{}: {var_name} has {} bytes, which exceed string<={size}",
+                    source.path.display(),
+                    n + 1,
+                    value.len()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse `source` into its parts, which are the message, the request and the response,
+/// or the goal, the result and the feedback.
+/// The error is the message of the diagnostic.
+fn parse_definition(source: &Source) -> Result<Vec<Vec<Expr>>, String> {
+    let (contents, type_name) = (source.contents.as_str(), source.type_name.as_str());
+    if source.is_idl() {
+        return match source.ext.as_str() {
+            "msg" => idl::parse_msg(contents, type_name).map(|msg| vec![msg]),
+            "srv" => idl::parse_srv(contents, type_name).map(|(req, resp)| vec![req, resp]),
+            _ => idl::parse_action(contents, type_name)
+                .map(|(goal, result, feedback)| vec![goal, result, feedback]),
+        };
+    }
+
+    let parsed = match source.ext.as_str() {
+        "msg" => parser::parse_msg(contents)
+            .finish()
+            .map(|(_, msg)| vec![msg]),
+        "srv" => parser::parse_srv(contents)
+            .finish()
+            .map(|(_, (req, resp))| vec![req, resp]),
+        _ => parser::parse_action(contents)
+            .finish()
+            .map(|(_, (goal, result, feedback))| vec![goal, result, feedback]),
+    };
+    parsed.map_err(|e| convert_error(contents, e))
+}
+
+fn generate_definition<'a>(
+    generator: &mut Generator,
+    source: &Source,
+    module_name: &'a str,
+    type_name: &'a str,
+    timings: &mut Timings,
+) -> Result<VecDeque<Cow<'a, str>>, Box<dyn Error>> {
+    let path = &source.path;
+    let parsed = timings.time(Some(module_name), "parse", || parse_definition(source));
+    let parts = match parsed {
+        Ok(parts) => parts,
+        Err(e) => {
+            diagnostics::report(Message::Diagnostic {
+                level: Severity::Error,
+                code: None,
+                message: &e,
+                file: Some(path),
+            });
+            let msg = format!("failed to parse: {}", path.display());
+            return Err(msg.into());
+        }
+    };
+
+    timings
+        .time(Some(module_name), "generate", || match parts.as_slice() {
+            [msg] => generator.gen_msg(module_name, type_name, msg),
+            [req, resp] => generator.gen_srv(module_name, type_name, req, resp),
+            [goal, result, feedback] => {
+                generator.gen_action(module_name, type_name, goal, result, feedback)
+            }
+            _ => unreachable!(),
+        })
+        .map_err(|e| format!("failed to generate {}: {e}", path.display()).into())
+}
+
+fn add_modules(map: &mut BTreeMap<OsString, Vec<String>>, key: &OsStr, value: String) {
+    if let Some(v) = map.get_mut(key) {
+        v.push(value);
+    } else {
+        let v = vec![value];
+        map.insert(key.to_os_string(), v);
+    }
+}
+
+/// Generate `msg.rs` or `srv.rs`. Modules are sorted by their names,
+/// because the order of types does not always match the order of their snake case names.
+/// `doc` is the `//!` documentation of the module.
+fn generate_msg_srv_rs(modules: &[String], doc: &str) -> String {
+    let modules: BTreeSet<&String> = modules.iter().collect();
+    let mut result = String::from("// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).\n");
+    result.push_str(doc);
+
+    for module in modules.iter() {
+        result.push_str(&format!("mod {module};\n"));
+    }
+
+    result.push('\n');
+
+    for module in modules.iter() {
+        result.push_str(&format!("pub use {module}::*;\n"));
+    }
+
+    result
+}
+
+/// Keywords of Rust including reserved ones.
+const KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "async", "await", "dyn", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "typeof", "unsized", "virtual", "yield", "try", "gen",
+];
+
+/// Keywords which cannot be raw identifiers.
+const NON_RAW_KEYWORDS: &[&str] = &["crate", "self", "Self", "super", "_"];
+
+pub fn mangle(var_name: &str) -> Cow<'_, str> {
+    match var_name {
+        "i8" | "u8" | "i16" | "u16" | "i32" | "u32" | "i64" | "u64" | "bool" | "char" => {
+            format!("{var_name}_").into()
+        }
+        _ if KEYWORDS.contains(&var_name) => format!("{var_name}_").into(),
+        _ => var_name.into(),
+    }
+}
+
+/// Module name of a package. Keywords are raw identifiers, e.g. `r#impl`,
+/// so that the directory name is kept.
+pub fn mangle_module(module_name: &str) -> Cow<'_, str> {
+    if KEYWORDS.contains(&module_name) {
+        format!("r#{module_name}").into()
+    } else {
+        module_name.into()
+    }
+}
+
+/// Check that `topic` is a name of a topic of ROS 2, which may be relative, private or substituted like `{node}`.
+fn validate_topic(topic: &str) -> Result<(), String> {
+    let name = topic.strip_prefix('~').unwrap_or(topic);
+    let is_token = |token: &str| {
+        !token.is_empty()
+            && !token.starts_with(|c: char| c.is_ascii_digit())
+            && token
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '{' | '}'))
+    };
+
+    // the first token is empty if the name is absolute, and `~` must be followed by `/`
+    let is_topic = !topic.is_empty()
+        && (name == topic || name.is_empty() || name.starts_with('/'))
+        && name
+            .split('/')
+            .enumerate()
+            .all(|(i, token)| (i == 0 && token.is_empty()) || is_token(token));
+
+    if is_topic {
+        Ok(())
+    } else {
+        Err(format!("`{topic}` is not a valid topic name"))
+    }
+}
+
+/// Check that a package or type name can be a Rust identifier after mangling.
+fn validate_name(kind: &str, name: &str, path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut chars = name.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if !is_identifier {
+        Err(format!(
+            "{kind} name `{name}` is not a valid identifier: {}",
+            path.display()
+        )
+        .into())
+    } else if NON_RAW_KEYWORDS.contains(&name) {
+        Err(format!(
+            "{kind} name `{name}` is a reserved word of Rust: {}",
+            path.display()
+        )
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generator::{Backend, FallibleApi, Generator, PartialEqImpl, Renames};
+    use crate::seq::SeqImpl;
+
+    use super::parser;
+    use nom::Finish;
+
+    /// Compile `code`, which is generated code with mocks of the types it refers to, as tests by rustc and run them.
+    /// Panics with the output of rustc or the tests if they fail.
+    pub(crate) fn run_generated_tests(name: &str, code: &str) {
+        use std::process::Command;
+
+        let tmp = std::env::temp_dir().join(format!("ros2msg_to_rs_{name}_{}", std::process::id()));
+        std::fs::create_dir_all(&tmp).unwrap();
+        let (src, bin) = (tmp.join("lib.rs"), tmp.join("tests"));
+        std::fs::write(&src, code).unwrap();
+
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let build = Command::new(rustc)
+            .args(["--edition", "2021", "--test", "--crate-name", name, "-o"])
+            .args([&bin, &src])
+            .output()
+            .unwrap();
+        let run = build
+            .status
+            .success()
+            .then(|| Command::new(&bin).output().unwrap());
+        std::fs::remove_dir_all(&tmp).unwrap();
+
+        assert!(
+            build.status.success(),
+            "{}",
+            String::from_utf8_lossy(&build.stderr)
+        );
+        let run = run.unwrap();
+        assert!(
+            run.status.success(),
+            "{}",
+            String::from_utf8_lossy(&run.stdout)
+        );
+    }
+
+    #[test]
+    fn test_prefer() {
+        use super::{collect_sources, Prefer};
+        use std::fs::{create_dir_all, remove_dir_all, write};
+
+        // two roots define p/msg/T
+        let tmp = std::env::temp_dir().join(format!("ros2msg_to_rs_prefer_{}", std::process::id()));
+        let roots = ["a", "b"].map(|root| tmp.join(root));
+        for root in roots.iter() {
+            create_dir_all(root.join("p/msg")).unwrap();
+            write(root.join("p/msg/T.msg"), "int32 a\n").unwrap();
+        }
+        write(roots[1].join("p/msg/U.msg"), "int32 u\n").unwrap();
+
+        let key = ("p".to_string(), "msg".to_string(), "T".to_string());
+        let first = collect_sources(&roots, Prefer::First).unwrap();
+        let last = collect_sources(&roots, Prefer::Last).unwrap();
+        remove_dir_all(&tmp).unwrap();
+
+        assert_eq!(first[&key].path, roots[0].join("p/msg/T.msg"));
+        assert_eq!(last[&key].path, roots[1].join("p/msg/T.msg"));
+
+        // definitions of only one root are used regardless of the priority
+        assert_eq!(first.len(), 2);
+        assert_eq!(last.len(), 2);
+    }
+
+    #[test]
+    fn test_check_overwrite() {
+        use super::{generate_workspace, Args};
+        use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+
+        let tmp =
+            std::env::temp_dir().join(format!("ros2msg_to_rs_overwrite_{}", std::process::id()));
+        let (input, out) = (tmp.join("in"), tmp.join("out"));
+        create_dir_all(input.join("p/msg")).unwrap();
+        write(input.join("p/msg/T.msg"), "int32 a\n").unwrap();
+
+        let args = |force: bool| {
+            let mut args = vec![
+                "-i",
+                input.to_str().unwrap(),
+                "-o",
+                out.to_str().unwrap(),
+                "--disable-common-interfaces",
+                "--emit-crates",
+            ];
+            if force {
+                args.push("--force");
+            }
+            Args::new(args).unwrap()
+        };
+
+        // earlier output including Cargo.toml and build.rs is regenerated
+        let result =
+            generate_workspace(&args(false)).and_then(|_| generate_workspace(&args(false)));
+        let cargo_toml = read_to_string(out.join("p/Cargo.toml"));
+        let build_rs = read_to_string(out.join("p/build.rs"));
+
+        // a file without the provenance header is refused, and kept as is
+        write(out.join("p/build.rs"), "fn main() {}\n").unwrap();
+        let refused = generate_workspace(&args(false)).map_err(|e| e.to_string());
+        let kept = read_to_string(out.join("p/build.rs"));
+
+        // --force overwrites it
+        let forced = generate_workspace(&args(true)).map_err(|e| e.to_string());
+        let overwritten = read_to_string(out.join("p/build.rs"));
+        remove_dir_all(&tmp).unwrap();
+
+        result.unwrap();
+        assert!(cargo_toml.unwrap().contains("[package]\nname = \"p\"\n"));
+        assert!(build_rs
+            .unwrap()
+            .contains("cargo:rustc-link-lib=p__rosidl_generator_c"));
+
+        let e = refused.unwrap_err();
+        assert!(e.starts_with("refusing to overwrite files not generated by ros2msg_to_rs"));
+        assert!(e.ends_with(&out.join("p/build.rs").display().to_string()));
+        assert_eq!(kept.unwrap(), "fn main() {}\n");
+
+        forced.unwrap();
+        assert!(overwritten
+            .unwrap()
+            .contains("cargo:rustc-link-lib=p__rosidl_generator_c"));
+    }
+
+    #[test]
+    fn test_args() {
+        let args = super::Args::new(["-i", "src", "--no-std"]).unwrap();
+        assert_eq!(args.input, ["src"]);
+        assert_eq!(args.safe_drive.as_deref(), Some("safe_drive"));
+        assert!(args.no_std);
+
+        assert!(super::Args::new(["fmt", "src"]).is_err());
+        assert!(super::Args::new(["--unknown-option"]).is_err());
+    }
+
+    #[test]
+    fn test_versioned_conversions() {
+        use super::{Source, Tree};
+        use std::collections::{BTreeMap, BTreeSet};
+
+        let tree = |version: &'static str, definitions: &[(&str, &str)]| {
+            let mut renames = Renames::default();
+            renames
+                .packages
+                .insert("q".to_string(), format!("q_{version}"));
+            let inputs = definitions
+                .iter()
+                .map(|(type_name, contents)| {
+                    let key = ("q".to_string(), "msg".to_string(), type_name.to_string());
+                    let source = Source {
+                        module_name: "q".to_string(),
+                        ext: "msg".to_string(),
+                        type_name: type_name.to_string(),
+                        path: format!("{type_name}.msg").into(),
+                        file: format!("{type_name}.msg").into(),
+                        contents: contents.to_string(),
+                    };
+                    (key, source)
+                })
+                .collect();
+            Tree {
+                version: Some(version),
+                inputs,
+                renames,
+                package_versions: BTreeMap::new(),
+                changed: BTreeSet::new(),
+            }
+        };
+        let v1 = tree(
+            "v1",
+            &[
+                ("Point", "float64 x\n"),
+                ("Ref", "q/Point p\n"),
+                ("Pose", "Point p\nint32 id\n"),
+                ("Stamp", "float64 t\n"),
+            ],
+        );
+        let v2 = tree(
+            "v2",
+            &[
+                ("Point", "float64 x\n"),
+                ("Ref", "q/Point p\n"),
+                ("Pose", "Point p\nint64 id\nbool valid\n"),
+                ("Stamp", "string t\n"),
+                ("New", "int32 x\n"),
+            ],
+        );
+
+        let args = super::Args::new(["-i", "src"]).unwrap();
+        let conversions = super::conversions(&v1, &v2, &Default::default(), &args);
+        let code = &conversions["q_v2"];
+        assert!(
+            code.contains("impl From<super::super::q_v1::msg::Point> for super::msg::Point {\n")
+        );
+        assert!(code.contains("impl From<super::super::q_v1::msg::Ref> for super::msg::Ref {\n"));
+        assert!(!code.contains("Pose"));
+        assert!(!code.contains("Stamp"));
+        assert!(!code.contains("New"));
+    }
+
+    #[test]
+    fn test_changed_definitions() {
+        use super::{Source, Tree};
+        use std::collections::{BTreeMap, BTreeSet};
+
+        let tree = |version: Option<&'static str>, definitions: &[(&str, &str, &str)]| {
+            let mut renames = Renames::default();
+            if let Some(version) = version {
+                renames
+                    .packages
+                    .insert("q".to_string(), format!("q_{version}"));
+            }
+            let inputs = definitions
+                .iter()
+                .map(|(ext, type_name, contents)| {
+                    let key = ("q".to_string(), ext.to_string(), type_name.to_string());
+                    let source = Source {
+                        module_name: "q".to_string(),
+                        ext: ext.to_string(),
+                        type_name: type_name.to_string(),
+                        path: format!("{type_name}.{ext}").into(),
+                        file: format!("{type_name}.{ext}").into(),
+                        contents: contents.to_string(),
+                    };
+                    (key, source)
+                })
+                .collect();
+            Tree {
+                version,
+                inputs,
+                renames,
+                package_versions: BTreeMap::new(),
+                changed: BTreeSet::new(),
+            }
+        };
+        let linked = tree(
+            None,
+            &[
+                ("msg", "Point", "float64 x\n"),
+                ("msg", "Pose", "Point p\nint32 id\n"),
+                ("msg", "Ref", "q/Point p\n"),
+                ("msg", "Stamp", "float64 t\n"),
+                ("srv", "Get", "int32 a\n---\nStamp b\n"),
+                ("srv", "Set", "int32 a\n---\nbool ok\n"),
+            ],
+        );
+        let v1 = tree(
+            Some("v1"),
+            &[
+                ("msg", "Point", "float32 x\n"),
+                ("msg", "Pose", "Point p\nint32 id\n"),
+                ("msg", "Ref", "q/Point p\n"),
+                ("msg", "Stamp", "float64 t # comment\n"),
+                ("msg", "New", "int32 x\n"),
+                ("srv", "Get", "int32 a\n---\nStamp b\n"),
+                ("srv", "Set", "int32 a\n---\nint32 ok\n"),
+            ],
+        );
+
+        let changed = super::changed_definitions(&linked, &v1);
+        let changed: Vec<(&str, &str)> = changed
+            .iter()
+            .map(|(_, ext, type_name)| (ext.as_str(), type_name.as_str()))
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                ("msg", "New"),
+                ("msg", "Point"),
+                ("msg", "Pose"),
+                ("msg", "Ref"),
+                ("srv", "Set")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_known_types() {
+        use super::{Source, Tree};
+        use std::collections::{BTreeMap, BTreeSet};
+
+        let inputs = [
+            ("p", "Point", "float64 x\n"),
+            ("p", "Stamped", "Point p\nbuiltin_interfaces/Time stamp\n"),
+            ("p", "Labeled", "std_msgs/String label\n"),
+            ("p", "Nested", "Labeled[] labels\n"),
+            ("q", "Path", "p/Stamped[] poses\n"),
+            ("q", "Missing", "Unknown u\n"),
+        ]
+        .iter()
+        .map(|(pkg, type_name, contents)| {
+            let key = (pkg.to_string(), "msg".to_string(), type_name.to_string());
+            let source = Source {
+                module_name: pkg.to_string(),
+                ext: "msg".to_string(),
+                type_name: type_name.to_string(),
+                path: format!("{type_name}.msg").into(),
+                file: format!("{type_name}.msg").into(),
+                contents: contents.to_string(),
+            };
+            (key, source)
+        })
+        .collect();
+        let tree = Tree {
+            version: None,
+            inputs,
+            renames: Renames::default(),
+            package_versions: BTreeMap::new(),
+            changed: BTreeSet::new(),
+        };
+
+        // std_msgs/String is not generated either
+        let args = super::Args::new(["-i", "src", "--disable-common-interfaces"]).unwrap();
+        let known = super::known_types(&tree, &BTreeSet::new(), &args, &BTreeMap::new());
+        let key = |pkg: &str, type_name: &str| (pkg.to_string(), type_name.to_string());
+        assert_eq!(
+            known,
+            [key("p", "Point"), key("p", "Stamped"), key("q", "Path")].into()
+        );
+    }
+
+    #[test]
+    fn test_backup() {
+        use super::{generate_workspace, Args};
+        use std::fs::{create_dir_all, read, remove_dir_all, write};
+
+        let tmp = std::env::temp_dir().join(format!("ros2msg_to_rs_backup_{}", std::process::id()));
+        let (input, out) = (tmp.join("in"), tmp.join("out"));
+        create_dir_all(input.join("my_pkg/msg")).unwrap();
+        write(input.join("my_pkg/msg/Example.msg"), "int32 x\n").unwrap();
+        let args = Args::new([
+            "-i",
+            input.to_str().unwrap(),
+            "-o",
+            out.to_str().unwrap(),
+            "--backup",
+        ])
+        .unwrap();
+        let (file, bak) = (
+            out.join("my_pkg/msg/example.rs"),
+            out.join("my_pkg/msg/example.rs.bak"),
+        );
+
+        // unchanged files are not saved
+        let first = generate_workspace(&args);
+        let second = generate_workspace(&args);
+        let unchanged_bak = bak.exists();
+
+        // a modified file, which is not UTF-8, is saved as it is
+        let mut modified = read(&file).unwrap();
+        modified.extend_from_slice(b"// \xff\n");
+        write(&file, &modified).unwrap();
+        let third = generate_workspace(&args);
+        let saved = read(&bak);
+        remove_dir_all(&tmp).unwrap();
+
+        first.unwrap();
+        second.unwrap();
+        third.unwrap();
+        assert!(!unchanged_bak);
+        assert_eq!(saved.unwrap(), modified);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_post_cmd() {
+        use super::{generate_workspace, Args};
+        use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+
+        let tmp =
+            std::env::temp_dir().join(format!("ros2msg_to_rs_post_cmd_{}", std::process::id()));
+        let (input, out) = (tmp.join("in"), tmp.join("out"));
+        create_dir_all(input.join("my_pkg/msg")).unwrap();
+        write(input.join("my_pkg/msg/Example.msg"), "int32 x\n").unwrap();
+        let args = |cmd: &str| {
+            Args::new([
+                "-i",
+                input.to_str().unwrap(),
+                "-o",
+                out.to_str().unwrap(),
+                "--post-cmd",
+                cmd,
+            ])
+            .unwrap()
+        };
+
+        let succeeded = generate_workspace(&args(
+            r#"echo "$ROS2MSG_TO_RS_OUT" > "$ROS2MSG_TO_RS_OUT/out.txt""#,
+        ));
+        let echoed = read_to_string(out.join("out.txt"));
+        let failed = generate_workspace(&args("exit 3"));
+        remove_dir_all(&tmp).unwrap();
+
+        succeeded.unwrap();
+        assert_eq!(echoed.unwrap().trim_end(), out.to_str().unwrap());
+        let e = failed.unwrap_err().to_string();
+        assert!(e.contains("`exit 3` failed"), "{e}");
+    }
+
+    #[test]
+    fn test_cargo_target_dir() {
+        use super::{generate_workspace, Args};
+        use std::fs::{create_dir_all, remove_dir_all, write};
+
+        let tmp = std::env::temp_dir().join(format!("ros2msg_to_rs_target_{}", std::process::id()));
+        let (input, target) = (tmp.join("in"), tmp.join("target"));
+        create_dir_all(input.join("my_pkg/msg")).unwrap();
+        create_dir_all(&target).unwrap();
+        write(input.join("my_pkg/msg/Example.msg"), "int32 x\n").unwrap();
+        write(
+            target.join("CACHEDIR.TAG"),
+            "Signature: 8a477f597d28d172789f06886806bc55\n# This file is a cache directory tag created by cargo.\n",
+        )
+        .unwrap();
+        let out = target.join("debug/generated");
+        let args = |force: bool| {
+            let mut options = vec!["-i", input.to_str().unwrap(), "-o", out.to_str().unwrap()];
+            if force {
+                options.push("--force");
+            }
+            Args::new(options).unwrap()
+        };
+
+        let refused = generate_workspace(&args(false));
+        let refused_written = out.exists();
+        let forced = generate_workspace(&args(true));
+        let forced_written = out.join("my_pkg/msg/example.rs").exists();
+        remove_dir_all(&tmp).unwrap();
+
+        let e = refused.unwrap_err().to_string();
+        assert!(
+            e.contains(&format!(
+                "refusing to write into cargo's build directory {}",
+                target.display()
+            )),
+            "{e}"
+        );
+        assert!(!refused_written);
+        forced.unwrap();
+        assert!(forced_written);
+    }
+
+    #[test]
+    fn test_mangle_names() {
+        use super::{generate_workspace, Args};
+        use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+
+        let tmp = std::env::temp_dir().join(format!("ros2msg_to_rs_mangle_{}", std::process::id()));
+        let generate = |name: &str, definitions: &[(&str, &str)]| {
+            let (input, out) = (tmp.join(name).join("in"), tmp.join(name).join("out"));
+            for (path, contents) in definitions {
+                let path = input.join(path);
+                create_dir_all(path.parent().unwrap()).unwrap();
+                write(path, contents).unwrap();
+            }
+            let args = Args::new(["-i", input.to_str().unwrap(), "-o", out.to_str().unwrap()]);
+            generate_workspace(&args.unwrap()).map(|_| out)
+        };
+
+        // keywords of packages and types are mangled
+        let out = generate(
+            "keywords",
+            &[
+                ("impl/msg/Type.msg", "int32 x\n"),
+                ("other/msg/Use.msg", "impl/Type t\n"),
+            ],
+        )
+        .unwrap();
+        let mod_rs = read_to_string(out.join("mod.rs"));
+        let msg_rs = read_to_string(out.join("impl/msg.rs"));
+        let use_rs = read_to_string(out.join("other/msg/use_.rs"));
+
+        let invalid = generate("invalid", &[("my-pkg/msg/A.msg", "int32 x\n")]);
+        let reserved = generate("reserved", &[("self/msg/A.msg", "int32 x\n")]);
+        remove_dir_all(&tmp).unwrap();
+
+        assert!(mod_rs.unwrap().contains("pub mod r#impl;\n"));
+        assert!(msg_rs.unwrap().contains("mod type_;\n"));
+        assert!(use_rs.unwrap().contains("pub t: r#impl::msg::Type,\n"));
+        let e = invalid.unwrap_err().to_string();
+        assert!(
+            e.contains("package name `my-pkg` is not a valid identifier"),
+            "{e}"
+        );
+        let e = reserved.unwrap_err().to_string();
+        assert!(
+            e.contains("package name `self` is a reserved word of Rust"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn test_no_std() {
+        use super::{generate_workspace, Args};
+        use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+
+        let tmp = std::env::temp_dir().join(format!("ros2msg_to_rs_no_std_{}", std::process::id()));
+        let (input, out) = (tmp.join("in"), tmp.join("out"));
+        create_dir_all(input.join("my_pkg/msg")).unwrap();
+        create_dir_all(input.join("my_pkg/srv")).unwrap();
+        write(
+            input.join("my_pkg/msg/Example.msg"),
+            "int32 x\nstring s\nfloat64[] v\nstring<=4[<=2] names\nuint8 K = 3\n",
+        )
+        .unwrap();
+        write(
+            input.join("my_pkg/srv/Get.srv"),
+            "int32 a\n---\nExample b\n",
+        )
+        .unwrap();
+        let args = Args::new([
+            "-i",
+            input.to_str().unwrap(),
+            "-o",
+            out.to_str().unwrap(),
+            "--no-std",
+            "--cdr",
+            "--diff",
+            "--owned",
+            "--random",
+            "--serde",
+            "--emit-crates",
+            "--disable-common-interfaces",
+        ])
+        .unwrap();
+        let result = generate_workspace(&args);
+
+        // build.rs runs on the host, so it may use std
+        let mut files = Vec::new();
+        for entry in walkdir::WalkDir::new(&out) {
+            let path = entry.unwrap().into_path();
+            if path.extension().is_some_and(|ext| ext == "rs") && !path.ends_with("build.rs") {
+                files.push((path.clone(), read_to_string(&path).unwrap()));
+            }
+        }
+        remove_dir_all(&tmp).unwrap();
+
+        result.unwrap();
+        assert!(files.len() > 5);
+        for (path, code) in files.iter() {
+            for (i, _) in code.match_indices("std::") {
+                let prev = code[..i].chars().next_back();
+                assert!(
+                    prev.is_some_and(|c| c.is_alphanumeric() || c == '_' || c == ':'),
+                    "std is used in {}",
+                    path.display()
+                );
+            }
+        }
+        let mod_rs = files
+            .iter()
+            .find(|(path, _)| path.ends_with("my_pkg/mod.rs"));
+        assert!(mod_rs.unwrap().1.contains("#![no_std]\n"));
+    }
+
+    #[test]
+    fn test_skip() {
+        use super::{generate_workspace, Args};
+        use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+
+        let tmp = std::env::temp_dir().join(format!("ros2msg_to_rs_skip_{}", std::process::id()));
+        let (input, out, config) = (tmp.join("in"), tmp.join("out"), tmp.join("config.json"));
+        create_dir_all(input.join("my_pkg/msg")).unwrap();
+        write(input.join("my_pkg/msg/Provided.msg"), "int32 x\n").unwrap();
+        write(input.join("my_pkg/msg/Other.msg"), "int32 y\n").unwrap();
+        write(&config, r#"{"skip": ["my_pkg/msg/Provided"]}"#).unwrap();
+        let args = |deny: bool| {
+            let mut options = vec![
+                "-i",
+                input.to_str().unwrap(),
+                "-o",
+                out.to_str().unwrap(),
+                "--config",
+                config.to_str().unwrap(),
+            ];
+            if deny {
+                options.extend(["--deny", "skipped-types"]);
+            }
+            Args::new(options).unwrap()
+        };
+
+        let skipped = generate_workspace(&args(true));
+        let msg_rs = read_to_string(out.join("my_pkg/msg.rs"));
+        let provided = out.join("my_pkg/msg/provided.rs").exists();
+
+        // references to skipped types are reported
+        write(input.join("my_pkg/msg/User.msg"), "Provided p\n").unwrap();
+        let referred = generate_workspace(&args(true));
+        remove_dir_all(&tmp).unwrap();
+
+        skipped.unwrap();
+        let msg_rs = msg_rs.unwrap();
+        assert!(msg_rs.contains("mod other;\n"));
+        assert!(!msg_rs.contains("provided"));
+        assert!(!provided);
+        let e = referred.unwrap_err().to_string();
+        assert_eq!(e, "1 denied warning(s) reported");
+    }
+
+    #[test]
+    fn test_string_constants() {
+        use super::{generate_workspace, Args};
+        use std::fs::{create_dir_all, remove_dir_all, write};
+
+        let tmp = std::env::temp_dir().join(format!("ros2msg_to_rs_consts_{}", std::process::id()));
+        let (input, out) = (tmp.join("in"), tmp.join("out"));
+        create_dir_all(input.join("my_pkg/msg")).unwrap();
+        create_dir_all(input.join("my_pkg/srv")).unwrap();
+        let args = Args::new(["-i", input.to_str().unwrap(), "-o", out.to_str().unwrap()]).unwrap();
+
+        write(
+            input.join("my_pkg/msg/Example.msg"),
+            "string<=2 S = \"ab\"\n",
+        )
+        .unwrap();
+        let fitting = generate_workspace(&args);
+        write(
+            input.join("my_pkg/srv/Get.srv"),
+            "int32 a\n---\nstring<=1 T = \"ab\"\n",
+        )
+        .unwrap();
+        let exceeding = generate_workspace(&args);
+        remove_dir_all(&tmp).unwrap();
+
+        fitting.unwrap();
+        let e = exceeding.unwrap_err().to_string();
+        assert!(
+            e.ends_with("Get.srv:3: T has 2 bytes, which exceed string<=1"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn test_run_errors() {
+        let e = super::run(["ros2msg_to_rs", "--version"]).unwrap_err();
+        assert_eq!(
+            e.downcast::<clap::Error>().unwrap().kind(),
+            clap::error::ErrorKind::DisplayVersion
+        );
+
+        let input = std::env::temp_dir().join(format!("ros2msg_to_rs_run_{}", std::process::id()));
+        let e = super::run([
+            "ros2msg_to_rs",
+            "-i",
+            input.to_str().unwrap(),
+            "--message-format",
+            "json",
+        ])
+        .unwrap_err();
+        assert!(e.is::<super::Reported>());
+    }
+
+    #[test]
+    fn test_cross_package_types() {
+        use super::{generate_workspace, Args};
+        use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+
+        let tmp = std::env::temp_dir().join(format!("ros2msg_to_rs_cross_{}", std::process::id()));
+        let (input, out) = (tmp.join("in"), tmp.join("out"));
+        create_dir_all(input.join("my_pkg/msg")).unwrap();
+        create_dir_all(input.join("other/msg")).unwrap();
+        write(input.join("my_pkg/msg/Example.msg"), "other/Point p\n").unwrap();
+        write(input.join("other/msg/Point.msg"), "float64 x\n").unwrap();
+
+        // other is not in common_interfaces of safe_drive but generated together
+        let args = Args::new([
+            "-i",
+            input.to_str().unwrap(),
+            "-o",
+            out.to_str().unwrap(),
+            "--owned",
+            "--diff",
+            "--cdr",
+            "--deny",
+            "unmirrored-messages",
+        ])
+        .unwrap();
+        let result = generate_workspace(&args);
+        let code = read_to_string(out.join("my_pkg/msg/example.rs"));
+        remove_dir_all(&tmp).unwrap();
+
+        result.unwrap();
+        let code = code.unwrap();
+        assert!(code.contains("unsafe impl Send for Example {}"));
+        assert!(code.contains("pub fn diff(&self, other: &Self)"));
+        assert!(code.contains("pub fn encode_cdr("));
+        assert!(code.contains("pub struct ExampleOwned {"));
+    }
+
+    #[test]
+    fn test_msg() {
+        let input1 = "
+    # comment
+# comment
+
+std_msgs/Bool d
+int32 a #comment
+bool b #co
+
+uint32 d 100
+f32 e 20.99 # comment
+i8 f = -5
+i64[] arr1 = [10, 20, 30]
+i64[3] arr2 = [10, 20, 30]
+i64[<=3] arr3 = [10, 20, 30]
+
+string s1 = \"abc\\\\ def \\\" ghi \"
+string s2 = \"\\r\\n\\t\"
+string<=10 s3
+string<=10 [5] s4
+
+";
+
+        let input2 = "
+bool a
+string b
+int8 c
+uint8 d
+int16 e
+uint16 f
+int32 g
+uint32 h
+int64 i # aaa
+uint64 k
+float32 l
+float64 m
+
+bool o = true
+float64 p = 10.2
+
+string s1 = \"abc\\\\ def \\\" ghi \" # bbbb
+string s2 = \"\\r\\n\\t\"
+string<=10 s3
+
+i32[] array1
+i32[10] array2
+string[] array3
+string<=10[3] array4
+string<=10[] array5
+
+std_msgs/Bool std1
+std_msgs/Bool std2
+std_msgs/Header std3
+";
+
+        generate(input1);
+        generate(input2);
+    }
+
+    #[test]
+    fn test_srv_common_interfaces() {
+        let (_, (req, resp)) = parser::parse_srv("int32 a\n---\nint32 b\n")
+            .finish()
+            .unwrap();
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        let lines = g.gen_srv("TestModule", "TestSrv", &req, &resp).unwrap();
+        assert!(lines.iter().any(|l| l.contains("common_interfaces")));
+
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), true);
+        let lines = g.gen_srv("TestModule", "TestSrv", &req, &resp).unwrap();
+        assert!(!lines.iter().any(|l| l.contains("common_interfaces")));
+    }
+
+    #[test]
+    fn test_partial_eq_rust() {
+        let input = "
+int32 a
+int32 B = 10
+string c
+";
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_partial_eq(PartialEqImpl::Rust);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(!code.contains("__are_equal"));
+        assert!(code.contains("self.a == other.a &&"));
+        assert!(code.contains("self.c == other.c"));
+        assert!(!code.contains("self.B"));
+    }
+
+    #[test]
+    fn test_const_only_msg() {
+        let input = "
+int32 A = 1
+string S = \"x\"
+";
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_partial_eq(PartialEqImpl::Rust);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("pub const A: i32 = 1;"));
+        assert!(code.contains("pub const S: &[u8] = "));
+        assert!(code.contains("    structure_needs_at_least_one_member: u8"));
+        assert!(code.contains("fn eq(&self, _other: &Self) -> bool {\n        true"));
+    }
+
+    #[test]
+    fn test_empty_layout() {
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        let (_, exprs) = parser::parse_msg("# no fields\n").finish().unwrap();
+        let lines = g.gen_msg("TestModule", "Empty", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        // the same as `typedef struct { uint8_t structure_needs_at_least_one_member; } TestModule__msg__Empty;`
+        assert!(code.contains(
+            "#[repr(C)]\n#[derive(Debug)]\npub struct Empty {\n    #[allow(dead_code)]\n    structure_needs_at_least_one_member: u8\n}"
+        ));
+        assert!(!code.contains("_unused"));
+
+        // checked by the compiler
+        assert!(code.contains(
+            "}\nconst _: () = assert!(\n    core::mem::size_of::<Empty>() == 1 && core::mem::align_of::<Empty>() == 1\n);\n"
+        ));
+
+        // layouts overridden by `repr` are not checked
+        g.set_repr(Some("C, align(8)".to_string()));
+        let lines = g.gen_msg("TestModule", "Empty2", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(!code.contains("size_of::<Empty2>()"));
+    }
+
+    #[test]
+    fn test_bounded_string_const() {
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        let (_, exprs) = parser::parse_msg("string<=5 S = \"abc\"\n")
+            .finish()
+            .unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains(
+            "pub const S: &[u8] = { const V: &[u8] = b\"abc\\0\"; assert!(V.len() - 1 <= 5"
+        ));
+    }
+
+    #[test]
+    fn test_srv_consts_and_empty_sections() {
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+
+        // constants of the same name in the request and the response
+        let input = "int32 A = 1\n---\nint32 A = 2\nuint8 OK = 0\n";
+        let (_, (req, resp)) = parser::parse_srv(input).finish().unwrap();
+        let lines = g.gen_srv("TestModule", "TestSrv", &req, &resp).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(!code.contains("\npub const A"));
+        assert!(code.contains("\npub const OK: u8 = 0;"));
+        assert!(code.contains("impl TestSrvRequest {\n    pub const A: i32 = 1;\n}"));
+        assert!(code.contains(
+            "impl TestSrvResponse {\n    pub const A: i32 = 2;\n    pub const OK: u8 = 0;\n}"
+        ));
+        assert_eq!(
+            code.matches("    structure_needs_at_least_one_member: u8")
+                .count(),
+            2
+        );
+
+        // empty request and response
+        let (_, (req, resp)) = parser::parse_srv("---\n").finish().unwrap();
+        let lines = g.gen_srv("TestModule", "EmptySrv", &req, &resp).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains(
+            "pub struct EmptySrvRequest {\n    #[allow(dead_code)]\n    structure_needs_at_least_one_member: u8\n}"
+        ));
+        assert!(code.contains(
+            "pub struct EmptySrvResponse {\n    #[allow(dead_code)]\n    structure_needs_at_least_one_member: u8\n}"
+        ));
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        g.set_aliases(true);
+
+        let (_, (req, resp)) = parser::parse_srv("int32 a\n---\n").finish().unwrap();
+        let lines = g.gen_srv("TestModule", "TestSrv", &req, &resp).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(
+            code.contains("pub type TestSrvClient = safe_drive::service::client::Client<TestSrv>;")
+        );
+        assert!(
+            code.contains("pub type TestSrvServer = safe_drive::service::server::Server<TestSrv>;")
+        );
+
+        // requests and responses are not published
+        assert!(!code.contains("Publisher"));
+
+        let (_, exprs) = parser::parse_msg("int32 a\n").finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains(
+            "pub type TestMsgPublisher = safe_drive::topic::publisher::Publisher<TestMsg>;"
+        ));
+        assert!(code.contains(
+            "pub type TestMsgSubscriber = safe_drive::topic::subscriber::Subscriber<TestMsg>;"
+        ));
+
+        // packed messages cannot be published
+        g.set_repr(Some("packed(1)".to_string()));
+        let (_, exprs) = parser::parse_msg("uint8 id\n").finish().unwrap();
+        let lines = g.gen_msg("TestModule", "Register", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(!code.contains("RegisterPublisher"));
+    }
+
+    #[test]
+    fn test_seq_macro() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        g.set_seq_impl(SeqImpl::Macro);
+
+        let (_, (req, resp)) = parser::parse_srv("int32 a\n---\n").finish().unwrap();
+        let lines = g.gen_srv("TestModule", "TestSrv", &req, &resp).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("super::super::seq::impl_seq!(\n    TestSrvRequest,\n    TestSrvRequestSeqRaw,\n    TestSrvRequestSeq,\n    TestModule__srv__TestSrv_Request__init,"));
+        assert!(code.contains("    TestModule__srv__TestSrv_Response__Sequence__fini\n);"));
+        assert!(!code.contains("pub struct TestSrvRequestSeq"));
+    }
+
+    #[test]
+    fn test_typed_seq() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        g.set_seq_impl(SeqImpl::Typed);
+
+        let (_, exprs) = parser::parse_msg(
+            "int32 a
+",
+        )
+        .finish()
+        .unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("unsafe impl super::super::seq::SeqElement for TestMsg {"));
+        assert!(code.contains("    const SEQ_INIT: unsafe extern \"C\" fn(*mut TestMsgSeqRaw, usize) -> bool = TestModule__msg__TestMsg__Sequence__init;"));
+        assert!(code.contains(
+            "pub type TestMsgSeq<const N: usize> = super::super::seq::TypedSeq<TestMsg, N>;"
+        ));
+        assert!(code.contains("impl Drop for TestMsg {"));
+        assert!(!code.contains("pub struct TestMsgSeq"));
+    }
+
+    #[test]
+    fn test_externs() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        g.set_extern_link(true);
+        g.set_extern_allow(vec!["improper_ctypes".to_string(), "dead_code".to_string()]);
+
+        let (_, exprs) = parser::parse_msg("int32 a\n").finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert_eq!(code.matches("extern \"C\" {").count(), 1);
+        assert!(code.contains(
+            "#[link(name = \"TestModule__rosidl_generator_c\")]
+#[link(name = \"TestModule__rosidl_typesupport_c\")]
+#[allow(improper_ctypes, dead_code)]
+extern \"C\" {
+    fn TestModule__msg__TestMsg__init(msg: *mut TestMsg) -> bool;
+    fn TestModule__msg__TestMsg__fini(msg: *mut TestMsg);
+    fn TestModule__msg__TestMsg__are_equal(lhs: *const TestMsg, rhs: *const TestMsg) -> bool;
+    fn TestModule__msg__TestMsg__Sequence__init(msg: *mut TestMsgSeqRaw, size: usize) -> bool;
+    fn TestModule__msg__TestMsg__Sequence__fini(msg: *mut TestMsgSeqRaw);
+    fn TestModule__msg__TestMsg__Sequence__are_equal(lhs: *const TestMsgSeqRaw, rhs: *const TestMsgSeqRaw) -> bool;
+    fn rosidl_typesupport_c__get_message_type_support_handle__TestModule__msg__TestMsg() -> *const rcl::rosidl_message_type_support_t;
+}"
+        ));
+    }
+
+    #[test]
+    fn test_renames() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        let mut renames = Renames::default();
+        renames
+            .packages
+            .insert("other".to_string(), "other_module".to_string());
+        for (pkg, type_name, new_name) in [
+            ("my_library", "TestMsg", "Renamed"),
+            ("my_library", "Inner", "Core"),
+            ("other", "Foo", "Bar"),
+        ] {
+            renames.types.insert(
+                (pkg.to_string(), "msg".to_string(), type_name.to_string()),
+                new_name.to_string(),
+            );
+        }
+        g.set_renames(renames);
+
+        let (_, exprs) = parser::parse_msg("Inner a\nmy_library/Inner b\nother/Foo c\n")
+            .finish()
+            .unwrap();
+        let lines = g.gen_msg("my_library", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("pub struct Renamed {"));
+        assert!(code.contains("pub a: Core,"));
+        assert!(code.contains("pub b: Core,"));
+        assert!(code.contains("pub c: other_module::msg::Bar,"));
+
+        // C symbols are not renamed
+        assert!(code.contains("fn my_library__msg__TestMsg__init(msg: *mut Renamed) -> bool;"));
+    }
+
+    #[test]
+    fn test_micro_ros() {
+        let input = "
+int32[<=4] values
+string<=8 name
+int32[] unbounded
+";
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_backend(Backend::MicroRos);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains(
+            "fn rosidl_typesupport_microxrcedds_c__get_message_type_support_handle__TestModule__msg__TestMsg()"
+        ));
+        assert!(!code.contains("rosidl_typesupport_c__"));
+        assert!(
+            code.contains("pub struct TestMsgPool {\n    values: [i32; 4],\n    name: [u8; 9],\n}")
+        );
+        assert!(code.contains(
+            "pub unsafe fn with_pool(pool: &'static mut TestMsgPool) -> TestMsgPooled {"
+        ));
+        assert!(code.contains("pub fn values_set(&mut self, values: &[i32]) -> bool {"));
+        assert!(code.contains("pub fn name_set(&mut self, value: &str) -> bool {"));
+        assert!(!code.contains("unbounded: ["));
+    }
+
+    #[test]
+    fn test_fill_random() {
+        let input = "
+int32 a
+uint8[<=4] b
+string<=5 c
+builtin_interfaces/Time stamp
+int32 K = 1
+";
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_random(true);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("pub fn fill_random(&mut self, rng: &mut impl rand::Rng)"));
+        assert!(code.contains("self.a = rng.random();"));
+        assert!(code.contains("<crate::msg::U8Seq<4>>::new(rng.random_range(0..=4))"));
+        assert!(code.contains("self.c.assign(&random_string(rng, 5));"));
+        assert!(!code.contains("self.stamp.fill_random"));
+        assert!(!code.contains("self.K"));
+    }
+
+    #[test]
+    fn test_const_tests() {
+        let input = "
+int32 a
+int8 NEG = -3
+float32 HALF = 0.5
+bool FLAG = true
+string S = \"a\\tb\"
+";
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_const_tests(true);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("mod const_tests {"));
+        assert!(code.contains("assert_eq!(NEG as i128, -3);"));
+        assert!(code.contains("assert_eq!(HALF.to_bits(), 0x3f000000);"));
+        assert!(code.contains("assert_eq!(u8::from(FLAG), 1);"));
+        assert!(code.contains("assert_eq!(S, [97, 9, 98, 0].as_slice());"));
+    }
+
+    #[test]
+    fn test_optional() {
+        let input = "
+float64 altitude # @optional
+bool has_altitude
+";
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains(
+            "    /// Present if `has_altitude` is true, as annotated by `@optional`.\n    pub altitude: f64,"
+        ));
+        assert!(code.contains("    pub fn altitude_opt(&self) -> Option<f64> {\n"));
+
+        let (_, exprs) = parser::parse_msg("float64 altitude # @optional\n")
+            .finish()
+            .unwrap();
+        let err = g.gen_msg("TestModule", "Invalid", &exprs).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid::altitude: `has_altitude` of @optional is not a bool field"
+        );
+    }
+
+    #[test]
+    fn test_owned() {
+        let input = "
+std_msgs/Header header
+string name
+int32[] xs
+";
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_owned(true);
+        g.set_fallible_api(FallibleApi::Result);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        // fields of std_msgs of safe_drive cannot be mirrored
+        assert!(!code.contains("TestMsgOwned"));
+
+        let input = "
+builtin_interfaces/Time stamp
+string name
+int32[] xs
+";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg2", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains(
+            "pub struct TestMsg2Owned {\n    pub stamp: super::super::owned::TimeOwned,\n    pub name: std::string::String,\n    pub xs: std::vec::Vec<i32>,\n}"
+        ));
+        assert!(code.contains("    type Error = super::super::error::MsgError;\n"));
+        assert!(code.contains("        let mut out = Self::new()?;\n"));
+    }
+
+    #[test]
+    fn test_diff() {
+        let input = "
+std_msgs/Header header
+float64 x
+";
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_diff(true);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        // fields of std_msgs of safe_drive are unknown
+        assert!(!code.contains("pub fn diff("));
+
+        let (_, exprs) = parser::parse_msg("float64 x\nPoint p\n").finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg2", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("        diff::compare(diffs, prefix, \"x\", &self.x, &other.x);\n"));
+        assert!(code.contains("        self.p.diff_into(&other.p, &nested, diffs);\n"));
+    }
+
+    #[test]
+    fn test_cdr() {
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_cdr(true);
+        let (_, (req, resp)) = parser::parse_srv(
+            "builtin_interfaces/Time stamp\nstring[<=2] names\n---\nstd_msgs/Header header\n",
+        )
+        .finish()
+        .unwrap();
+        let lines = g.gen_srv("TestModule", "TestSrv", &req, &resp).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains(
+            "    pub fn decode_cdr(payload: &[u8]) -> Result<Self, super::super::cdr::Error> {\n"
+        ));
+        assert!(code.contains("            writer.write_i32(self.stamp.sec);\n"));
+        assert!(code.contains("                let len = reader.read_bounded_len(5, 2)?;\n"));
+
+        // layouts of std_msgs of safe_drive are unknown
+        assert_eq!(code.matches("pub fn encode_cdr(").count(), 1);
+    }
+
+    #[test]
+    fn test_bitflags() {
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_bitflags(true);
+        let (_, (req, resp)) =
+            parser::parse_srv("uint8 MASK_A = 1\nuint8 MASK_B = 2\nuint8 mask\n---\nbool ok\n")
+                .finish()
+                .unwrap();
+        let lines = g.gen_srv("TestModule", "TestSrv", &req, &resp).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("pub struct TestSrvRequestMaskFlags(pub u8);\n"));
+        assert!(code.contains("    pub const A: Self = Self(TestSrvRequest::MASK_A);\n"));
+        assert!(!code.contains("TestSrvResponseMaskFlags"));
+    }
+
+    #[test]
+    fn test_constants_by_name() {
+        let input = "
+int32 a
+uint8 MODE_AUTO = 1
+";
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_constants_by_name(true);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains(
+            "            \"MODE_AUTO\" => Some(ConstantValue::Int(i128::from(MODE_AUTO))),\n"
+        ));
+
+        let (_, (req, resp)) = parser::parse_srv("uint8 LEVEL = 2\n---\nbool ok\n")
+            .finish()
+            .unwrap();
+        let lines = g.gen_srv("TestModule", "TestSrv", &req, &resp).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains(
+            "            \"LEVEL\" => Some(ConstantValue::Int(i128::from(Self::LEVEL))),\n"
+        ));
+        assert!(code.contains("impl TestSrvResponse {\n    /// Value of the constant named `name` in the definition.\n    pub fn constant_by_name(_name: &str)"));
+    }
+
+    #[test]
+    fn test_fallible_api_result() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        g.set_fallible_api(FallibleApi::Result);
+
+        let (_, exprs) = parser::parse_msg("TestMsg[<=2] children\nint32[<=2] xs\n")
+            .finish()
+            .unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("pub fn new() -> Result<Self, super::super::error::MsgError> {"));
+        assert!(code
+            .contains("pub fn new(size: usize) -> Result<Self, super::super::error::MsgError> {"));
+        assert!(code.contains(
+            "return Err(super::super::error::MsgError::ExceedsBound { field: None, len: size, max: N });"
+        ));
+        assert!(code.contains("let Ok(mut seq) = Self::new(capacity) else {"));
+
+        // constructors of safe_drive still return Option
+        assert!(code.contains("<TestMsgSeq<2>>::new(len + 1).ok().ok_or("));
+        assert!(code.contains("<safe_drive::msg::I32Seq<2>>::new(len + 1).ok_or("));
+
+        // errors of fields have their names
+        assert!(code.contains("pub fn xs_try_set(&mut self, values: &[i32]) -> Result<(), super::super::error::MsgError> {"));
+        assert!(code.contains("return Err(super::super::error::MsgError::ExceedsBound { field: Some(\"TestMsg::xs\"), len: values.len(), max: 2 });"));
+        assert!(code.contains(".ok_or(super::super::error::MsgError::Allocation { field: Some(\"TestMsg::children\") })?;"));
+
+        let (_, exprs) = parser::parse_msg("uint8[<=4] data\n").finish().unwrap();
+        let lines = g.gen_msg("TestModule", "Bytes", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("    type Error = super::super::error::MsgError;"));
+        assert!(code.contains("Err(super::super::error::MsgError::ExceedsBound { field: Some(\"Bytes::data\"), len: value.len(), max: 4 })"));
+    }
+
+    #[test]
+    fn test_must_use() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        let (_, exprs) = parser::parse_msg("uint8[] data\n").finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("    #[must_use]\n    pub fn new() -> Option<Self> {"));
+        assert!(code.contains("    #[must_use]\n    pub fn new(size: usize) -> Option<Self> {"));
+        assert!(code.contains("    #[must_use]\n    pub fn null() -> Self {"));
+        assert!(code.contains(
+            "    #[must_use = \"the sequence is not resized if `false` is returned\"]\n    pub fn resize("
+        ));
+        assert!(code.contains(
+            "    #[must_use = \"the field is not replaced if `false` is returned\"]\n    pub fn data_copy_from_slice("
+        ));
+    }
+
+    #[test]
+    fn test_send_sync() {
+        let input = "
+string name
+Inner[] inners
+builtin_interfaces/Time stamp
+";
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("unsafe impl Send for TestMsg {}\nunsafe impl Sync for TestMsg {}"));
+
+        // types of common interfaces are left to the auto traits
+        let (_, exprs) = parser::parse_msg("geometry_msgs/Point point\n")
+            .finish()
+            .unwrap();
+        let lines = g.gen_msg("TestModule", "PointMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(!code.contains("unsafe impl Send for PointMsg"));
+
+        // and so are messages of nested messages left to the auto traits
+        g.set_known_types([("my_library".to_string(), "Known".to_string())].into());
+        let (_, exprs) = parser::parse_msg("Known known\n").finish().unwrap();
+        let lines = g.gen_msg("TestModule", "KnownMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("unsafe impl Send for KnownMsg"));
+        let (_, exprs) = parser::parse_msg("PointMsg point\n").finish().unwrap();
+        let lines = g.gen_msg("TestModule", "NestedMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(!code.contains("unsafe impl Send for NestedMsg"));
+
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        g.set_send_sync(false);
+        let (_, exprs) = parser::parse_msg("string name\n").finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(!code.contains("unsafe impl Send"));
+    }
+
+    #[test]
+    fn test_bound_helpers() {
+        let input = "
+int32[<=3] xs
+string[<=2] names
+Inner[] unbounded
+";
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("    pub const XS_MAX_LEN: usize = 3;"));
+        assert!(code.contains(
+            "    pub fn xs_push_checked(&mut self, value: i32) -> Result<(), &'static str> {"
+        ));
+        assert!(code.contains(
+            "    pub fn xs_try_set(&mut self, values: &[i32]) -> Result<(), &'static str> {"
+        ));
+        assert!(code.contains("return Err(\"TestMsg::xs cannot have more than 3 elements\");"));
+        assert!(code.contains("let mut seq = <safe_drive::msg::RosStringSeq<0, 2>>::new(len + 1)"));
+        assert!(code.contains(
+            "pub fn names_push_checked(&mut self, value: safe_drive::msg::RosString<0>)"
+        ));
+        assert!(!code.contains("names_try_set"));
+        assert!(!code.contains("unbounded_push_checked"));
+    }
+
+    #[test]
+    fn test_layout_override() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        g.set_repr(Some("align(64)".to_string()));
+        let (_, exprs) = parser::parse_msg("string name\n").finish().unwrap();
+        let lines = g.gen_msg("TestModule", "Frame", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("#[repr(C, align(64))]\n#[derive(Debug)]\npub struct Frame {"));
+
+        // derived traits cannot refer to unaligned fields which are not `Copy`
+        g.set_repr(Some("packed(1)".to_string()));
+        let (_, exprs) = parser::parse_msg("uint8 id\nfloat64[3] xs\n")
+            .finish()
+            .unwrap();
+        let lines = g.gen_msg("TestModule", "Register", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("#[repr(C, packed(1))]\n"));
+
+        // the offsets differ from the C struct, so no C function is called
+        let (_, exprs) = parser::parse_msg("uint8 a\nuint32 b 7\n").finish().unwrap();
+        let lines = g.gen_msg("TestModule", "Unaligned", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(!code.contains("extern \"C\""));
+        assert!(!code.contains("__init"));
+        assert!(!code.contains("impl Drop"));
+        assert!(!code.contains("TypeSupport"));
+        assert!(!code.contains("UnalignedSeq"));
+        assert!(code.contains("        msg.b = 7u32;\n"));
+        assert!(code.contains("        let (left, right) = (self.b, other.b);\n"));
+
+        let (_, exprs) = parser::parse_msg("string name\n").finish().unwrap();
+        assert!(g.gen_msg("TestModule", "Named", &exprs).is_err());
+
+        let (_, (req, resp)) = parser::parse_srv("uint8 a\n---\nuint8 b\n")
+            .finish()
+            .unwrap();
+        assert!(g.gen_srv("TestModule", "Get", &req, &resp).is_err());
+    }
+
+    #[test]
+    fn test_msg_alias() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        let (_, exprs) = parser::parse_msg("int32 LIMIT=3\nfloat64 x\n")
+            .finish()
+            .unwrap();
+        let lines = g
+            .gen_msg_alias("Point", "other_library", "Point", &exprs)
+            .unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("pub use other_library::msg::{Point, PointSeq};\n"));
+        assert!(code.contains("pub use other_library::msg::LIMIT;\n"));
+        assert!(g.libs.contains("other_library"));
+
+        let lines = g
+            .gen_msg_alias("Vector", "other_library", "Point", &exprs)
+            .unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("pub use other_library::msg::Point as Vector;\n"));
+        assert!(code.contains("pub use other_library::msg::PointSeq as VectorSeq;\n"));
+    }
+
+    #[test]
+    fn test_default_values() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        let input = "int32 x 42\nstring s \"hello\"\n";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("my_library", "Example", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains(
+            "        if unsafe { my_library__msg__Example__init(&mut msg) } {\n            msg.x = 42i32;\n            if !msg.s.assign(\"hello\") { return None; }\n            Some(msg)\n"
+        ));
+
+        let (_, exprs) = parser::parse_msg("uint8 x 256\n").finish().unwrap();
+        let lines = g.gen_msg("my_library", "Invalid", &exprs).unwrap();
+        assert!(!lines
+            .iter()
+            .any(|l| l.contains("Fields are set to the default values")));
+    }
+
+    #[test]
+    fn test_topic() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        let input = "# @topic /sensor/imu\nfloat64 x\n";
+        g.set_topic(parser::parse_file_topic(input));
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("my_library", "Imu", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("    pub const DEFAULT_TOPIC: &str = \"/sensor/imu\";\n"));
+        assert!(code.contains(
+            ") -> safe_drive::error::RCLResult<safe_drive::topic::subscriber::Subscriber<Self>> {\n"
+        ));
+
+        for topic in ["/sensor/imu", "imu", "~", "~/imu", "/{node}/imu_2"] {
+            assert!(super::validate_topic(topic).is_ok(), "{topic}");
+        }
+        for topic in [
+            "",
+            "/",
+            "imu/",
+            "//imu",
+            "~imu",
+            "/2d",
+            "/imu data",
+            "/imu\"",
+        ] {
+            assert!(super::validate_topic(topic).is_err(), "{topic}");
+        }
+    }
+
+    #[test]
+    fn test_recommended_qos() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        let input = "# @qos(reliability=reliable, durability=transient_local)\n---\nbool ok\n";
+        let args = parser::parse_file_qos(input).unwrap();
+        g.set_qos(Some(super::qos::Qos::from_args(&args).unwrap()));
+        let (_, (req, resp)) = parser::parse_srv(input).finish().unwrap();
+        let lines = g.gen_srv("my_library", "Reset", &req, &resp).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(
+            code.contains("impl Reset {\n    /// QoS recommended by the definition of `Reset`.\n")
+        );
+        assert!(code.contains(
+            "            durability: safe_drive::qos::DurabilityPolicy::TransientLocal,\n"
+        ));
+    }
+
+    #[test]
+    fn test_action() {
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        let (_, (goal, result, feedback)) =
+            parser::parse_action("int32 order\n---\nint32[] sequence\n---\nint32[] partial\n")
+                .finish()
+                .unwrap();
+        let lines = g
+            .gen_action("my_module", "Fibonacci", &goal, &result, &feedback)
+            .unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("pub struct FibonacciGoal {\n    pub order: i32,\n}"));
+        assert!(code.contains("pub struct FibonacciSendGoalRequest {"));
+        assert!(code.contains("    pub goal: FibonacciGoal,\n"));
+        assert!(code.contains("pub struct FibonacciFeedbackMessage {"));
+        assert!(code.contains("pub struct FibonacciGetResult;"));
+        assert!(code.contains(
+            "rosidl_typesupport_c__get_action_type_support_handle__my_module__action__Fibonacci()"
+        ));
+    }
+
+    #[test]
+    fn test_string_consts() {
+        let input = r#"
+string QUOTED = "say \"hi\"\\n"
+string RAW = C:\path "x"
+string<=8 UTF8 = 'é\t'
+"#;
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_const_tests(true);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains(r#"pub const QUOTED: &[u8] = b"say \"hi\"\\n\0";"#));
+        assert!(code.contains(r#"pub const RAW: &[u8] = b"C:\\path \"x\"\0";"#));
+        assert!(code.contains(r#"const V: &[u8] = b"\xc3\xa9\t\0";"#));
+        assert!(code.contains("assert_eq!(UTF8, [195, 169, 9, 0].as_slice());"));
+    }
+
+    #[test]
+    fn test_char_and_byte_consts() {
+        let input = "
+char C = 'a'
+char NL = '\\n'
+byte B = 0x41
+byte Q = '\\''
+uint32 MASK = 0XFF00
+";
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_const_tests(true);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("pub const C: i8 = b'a' as i8;"));
+        assert!(code.contains("pub const NL: i8 = b'\\n' as i8;"));
+        assert!(code.contains("pub const B: u8 = 65;"));
+        assert!(code.contains("pub const Q: u8 = b'\\'';"));
+        assert!(code.contains("pub const MASK: u32 = 65280;"));
+        assert!(code.contains("assert_eq!(C as i128, 97);"));
+        assert!(code.contains("assert_eq!(NL as i128, 10);"));
+
+        let (_, exprs) = parser::parse_msg("char C = 'ab'\n").finish().unwrap();
+        let err = g.gen_msg("TestModule", "Other", &exprs).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Other::C: 'ab' is not a single ASCII character"
+        );
+    }
+
+    #[test]
+    fn test_example_doc() {
+        let input = "
+geometry_msgs/Point p
+float64 x
+string<=3 name
+int32 y
+";
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        let expected = "/// # Example
+///
+/// ```ignore
+/// let mut msg = TestMsg::new().unwrap();
+/// msg.x = 1.0;
+/// msg.name.assign(\"nam\");
+/// ```
+#[repr(C)]";
+        assert!(code.contains(expected));
+    }
+
+    #[test]
+    fn test_shadowing_types() {
+        let input = "
+Header header
+my_library/Time[] times
+string s
+";
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_no_std(true);
+        g.set_random(true);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "String", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("pub header: super::super::msg::Header,"));
+        assert!(code.contains("pub times: super::super::msg::TimeSeq<0>,"));
+        assert!(code.contains("use alloc::{format, vec::Vec};"));
+        assert!(code.contains("-> alloc::string::String {"));
+    }
+
+    #[test]
+    fn test_local_types() {
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        g.set_local_types(["Inner".to_string()].into_iter().collect());
+
+        let (_, exprs) = parser::parse_msg("Inner a\nmy_library/Inner b\n")
+            .finish()
+            .unwrap();
+        assert!(g.gen_msg("TestModule", "TestMsg", &exprs).is_ok());
+
+        let (_, exprs) = parser::parse_msg("int32 a\nmy_library/Missing b\n")
+            .finish()
+            .unwrap();
+        let e = g.gen_msg("TestModule", "TestMsg2", &exprs).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "TestMsg2::b: my_library/Missing is not found in the package my_library"
+        );
+    }
+
+    #[test]
+    fn test_msg_srv_rs_order() {
+        let modules = ["b_a".to_string(), "ab".to_string(), "a_b".to_string()];
+        assert_eq!(
+            super::generate_msg_srv_rs(&modules, ""),
+            "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).\nmod a_b;\nmod ab;\nmod b_a;\n\npub use a_b::*;\npub use ab::*;\npub use b_a::*;\n"
+        );
+    }
+
+    #[test]
+    fn test_package_doc() {
+        let xml = "<?xml version=\"1.0\"?>\n<package format=\"3\">\n  <name>my_module</name>\n  <version>1.2.3</version>\n</package>\n";
+        assert_eq!(super::parse_package_version(xml).as_deref(), Some("1.2.3"));
+        assert_eq!(super::parse_package_version("<package/>"), None);
+
+        let definitions = [
+            ("srv".to_string(), "Get".to_string()),
+            ("msg".to_string(), "Outer".to_string()),
+            ("msg".to_string(), "Inner".to_string()),
+        ];
+        assert_eq!(
+            super::gen_package_doc("my_module", Some("1.2.3"), &definitions),
+            "//! Interfaces of `my_module` 1.2.3.
+//!
+//! # Messages
+//!
+//! - [`msg::Inner`]
+//! - [`msg::Outer`]
+//!
+//! # Services
+//!
+//! - [`srv::Get`], [`srv::GetRequest`] and [`srv::GetResponse`]
+"
+        );
+    }
+
+    #[test]
+    fn test_adversarial_comments() {
+        let input = "
+int32 a #!inner doc
+int32 b #/outer doc
+int32 c # */ \" /* \\
+int32 d # \u{202e}reversed\u{2066} [m/\u{202e}s]
+int32 E = 1 #!const
+";
+        let mut g = Generator::new("my_library".to_string(), "safe_drive".to_string(), false);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("    pub a: i32, // !inner doc\n"));
+        assert!(code.contains("    pub b: i32, // /outer doc\n"));
+        assert!(code.contains("    pub c: i32, // */ \" /* \\\n"));
+        assert!(code.contains("    pub d: i32, // \\u{202e}reversed\\u{2066} [m/\\u{202e}s]\n"));
+        assert!(code.contains("    /// Unit: `m/\\u{202e}s`\n"));
+        assert!(code.contains("pub const E: i32 = 1; // !const\n"));
+        assert!(!code.contains('\u{202e}'));
+
+        // invalid UTF-8 in comments does not fail reading
+        let bytes = b"int32 a # \xff\xfe\n";
+        let contents = crate::archive::read_definition(&bytes[..]).unwrap();
+        let (_, exprs) = parser::parse_msg(&contents).finish().unwrap();
+        let lines = g.gen_msg("TestModule", "TestMsg2", &exprs).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains("    pub a: i32, // \u{fffd}\u{fffd}\n"));
+    }
+
+    fn generate(input: &str) {
+        let mut g = Generator::new("my_library".to_string(), "crate".to_string(), false);
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        g.gen_msg("TestModule", "TestMsg", &exprs).unwrap();
+    }
+}
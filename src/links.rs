@@ -0,0 +1,45 @@
+//! Check whether the C libraries of generated packages can be linked.
+//!
+//! Each package requires `lib{pkg}__rosidl_typesupport_c` and `lib{pkg}__rosidl_generator_c`,
+//! which are searched in `lib` directories of `AMENT_PREFIX_PATH` and in `LD_LIBRARY_PATH`.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Suffixes of the C libraries of a package.
+const LIB_SUFFIXES: [&str; 2] = ["__rosidl_typesupport_c", "__rosidl_generator_c"];
+
+/// Extensions of library files.
+const LIB_EXTENSIONS: [&str; 3] = ["so", "dylib", "a"];
+
+/// Directories where the C libraries are searched.
+pub fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    if let Some(paths) = env::var_os("AMENT_PREFIX_PATH") {
+        dirs.extend(env::split_paths(&paths).map(|path| path.join("lib")));
+    }
+
+    if let Some(paths) = env::var_os("LD_LIBRARY_PATH") {
+        dirs.extend(env::split_paths(&paths));
+    }
+
+    dirs
+}
+
+/// Return the names of the libraries of `pkg` which are not found in `dirs`.
+pub fn missing_libs(pkg: &str, dirs: &[PathBuf]) -> Vec<String> {
+    LIB_SUFFIXES
+        .iter()
+        .map(|suffix| format!("{pkg}{suffix}"))
+        .filter(|lib| !dirs.iter().any(|dir| has_lib(dir, lib)))
+        .collect()
+}
+
+fn has_lib(dir: &Path, lib: &str) -> bool {
+    LIB_EXTENSIONS
+        .iter()
+        .any(|ext| dir.join(format!("lib{lib}.{ext}")).exists())
+}
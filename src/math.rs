@@ -0,0 +1,297 @@
+//! Conversions between geometry messages and types of math libraries.
+//!
+//! Messages are matched by their structure rather than by their names,
+//! so that user defined types having the same layout as geometry_msgs also get conversions.
+//!
+//! | Structure                                    | nalgebra                           | glam                  |
+//! |----------------------------------------------|------------------------------------|-----------------------|
+//! | `float64 x, y, z`                            | `Vector3<f64>` (`Point3<f64>` too) | `DVec3`               |
+//! | `float64 x, y, z, w`                         | `UnitQuaternion<f64>`              | `DQuat`               |
+//! | `Point position`, `Quaternion orientation`   | `Isometry3<f64>`                   | `DAffine3`            |
+//! | `Vector3 translation`, `Quaternion rotation` | `Isometry3<f64>`                   | `DAffine3`            |
+
+use crate::parser::{ArrayInfo, Expr, TypeName, ValueType};
+use std::{borrow::Cow, collections::VecDeque};
+
+/// Math library to be converted from and to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MathLib {
+    Nalgebra,
+    Glam,
+}
+
+#[derive(Debug)]
+enum Shape<'a> {
+    /// `float64 x, y, z`
+    Vector3,
+
+    /// `float64 x, y, z, w`
+    Quaternion,
+
+    /// A pair of a translation and a rotation.
+    /// The names of the fields and the types are stored.
+    Isometry {
+        translation: &'a str,
+        rotation: &'a str,
+    },
+}
+
+/// Generate conversions if the struct has one of the supported structures.
+pub fn gen_math_conversions(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    struct_name: &str,
+    exprs: &[Expr],
+    libs: &[MathLib],
+) {
+    let Some(shape) = detect_shape(exprs) else {
+        return;
+    };
+
+    for lib in libs {
+        let code = match (lib, &shape) {
+            (MathLib::Nalgebra, Shape::Vector3) => nalgebra_vector3(struct_name),
+            (MathLib::Nalgebra, Shape::Quaternion) => nalgebra_quaternion(struct_name),
+            (
+                MathLib::Nalgebra,
+                Shape::Isometry {
+                    translation,
+                    rotation,
+                },
+            ) => nalgebra_isometry(struct_name, translation, rotation),
+            (MathLib::Glam, Shape::Vector3) => glam_vector3(struct_name),
+            (MathLib::Glam, Shape::Quaternion) => glam_quaternion(struct_name),
+            (
+                MathLib::Glam,
+                Shape::Isometry {
+                    translation,
+                    rotation,
+                },
+            ) => glam_isometry(struct_name, translation, rotation),
+        };
+        lines.push_back(code.into());
+    }
+}
+
+fn detect_shape(exprs: &[Expr]) -> Option<Shape<'_>> {
+    let mut fields = Vec::new();
+    for expr in exprs.iter() {
+        match expr {
+            Expr::Variable {
+                value: Some(ValueType::Const(_)),
+                ..
+            } => (),
+            Expr::Variable {
+                type_name:
+                    TypeName::Type {
+                        type_name,
+                        array_info: ArrayInfo::NotArray,
+                    },
+                var_name,
+                ..
+            } => fields.push((var_name.as_str(), type_name.as_str())),
+            Expr::Variable { .. } => return None,
+            _ => (),
+        }
+    }
+
+    match fields.as_slice() {
+        [("x", "float64"), ("y", "float64"), ("z", "float64")] => Some(Shape::Vector3),
+        [("x", "float64"), ("y", "float64"), ("z", "float64"), ("w", "float64")] => {
+            Some(Shape::Quaternion)
+        }
+        [("position", "Point"), ("orientation", "Quaternion")] => Some(Shape::Isometry {
+            translation: "position",
+            rotation: "orientation",
+        }),
+        [("translation", "Vector3"), ("rotation", "Quaternion")] => Some(Shape::Isometry {
+            translation: "translation",
+            rotation: "rotation",
+        }),
+        _ => None,
+    }
+}
+
+fn nalgebra_vector3(struct_name: &str) -> String {
+    format!(
+        "
+impl From<&{struct_name}> for nalgebra::Vector3<f64> {{
+    fn from(value: &{struct_name}) -> Self {{
+        nalgebra::Vector3::new(value.x, value.y, value.z)
+    }}
+}}
+
+impl From<&{struct_name}> for nalgebra::Point3<f64> {{
+    fn from(value: &{struct_name}) -> Self {{
+        nalgebra::Point3::new(value.x, value.y, value.z)
+    }}
+}}
+
+impl From<nalgebra::Vector3<f64>> for {struct_name} {{
+    fn from(value: nalgebra::Vector3<f64>) -> Self {{
+        Self {{ x: value.x, y: value.y, z: value.z }}
+    }}
+}}
+
+impl From<nalgebra::Point3<f64>> for {struct_name} {{
+    fn from(value: nalgebra::Point3<f64>) -> Self {{
+        Self {{ x: value.x, y: value.y, z: value.z }}
+    }}
+}}
+"
+    )
+}
+
+fn nalgebra_quaternion(struct_name: &str) -> String {
+    format!(
+        "
+/// The quaternion is normalized.
+impl From<&{struct_name}> for nalgebra::UnitQuaternion<f64> {{
+    fn from(value: &{struct_name}) -> Self {{
+        nalgebra::UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(value.w, value.x, value.y, value.z))
+    }}
+}}
+
+impl From<nalgebra::UnitQuaternion<f64>> for {struct_name} {{
+    fn from(value: nalgebra::UnitQuaternion<f64>) -> Self {{
+        Self {{ x: value.i, y: value.j, z: value.k, w: value.w }}
+    }}
+}}
+"
+    )
+}
+
+fn nalgebra_isometry(struct_name: &str, translation: &str, rotation: &str) -> String {
+    format!(
+        "
+impl From<&{struct_name}> for nalgebra::Isometry3<f64> {{
+    fn from(value: &{struct_name}) -> Self {{
+        let t = &value.{translation};
+        nalgebra::Isometry3::from_parts(
+            nalgebra::Translation3::new(t.x, t.y, t.z),
+            (&value.{rotation}).into(),
+        )
+    }}
+}}
+
+impl From<nalgebra::Isometry3<f64>> for {struct_name} {{
+    fn from(value: nalgebra::Isometry3<f64>) -> Self {{
+        Self {{
+            {translation}: value.translation.vector.into(),
+            {rotation}: value.rotation.into(),
+        }}
+    }}
+}}
+"
+    )
+}
+
+fn glam_vector3(struct_name: &str) -> String {
+    format!(
+        "
+impl From<&{struct_name}> for glam::DVec3 {{
+    fn from(value: &{struct_name}) -> Self {{
+        glam::DVec3::new(value.x, value.y, value.z)
+    }}
+}}
+
+impl From<glam::DVec3> for {struct_name} {{
+    fn from(value: glam::DVec3) -> Self {{
+        Self {{ x: value.x, y: value.y, z: value.z }}
+    }}
+}}
+"
+    )
+}
+
+fn glam_quaternion(struct_name: &str) -> String {
+    format!(
+        "
+impl From<&{struct_name}> for glam::DQuat {{
+    fn from(value: &{struct_name}) -> Self {{
+        glam::DQuat::from_xyzw(value.x, value.y, value.z, value.w)
+    }}
+}}
+
+impl From<glam::DQuat> for {struct_name} {{
+    fn from(value: glam::DQuat) -> Self {{
+        Self {{ x: value.x, y: value.y, z: value.z, w: value.w }}
+    }}
+}}
+"
+    )
+}
+
+fn glam_isometry(struct_name: &str, translation: &str, rotation: &str) -> String {
+    format!(
+        "
+impl From<&{struct_name}> for glam::DAffine3 {{
+    fn from(value: &{struct_name}) -> Self {{
+        glam::DAffine3::from_rotation_translation((&value.{rotation}).into(), (&value.{translation}).into())
+    }}
+}}
+
+/// The scale of the affine transformation is ignored.
+impl From<glam::DAffine3> for {struct_name} {{
+    fn from(value: glam::DAffine3) -> Self {{
+        let (_, rotation, translation) = value.to_scale_rotation_translation();
+        Self {{
+            {translation}: translation.into(),
+            {rotation}: rotation.into(),
+        }}
+    }}
+}}
+"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::detect_shape;
+    use crate::parser;
+    use nom::Finish;
+
+    fn shape(input: &str) -> Option<String> {
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        detect_shape(&exprs).map(|shape| format!("{shape:?}"))
+    }
+
+    #[test]
+    fn test_detect_shape() {
+        let vector3 = Some("Vector3".to_string());
+        assert_eq!(shape("float64 x\nfloat64 y\nfloat64 z\n"), vector3);
+        assert_eq!(
+            shape("float64 x\nfloat64 y\nfloat64 z\nfloat64 w\n").as_deref(),
+            Some("Quaternion")
+        );
+        assert_eq!(
+            shape("Point position\nQuaternion orientation\n").as_deref(),
+            Some(r#"Isometry { translation: "position", rotation: "orientation" }"#)
+        );
+        assert_eq!(
+            shape("Vector3 translation\nQuaternion rotation\n").as_deref(),
+            Some(r#"Isometry { translation: "translation", rotation: "rotation" }"#)
+        );
+
+        // constants are not fields, and default values do not change the structure
+        assert_eq!(
+            shape("int32 DIM=3\nfloat64 x\nfloat64 y\nfloat64 z\n"),
+            vector3
+        );
+        assert_eq!(shape("float64 x 1.0\nfloat64 y\nfloat64 z\n"), vector3);
+    }
+
+    #[test]
+    fn test_detect_shape_mismatch() {
+        // types, names, the order and the number of fields must match
+        assert_eq!(shape("float32 x\nfloat32 y\nfloat32 z\n"), None);
+        assert_eq!(shape("float64 a\nfloat64 b\nfloat64 c\n"), None);
+        assert_eq!(shape("float64 z\nfloat64 y\nfloat64 x\n"), None);
+        assert_eq!(shape("float64 x\nfloat64 y\n"), None);
+        assert_eq!(shape("float64 x\nfloat64 y\nfloat64 z\nfloat64 t\n"), None);
+        assert_eq!(shape("Quaternion orientation\nPoint position\n"), None);
+
+        // arrays are not supported
+        assert_eq!(shape("float64[3] x\n"), None);
+        assert_eq!(shape("float64[] x\nfloat64 y\nfloat64 z\n"), None);
+    }
+}
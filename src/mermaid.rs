@@ -0,0 +1,190 @@
+//! Class diagrams of packages in Mermaid.
+//!
+//! `--emit-mermaid` writes `{package}/classes.mmd`, where each message, request and response is a class,
+//! and fields of message types are compositions labeled by the field names and multiplicities of arrays.
+//! Types of other packages are declared as classes without members.
+
+use crate::{
+    cst::{parse_cst, LineKind},
+    generator::gen_primitives,
+    parser::{ArrayInfo, Expr, TypeName, ValueType},
+};
+use std::{collections::BTreeSet, error::Error};
+
+/// Class diagram of a package.
+#[derive(Default)]
+pub struct Diagram {
+    classes: String,
+    relations: Vec<String>,
+
+    /// IDs of classes defined by `classes`.
+    defined: BTreeSet<String>,
+
+    /// IDs and labels of classes referred by `relations`.
+    referred: BTreeSet<(String, String)>,
+}
+
+impl Diagram {
+    /// Add classes of `pkg/(msg|srv|action)/type_name` defined by `contents`.
+    pub fn add(
+        &mut self,
+        pkg: &str,
+        ext: &str,
+        type_name: &str,
+        contents: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let cst = parse_cst(contents)?;
+
+        let names = match ext {
+            "msg" => vec![format!("{pkg}/msg/{type_name}")],
+            "srv" => vec![
+                format!("{pkg}/srv/{type_name}_Request"),
+                format!("{pkg}/srv/{type_name}_Response"),
+            ],
+            _ => vec![
+                format!("{pkg}/action/{type_name}_Goal"),
+                format!("{pkg}/action/{type_name}_Result"),
+                format!("{pkg}/action/{type_name}_Feedback"),
+            ],
+        };
+
+        let mut members = vec![String::new(); names.len()];
+        let mut index = 0;
+        for line in cst.lines.iter() {
+            let definition = match &line.kind {
+                LineKind::Separator => {
+                    index = (index + 1).min(names.len() - 1);
+                    continue;
+                }
+                LineKind::Blank => continue,
+                LineKind::Definition(definition) => definition,
+            };
+
+            let Expr::Variable {
+                type_name, value, ..
+            } = &definition.expr
+            else {
+                continue;
+            };
+
+            let from = &names[index];
+            let member = format!("{} {}", definition.type_name, definition.name);
+            if let Some(ValueType::Const(_)) = value {
+                // `$` marks static members
+                let value = definition.value.trim().trim_start_matches('=').trim();
+                members[index].push_str(&format!("        +{member} = {}$\n", escape(value)));
+                continue;
+            }
+            members[index].push_str(&format!("        +{member}\n"));
+
+            let (scope, nested, array_info) = match type_name {
+                TypeName::Type {
+                    type_name,
+                    array_info,
+                } if gen_primitives(type_name).is_none() => (pkg, type_name, array_info),
+                TypeName::ScopedType {
+                    scope,
+                    type_name,
+                    array_info,
+                } => (scope.as_str(), type_name, array_info),
+                _ => continue,
+            };
+
+            let to = format!("{scope}/msg/{nested}");
+            let multiplicity = match array_info {
+                ArrayInfo::NotArray => "1".to_string(),
+                ArrayInfo::Static(n) => n.to_string(),
+                ArrayInfo::Limited(n) => format!("0..{n}"),
+                ArrayInfo::Dynamic => "*".to_string(),
+            };
+            self.relations.push(format!(
+                "    {} \"1\" *-- \"{multiplicity}\" {} : {}",
+                class_id(from),
+                class_id(&to),
+                definition.name
+            ));
+            self.referred.insert((class_id(&to), to));
+        }
+
+        for (name, members) in names.iter().zip(members) {
+            let id = class_id(name);
+            if members.is_empty() {
+                self.classes
+                    .push_str(&format!("    class {id}[\"{name}\"]\n"));
+            } else {
+                let class = format!("    class {id}[\"{name}\"] {{\n{members}    }}\n");
+                self.classes.push_str(&class);
+            }
+            self.defined.insert(id);
+        }
+
+        Ok(())
+    }
+
+    /// Render the diagram, where classes of other packages follow classes of the package.
+    pub fn render(&self) -> String {
+        let mut result = String::from("%% This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).\n");
+        result.push_str("classDiagram\n");
+        result.push_str(&self.classes);
+        for (id, name) in self.referred.iter() {
+            if !self.defined.contains(id) {
+                result.push_str(&format!("    class {id}[\"{name}\"]\n"));
+            }
+        }
+        for relation in self.relations.iter() {
+            result.push_str(relation);
+            result.push('\n');
+        }
+        result
+    }
+}
+
+/// `pkg/msg/Type` -> `pkg_msg_Type`
+fn class_id(name: &str) -> String {
+    name.replace('/', "_")
+}
+
+/// Escape characters which end labels and members of Mermaid.
+fn escape(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Diagram;
+
+    #[test]
+    fn test_diagram() {
+        let mut diagram = Diagram::default();
+        diagram
+            .add(
+                "my_pkg",
+                "msg",
+                "Example",
+                "int32 A = 1\nInner[] inners\ngeometry_msgs/Point[<=2] p # c\n",
+            )
+            .unwrap();
+        diagram.add("my_pkg", "msg", "Inner", "").unwrap();
+        diagram
+            .add("my_pkg", "srv", "Get", "---\nstring s \"x\"\n")
+            .unwrap();
+
+        let expected = r#"%% This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+classDiagram
+    class my_pkg_msg_Example["my_pkg/msg/Example"] {
+        +int32 A = 1$
+        +Inner[] inners
+        +geometry_msgs/Point[<=2] p
+    }
+    class my_pkg_msg_Inner["my_pkg/msg/Inner"]
+    class my_pkg_srv_Get_Request["my_pkg/srv/Get_Request"]
+    class my_pkg_srv_Get_Response["my_pkg/srv/Get_Response"] {
+        +string s
+    }
+    class geometry_msgs_msg_Point["geometry_msgs/msg/Point"]
+    my_pkg_msg_Example "1" *-- "*" my_pkg_msg_Inner : inners
+    my_pkg_msg_Example "1" *-- "0..2" geometry_msgs_msg_Point : p
+"#;
+        assert_eq!(diagram.render(), expected);
+    }
+}
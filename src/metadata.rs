@@ -0,0 +1,149 @@
+//! Metadata of fields annotated in the same way as IDL.
+//!
+//! ```text
+//! int32 id # @key
+//! float64 gain # @range(min=0.0, max=1.0) @default(value=0.5)
+//! ```
+//!
+//! `@key` fields are listed in `KEY_FIELDS`, which can be used by keyed topics and content filters.
+//! `@range` and `@default` of primitive fields generate `{field}_range()` and `{FIELD}_DEFAULT`,
+//! whose values are typed by the fields, e.g. `0f64` of `@range(min=0, max=10)` of `float64`.
+
+use crate::{
+    defaults::gen_primitive_value,
+    generator::gen_primitives,
+    parser::{self, ArrayInfo, Expr, TypeName, ValueType},
+};
+use std::{borrow::Cow, collections::VecDeque, error::Error};
+
+/// Generate metadata of annotated fields.
+/// Returns an error if a value of `@range` or `@default` does not match the type of the field.
+pub fn gen_metadata(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    struct_name: &str,
+    exprs: &[Expr],
+) -> Result<(), Box<dyn Error>> {
+    let mut keys = Vec::new();
+    let mut items = String::new();
+
+    for expr in exprs.iter() {
+        let Expr::Variable {
+            type_name,
+            var_name,
+            value: None | Some(ValueType::Default(_)),
+            key,
+            range,
+            default,
+            ..
+        } = expr
+        else {
+            continue;
+        };
+
+        if *key {
+            keys.push(format!("\"{var_name}\""));
+        }
+
+        // only primitive fields have ranges and defaults
+        let TypeName::Type {
+            type_name,
+            array_info: ArrayInfo::NotArray,
+        } = type_name
+        else {
+            continue;
+        };
+        let Some(ty) = gen_primitives(type_name) else {
+            continue;
+        };
+        let literal = |annotation: &str, value: &str| {
+            parser::parse_annotation_value(value)
+                .map_err(Into::into)
+                .and_then(|value| gen_primitive_value(type_name, ty, &value))
+                .map_err(|e| format!("{struct_name}::{var_name}: @{annotation}: {e}"))
+        };
+
+        let field = crate::mangle(var_name);
+        if let Some((min, max)) = range {
+            let (min, max) = (literal("range", min)?, literal("range", max)?);
+            items.push_str(&format!(
+                "
+    /// Range of `{var_name}` annotated by `@range`.
+    pub fn {var_name}_range() -> core::ops::RangeInclusive<{ty}> {{
+        {min}..={max}
+    }}
+
+    /// Check `{var_name}` is in the range annotated by `@range`.
+    pub fn {var_name}_in_range(&self) -> bool {{
+        Self::{var_name}_range().contains(&self.{field})
+    }}
+"
+            ));
+        }
+
+        if let Some(default) = default {
+            let default = literal("default", default)?;
+            let upper = var_name.to_uppercase();
+            items.push_str(&format!(
+                "
+    /// Default value of `{var_name}` annotated by `@default`.
+    pub const {upper}_DEFAULT: {ty} = {default};
+"
+            ));
+        }
+    }
+
+    if !keys.is_empty() {
+        items.insert_str(
+            0,
+            &format!(
+                "
+    /// Fields annotated by `@key`.
+    pub const KEY_FIELDS: &'static [&'static str] = &[{}];
+",
+                keys.join(", ")
+            ),
+        );
+    }
+
+    if !items.is_empty() {
+        lines.push_back(format!("\nimpl {struct_name} {{{items}}}").into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::gen_metadata;
+    use crate::parser;
+    use nom::Finish;
+    use std::collections::VecDeque;
+
+    fn gen(input: &str) -> Result<String, String> {
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let mut lines = VecDeque::new();
+        gen_metadata(&mut lines, "TestMsg", &exprs).map_err(|e| e.to_string())?;
+        Ok(lines.iter().map(|l| format!("{l}\n")).collect())
+    }
+
+    #[test]
+    fn test_typed_values() {
+        // integer literals of float fields
+        let code = gen("float64 speed # @range(min=0, max=10) @default(value=5)\n").unwrap();
+        assert!(code.contains(
+            "    pub fn speed_range() -> core::ops::RangeInclusive<f64> {\n        0f64..=10f64\n"
+        ));
+        assert!(code.contains("    pub const SPEED_DEFAULT: f64 = 5f64;\n"));
+
+        let code = gen("uint8 level # @range(min=1, max=0x10)\n").unwrap();
+        assert!(code.contains("        1u8..=16u8\n"));
+    }
+
+    #[test]
+    fn test_mismatched_values() {
+        let e = gen("uint8 level # @default(value=-1)\n").unwrap_err();
+        assert!(e.starts_with("TestMsg::level: @default: "));
+        assert!(gen("uint8 level # @range(min=0, max=256)\n").is_err());
+        assert!(gen("bool on # @default(value=1)\n").is_err());
+        assert!(gen("float32 gain # @default(value=high)\n").is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! Backend for micro-ROS.
+//!
+//! micro-ROS links `rosidl_typesupport_microxrcedds_c` instead of `rosidl_typesupport_c`,
+//! and firmware usually cannot allocate memory dynamically.
+//! So, `{Type}Pool` is generated for messages having bounded sequences or bounded strings of primitive types,
+//! and `{Type}::with_pool()` creates `{Type}Pooled`, which is a message whose bounded fields use the static memory.
+//!
+//! ```ignore
+//! static mut POOL: my_module::msg::ExamplePool = my_module::msg::ExamplePool::new();
+//! let mut msg = unsafe { my_module::msg::Example::with_pool(&mut *core::ptr::addr_of_mut!(POOL)) };
+//! assert!(msg.values_set(&[1, 2, 3]));
+//! ```
+//!
+//! The message is zero-initialized without calling `__init`, so default values are not set,
+//! and it is never finalized because `__fini` must not free the pool.
+//! Safe methods of messages, such as `assign()` of strings and `{field}_try_set()`, free and reallocate fields,
+//! so `{Type}Pooled` only dereferences to `&{Type}`,
+//! and its bounded fields are set by `{field}_set()` copying values into the pool within the capacity.
+
+use crate::{
+    generator::{gen_primitives, Backend},
+    parser::{ArrayInfo, Expr, TypeName, ValueType},
+};
+use std::{borrow::Cow, collections::VecDeque};
+
+/// Symbol of the function returning the type support of a message or a service.
+/// `kind` is `message` or `service`, and `name` is `{pkg}__(msg|srv)__{Type}`.
+pub fn type_support_symbol(backend: Backend, kind: &str, name: &str) -> String {
+    let typesupport = typesupport(backend);
+    format!("{typesupport}__get_{kind}_type_support_handle__{name}")
+}
+
+/// Type support library of the backend. `lib{pkg}__{typesupport}` defines the type supports of `pkg`.
+pub fn typesupport(backend: Backend) -> &'static str {
+    match backend {
+        Backend::SafeDrive => "rosidl_typesupport_c",
+        Backend::MicroRos => "rosidl_typesupport_microxrcedds_c",
+    }
+}
+
+/// Generate `{struct_name}Pool`, `{struct_name}Pooled` and `{struct_name}::with_pool()` if the struct has bounded fields.
+pub fn gen_static_pool(lines: &mut VecDeque<Cow<'_, str>>, struct_name: &str, exprs: &[Expr]) {
+    let mut fields = String::new();
+    let mut assigns = String::new();
+    let mut setters = String::new();
+
+    for expr in exprs.iter() {
+        let Expr::Variable {
+            type_name,
+            var_name,
+            value: None | Some(ValueType::Default(_)),
+            ..
+        } = expr
+        else {
+            continue;
+        };
+
+        // element type and capacity including the null terminator of strings
+        let (ty, capacity, is_string) = match type_name {
+            TypeName::Type {
+                type_name,
+                array_info: ArrayInfo::Limited(n),
+            } => match gen_primitives(type_name) {
+                Some(ty) => (ty, *n, false),
+                None => continue,
+            },
+            TypeName::LimitedString {
+                size,
+                array_info: ArrayInfo::NotArray,
+            } => ("u8", size + 1, true),
+            _ => continue,
+        };
+
+        let field = crate::mangle(var_name);
+        fields.push_str(&format!("    {field}: [{ty}; {capacity}],\n"));
+        assigns.push_str(&format!(
+            "
+        let raw = core::ptr::addr_of_mut!(msg.{field}) as *mut Raw<{ty}>;
+        unsafe {{
+            (*raw).data = pool.{field}.as_mut_ptr();
+            (*raw).size = 0;
+            (*raw).capacity = {capacity};
+        }}
+"
+        ));
+
+        let (arg, max, terminate) = if is_string {
+            (
+                "value: &str) -> bool {\n        let values = value.as_bytes();".to_string(),
+                capacity - 1,
+                "\n            *(*raw).data.add(values.len()) = 0;",
+            )
+        } else {
+            (format!("values: &[{ty}]) -> bool {{"), capacity, "")
+        };
+        setters.push_str(&format!(
+            "
+    /// Copy `{var_name}` into the pool.
+    /// Returns `false` and keeps the field if it is longer than {max}.
+    #[must_use = \"the field is not set if `false` is returned\"]
+    pub fn {var_name}_set(&mut self, {arg}
+        if values.len() > {max} {{
+            return false;
+        }}
+
+        {RAW}
+        let raw = core::ptr::addr_of_mut!(self.0.{field}) as *mut Raw<{ty}>;
+        unsafe {{
+            core::ptr::copy_nonoverlapping(values.as_ptr(), (*raw).data, values.len());{terminate}
+            (*raw).size = values.len();
+        }}
+        true
+    }}
+"
+        ));
+    }
+
+    if fields.is_empty() {
+        return;
+    }
+
+    lines.push_back(
+        format!(
+            "
+/// Static memory of bounded fields of `{struct_name}` for micro-ROS.
+#[repr(C)]
+pub struct {struct_name}Pool {{
+{fields}}}
+
+impl {struct_name}Pool {{
+    pub const fn new() -> Self {{
+        unsafe {{ core::mem::MaybeUninit::zeroed().assume_init() }}
+    }}
+}}
+
+/// `{struct_name}` whose bounded fields use `{struct_name}Pool`, created by `{struct_name}::with_pool()`.
+/// The message is never finalized, and bounded fields are set in place by the setters,
+/// because safe methods of `{struct_name}` free or reallocate them.
+pub struct {struct_name}Pooled(core::mem::ManuallyDrop<{struct_name}>);
+
+impl core::ops::Deref for {struct_name}Pooled {{
+    type Target = {struct_name};
+
+    fn deref(&self) -> &{struct_name} {{
+        &self.0
+    }}
+}}
+
+impl {struct_name}Pooled {{
+    /// The message which can be modified.
+    ///
+    /// # Safety
+    ///
+    /// Bounded fields must not be replaced, resized or freed,
+    /// for example by `assign()` of strings, `{{field}}_try_set()` or `read_cdr()`.
+    pub unsafe fn as_mut_unchecked(&mut self) -> &mut {struct_name} {{
+        &mut self.0
+    }}
+{setters}}}
+
+impl {struct_name} {{
+    /// Create a message whose bounded fields use `pool` instead of dynamic allocation.
+    /// The message is zero-initialized, and it is never finalized because the pool is not allocated by `__init`.
+    ///
+    /// # Safety
+    ///
+    /// The message must not be passed to C functions finalizing or reallocating its fields, such as `__fini`.
+    /// C functions writing within the capacities, such as `rcl_take()` of micro-ROS, can be used.
+    #[must_use]
+    pub unsafe fn with_pool(pool: &'static mut {struct_name}Pool) -> {struct_name}Pooled {{
+        {RAW}
+        let mut msg: Self = unsafe {{ core::mem::MaybeUninit::zeroed().assume_init() }};
+{assigns}
+        {struct_name}Pooled(core::mem::ManuallyDrop::new(msg))
+    }}
+}}
+"
+        )
+        .into(),
+    );
+}
+
+/// Struct having the same layout as sequences and strings of rosidl, which is defined in each function using it.
+const RAW: &str = "// the same layout as sequences and strings of rosidl
+        #[repr(C)]
+        struct Raw<T> {
+            data: *mut T,
+            size: usize,
+            capacity: usize,
+        }
+";
+
+#[cfg(test)]
+mod tests {
+    use super::gen_static_pool;
+    use crate::parser;
+    use nom::Finish;
+    use std::collections::VecDeque;
+
+    /// Mocks of a message having `int32[<=4] values` and `string<=8 name`, whose fields are raw sequences of rosidl.
+    const MOCKS: &str = "
+#[repr(C)]
+pub struct Raw<T> {
+    pub data: *mut T,
+    pub size: usize,
+    pub capacity: usize,
+}
+
+impl<T> Raw<T> {
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.data, self.size) }
+    }
+}
+
+#[repr(C)]
+pub struct Msg {
+    pub id: i32,
+    pub values: Raw<i32>,
+    pub name: Raw<u8>,
+}
+";
+
+    const TESTS: &str = r#"
+#[test]
+fn test_pool() {
+    static mut POOL: MsgPool = MsgPool::new();
+    let mut msg = unsafe { Msg::with_pool(&mut *std::ptr::addr_of_mut!(POOL)) };
+    assert!(msg.values.as_slice().is_empty());
+    assert_eq!(msg.values.capacity, 4);
+    assert_eq!(msg.name.capacity, 9);
+
+    // values are copied into the pool
+    assert!(msg.values_set(&[1, 2, 3]));
+    assert_eq!(msg.values.as_slice(), [1, 2, 3]);
+    assert_eq!(msg.values.data, unsafe { std::ptr::addr_of_mut!(POOL.values) } as *mut i32);
+
+    // values beyond the capacity are refused, and the field is kept
+    assert!(!msg.values_set(&[0; 5]));
+    assert_eq!(msg.values.as_slice(), [1, 2, 3]);
+    assert!(msg.values_set(&[4, 5, 6, 7]));
+    assert_eq!(msg.values.as_slice(), [4, 5, 6, 7]);
+
+    // strings are null-terminated within the capacity
+    assert!(msg.name_set("abcdefgh"));
+    assert_eq!(msg.name.as_slice(), b"abcdefgh");
+    assert_eq!(unsafe { *msg.name.data.add(8) }, 0);
+    assert!(!msg.name_set("abcdefghi"));
+    assert!(msg.name_set("xy"));
+    assert_eq!(msg.name.as_slice(), b"xy");
+    assert_eq!(unsafe { *msg.name.data.add(2) }, 0);
+
+    // fields without the pool are modified by the unsafe accessor
+    unsafe { msg.as_mut_unchecked() }.id = 10;
+    assert_eq!(msg.id, 10);
+}
+"#;
+
+    #[test]
+    fn test_static_pool() {
+        let (_, exprs) = parser::parse_msg("int32 id\nint32[<=4] values\nstring<=8 name\n")
+            .finish()
+            .unwrap();
+        let mut lines = VecDeque::new();
+        gen_static_pool(&mut lines, "Msg", &exprs);
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        crate::tests::run_generated_tests("static_pool", &format!("{MOCKS}{code}{TESTS}"));
+    }
+}
@@ -0,0 +1,70 @@
+//! Error of constructors of messages and sequences.
+//!
+//! `--fallible-api result` generates `{package}/error.rs` having `MsgError`,
+//! and `new()` of messages and `{Type}Seq<N>` return `Result<Self, MsgError>` instead of `Option<Self>`,
+//! so that applications can tell failures of allocation from violations of bounds.
+//! Errors of helpers setting fields, such as `{field}_push_checked()`, have the name of the field,
+//! so that the failing field of a large message is found immediately.
+
+/// Generate the content of `error.rs`.
+/// `std::error::Error` is implemented unless `no_std` is true.
+pub fn gen_error_module(no_std: bool) -> String {
+    let impl_error = if no_std {
+        ""
+    } else {
+        "\nimpl std::error::Error for MsgError {}\n"
+    };
+
+    format!(
+        "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+//! Error of constructors of messages and sequences.
+{ERROR_MODULE}{impl_error}"
+    )
+}
+
+const ERROR_MODULE: &str = r#"
+/// Error of `new()` of messages and sequences, and of helpers setting fields.
+/// `field` is the field failing, such as `Example::points`, if the error is of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MsgError {
+    /// The C function failed to allocate or initialize memory.
+    Allocation { field: Option<&'static str> },
+
+    /// `len` elements are requested, but the sequence can have at most `max` elements.
+    ExceedsBound {
+        field: Option<&'static str>,
+        len: usize,
+        max: usize,
+    },
+}
+
+impl MsgError {
+    /// The field failing, such as `Example::points`.
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            MsgError::Allocation { field } | MsgError::ExceedsBound { field, .. } => *field,
+        }
+    }
+}
+
+impl core::fmt::Display for MsgError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MsgError::Allocation { field: None } => {
+                write!(f, "failed to allocate a message or a sequence")
+            }
+            MsgError::Allocation { field: Some(field) } => write!(f, "failed to allocate {field}"),
+            MsgError::ExceedsBound {
+                field: None,
+                len,
+                max,
+            } => write!(f, "{len} elements exceed the bound of {max} elements"),
+            MsgError::ExceedsBound {
+                field: Some(field),
+                len,
+                max,
+            } => write!(f, "{len} elements of {field} exceed the bound of {max} elements"),
+        }
+    }
+}
+"#;
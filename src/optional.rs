@@ -0,0 +1,199 @@
+//! Optional fields emulated by presence flags or sentinel values.
+//!
+//! ```text
+//! float64 altitude # @optional
+//! bool has_altitude
+//! int32 id # @optional(flag=id_valid)
+//! bool id_valid
+//! int32 count # @optional(sentinel=-1)
+//! ```
+//!
+//! Each annotated field has `{field}_opt()` returning `Option`, which is `None` if the flag is false
+//! or the field is the sentinel, and `set_{field}_opt()` setting the field and the flag, or the sentinel for `None`.
+//! Primitives are returned by value, and the other types by reference.
+
+use crate::{
+    defaults::gen_primitive_value,
+    generator::gen_primitives,
+    parser::{ArrayInfo, Expr, Optional, TypeName, Value, ValueType},
+};
+use std::{borrow::Cow, collections::VecDeque, error::Error};
+
+/// Generate accessors of fields of `struct_name` annotated by `@optional`,
+/// where `gen_type` generates the Rust type of a field.
+pub fn gen_optional_accessors(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    struct_name: &str,
+    exprs: &[Expr],
+    mut gen_type: impl FnMut(&TypeName) -> Result<String, Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut methods = String::new();
+    for expr in exprs.iter() {
+        let Expr::Variable {
+            type_name,
+            var_name,
+            value: None | Some(ValueType::Default(_)),
+            optional: Some(optional),
+            ..
+        } = expr
+        else {
+            continue;
+        };
+
+        let field = crate::mangle(var_name);
+        let path = format!("{struct_name}::{var_name}");
+        let ty = gen_type(type_name)?;
+        let primitive = match type_name {
+            TypeName::Type {
+                type_name,
+                array_info: ArrayInfo::NotArray,
+            } => gen_primitives(type_name).map(|ty| (type_name, ty)),
+            _ => None,
+        };
+
+        match optional {
+            Optional::Flag(flag) => {
+                let flag_field = flag_field(exprs, flag)
+                    .ok_or_else(|| format!("{path}: `{flag}` of @optional is not a bool field"))?;
+                let (ret, value) = if primitive.is_some() {
+                    (ty.clone(), format!("self.{field}"))
+                } else {
+                    (format!("&{ty}"), format!("&self.{field}"))
+                };
+                methods.push_str(&format!(
+                    "
+    /// `{var_name}` if `{flag}` is true, as annotated by `@optional`.
+    pub fn {var_name}_opt(&self) -> Option<{ret}> {{
+        if self.{flag_field} {{
+            Some({value})
+        }} else {{
+            None
+        }}
+    }}
+
+    /// Set `{var_name}` to `value` and `{flag}` to true, or `{flag}` to false if `value` is `None`.
+    pub fn set_{var_name}_opt(&mut self, value: Option<{ty}>) {{
+        match value {{
+            Some(value) => {{
+                self.{field} = value;
+                self.{flag_field} = true;
+            }}
+            None => self.{flag_field} = false,
+        }}
+    }}
+"
+                ));
+            }
+            Optional::Sentinel(sentinel) => {
+                let Some((type_name, prim)) = primitive else {
+                    return Err(
+                        format!("{path}: sentinel of @optional requires a primitive type").into(),
+                    );
+                };
+                let literal = gen_primitive_value(type_name, prim, &parse_sentinel(sentinel)?)
+                    .map_err(|e| format!("{path}: sentinel of @optional: {e}"))?;
+                methods.push_str(&format!(
+                    "
+    /// `{var_name}` unless it is the sentinel `{sentinel}`, as annotated by `@optional`.
+    pub fn {var_name}_opt(&self) -> Option<{ty}> {{
+        if self.{field} == {literal} {{
+            None
+        }} else {{
+            Some(self.{field})
+        }}
+    }}
+
+    /// Set `{var_name}` to `value`, or the sentinel `{sentinel}` if `value` is `None`.
+    pub fn set_{var_name}_opt(&mut self, value: Option<{ty}>) {{
+        self.{field} = value.unwrap_or({literal});
+    }}
+"
+                ));
+            }
+        }
+    }
+
+    if !methods.is_empty() {
+        lines.push_back(format!("\nimpl {struct_name} {{{methods}}}").into());
+    }
+    Ok(())
+}
+
+/// Field of the bool `flag`, which is named in the definition, or `None` if there is no such field.
+fn flag_field<'a>(exprs: &'a [Expr], flag: &str) -> Option<Cow<'a, str>> {
+    exprs.iter().find_map(|expr| match expr {
+        Expr::Variable {
+            type_name:
+                TypeName::Type {
+                    type_name,
+                    array_info: ArrayInfo::NotArray,
+                },
+            var_name,
+            value: None | Some(ValueType::Default(_)),
+            ros_name,
+            ..
+        } if type_name == "bool" && ros_name.as_ref().unwrap_or(var_name) == flag => {
+            Some(crate::mangle(var_name))
+        }
+        _ => None,
+    })
+}
+
+/// Value of a sentinel, which is a bool or a finite number.
+fn parse_sentinel(sentinel: &str) -> Result<Value, Box<dyn Error>> {
+    let value = match sentinel {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => match (sentinel.parse(), sentinel.parse(), sentinel.parse::<f64>()) {
+            (Ok(n), _, _) => Value::Int(n),
+            (_, Ok(n), _) => Value::Uint(n),
+            (_, _, Ok(n)) if n.is_finite() => Value::Float(n),
+            _ => return Err(format!("invalid sentinel of @optional: {sentinel}").into()),
+        },
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::gen_optional_accessors;
+    use crate::{generator::gen_primitives, parser};
+    use nom::Finish;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_optional_accessors() {
+        let input = "float64 altitude # @optional\nbool has_altitude\nPoint p # @optional(flag=p_valid)\nbool p_valid\nint32 count # @optional(sentinel=-1)\n";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let gen_type = |type_name: &parser::TypeName| match type_name {
+            parser::TypeName::Type { type_name, .. } => {
+                Ok(gen_primitives(type_name).unwrap_or(type_name).to_string())
+            }
+            _ => unreachable!(),
+        };
+        let mut lines = VecDeque::new();
+        gen_optional_accessors(&mut lines, "Example", &exprs, gen_type).unwrap();
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+
+        assert!(code.contains("    pub fn altitude_opt(&self) -> Option<f64> {\n        if self.has_altitude {\n            Some(self.altitude)\n"));
+        assert!(code.contains("    pub fn p_opt(&self) -> Option<&Point> {\n        if self.p_valid {\n            Some(&self.p)\n"));
+        assert!(code.contains("    pub fn set_p_opt(&mut self, value: Option<Point>) {\n"));
+        assert!(code.contains("        if self.count == -1i32 {\n            None\n"));
+        assert!(code.contains("        self.count = value.unwrap_or(-1i32);\n"));
+
+        for input in [
+            "float64 x # @optional\n",
+            "float64 x # @optional\nint32 has_x\n",
+            "float64 x # @optional(sentinel=nan)\n",
+            "uint8 x # @optional(sentinel=256)\n",
+            "Point p # @optional(sentinel=0)\n",
+        ] {
+            let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+            let mut lines = VecDeque::new();
+            assert!(
+                gen_optional_accessors(&mut lines, "Example", &exprs, gen_type).is_err(),
+                "{input}"
+            );
+        }
+    }
+}
@@ -0,0 +1,450 @@
+//! Owned mirrors of messages, whose strings and sequences are `String` and `Vec`.
+//!
+//! `--owned` generates `ExampleOwned` for each message `Example`,
+//! so that application code constructs and inspects messages without `RosString` and sequences of safe_drive.
+//! `From<&Example>` copies a message to its mirror, and `TryFrom<&ExampleOwned>` copies it back,
+//! which fails if memory allocation fails or bounds of strings and sequences are exceeded.
+//! `builtin_interfaces/Time` and `Duration` are mirrored by `TimeOwned` and `DurationOwned` of `owned.rs` of the package.
+//! Messages having fields of types not generated by ros2msg_to_rs, such as types of common_interfaces of safe_drive,
+//! directly or by nested messages, have no mirrors.
+
+use crate::{
+    generator::{gen_primitives, FallibleApi},
+    parser::{ArrayInfo, TypeName},
+};
+use std::{borrow::Cow, collections::VecDeque};
+
+/// Field of a message mirrored by the owned type.
+pub struct OwnedField<'a> {
+    /// Name of the field in the definition.
+    pub var_name: &'a str,
+
+    pub type_name: &'a TypeName,
+
+    /// Rust type of the field, which is a sequence if the field is a sequence.
+    pub ty: Cow<'a, str>,
+
+    /// Rust type of the elements of the field.
+    pub element: Cow<'a, str>,
+}
+
+/// Kind of elements of a field.
+enum Kind<'a> {
+    Primitive(&'a str),
+    String(Option<usize>),
+
+    /// `builtin_interfaces/Time` or `Duration`, which are `sec` and `nanosec`.
+    Time(&'a str),
+    Message,
+}
+
+/// Generate `owned.rs` of a package, which has the mirrors of `builtin_interfaces/Time` and `Duration`.
+pub fn gen_owned_module() -> String {
+    let mut code = "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+//! Owned mirrors of `builtin_interfaces`, which are fields of `{Type}Owned`.
+"
+    .to_string();
+    for type_name in ["Time", "Duration"] {
+        code.push_str(&format!(
+            "
+/// Owned mirror of `builtin_interfaces/{type_name}`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct {type_name}Owned {{
+    pub sec: i32,
+    pub nanosec: u32,
+}}
+"
+        ));
+    }
+    code
+}
+
+/// Generate `{struct_name}Owned` and the conversions between `struct_name` and it.
+pub fn gen_owned(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    struct_name: &str,
+    fields: &[OwnedField<'_>],
+    no_std: bool,
+    fallible_api: FallibleApi,
+) {
+    // `String` and `Vec` are qualified because messages of the package may be named `String`
+    let alloc = if no_std { "alloc" } else { "std" };
+    let to_option = fallible_api.to_option();
+    let owned_name = format!("{struct_name}Owned");
+
+    let mut members = String::new();
+    let mut to_owned = String::new();
+    let mut from_owned = String::new();
+    for OwnedField {
+        var_name,
+        type_name,
+        ty,
+        element,
+    } in fields.iter()
+    {
+        let field = crate::mangle(var_name);
+        let path = format!("{struct_name}::{var_name}");
+        let allocation = fallible_api.allocation(&path);
+
+        let (kind, array_info) = match type_name {
+            TypeName::Type {
+                type_name,
+                array_info,
+            } => match gen_primitives(type_name) {
+                Some(primitive) => (Kind::Primitive(primitive), array_info),
+                None => (Kind::Message, array_info),
+            },
+            TypeName::ScopedType {
+                scope,
+                type_name,
+                array_info,
+            } if scope == "builtin_interfaces" => (Kind::Time(type_name), array_info),
+            TypeName::ScopedType { array_info, .. } => (Kind::Message, array_info),
+            TypeName::String(array_info) => (Kind::String(None), array_info),
+            TypeName::LimitedString { size, array_info } => (Kind::String(Some(*size)), array_info),
+        };
+
+        let owned_element = match kind {
+            Kind::Primitive(primitive) => primitive.to_string(),
+            Kind::String(_) => format!("{alloc}::string::String"),
+            Kind::Time(type_name) => format!("super::super::owned::{type_name}Owned"),
+            Kind::Message => format!("{element}Owned"),
+        };
+        let owned_ty = match array_info {
+            ArrayInfo::NotArray => owned_element.clone(),
+            ArrayInfo::Static(n) => format!("[{owned_element}; {n}]"),
+            ArrayInfo::Dynamic | ArrayInfo::Limited(_) => {
+                format!("{alloc}::vec::Vec<{owned_element}>")
+            }
+        };
+        members.push_str(&format!("    pub {field}: {owned_ty},\n"));
+
+        // expression of the owned value of `msg.{field}`
+        let expr = match (&kind, array_info) {
+            (Kind::Primitive(_), ArrayInfo::NotArray | ArrayInfo::Static(_)) => {
+                format!("msg.{field}")
+            }
+            (Kind::Primitive(_), _) => format!("msg.{field}.as_slice().to_vec()"),
+            (Kind::String(_), ArrayInfo::NotArray) => format!("msg.{field}.get_string()"),
+            (Kind::String(_), ArrayInfo::Static(_)) => {
+                format!("msg.{field}.each_ref().map(|v| v.get_string())")
+            }
+            (Kind::String(_), _) => {
+                format!("msg.{field}.as_slice().iter().map(|v| v.get_string()).collect()")
+            }
+            (Kind::Time(_), ArrayInfo::NotArray) => {
+                format!("{owned_element} {{ sec: msg.{field}.sec, nanosec: msg.{field}.nanosec }}")
+            }
+            (Kind::Time(_), ArrayInfo::Static(_)) => format!(
+                "msg.{field}.each_ref().map(|v| {owned_element} {{ sec: v.sec, nanosec: v.nanosec }})"
+            ),
+            (Kind::Time(_), _) => format!(
+                "msg.{field}.as_slice().iter().map(|v| {owned_element} {{ sec: v.sec, nanosec: v.nanosec }}).collect()"
+            ),
+            (Kind::Message, ArrayInfo::NotArray) => format!("(&msg.{field}).into()"),
+            (Kind::Message, ArrayInfo::Static(_)) => {
+                format!("msg.{field}.each_ref().map(Into::into)")
+            }
+            (Kind::Message, _) => {
+                format!("msg.{field}.as_slice().iter().map(Into::into).collect()")
+            }
+        };
+        to_owned.push_str(&format!("            {field}: {expr},\n"));
+
+        // statement copying `src` to `dst`, which are references to elements of `owned.{field}` and `out.{field}`
+        let copy = match kind {
+            // primitives are copied as a whole
+            Kind::Primitive(_) => String::new(),
+            Kind::String(size) => {
+                let check = size.map_or(String::new(), |size| {
+                    format!(
+                        "if src.len() > {size} {{
+                return Err({});
+            }}
+            ",
+                        exceeds_string_bound(fallible_api, &path, "src.len()", size)
+                    )
+                });
+                format!(
+                    "{check}if !dst.assign(src) {{
+                return Err({allocation});
+            }}"
+                )
+            }
+            Kind::Time(_) => "dst.sec = src.sec;
+            dst.nanosec = src.nanosec;"
+                .to_string(),
+            Kind::Message => "*dst = src.try_into()?;".to_string(),
+        };
+
+        let statement = match (&kind, array_info) {
+            (Kind::Primitive(_), ArrayInfo::NotArray | ArrayInfo::Static(_)) => {
+                format!("out.{field} = owned.{field};")
+            }
+            (Kind::String(size), ArrayInfo::NotArray) => {
+                let check = size.map_or(String::new(), |size| {
+                    format!(
+                        "if owned.{field}.len() > {size} {{
+            return Err({});
+        }}
+        ",
+                        exceeds_string_bound(
+                            fallible_api,
+                            &path,
+                            &format!("owned.{field}.len()"),
+                            size
+                        )
+                    )
+                });
+                format!(
+                    "{check}if !out.{field}.assign(&owned.{field}) {{
+            return Err({allocation});
+        }}"
+                )
+            }
+            (Kind::Time(_), ArrayInfo::NotArray) => {
+                format!(
+                    "out.{field}.sec = owned.{field}.sec;
+        out.{field}.nanosec = owned.{field}.nanosec;"
+                )
+            }
+            (Kind::Message, ArrayInfo::NotArray) => {
+                format!("out.{field} = (&owned.{field}).try_into()?;")
+            }
+            (_, ArrayInfo::Static(_)) => {
+                format!(
+                    "for (dst, src) in out.{field}.iter_mut().zip(owned.{field}.iter()) {{
+            {copy}
+        }}"
+                )
+            }
+            (Kind::Primitive(_), ArrayInfo::Dynamic | ArrayInfo::Limited(_)) => {
+                let check = gen_bound_check(fallible_api, &path, &field, array_info);
+                format!(
+                    "{check}out.{field} = <{ty}>::new(owned.{field}.len()).ok_or({allocation})?;
+        out.{field}.as_slice_mut().copy_from_slice(&owned.{field});"
+                )
+            }
+            (_, ArrayInfo::Dynamic | ArrayInfo::Limited(_)) => {
+                let check = gen_bound_check(fallible_api, &path, &field, array_info);
+                let to_option = if matches!(kind, Kind::Message) {
+                    to_option
+                } else {
+                    ""
+                };
+                format!(
+                    "{check}out.{field} = <{ty}>::new(owned.{field}.len()){to_option}.ok_or({allocation})?;
+        for (dst, src) in out.{field}.as_slice_mut().iter_mut().zip(owned.{field}.iter()) {{
+            {copy}
+        }}"
+                )
+            }
+        };
+        from_owned.push_str(&format!("        {statement}\n"));
+    }
+
+    let error = fallible_api.field_error();
+    let new_error = match fallible_api {
+        FallibleApi::Option => format!(".ok_or(\"failed to allocate {struct_name}\")"),
+        FallibleApi::Result => String::new(),
+    };
+    let (msg, owned, mutability) = if fields.is_empty() {
+        ("_msg", "_owned", "")
+    } else {
+        ("msg", "owned", "mut ")
+    };
+
+    lines.push_back(
+        format!(
+            "
+/// Owned mirror of [`{struct_name}`], whose strings and sequences are `String` and `Vec`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct {owned_name} {{
+{members}}}
+
+impl Default for {owned_name} {{
+    /// Mirror of `{struct_name}::default()`, which has the default values of the definition.
+    fn default() -> Self {{
+        Self::from(&{struct_name}::default())
+    }}
+}}
+
+impl From<&{struct_name}> for {owned_name} {{
+    fn from({msg}: &{struct_name}) -> Self {{
+        Self {{
+{to_owned}        }}
+    }}
+}}
+
+impl TryFrom<&{owned_name}> for {struct_name} {{
+    type Error = {error};
+
+    /// Copy `owned` to a new message, which fails if memory allocation fails or bounds are exceeded.
+    fn try_from({owned}: &{owned_name}) -> Result<Self, Self::Error> {{
+        let {mutability}out = Self::new(){new_error}?;
+{from_owned}        Ok(out)
+    }}
+}}"
+        )
+        .into(),
+    );
+}
+
+/// Error of the string `path`, which cannot have `len` bytes but `max` bytes.
+fn exceeds_string_bound(fallible_api: FallibleApi, path: &str, len: &str, max: usize) -> String {
+    match fallible_api {
+        FallibleApi::Option => format!("\"{path} cannot have more than {max} bytes\""),
+        FallibleApi::Result => fallible_api.exceeds_bound(path, len, max),
+    }
+}
+
+/// Check that `owned.{field}` does not exceed the bound of a limited sequence.
+fn gen_bound_check(
+    fallible_api: FallibleApi,
+    path: &str,
+    field: &str,
+    array_info: &ArrayInfo,
+) -> String {
+    let ArrayInfo::Limited(n) = array_info else {
+        return String::new();
+    };
+    format!(
+        "if owned.{field}.len() > {n} {{
+            return Err({});
+        }}
+        ",
+        fallible_api.exceeds_bound(path, &format!("owned.{field}.len()"), *n)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gen_owned, gen_owned_module, OwnedField};
+    use crate::{
+        generator::FallibleApi,
+        parser::{self, Expr},
+    };
+    use nom::Finish;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_owned() {
+        let input = "float64 x\nint32[] xs\nuint8[2] pair\nstring<=4 name\nstring[] tags\nPoint p\nPoint[<=2] ps\n";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let types = [
+            ("f64", "f64"),
+            ("I32Seq<0>", "i32"),
+            ("[u8; 2]", "u8"),
+            ("RosString<4>", "RosString<4>"),
+            ("RosStringSeq<0, 0>", "RosString<0>"),
+            ("Point", "Point"),
+            ("PointSeq<2>", "Point"),
+        ];
+        let fields: Vec<OwnedField> = exprs
+            .iter()
+            .zip(types)
+            .map(|(expr, (ty, element))| {
+                let Expr::Variable {
+                    type_name,
+                    var_name,
+                    ..
+                } = expr
+                else {
+                    panic!();
+                };
+                OwnedField {
+                    var_name,
+                    type_name,
+                    ty: ty.into(),
+                    element: element.into(),
+                }
+            })
+            .collect();
+
+        let mut lines = VecDeque::new();
+        gen_owned(&mut lines, "Example", &fields, false, FallibleApi::Option);
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains(
+            "pub struct ExampleOwned {
+    pub x: f64,
+    pub xs: std::vec::Vec<i32>,
+    pub pair: [u8; 2],
+    pub name: std::string::String,
+    pub tags: std::vec::Vec<std::string::String>,
+    pub p: PointOwned,
+    pub ps: std::vec::Vec<PointOwned>,
+}"
+        ));
+        assert!(code.contains("            xs: msg.xs.as_slice().to_vec(),\n"));
+        assert!(code.contains("            p: (&msg.p).into(),\n"));
+        assert!(code.contains(
+            "impl TryFrom<&ExampleOwned> for Example {\n    type Error = &'static str;\n"
+        ));
+        assert!(code.contains(
+            "        let mut out = Self::new().ok_or(\"failed to allocate Example\")?;\n"
+        ));
+        assert!(code.contains("        if owned.name.len() > 4 {\n            return Err(\"Example::name cannot have more than 4 bytes\");\n"));
+        assert!(code.contains("        out.p = (&owned.p).try_into()?;\n"));
+        assert!(code.contains(
+            "        out.ps = <PointSeq<2>>::new(owned.ps.len()).ok_or(\"failed to allocate Example::ps\")?;\n"
+        ));
+    }
+
+    #[test]
+    fn test_owned_time() {
+        let input = "builtin_interfaces/Time stamp\nbuiltin_interfaces/Duration[<=2] ds\n";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let types = [
+            (
+                "builtin_interfaces::UnsafeTime",
+                "builtin_interfaces::UnsafeTime",
+            ),
+            (
+                "builtin_interfaces::UnsafeDurationSeq<2>",
+                "builtin_interfaces::UnsafeDuration",
+            ),
+        ];
+        let fields: Vec<OwnedField> = exprs
+            .iter()
+            .zip(types)
+            .map(|(expr, (ty, element))| {
+                let Expr::Variable {
+                    type_name,
+                    var_name,
+                    ..
+                } = expr
+                else {
+                    panic!();
+                };
+                OwnedField {
+                    var_name,
+                    type_name,
+                    ty: ty.into(),
+                    element: element.into(),
+                }
+            })
+            .collect();
+
+        let mut lines = VecDeque::new();
+        gen_owned(&mut lines, "Stamped", &fields, false, FallibleApi::Option);
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        assert!(code.contains(
+            "    pub stamp: super::super::owned::TimeOwned,
+    pub ds: std::vec::Vec<super::super::owned::DurationOwned>,
+"
+        ));
+        assert!(code.contains("            stamp: super::super::owned::TimeOwned { sec: msg.stamp.sec, nanosec: msg.stamp.nanosec },\n"));
+        assert!(code.contains("        out.stamp.sec = owned.stamp.sec;\n        out.stamp.nanosec = owned.stamp.nanosec;\n"));
+        assert!(code.contains(
+            "        out.ds = <builtin_interfaces::UnsafeDurationSeq<2>>::new(owned.ds.len()).ok_or(\"failed to allocate Stamped::ds\")?;\n"
+        ));
+        assert!(code
+            .contains("            dst.sec = src.sec;\n            dst.nanosec = src.nanosec;\n"));
+
+        let module = gen_owned_module();
+        assert!(
+            module.contains("pub struct TimeOwned {\n    pub sec: i32,\n    pub nanosec: u32,\n}")
+        );
+        assert!(module.contains("pub struct DurationOwned {"));
+    }
+}
@@ -22,8 +22,9 @@
 //! $ID = Regex((_|[a..zA..Z]+)([a..zA..Z0..9]|_)*)
 //! $CapitalID = Regex((_|[A..Z]+)([A..Z0..9]|_)*)
 //!
-//! $Value = $Bool | $Num | $Array | $String | $RawString
+//! $Value = $Bool | $Hex | $Num | $Array | $String | $RawString
 //! $Bool = true | false
+//! $Hex = Regex(0(x|X)[0..9a..fA..F]+)
 //! $Num = Regex(-?[0..9]+(.[0..9]+)?)
 //! $String = 'characters' | "characters"
 //! $RawString = characters
@@ -31,28 +32,37 @@
 //! $Array = [ $Elements ]
 //! $Elements = $Value | $Value , $Elements
 //! ```
+//!
+//! # Limits
+//!
+//! Lengths of lines, fields of a message, elements of an array and nesting of arrays are limited by `Limits`,
+//! so that pathological or corrupted inputs fail fast with clear errors.
+//! `generate_workspace()` applies the limits of its arguments while parsing.
 
+use crate::context;
 use nom::{
     branch::alt,
     bytes::complete::tag,
     character::{
         self,
         complete::{
-            alpha1, anychar, line_ending, not_line_ending, one_of, satisfy, space0, space1,
+            alpha1, anychar, hex_digit1, line_ending, not_line_ending, one_of, satisfy, space0,
+            space1,
         },
-        is_alphanumeric,
     },
-    combinator::peek,
-    error::VerboseError,
-    multi::{many0, many1, separated_list1},
+    combinator::{not, peek},
+    error::{VerboseError, VerboseErrorKind},
+    multi::{many0, many1},
     number,
     sequence::{delimited, preceded},
     IResult,
 };
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display};
 
 type PResult<'a, OUT> = IResult<&'a str, OUT, VerboseError<&'a str>>;
 
+// Variable is the only variant kept after parsing, so boxing it saves nothing.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum Expr {
     Variable {
@@ -60,12 +70,45 @@ pub enum Expr {
         var_name: String,
         value: Option<ValueType>,
         comment: Option<String>,
+
+        /// Unit annotated in the comment, e.g. `m/s` of `float64 speed # [m/s]`.
+        unit: Option<String>,
+
+        /// Reason annotated by `# @deprecated reason`. The reason can be empty.
+        deprecated: Option<String>,
+
+        /// Name in the .msg or .srv file if the field is renamed by `# @rust_name new_name`.
+        /// `var_name` is the new name in this case.
+        ros_name: Option<String>,
+
+        /// Annotated by `# @key` as a key of the topic.
+        key: bool,
+
+        /// Minimum and maximum annotated by `# @range(min=0, max=10)`.
+        range: Option<(String, String)>,
+
+        /// Default value annotated by `# @default(value=5)`.
+        default: Option<String>,
+
+        /// Presence of the field annotated by `# @optional`.
+        optional: Option<Optional>,
     },
     Empty, // comment or empty line
     Comment,
     Eof,
 }
 
+/// Presence of a field emulated by another field or a sentinel value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Optional {
+    /// The field is present if the bool field is true,
+    /// which is `has_{field}` of `@optional` or `valid` of `@optional(flag=valid)`.
+    Flag(String),
+
+    /// The field is absent if it is the value of `@optional(sentinel=-1)`.
+    Sentinel(String),
+}
+
 #[derive(Debug)]
 pub enum ValueType {
     Const(Value),
@@ -75,6 +118,9 @@ pub enum ValueType {
 #[derive(Debug)]
 pub enum Value {
     Bool(bool),
+
+    /// Characters of a string, whose escapes are resolved by the parser.
+    /// `Display` renders it as an escaped byte string literal with the null terminator.
     String(String),
     Float(f64),
     Uint(u64),
@@ -88,14 +134,14 @@ impl Display for Value {
             Value::Int(n) => write!(f, "{n}"),
             Value::Uint(n) => write!(f, "{n}"),
             Value::Float(n) => write!(f, "{n}"),
-            Value::String(n) => write!(f, "b\"{n}\\0\""),
+            Value::String(n) => write!(f, "b\"{}\\0\"", n.as_bytes().escape_ascii()),
             Value::Bool(n) => write!(f, "{n}"),
             Value::Array(n) => write!(f, "{:?}", n),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum TypeName {
     Type {
         type_name: String,
@@ -113,7 +159,7 @@ pub enum TypeName {
     String(ArrayInfo),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ArrayInfo {
     NotArray,
     Dynamic,
@@ -121,6 +167,42 @@ pub enum ArrayInfo {
     Limited(usize),
 }
 
+/// Limits of definitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum bytes of a line.
+    pub max_line_length: usize,
+
+    /// Maximum fields and constants of a message, a request or a response.
+    pub max_fields: usize,
+
+    /// Maximum elements of an array literal.
+    pub max_array_elements: usize,
+
+    /// Maximum nesting of array literals.
+    pub max_nesting: usize,
+}
+
+impl Limits {
+    pub const DEFAULT: Limits = Limits {
+        max_line_length: 4096,
+        max_fields: 4096,
+        max_array_elements: 65536,
+        max_nesting: 8,
+    };
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Limits of the current generation, or the default ones outside `generate_workspace()`.
+pub fn limits() -> Limits {
+    context::with(|c| c.limits)
+}
+
 /// Parse .msg file.
 ///
 /// # Grammar
@@ -128,64 +210,175 @@ pub enum ArrayInfo {
 /// ```text
 /// $Msg = $Expr $Expr | $Expr
 /// ```
-pub fn parse_msg(mut input: &str) -> PResult<Vec<Expr>> {
+pub fn parse_msg(mut input: &str) -> PResult<'_, Vec<Expr>> {
+    let limits = limits();
     let mut result = Vec::new();
     loop {
         if input.is_empty() {
             break;
         }
 
+        let line = input.lines().next().unwrap_or_default();
+        if line.len() > limits.max_line_length {
+            return Err(failure(input, "line is too long, see --max-line-length"));
+        }
+
+        // separators are split by parse_srv, so they are misplaced if found here
+        if is_separator(line) {
+            let context = match input.lines().filter(|line| is_separator(line)).count() {
+                1 => "found 1 separator, .msg must not have any; did you mean to create a .srv file?",
+                2 => "found 2 separators, .msg must not have any; did you mean to create an .action file?",
+                _ => "found separators, .msg must not have any",
+            };
+            return Err(failure(input, context));
+        }
+        if is_malformed_separator(line) {
+            return Err(failure(input, MALFORMED_SEPARATOR));
+        }
+
         let (next, expr) = parse_expr(input)?;
-        input = next;
 
         if let Expr::Variable { .. } = &expr {
+            if result.len() == limits.max_fields {
+                return Err(failure(input, "too many fields, see --max-fields"));
+            }
             result.push(expr);
         }
+        input = next;
     }
 
     Ok((input, result))
 }
 
-pub fn parse_srv(mut input: &str) -> PResult<(Vec<Expr>, Vec<Expr>)> {
-    // parse request
-    let mut request = Vec::new();
-    loop {
-        if peek_tag("---", input).is_ok() {
-            break;
-        }
+/// Parse .srv file.
+///
+/// # Grammar
+///
+/// ```text
+/// $Srv = $Msg $Separator $Msg
+/// $Separator = --- $Comment $End | --- $End
+/// ```
+///
+/// Whitespaces around `---` are allowed.
+pub fn parse_srv(input: &str) -> PResult<'_, (Vec<Expr>, Vec<Expr>)> {
+    let separators = find_separators(input);
+
+    let [(start, end)] = separators[..] else {
+        // point the second separator if duplicated
+        let Some((second, _)) = separators.get(1) else {
+            find_malformed_separator(input)?;
+            return Err(failure(
+                input,
+                "found no separator, .srv requires exactly 1 '---' between the request and the response",
+            ));
+        };
 
-        let (next, expr) = parse_expr(input)?;
-        input = next;
+        let context = if separators.len() == 2 {
+            "found 2 separators, .srv requires exactly 1; did you mean to create an .action file?"
+        } else {
+            "found more than 2 separators, .srv requires exactly 1"
+        };
+        return Err(failure(&input[*second..], context));
+    };
 
-        if let Expr::Variable { .. } = &expr {
-            request.push(expr);
-        }
-    }
+    let (_, request) = parse_msg(&input[..start])?;
+    let (_, response) = parse_msg(&input[end..])?;
 
-    let (mut input, _) = tag("---")(input)?;
+    Ok(("", (request, response)))
+}
 
-    // parse response
-    let mut response = Vec::new();
-    loop {
-        if input.is_empty() {
-            break;
-        }
+/// Parse .action file.
+///
+/// # Grammar
+///
+/// ```text
+/// $Action = $Msg $Separator $Msg $Separator $Msg
+/// ```
+///
+/// The sections are the goal, the result and the feedback.
+pub fn parse_action(input: &str) -> PResult<'_, (Vec<Expr>, Vec<Expr>, Vec<Expr>)> {
+    let separators = find_separators(input);
+
+    let [(start1, end1), (start2, end2)] = separators[..] else {
+        // point the third separator if more than 2
+        let Some((third, _)) = separators.get(2) else {
+            find_malformed_separator(input)?;
+            let context = if separators.is_empty() {
+                "found no separator, .action requires exactly 2 '---' between the goal, the result and the feedback"
+            } else {
+                "found 1 separator, .action requires exactly 2 '---' between the goal, the result and the feedback"
+            };
+            return Err(failure(input, context));
+        };
+        return Err(failure(
+            &input[*third..],
+            "found more than 2 separators, .action requires exactly 2",
+        ));
+    };
 
-        let (next, expr) = parse_expr(input)?;
-        input = next;
+    let (_, goal) = parse_msg(&input[..start1])?;
+    let (_, result) = parse_msg(&input[end1..start2])?;
+    let (_, feedback) = parse_msg(&input[end2..])?;
 
-        if let Expr::Variable { .. } = &expr {
-            response.push(expr);
+    Ok(("", (goal, result, feedback)))
+}
+
+/// Offsets of the start and the end of lines of `---`.
+fn find_separators(input: &str) -> Vec<(usize, usize)> {
+    let mut separators = Vec::new();
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        if is_separator(line) {
+            separators.push((offset, offset + line.len()));
         }
+        offset += line.len();
     }
+    separators
+}
 
-    Ok(("", (request, response)))
+/// Fail at `--` or `----`, which is likely meant to be the separator.
+fn find_malformed_separator(input: &str) -> PResult<'_, ()> {
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        if is_malformed_separator(line) {
+            return Err(failure(&input[offset..], MALFORMED_SEPARATOR));
+        }
+        offset += line.len();
+    }
+    Ok((input, ()))
+}
+
+pub fn is_separator(line: &str) -> bool {
+    let line = match line.find('#') {
+        Some(n) => &line[..n],
+        None => line,
+    };
+    line.trim() == "---"
+}
+
+const MALFORMED_SEPARATOR: &str = "malformed separator, it must be '---' alone on its line";
+
+/// Lines like `--`, `----` or `--- x`, which are likely meant to be `---`.
+fn is_malformed_separator(line: &str) -> bool {
+    let line = match line.find('#') {
+        Some(n) => &line[..n],
+        None => line,
+    };
+    let line = line.trim();
+    line.starts_with("--") && line != "---"
+}
+
+/// Failure at `at`, which is not recovered by `alt`.
+fn failure<'a>(at: &'a str, context: &'static str) -> nom::Err<VerboseError<&'a str>> {
+    nom::Err::Failure(VerboseError {
+        errors: vec![(at, VerboseErrorKind::Context(context))],
+    })
 }
 
 /// ```text
 /// $Expr = $Empty | $Comment | $VarDef
 /// ```
-fn parse_expr(input: &str) -> PResult<Expr> {
+fn parse_expr(input: &str) -> PResult<'_, Expr> {
     let (input, _) = space0(input)?;
     alt((parse_empty, parse_comment, parse_variable))(input)
 }
@@ -194,7 +387,7 @@ fn parse_expr(input: &str) -> PResult<Expr> {
 /// $VarDef = $Variable $Comment $End | $Variable $End
 /// $Variable = $TypeName $CapitalID = $Value | $TypeName $ID | $TypeName $ID $Value
 /// ```
-fn parse_variable(input: &str) -> PResult<Expr> {
+pub fn parse_variable(input: &str) -> PResult<'_, Expr> {
     let (input, type_name) = parse_typename(input)?;
 
     // skip whitespaces
@@ -223,36 +416,229 @@ fn parse_variable(input: &str) -> PResult<Expr> {
         input
     };
 
-    Ok((
-        input,
-        Expr::Variable {
-            type_name,
-            var_name,
-            value,
-            comment,
-        },
-    ))
+    Ok((input, variable(type_name, var_name, value, comment)))
+}
+
+/// Variable annotated by `comment`, which is the text after `#` of a .msg file.
+pub fn variable(
+    type_name: TypeName,
+    var_name: String,
+    value: Option<ValueType>,
+    comment: Option<String>,
+) -> Expr {
+    let unit = comment.as_deref().and_then(parse_unit);
+    let deprecated = comment.as_deref().and_then(parse_deprecated);
+    let key = comment.as_deref().is_some_and(parse_key);
+    let range = comment.as_deref().and_then(parse_range);
+    let default = comment
+        .as_deref()
+        .and_then(|c| parse_idl_annotation(c, "default"))
+        .and_then(|args| args.get("value").cloned());
+    let optional = comment
+        .as_deref()
+        .and_then(|c| parse_optional(c, &var_name));
+
+    // the layout of the C struct is kept because only the name is changed
+    let (var_name, ros_name) = match comment.as_deref().and_then(parse_rust_name) {
+        Some(rust_name) if rust_name != var_name => (rust_name, Some(var_name)),
+        _ => (var_name, None),
+    };
+
+    Expr::Variable {
+        type_name,
+        var_name,
+        value,
+        comment,
+        unit,
+        deprecated,
+        ros_name,
+        key,
+        range,
+        default,
+        optional,
+    }
+}
+
+/// Check `@key` is in a comment.
+fn parse_key(comment: &str) -> bool {
+    has_annotation(comment, "@key")
 }
 
-fn parse_mutable_var(input: &str) -> PResult<(String, Option<ValueType>)> {
+/// Check `annotation` is in a comment as a word, not as a prefix like `@keyframe`.
+fn has_annotation(comment: &str, annotation: &str) -> bool {
+    comment.match_indices(annotation).any(|(n, _)| {
+        !comment[n + annotation.len()..]
+            .starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Extract the presence of `var_name` from `@optional`, `@optional(flag=valid)` or `@optional(sentinel=-1)`.
+fn parse_optional(comment: &str, var_name: &str) -> Option<Optional> {
+    if let Some(args) = parse_idl_annotation(comment, "optional") {
+        return match (args.get("flag"), args.get("sentinel")) {
+            (Some(flag), None) => Some(Optional::Flag(flag.clone())),
+            (None, Some(sentinel)) => Some(Optional::Sentinel(sentinel.clone())),
+            _ => None,
+        };
+    }
+    has_annotation(comment, "@optional").then(|| Optional::Flag(format!("has_{var_name}")))
+}
+
+/// Extract the minimum and maximum of `@range(min=0, max=10)` in a comment.
+fn parse_range(comment: &str) -> Option<(String, String)> {
+    let args = parse_idl_annotation(comment, "range")?;
+    Some((args.get("min")?.clone(), args.get("max")?.clone()))
+}
+
+/// Extract the arguments of an annotation in the same syntax as IDL,
+/// e.g. `{"min": "0", "max": "10"}` of `@range(min=0, max=10)`.
+fn parse_idl_annotation(comment: &str, name: &str) -> Option<BTreeMap<String, String>> {
+    let pattern = format!("@{name}(");
+    let start = comment.find(&pattern)? + pattern.len();
+    let len = comment[start..].find(')')?;
+
+    let mut args = BTreeMap::new();
+    for arg in comment[start..start + len].split(',') {
+        let (k, v) = arg.split_once('=')?;
+        args.insert(k.trim().to_string(), v.trim().to_string());
+    }
+
+    Some(args)
+}
+
+/// Extract the name of the Rust field from a comment.
+///
+/// ```text
+/// int32 Type # @rust_name type_id
+/// ```
+fn parse_rust_name(comment: &str) -> Option<String> {
+    let start = comment.find("@rust_name")?;
+    let name = comment[start + "@rust_name".len()..]
+        .split_whitespace()
+        .next()?;
+
+    let mut chars = name.chars();
+    let head = chars.next()?;
+    if (head.is_ascii_alphabetic() || head == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Some(name.to_string())
+    } else {
+        None
+    }
+}
+
+/// Extract the reason of deprecation from a comment.
+///
+/// ```text
+/// int32 old_field # @deprecated use new_field instead
+/// ```
+fn parse_deprecated(comment: &str) -> Option<String> {
+    let start = comment.find("@deprecated")?;
+    Some(comment[start + "@deprecated".len()..].trim().to_string())
+}
+
+/// Extract the reason of deprecation from the comments at the top of a file.
+/// The comments before the first definition are examined.
+///
+/// ```text
+/// # @deprecated use NewType instead
+/// int32 data
+/// ```
+pub fn parse_file_deprecated(input: &str) -> Option<String> {
+    file_comments(input).find_map(parse_deprecated)
+}
+
+/// Extract the topic of a message from the comments at the top of a file.
+/// The topic is the word after `@topic`.
+///
+/// ```text
+/// # @topic /sensor/imu
+/// float64 x
+/// ```
+pub fn parse_file_topic(input: &str) -> Option<String> {
+    file_comments(input).find_map(|comment| {
+        let (_, rest) = comment.split_once("@topic")?;
+        if !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        rest.split_whitespace()
+            .next()
+            .map(|topic| topic.to_string())
+    })
+}
+
+/// Extract the arguments of `@qos(...)` in the comments at the top of a file.
+///
+/// ```text
+/// # @qos(reliability=best_effort, depth=5)
+/// float64 x
+/// ```
+pub fn parse_file_qos(input: &str) -> Option<BTreeMap<String, String>> {
+    file_comments(input).find_map(|comment| parse_idl_annotation(comment, "qos"))
+}
+
+/// Comments before the first definition of a file, which follow `#`.
+fn file_comments(input: &str) -> impl Iterator<Item = &str> {
+    input
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map_while(|line| line.strip_prefix('#'))
+}
+
+/// Extract a unit from a comment.
+/// The unit is `[...]` at the end of the comment, followed only by annotations, or `@unit(value=...)` as IDL.
+/// Brackets containing `,` are not units but lists like `[x, y, z]`.
+///
+/// ```text
+/// float64 speed # [m/s]
+/// float64 yaw # yaw angle [rad] @range(min=-3.15, max=3.15)
+/// float64 height # @unit(value="m")
+/// ```
+fn parse_unit(comment: &str) -> Option<String> {
+    let unit = if let Some(args) = parse_idl_annotation(comment, "unit") {
+        args.get("value")?.trim_matches('"').trim().to_string()
+    } else {
+        let text = match comment.find('@') {
+            Some(n) => &comment[..n],
+            None => comment,
+        };
+        let text = text.trim_end().strip_suffix(']')?;
+        let start = text.rfind('[')?;
+        text[start + 1..].trim().to_string()
+    };
+
+    if unit.is_empty() || unit.contains([',', '[', ']']) {
+        None
+    } else {
+        Some(unit)
+    }
+}
+
+pub fn parse_mutable_var(input: &str) -> PResult<'_, (String, Option<ValueType>)> {
     // parse variable name
     // $ID
     let (input, var_name) = parse_identifier(input)?;
 
     // having default value?
-    fn get_value(input: &str) -> PResult<Value> {
+    fn get_value(input: &str) -> PResult<'_, Value> {
         let (input, _) = space1(input)?;
+
+        // a comment is not a default value
+        not(tag("#"))(input)?;
+
         parse_value(input)
     }
 
-    if let Ok((input, val)) = get_value(input) {
-        Ok((input, (var_name, Some(ValueType::Default(val))))) // having default value
-    } else {
-        Ok((input, (var_name, None))) // no default value
+    match get_value(input) {
+        Ok((input, val)) => Ok((input, (var_name, Some(ValueType::Default(val))))), // having default value
+        Err(e @ nom::Err::Failure(_)) => Err(e), // exceeding limits
+        Err(_) => Ok((input, (var_name, None))), // no default value
     }
 }
 
-fn parse_immutable_var(input: &str) -> PResult<(String, Option<ValueType>)> {
+pub fn parse_immutable_var(input: &str) -> PResult<'_, (String, Option<ValueType>)> {
     // parse variable name
     // $CapitalID
     let (input, var_name) = parse_captal_identifier(input)?;
@@ -280,7 +666,7 @@ fn parse_immutable_var(input: &str) -> PResult<(String, Option<ValueType>)> {
 ///     $ID $ArrayInfo |
 ///     $ID
 /// ```
-fn parse_typename(input: &str) -> PResult<TypeName> {
+pub fn parse_typename(input: &str) -> PResult<'_, TypeName> {
     // parse type name
     let (input, scope) = parse_identifier(input)?;
 
@@ -317,7 +703,7 @@ fn parse_typename(input: &str) -> PResult<TypeName> {
     }
 }
 
-fn parse_string_type(input: &str) -> PResult<TypeName> {
+fn parse_string_type(input: &str) -> PResult<'_, TypeName> {
     if peek_tag("<=", input).is_ok() {
         let (input, _) = tag("<=")(input)?;
         let (input, size) = character::complete::u64(input)?;
@@ -338,12 +724,12 @@ fn parse_string_type(input: &str) -> PResult<TypeName> {
 /// ```text
 /// $ID = Regex((_|[a..zA..Z]+)([a..zA..Z0..9]|_)*)
 /// ```
-fn parse_identifier(input: &str) -> PResult<String> {
+fn parse_identifier(input: &str) -> PResult<'_, String> {
     // (_|[a..zA..Z]+)
     let (input, head) = alt((tag("_"), alpha1))(input)?;
 
     // [a..zA..Z0..9_]*
-    let (input, tail) = many0(satisfy(|c| is_alphanumeric(c as u8) || c == '_'))(input)?;
+    let (input, tail) = many0(satisfy(|c| c.is_ascii_alphanumeric() || c == '_'))(input)?;
 
     let tail: String = tail.iter().collect();
     Ok((input, head.to_string() + &tail))
@@ -352,12 +738,14 @@ fn parse_identifier(input: &str) -> PResult<String> {
 /// ```text
 /// $ID = Regex((_|[A..Z]+)([A..Z0..9]|_)*)
 /// ```
-fn parse_captal_identifier(input: &str) -> PResult<String> {
+fn parse_captal_identifier(input: &str) -> PResult<'_, String> {
     // (_|[A..Z]+)
-    let (input, head) = satisfy(|c| 'A' <= c && c <= 'Z' || c == '_')(input)?;
+    let (input, head) = satisfy(|c| c.is_ascii_uppercase() || c == '_')(input)?;
 
     // ([A..Z0..9]|_)*
-    let (input, tail) = many0(satisfy(|c| 'A' <= c && c <= 'Z' || c == '_'))(input)?;
+    let (input, tail) = many0(satisfy(|c| {
+        c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_'
+    }))(input)?;
 
     let tail: String = tail.iter().collect();
     Ok((input, format!("{head}{tail}")))
@@ -366,7 +754,7 @@ fn parse_captal_identifier(input: &str) -> PResult<String> {
 /// ```text
 /// $Comment = Regex(#.*) $End
 /// ```
-fn parse_comment(input: &str) -> PResult<Expr> {
+fn parse_comment(input: &str) -> PResult<'_, Expr> {
     let (input, _) = tag("#")(input)?;
     let (input, _) = not_line_ending(input)?;
 
@@ -382,7 +770,7 @@ fn parse_comment(input: &str) -> PResult<Expr> {
 }
 
 /// empty line or EOF
-fn parse_empty(input: &str) -> PResult<Expr> {
+fn parse_empty(input: &str) -> PResult<'_, Expr> {
     if input.is_empty() {
         Ok((input, Expr::Eof))
     } else {
@@ -396,26 +784,55 @@ fn peek_tag<'a>(c: &'static str, input: &'a str) -> PResult<'a, &'a str> {
 }
 
 /// ```text
-/// $Value = $Bool | $Num | $Array | $String | $RawString
+/// $Value = $Bool | $Hex | $Num | $Array | $String | $RawString
 /// $Bool = true | false
+/// $Hex = Regex(0(x|X)[0..9a..fA..F]+)
 /// $Num = Regex(-?[0..9]+(.[0..9]+)?)
 /// $String = 'characters' | "characters"
 /// $RawString = characters
 /// ```
-fn parse_value(input: &str) -> PResult<Value> {
+fn parse_value(input: &str) -> PResult<'_, Value> {
+    parse_nested_value(input, 0)
+}
+
+/// Parse a whole value of an annotation, such as `0.5` of `@default(value=0.5)`.
+pub fn parse_annotation_value(input: &str) -> Result<Value, String> {
+    match parse_value(input) {
+        Ok(("", value)) => Ok(value),
+        _ => Err(format!("invalid value: {input}")),
+    }
+}
+
+/// Parse a value in `depth` arrays.
+fn parse_nested_value(input: &str, depth: usize) -> PResult<'_, Value> {
     alt((
+        parse_hex,
         parse_num,
         parse_bool,
-        parse_array,
+        |input| parse_array(input, depth),
         parse_string,
         parse_raw_string,
     ))(input)
 }
 
+/// ```text
+/// $Hex = Regex(0(x|X)[0..9a..fA..F]+)
+/// ```
+///
+/// Legacy definitions write bytes in hexadecimal like `byte B = 0x41`.
+fn parse_hex(input: &str) -> PResult<'_, Value> {
+    let (input, _) = alt((tag("0x"), tag("0X")))(input)?;
+    let (rest, digits) = hex_digit1(input)?;
+    match u64::from_str_radix(digits, 16) {
+        Ok(n) => Ok((rest, Value::Uint(n))),
+        Err(_) => Err(failure(input, "hexadecimal number exceeds 64 bits")),
+    }
+}
+
 /// ```text
 /// $Num = Regex(-?[0..9]+(.[0..9]+)?)
 /// ```
-fn parse_num(input: &str) -> PResult<Value> {
+fn parse_num(input: &str) -> PResult<'_, Value> {
     // parse minus
     let (input, minus) = if peek_tag("-", input).is_ok() {
         let (input, _) = tag("-")(input)?;
@@ -439,7 +856,7 @@ fn parse_num(input: &str) -> PResult<Value> {
 /// ```text
 /// $Bool = true | false
 /// ```
-fn parse_bool(input: &str) -> PResult<Value> {
+fn parse_bool(input: &str) -> PResult<'_, Value> {
     let (input, val) = alt((tag("true"), tag("false")))(input)?;
     if val == "true" {
         Ok((input, Value::Bool(true)))
@@ -452,18 +869,45 @@ fn parse_bool(input: &str) -> PResult<Value> {
 /// $Array = [ $Elements ]
 /// $Elements = $Value | $Elements , $Value
 /// ```
-fn parse_array(input: &str) -> PResult<Value> {
-    let p = delimited(space0, parse_value, space0);
-    let (input, val) = delimited(tag("["), separated_list1(tag(","), p), tag("]"))(input)?;
-    Ok((input, Value::Array(val)))
+///
+/// `depth` is the number of arrays containing this array.
+fn parse_array(input: &str, depth: usize) -> PResult<'_, Value> {
+    let limits = limits();
+    let (mut input, _) = tag("[")(input)?;
+    if depth == limits.max_nesting {
+        return Err(failure(
+            input,
+            "arrays are nested too deeply, see --max-nesting",
+        ));
+    }
+
+    let mut val = Vec::new();
+    loop {
+        if val.len() == limits.max_array_elements {
+            return Err(failure(
+                input,
+                "too many elements of an array, see --max-array-elements",
+            ));
+        }
+
+        let (next, v) = delimited(space0, |i| parse_nested_value(i, depth + 1), space0)(input)?;
+        val.push(v);
+
+        if let Ok((next, _)) = tag::<_, _, VerboseError<&str>>(",")(next) {
+            input = next;
+        } else {
+            let (next, _) = tag("]")(next)?;
+            return Ok((next, Value::Array(val)));
+        }
+    }
 }
 
 /// ```text
 /// $ArrayInfo = [] | [$PlusNum] | [<=$PlusNum]
 /// $PlusNum = Regex([0..9]+)
 /// ```
-fn parse_array_info(input: &str) -> PResult<ArrayInfo> {
-    fn is_array(input: &str) -> PResult<()> {
+fn parse_array_info(input: &str) -> PResult<'_, ArrayInfo> {
+    fn is_array(input: &str) -> PResult<'_, ()> {
         let (input, _) = peek(preceded(space0, tag("[")))(input)?;
         Ok((input, ()))
     }
@@ -502,7 +946,7 @@ fn parse_array_info(input: &str) -> PResult<ArrayInfo> {
 /// - \n
 /// - \t
 /// - \' or \"
-fn parse_string(input: &str) -> PResult<Value> {
+fn parse_string(input: &str) -> PResult<'_, Value> {
     let (mut input, quote) = one_of("\"'")(input)?;
 
     let mut val = String::new();
@@ -518,25 +962,10 @@ fn parse_string(input: &str) -> PResult<Value> {
                 input = next;
 
                 match c {
-                    'r' => {
-                        val.push_str("\\r");
-                    }
-                    'n' => {
-                        val.push_str("\\n");
-                    }
-                    't' => {
-                        val.push_str("\\t");
-                    }
-                    '\\' => {
-                        val.push_str("\\\\");
-                    }
-                    c if c == quote => {
-                        if c == '"' {
-                            val.push('\\');
-                        }
-                        val.push(quote);
-                    }
-                    _ => unreachable!(),
+                    'r' => val.push('\r'),
+                    'n' => val.push('\n'),
+                    't' => val.push('\t'),
+                    c => val.push(c), // a backslash or the quote
                 }
             }
             _ => {
@@ -546,35 +975,335 @@ fn parse_string(input: &str) -> PResult<Value> {
     }
 }
 
-fn parse_raw_string(input: &str) -> PResult<Value> {
+fn parse_raw_string(input: &str) -> PResult<'_, Value> {
     let (input, result) = many1(satisfy(|c| c != '\r' && c != '\n'))(input)?;
-    let result = result.iter().fold(String::new(), |s, c| match c {
-        '\\' => format!("{s}\\\\"),
-        '"' => format!("{s}\\\""),
-        _ => format!("{s}{c}"),
-    });
-
-    Ok((input, Value::String(result)))
+    Ok((input, Value::String(result.into_iter().collect())))
 }
 
 #[cfg(test)]
 mod tests {
     use nom::Finish;
 
-    use super::parse_raw_string;
+    use super::{
+        parse_action, parse_file_deprecated, parse_file_topic, parse_msg, parse_raw_string,
+        parse_srv, parse_string, parse_unit, parse_value, Expr, Limits, Optional, Value, ValueType,
+    };
+    use nom::error::VerboseErrorKind;
 
     #[test]
     fn test_parse_raw() {
         let s = "\\";
         let (_, v) = parse_raw_string(s).finish().unwrap();
-        println!("{v}");
+        assert_eq!(v.to_string(), "b\"\\\\\\0\"");
 
         let s = "\"";
         let (_, v) = parse_raw_string(s).finish().unwrap();
-        println!("{v}");
+        assert_eq!(v.to_string(), "b\"\\\"\\0\"");
 
         let s = "\\\"";
         let (_, v) = parse_raw_string(s).finish().unwrap();
-        println!("{v}");
+        assert_eq!(v.to_string(), "b\"\\\\\\\"\\0\"");
+    }
+
+    #[test]
+    fn test_parse_string() {
+        let (_, v) = parse_string(r#""a\"b\\c\n'""#).finish().unwrap();
+        assert!(matches!(&v, Value::String(s) if s == "a\"b\\c\n'"));
+        assert_eq!(v.to_string(), r#"b"a\"b\\c\n\'\0""#);
+
+        let (_, v) = parse_string(r"'it\'s é'").finish().unwrap();
+        assert!(matches!(&v, Value::String(s) if s == "it's é"));
+        assert_eq!(v.to_string(), r#"b"it\'s \xc3\xa9\0""#);
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = Limits::DEFAULT;
+
+        let line = format!("int32 a # {}\n", "x".repeat(limits.max_line_length));
+        assert!(parse_msg(&line).finish().is_err());
+
+        let msg = "int32 a\n".repeat(limits.max_fields);
+        assert!(parse_msg(&msg).finish().is_ok());
+        let msg = "int32 a\n".repeat(limits.max_fields + 1);
+        assert!(parse_msg(&msg).finish().is_err());
+
+        let nested = |n: usize| format!("int32[] A = {}1{}\n", "[".repeat(n), "]".repeat(n));
+        assert!(parse_msg(&nested(limits.max_nesting)).finish().is_ok());
+        assert!(parse_msg(&nested(limits.max_nesting + 1)).finish().is_err());
+
+        let elements = vec!["0"; limits.max_array_elements + 1].join(",");
+        let array = format!("[{elements}]");
+        let e = parse_value(&array).finish().unwrap_err();
+        assert!(matches!(
+            e.errors[0].1,
+            VerboseErrorKind::Context("too many elements of an array, see --max-array-elements")
+        ));
+
+        // a default value exceeding the limits is an error rather than a default value ignored
+        let n = limits.max_nesting + 1;
+        let msg = format!("int32[] a {}1{}\n", "[".repeat(n), "]".repeat(n));
+        let e = parse_msg(&msg).finish().unwrap_err();
+        assert!(matches!(
+            e.errors[0].1,
+            VerboseErrorKind::Context("arrays are nested too deeply, see --max-nesting")
+        ));
+    }
+
+    #[test]
+    fn test_non_ascii_identifier() {
+        // 'š' is U+0161, which was truncated to 'a'
+        assert!(parse_msg("int32 aš\n").finish().is_err());
+    }
+
+    #[test]
+    fn test_parse_unit() {
+        assert_eq!(parse_unit(" [m/s]").as_deref(), Some("m/s"));
+        assert_eq!(parse_unit(" yaw angle [ rad ]").as_deref(), Some("rad"));
+        assert_eq!(
+            parse_unit(" yaw [rad] @range(min=-3.15, max=3.15)").as_deref(),
+            Some("rad")
+        );
+        assert_eq!(parse_unit(r#" @unit(value="m/s")"#).as_deref(), Some("m/s"));
+        assert_eq!(parse_unit(" no unit"), None);
+
+        // only the end of the comment is a unit, and lists are not units
+        assert_eq!(parse_unit(" position [x, y, z]"), None);
+        assert_eq!(parse_unit(" [m] of the vehicle"), None);
+        assert_eq!(parse_unit(" []"), None);
+        assert_eq!(parse_unit(" [m"), None);
+
+        let (_, exprs) = parse_msg("float64 speed # [m/s]\n").finish().unwrap();
+        match &exprs[0] {
+            Expr::Variable { value, unit, .. } => {
+                assert!(value.is_none());
+                assert_eq!(unit.as_deref(), Some("m/s"));
+            }
+            _ => unreachable!(),
+        }
+
+        // a comment after a default value is not a part of the value
+        let (_, exprs) = parse_msg("int32 x 5 # [m]\n").finish().unwrap();
+        match &exprs[0] {
+            Expr::Variable {
+                value: Some(ValueType::Default(Value::Uint(5))),
+                unit,
+                ..
+            } => assert_eq!(unit.as_deref(), Some("m")),
+            e => panic!("{e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_deprecated() {
+        let msg = "# @deprecated use NewType instead\nint32 old # @deprecated\nint32 new # [m]\n";
+        assert_eq!(
+            parse_file_deprecated(msg).as_deref(),
+            Some("use NewType instead")
+        );
+        assert_eq!(parse_file_deprecated("int32 a\n# @deprecated\n"), None);
+
+        let imu = "# IMU of the robot\n#  @topic  /sensor/imu  # by convention\nfloat64 x\n";
+        assert_eq!(parse_file_topic(imu).as_deref(), Some("/sensor/imu"));
+        assert_eq!(
+            parse_file_topic("# @topics /a\nint32 a\n# @topic /b\n"),
+            None
+        );
+
+        let (_, exprs) = parse_msg(msg).finish().unwrap();
+        let deprecated: Vec<Option<&str>> = exprs
+            .iter()
+            .map(|expr| match expr {
+                Expr::Variable { deprecated, .. } => deprecated.as_deref(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(deprecated, [Some(""), None]);
+    }
+
+    #[test]
+    fn test_parse_rust_name() {
+        let (_, exprs) = parse_msg("int32 Type # id @rust_name type_id\nint32 a # @rust_name 1a\n")
+            .finish()
+            .unwrap();
+        let names: Vec<(&str, Option<&str>)> = exprs
+            .iter()
+            .map(|expr| match expr {
+                Expr::Variable {
+                    var_name, ros_name, ..
+                } => (var_name.as_str(), ros_name.as_deref()),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(names, [("type_id", Some("Type")), ("a", None)]);
+    }
+
+    #[test]
+    fn test_parse_const_with_digits() {
+        let (_, exprs) = parse_msg("uint8 INT8 = 1\nuint8 FLOAT_64 = 8\n")
+            .finish()
+            .unwrap();
+        for expr in exprs.iter() {
+            assert!(matches!(
+                expr,
+                Expr::Variable {
+                    value: Some(ValueType::Const(_)),
+                    ..
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parse_action() {
+        let (_, (goal, result, feedback)) =
+            parse_action("int32 order\n---\nint32[] sequence\n---\nint32[] partial\nint32 n\n")
+                .finish()
+                .unwrap();
+        assert_eq!(goal.len(), 1);
+        assert_eq!(result.len(), 1);
+        assert_eq!(feedback.len(), 2);
+
+        for (action, expected) in [
+            (
+                "int32 a\n",
+                "found no separator, .action requires exactly 2",
+            ),
+            (
+                "int32 a\n---\nint32 b\n",
+                "found 1 separator, .action requires exactly 2",
+            ),
+            ("---\n---\n---\n", "found more than 2 separators"),
+            ("---\n--\n", "malformed separator"),
+        ] {
+            let e = parse_action(action).finish().unwrap_err();
+            let msg = nom::error::convert_error(action, e);
+            assert!(msg.contains(expected), "{msg}");
+        }
+    }
+
+    #[test]
+    fn test_parse_srv() {
+        let (_, (req, resp)) = parse_srv("int32 a\r\n  --- # separator\r\nint32 b\r\n")
+            .finish()
+            .unwrap();
+        assert_eq!(req.len(), 1);
+        assert_eq!(resp.len(), 1);
+
+        let (_, (req, resp)) = parse_srv("---").finish().unwrap();
+        assert!(req.is_empty());
+        assert!(resp.is_empty());
+
+        for (srv, expected) in [
+            ("int32 a\nint32 b\n", "found no separator, .srv requires exactly 1"),
+            (
+                "int32 a\n---\nint32 b\n---\n",
+                "found 2 separators, .srv requires exactly 1; did you mean to create an .action file?",
+            ),
+            ("---\n---\n---\n", "found more than 2 separators"),
+            ("int32 a\n----\nint32 b\n", "malformed separator"),
+        ] {
+            let e = parse_srv(srv).finish().unwrap_err();
+            let msg = nom::error::convert_error(srv, e);
+            assert!(msg.contains(expected), "{msg}");
+        }
+
+        for (msg, expected) in [
+            (
+                "int32 a\n---\nint32 b\n",
+                "did you mean to create a .srv file?",
+            ),
+            ("---\n---\n", "did you mean to create an .action file?"),
+            ("int32 a\n -- \n", "malformed separator"),
+        ] {
+            let e = parse_msg(msg).finish().unwrap_err();
+            let msg = nom::error::convert_error(msg, e);
+            assert!(msg.contains(expected), "{msg}");
+        }
+    }
+
+    #[test]
+    fn test_parse_separators() {
+        // each line replaces the only separator of the .srv and the first one of the .action
+        for (line, srv_expected, action_expected) in [
+            ("----", "malformed separator", "malformed separator"),
+            ("-- -", "malformed separator", "malformed separator"),
+            ("---x", "malformed separator", "malformed separator"),
+            (
+                "",
+                "found no separator, .srv requires exactly 1",
+                "found 1 separator, .action requires exactly 2",
+            ),
+            (
+                "---\n---",
+                "found 2 separators, .srv requires exactly 1",
+                "found more than 2 separators, .action requires exactly 2",
+            ),
+        ] {
+            let srv = format!("int32 a\n{line}\nint32 b\n");
+            let e = parse_srv(&srv).finish().unwrap_err();
+            let msg = nom::error::convert_error(srv.as_str(), e);
+            assert!(msg.contains(srv_expected), "{srv:?}: {msg}");
+
+            let action = format!("int32 a\n{line}\nint32 b\n---\nint32 c\n");
+            let e = parse_action(&action).finish().unwrap_err();
+            let msg = nom::error::convert_error(action.as_str(), e);
+            assert!(msg.contains(action_expected), "{action:?}: {msg}");
+        }
+    }
+
+    #[test]
+    fn test_parse_optional() {
+        let msg = "float64 altitude # @optional\nbool has_altitude\nint32 id # @optional(flag=id_valid)\nint32 count # @optional(sentinel=-1)\nint32 x # @optionality\n";
+        let (_, exprs) = parse_msg(msg).finish().unwrap();
+        let optionals: Vec<_> = exprs
+            .iter()
+            .map(|expr| match expr {
+                Expr::Variable { optional, .. } => optional.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(
+            optionals,
+            [
+                Some(Optional::Flag("has_altitude".to_string())),
+                None,
+                Some(Optional::Flag("id_valid".to_string())),
+                Some(Optional::Sentinel("-1".to_string())),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_idl_annotations() {
+        let msg = "int32 id # @key\nfloat64 x # @range(min=-1.0, max=1.0) @default(value=0.5)\nint32 keyframe # @keyframe\n";
+        let (_, exprs) = parse_msg(msg).finish().unwrap();
+        let annotations: Vec<_> = exprs
+            .iter()
+            .map(|expr| match expr {
+                Expr::Variable {
+                    key,
+                    range,
+                    default,
+                    ..
+                } => (*key, range.clone(), default.clone()),
+                _ => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(
+            annotations,
+            [
+                (true, None, None),
+                (
+                    false,
+                    Some(("-1.0".to_string(), "1.0".to_string())),
+                    Some("0.5".to_string())
+                ),
+                (false, None, None),
+            ]
+        );
     }
 }
@@ -0,0 +1,352 @@
+//! Typed point iterators for messages having the same structure as `sensor_msgs/PointCloud2`.
+//!
+//! ```text
+//! for (x, y, z) in cloud.iter_points::<(f32, f32, f32)>().unwrap() {
+//!     ...
+//! }
+//! ```
+//!
+//! Offsets and datatypes of points are resolved by the `fields` of the message,
+//! so user code does not need any pointer arithmetic.
+
+use crate::parser::{ArrayInfo, Expr, TypeName, ValueType};
+use std::{borrow::Cow, collections::VecDeque};
+
+/// Maximum number of elements of tuples read from points.
+const MAX_TUPLE_LEN: usize = 8;
+
+/// Generate the point iterator API if the struct has the same structure as `sensor_msgs/PointCloud2`.
+pub fn gen_point_cloud_iter(lines: &mut VecDeque<Cow<'_, str>>, struct_name: &str, exprs: &[Expr]) {
+    if !is_point_cloud2(exprs) {
+        return;
+    }
+
+    let mut tuples = String::new();
+    let params = ["A", "B", "C", "D", "E", "F", "G", "H"];
+    for n in 1..=MAX_TUPLE_LEN {
+        let args: Vec<String> = (0..n).map(|i| format!("{} {i}", params[i])).collect();
+        tuples.push_str(&format!(
+            "impl_{struct_name}_tuple!({});\n",
+            args.join(", ")
+        ));
+    }
+
+    let code = format!(
+        "
+/// Value of a field of points in `{struct_name}`.
+pub trait {struct_name}Value: Sized {{
+    /// `datatype` of `PointField` corresponding to this type.
+    const DATATYPE: u8;
+
+    /// Read a value from the head of `bytes`.
+    fn read(bytes: &[u8], is_bigendian: bool) -> Option<Self>;
+}}
+
+macro_rules! impl_{struct_name}_value {{
+    ($t:ty, $datatype:expr) => {{
+        impl {struct_name}Value for $t {{
+            const DATATYPE: u8 = $datatype;
+
+            fn read(bytes: &[u8], is_bigendian: bool) -> Option<Self> {{
+                let bytes = bytes.get(..core::mem::size_of::<$t>())?.try_into().ok()?;
+                if is_bigendian {{
+                    Some(<$t>::from_be_bytes(bytes))
+                }} else {{
+                    Some(<$t>::from_le_bytes(bytes))
+                }}
+            }}
+        }}
+    }};
+}}
+
+impl_{struct_name}_value!(i8, 1);
+impl_{struct_name}_value!(u8, 2);
+impl_{struct_name}_value!(i16, 3);
+impl_{struct_name}_value!(u16, 4);
+impl_{struct_name}_value!(i32, 5);
+impl_{struct_name}_value!(u32, 6);
+impl_{struct_name}_value!(f32, 7);
+impl_{struct_name}_value!(f64, 8);
+
+/// Tuple of values read from a point in `{struct_name}`.
+/// Tuples up to {MAX_TUPLE_LEN} elements are supported.
+pub trait {struct_name}Tuple: Sized {{
+    /// `datatype`s of `PointField` corresponding to the elements.
+    const DATATYPES: &'static [u8];
+
+    /// Read a tuple from a point. `offsets` are offsets of the elements in the point.
+    fn read(point: &[u8], offsets: &[usize], is_bigendian: bool) -> Option<Self>;
+}}
+
+macro_rules! impl_{struct_name}_tuple {{
+    ($($t:ident $i:tt),+) => {{
+        impl<$($t: {struct_name}Value),+> {struct_name}Tuple for ($($t,)+) {{
+            const DATATYPES: &'static [u8] = &[$($t::DATATYPE),+];
+
+            fn read(point: &[u8], offsets: &[usize], is_bigendian: bool) -> Option<Self> {{
+                Some(($($t::read(point.get(offsets[$i]..)?, is_bigendian)?,)+))
+            }}
+        }}
+    }};
+}}
+
+{tuples}
+impl {struct_name} {{
+    /// Iterate points as tuples of the first `P::DATATYPES.len()` fields.
+    /// Returns `None` if datatypes of the fields are different from the tuple.
+    pub fn iter_points<P: {struct_name}Tuple>(&self) -> Option<impl Iterator<Item = P> + '_> {{
+        let names: Vec<String> = self
+            .fields
+            .iter()
+            .take(P::DATATYPES.len())
+            .map(|field| field.name.get_string())
+            .collect();
+        let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+        self.iter_points_by_name(&names)
+    }}
+
+    /// Iterate points as tuples of the fields specified by `names`, e.g. `&[\"x\", \"y\", \"z\"]`.
+    /// Returns `None` if a field is not found or datatypes of the fields are different from the tuple.
+    ///
+    /// Iteration stops if `data` is shorter than described by `height`, `width`, `point_step` and `row_step`.
+    pub fn iter_points_by_name<P: {struct_name}Tuple>(&self, names: &[&str]) -> Option<impl Iterator<Item = P> + '_> {{
+        if names.len() != P::DATATYPES.len() {{
+            return None;
+        }}
+
+        let mut offsets = Vec::with_capacity(names.len());
+        for (name, datatype) in names.iter().zip(P::DATATYPES) {{
+            let field = self.fields.iter().find(|field| field.name.get_string() == *name)?;
+            if field.datatype != *datatype {{
+                return None;
+            }}
+            offsets.push(field.offset as usize);
+        }}
+
+        let data = self.data.as_slice();
+        let is_bigendian = self.is_bigendian;
+        let point_step = self.point_step as usize;
+        let row_step = self.row_step as usize;
+        let width = self.width as usize;
+
+        let iter = (0..self.height as usize)
+            .flat_map(move |row| (0..width).map(move |col| row * row_step + col * point_step))
+            .map_while(move |start| P::read(data.get(start..)?, &offsets, is_bigendian));
+
+        Some(iter)
+    }}
+}}
+"
+    );
+
+    lines.push_back(code.into());
+}
+
+/// Check the struct has the same fields as `sensor_msgs/PointCloud2`.
+pub fn is_point_cloud2(exprs: &[Expr]) -> bool {
+    let mut fields = Vec::new();
+    for expr in exprs.iter() {
+        match expr {
+            Expr::Variable {
+                value: Some(ValueType::Const(_)),
+                ..
+            } => (),
+            Expr::Variable {
+                type_name,
+                var_name,
+                ..
+            } => fields.push((var_name.as_str(), type_name)),
+            _ => (),
+        }
+    }
+
+    let expected = [
+        "header",
+        "height",
+        "width",
+        "fields",
+        "is_bigendian",
+        "point_step",
+        "row_step",
+        "data",
+        "is_dense",
+    ];
+
+    if fields.len() != expected.len() {
+        return false;
+    }
+
+    fields
+        .iter()
+        .zip(expected)
+        .all(|((name, type_name), expected)| {
+            *name == expected
+                && match (expected, type_name) {
+                    ("header", TypeName::ScopedType { type_name, .. }) => type_name == "Header",
+                    (
+                        "height" | "width" | "point_step" | "row_step",
+                        TypeName::Type {
+                            type_name,
+                            array_info: ArrayInfo::NotArray,
+                        },
+                    ) => type_name == "uint32",
+                    (
+                        "is_bigendian" | "is_dense",
+                        TypeName::Type {
+                            type_name,
+                            array_info: ArrayInfo::NotArray,
+                        },
+                    ) => type_name == "bool",
+                    (
+                        "data",
+                        TypeName::Type {
+                            type_name,
+                            array_info: ArrayInfo::Dynamic,
+                        },
+                    ) => type_name == "uint8",
+                    (
+                        "fields",
+                        TypeName::Type {
+                            type_name,
+                            array_info: ArrayInfo::Dynamic,
+                        }
+                        | TypeName::ScopedType {
+                            type_name,
+                            array_info: ArrayInfo::Dynamic,
+                            ..
+                        },
+                    ) => type_name == "PointField",
+                    _ => false,
+                }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::gen_point_cloud_iter;
+    use crate::parser;
+    use nom::Finish;
+    use std::collections::VecDeque;
+
+    const MOCKS: &str = "
+pub struct RosString(String);
+
+impl RosString {
+    pub fn get_string(&self) -> String {
+        self.0.clone()
+    }
+}
+
+pub struct PointField {
+    pub name: RosString,
+    pub offset: u32,
+    pub datatype: u8,
+}
+
+pub struct Cloud {
+    pub height: u32,
+    pub width: u32,
+    pub fields: Vec<PointField>,
+    pub is_bigendian: bool,
+    pub point_step: u32,
+    pub row_step: u32,
+    pub data: Vec<u8>,
+}
+";
+
+    const TESTS: &str = "
+/// `datatype`s of `sensor_msgs/PointField`.
+const INT8: u8 = 1;
+const UINT8: u8 = 2;
+const INT16: u8 = 3;
+const UINT16: u8 = 4;
+const INT32: u8 = 5;
+const UINT32: u8 = 6;
+const FLOAT32: u8 = 7;
+const FLOAT64: u8 = 8;
+
+/// A cloud of a row of points having `x: f32` at 0 and `intensity: u16` at 4.
+fn cloud(points: &[(f32, u16)], is_bigendian: bool) -> Cloud {
+    let field = |name: &str, offset, datatype| PointField { name: RosString(name.to_string()), offset, datatype };
+    let mut data = Vec::new();
+    for (x, intensity) in points {
+        if is_bigendian {
+            data.extend(x.to_be_bytes());
+            data.extend(intensity.to_be_bytes());
+        } else {
+            data.extend(x.to_le_bytes());
+            data.extend(intensity.to_le_bytes());
+        }
+        data.extend([0, 0]);
+    }
+    Cloud {
+        height: 1,
+        width: points.len() as u32,
+        fields: vec![field(\"x\", 0, FLOAT32), field(\"intensity\", 4, UINT16)],
+        is_bigendian,
+        point_step: 8,
+        row_step: 8 * points.len() as u32,
+        data,
+    }
+}
+
+#[test]
+fn test_datatypes() {
+    assert_eq!(
+        <(i8, u8, i16, u16, i32, u32, f32, f64)>::DATATYPES,
+        [INT8, UINT8, INT16, UINT16, INT32, UINT32, FLOAT32, FLOAT64]
+    );
+}
+
+#[test]
+fn test_endianness() {
+    let points = [(1.5, 0x0102), (-2.0, 0xfffe)];
+    for is_bigendian in [false, true] {
+        let cloud = cloud(&points, is_bigendian);
+        let read: Vec<(f32, u16)> = cloud.iter_points().unwrap().collect();
+        assert_eq!(read, points);
+
+        let read: Vec<(u16, f32)> = cloud.iter_points_by_name(&[\"intensity\", \"x\"]).unwrap().collect();
+        assert_eq!(read, [(0x0102, 1.5), (0xfffe, -2.0)]);
+    }
+
+    // bytes are not swapped if the endianness is wrong
+    let mut cloud = cloud(&points, true);
+    cloud.is_bigendian = false;
+    let read: Vec<(u16,)> = cloud.iter_points_by_name(&[\"intensity\"]).unwrap().collect();
+    assert_eq!(read, [(0x0201,), (0xfeff,)]);
+}
+
+#[test]
+fn test_mismatch() {
+    let mut cloud = cloud(&[(1.0, 1), (2.0, 2)], false);
+    assert!(cloud.iter_points::<(f64, u16)>().is_none());
+    assert!(cloud.iter_points_by_name::<(f32,)>(&[\"y\"]).is_none());
+    assert!(cloud.iter_points_by_name::<(f32,)>(&[\"x\", \"intensity\"]).is_none());
+
+    // iteration stops at the truncated point
+    cloud.data.truncate(12);
+    assert_eq!(cloud.iter_points::<(f32, u16)>().unwrap().count(), 1);
+}
+";
+
+    #[test]
+    fn test_point_cloud_iter() {
+        let input = "std_msgs/Header header
+uint32 height
+uint32 width
+PointField[] fields
+bool is_bigendian
+uint32 point_step
+uint32 row_step
+uint8[] data
+bool is_dense
+";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let mut lines = VecDeque::new();
+        gen_point_cloud_iter(&mut lines, "Cloud", &exprs);
+
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        crate::tests::run_generated_tests("point_cloud", &format!("{MOCKS}{code}{TESTS}"));
+    }
+}
@@ -0,0 +1,184 @@
+//! QoS recommended for messages and services.
+//!
+//! `# @qos(...)` at the top of a .msg or .srv file, or `qos` of the configuration file,
+//! generates `recommended_qos()` returning `qos::Profile` of safe_drive,
+//! so that interface packages carry the QoS their publishers and subscribers, or clients and servers, expect.
+//!
+//! ```text
+//! # @qos(reliability=best_effort, history=keep_last, depth=5)
+//! float64 x
+//! ```
+//!
+//! | Key                            | Values                                                  |
+//! |--------------------------------|---------------------------------------------------------|
+//! | `history`                      | `keep_last`, `keep_all` or `system_default`             |
+//! | `depth`                        | number of samples                                       |
+//! | `reliability`                  | `reliable`, `best_effort` or `system_default`           |
+//! | `durability`                   | `volatile`, `transient_local` or `system_default`       |
+//! | `liveliness`                   | `automatic`, `manual_by_topic` or `system_default`      |
+//! | `deadline_ms`                  | milliseconds                                            |
+//! | `lifespan_ms`                  | milliseconds                                            |
+//! | `liveliness_lease_duration_ms` | milliseconds                                            |
+//!
+//! Policies which are not specified are the ones of `Profile::default()`.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Qos {
+    pub history: Option<String>,
+    pub depth: Option<usize>,
+    pub reliability: Option<String>,
+    pub durability: Option<String>,
+    pub liveliness: Option<String>,
+    pub deadline_ms: Option<u64>,
+    pub lifespan_ms: Option<u64>,
+    pub liveliness_lease_duration_ms: Option<u64>,
+}
+
+impl Qos {
+    /// QoS of the arguments of `@qos(key=value, ...)`.
+    pub fn from_args(args: &BTreeMap<String, String>) -> Result<Qos, String> {
+        fn number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+            value
+                .parse()
+                .map_err(|_| format!("{key} of @qos must be a number, but it is `{value}`"))
+        }
+
+        let mut qos = Qos::default();
+        for (key, value) in args.iter() {
+            match key.as_str() {
+                "history" => qos.history = Some(value.clone()),
+                "depth" => qos.depth = Some(number(key, value)?),
+                "reliability" => qos.reliability = Some(value.clone()),
+                "durability" => qos.durability = Some(value.clone()),
+                "liveliness" => qos.liveliness = Some(value.clone()),
+                "deadline_ms" => qos.deadline_ms = Some(number(key, value)?),
+                "lifespan_ms" => qos.lifespan_ms = Some(number(key, value)?),
+                "liveliness_lease_duration_ms" => {
+                    qos.liveliness_lease_duration_ms = Some(number(key, value)?)
+                }
+                _ => return Err(format!("unknown key of @qos: {key}")),
+            }
+        }
+        qos.validate()?;
+        Ok(qos)
+    }
+
+    /// Check the names of the policies.
+    pub fn validate(&self) -> Result<(), String> {
+        for (kind, policy) in self.policies() {
+            if let Some(name) = policy {
+                variant(kind, name)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn policies(&self) -> [(&'static str, Option<&String>); 4] {
+        [
+            ("history", self.history.as_ref()),
+            ("reliability", self.reliability.as_ref()),
+            ("durability", self.durability.as_ref()),
+            ("liveliness", self.liveliness.as_ref()),
+        ]
+    }
+}
+
+/// Variant of the enum of safe_drive for `name` of the `kind` of policies.
+fn variant(kind: &str, name: &str) -> Result<&'static str, String> {
+    let variant = match (kind, name) {
+        (_, "system_default") => "SystemDefault",
+        ("history", "keep_last") => "KeepLast",
+        ("history", "keep_all") => "KeepAll",
+        ("reliability", "reliable") => "Reliable",
+        ("reliability", "best_effort") => "BestEffort",
+        ("durability", "volatile") => "Volatile",
+        ("durability", "transient_local") => "TransientLocal",
+        ("liveliness", "automatic") => "Automatic",
+        ("liveliness", "manual_by_topic") => "ManualByTopic",
+        _ => return Err(format!("unknown {kind} of QoS: {name}")),
+    };
+    Ok(variant)
+}
+
+/// Generate `recommended_qos()` of `type_name`, where `sd` is the path to safe_drive.
+pub fn gen_recommended_qos(type_name: &str, qos: &Qos, sd: &str) -> String {
+    let mut fields = String::new();
+    for (kind, policy) in qos.policies() {
+        if let Some(name) = policy {
+            // policies are validated when they are loaded
+            let variant = variant(kind, name).unwrap();
+            let mut chars = kind.chars();
+            let enum_name: String = chars.next().unwrap().to_uppercase().chain(chars).collect();
+            fields.push_str(&format!(
+                "            {kind}: {sd}::qos::{enum_name}Policy::{variant},\n"
+            ));
+        }
+    }
+    if let Some(depth) = qos.depth {
+        fields.push_str(&format!("            depth: {depth},\n"));
+    }
+    for (field, ms) in [
+        ("deadline", qos.deadline_ms),
+        ("lifespan", qos.lifespan_ms),
+        (
+            "liveliness_lease_duration",
+            qos.liveliness_lease_duration_ms,
+        ),
+    ] {
+        if let Some(ms) = ms {
+            fields.push_str(&format!(
+                "            {field}: core::time::Duration::from_millis({ms}),\n"
+            ));
+        }
+    }
+
+    format!(
+        "
+impl {type_name} {{
+    /// QoS recommended by the definition of `{type_name}`.
+    pub fn recommended_qos() -> {sd}::qos::Profile {{
+        {sd}::qos::Profile {{
+{fields}            ..Default::default()
+        }}
+    }}
+}}"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gen_recommended_qos, Qos};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_recommended_qos() {
+        let args: BTreeMap<String, String> = [
+            ("reliability", "best_effort"),
+            ("history", "keep_last"),
+            ("depth", "5"),
+            ("deadline_ms", "100"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let qos = Qos::from_args(&args).unwrap();
+        let code = gen_recommended_qos("Imu", &qos, "safe_drive");
+        assert!(code.contains("    pub fn recommended_qos() -> safe_drive::qos::Profile {\n"));
+        assert!(code.contains(
+            "            history: safe_drive::qos::HistoryPolicy::KeepLast,\n            reliability: safe_drive::qos::ReliabilityPolicy::BestEffort,\n            depth: 5,\n"
+        ));
+        assert!(code.contains("            deadline: core::time::Duration::from_millis(100),\n            ..Default::default()\n"));
+
+        let arg = |k: &str, v: &str| BTreeMap::from([(k.to_string(), v.to_string())]);
+        assert_eq!(
+            Qos::from_args(&arg("durability", "reliable")).unwrap_err(),
+            "unknown durability of QoS: reliable"
+        );
+        assert!(Qos::from_args(&arg("depth", "-1")).is_err());
+        assert!(Qos::from_args(&arg("priority", "1")).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+//! Random values of messages for tests and load generators.
+//!
+//! `--random` generates `fill_random()`, which sets every field to a random value by the `rand` crate.
+//! Lengths of bounded sequences and strings do not exceed their bounds,
+//! and lengths of unbounded ones do not exceed `MAX_UNBOUNDED_LEN`.
+//! Fields of types not generated by ros2msg_to_rs, such as types of common_interfaces of safe_drive,
+//! are left as they are because they do not have `fill_random()`.
+
+use crate::{
+    generator::FallibleApi,
+    parser::{ArrayInfo, TypeName},
+};
+use std::{borrow::Cow, collections::VecDeque};
+
+/// Maximum length of unbounded sequences and strings.
+const MAX_UNBOUNDED_LEN: usize = 8;
+
+/// Field filled by `fill_random()`.
+pub struct RandomField<'a> {
+    /// Mangled name of the field.
+    pub field: Cow<'a, str>,
+
+    pub type_name: &'a TypeName,
+
+    /// Rust type of the field.
+    pub ty: Cow<'a, str>,
+
+    /// Whether the element type has `fill_random()`, if it is not primitive or string.
+    pub generated: bool,
+}
+
+/// Generate `{struct_name}::fill_random()`.
+pub fn gen_fill_random(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    struct_name: &str,
+    fields: &[RandomField<'_>],
+    no_std: bool,
+    fallible_api: FallibleApi,
+) {
+    let mut body = String::new();
+    let mut has_string = false;
+
+    for RandomField {
+        field,
+        type_name,
+        ty,
+        generated,
+    } in fields.iter()
+    {
+        // statement setting `$v`, which is `&mut` of an element
+        let mut to_option = "";
+        let (fill, array_info) = match type_name {
+            TypeName::Type {
+                type_name,
+                array_info,
+            } if crate::generator::gen_primitives(type_name).is_some() => {
+                ("*$v = rng.random();".to_string(), array_info)
+            }
+            TypeName::Type { array_info, .. } | TypeName::ScopedType { array_info, .. } => {
+                if !generated {
+                    continue;
+                }
+                to_option = fallible_api.to_option();
+                ("$v.fill_random(rng);".to_string(), array_info)
+            }
+            TypeName::String(array_info) => {
+                has_string = true;
+                (
+                    format!("$v.assign(&random_string(rng, {MAX_UNBOUNDED_LEN}));"),
+                    array_info,
+                )
+            }
+            TypeName::LimitedString { size, array_info } => {
+                has_string = true;
+                (
+                    format!("$v.assign(&random_string(rng, {size}));"),
+                    array_info,
+                )
+            }
+        };
+
+        let stmt = match array_info {
+            ArrayInfo::NotArray => {
+                let fill = fill.replace("*$v", &format!("self.{field}"));
+                fill.replace("$v", &format!("self.{field}"))
+            }
+            ArrayInfo::Static(_) => {
+                let fill = fill.replace("$v", "v");
+                format!(
+                    "for v in self.{field}.iter_mut() {{
+            {fill}
+        }}"
+                )
+            }
+            ArrayInfo::Dynamic | ArrayInfo::Limited(_) => {
+                let max = match array_info {
+                    ArrayInfo::Limited(n) => *n,
+                    _ => MAX_UNBOUNDED_LEN,
+                };
+                let fill = fill.replace("$v", "v");
+                format!(
+                    "if let Some(mut seq) = <{ty}>::new(rng.random_range(0..={max})){to_option} {{
+            for v in seq.as_slice_mut().iter_mut() {{
+                {fill}
+            }}
+            self.{field} = seq;
+        }}"
+                )
+            }
+        };
+
+        body.push_str(&format!("\n        {stmt}"));
+    }
+
+    let rng = if body.is_empty() { "_rng" } else { "rng" };
+
+    // `String` is qualified because a message of the package may be named `String`
+    let random_string = if has_string {
+        let alloc = if no_std { "alloc" } else { "std" };
+        format!(
+            "
+        fn random_string(rng: &mut impl rand::Rng, max: usize) -> {alloc}::string::String {{
+            let len = rng.random_range(0..=max);
+            (0..len)
+                .map(|_| rng.sample(rand::distr::Alphanumeric) as char)
+                .collect()
+        }}
+"
+        )
+    } else {
+        String::new()
+    };
+
+    lines.push_back(
+        format!(
+            "
+impl {struct_name} {{
+    /// Set every field to a random value. Lengths of sequences and strings do not exceed their bounds.
+    pub fn fill_random(&mut self, {rng}: &mut impl rand::Rng) {{{random_string}{body}
+    }}
+}}
+"
+        )
+        .into(),
+    );
+}
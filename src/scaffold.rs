@@ -0,0 +1,145 @@
+//! Scaffolding of new interface packages.
+//!
+//! `new` creates `msg`, `srv` and `action` directories, `package.xml` and `CMakeLists.txt` for colcon,
+//! and a sample message, so that the package can be built by ROS 2 and generated by ros2msg_to_rs.
+//! The configuration file in the parent directory has the `default` profile generating crates of the directory.
+
+use crate::config::DEFAULT_CONFIG;
+use std::{
+    error::Error,
+    fs::{create_dir, create_dir_all, write},
+    path::Path,
+};
+
+/// Create the package `name` in `dir`. The configuration file of `dir` is created if it does not exist.
+/// Return whether the configuration file is created.
+pub fn new_package(dir: &Path, name: &str) -> Result<bool, Box<dyn Error>> {
+    let pkg_dir = dir.join(name);
+    if pkg_dir.exists() {
+        return Err(format!("{} already exists", pkg_dir.display()).into());
+    }
+
+    create_dir_all(dir)?;
+    create_dir(&pkg_dir)?;
+    for sub in ["msg", "srv", "action"] {
+        create_dir(pkg_dir.join(sub))?;
+    }
+
+    write(pkg_dir.join("package.xml"), gen_package_xml(name))?;
+    write(pkg_dir.join("CMakeLists.txt"), gen_cmake_lists(name))?;
+    write(pkg_dir.join("msg").join("Example.msg"), SAMPLE_MSG)?;
+
+    let config = dir.join(DEFAULT_CONFIG);
+    if config.exists() {
+        return Ok(false);
+    }
+    write(config, CONFIG)?;
+
+    Ok(true)
+}
+
+const SAMPLE_MSG: &str = "# An example message. Replace it with your definitions.
+
+# Time when the value is measured.
+builtin_interfaces/Time stamp
+
+# Name of the sensor.
+string<=32 name
+
+float64 value # [m/s]
+";
+
+/// Generate crates of packages in the directory by `--profile default`.
+const CONFIG: &str = r#"{
+  "profiles": {
+    "default": ["-i", ".", "-o", "generated/interfaces", "--emit-crates"]
+  }
+}
+"#;
+
+fn gen_package_xml(name: &str) -> String {
+    format!(
+        r#"<?xml version="1.0"?>
+<?xml-model href="http://download.ros.org/schema/package_format3.xsd" schematypens="http://www.w3.org/2001/XMLSchema"?>
+<package format="3">
+  <name>{name}</name>
+  <version>0.0.0</version>
+  <description>Interfaces of {name}</description>
+  <maintainer email="todo@example.com">TODO</maintainer>
+  <license>TODO</license>
+
+  <buildtool_depend>ament_cmake</buildtool_depend>
+  <buildtool_depend>rosidl_default_generators</buildtool_depend>
+
+  <depend>builtin_interfaces</depend>
+
+  <exec_depend>rosidl_default_runtime</exec_depend>
+
+  <member_of_group>rosidl_interface_packages</member_of_group>
+
+  <export>
+    <build_type>ament_cmake</build_type>
+  </export>
+</package>
+"#
+    )
+}
+
+fn gen_cmake_lists(name: &str) -> String {
+    format!(
+        "cmake_minimum_required(VERSION 3.8)
+project({name})
+
+find_package(ament_cmake REQUIRED)
+find_package(builtin_interfaces REQUIRED)
+find_package(rosidl_default_generators REQUIRED)
+
+rosidl_generate_interfaces(${{PROJECT_NAME}}
+  \"msg/Example.msg\"
+  DEPENDENCIES builtin_interfaces
+)
+
+ament_export_dependencies(rosidl_default_runtime)
+ament_package()
+"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::new_package;
+    use crate::{config::DEFAULT_CONFIG, generate_workspace, Args};
+    use std::fs::{read_to_string, remove_dir_all};
+
+    #[test]
+    fn test_new_package() {
+        let tmp = std::env::temp_dir().join(format!("ros2msg_to_rs_new_{}", std::process::id()));
+        let dir = tmp.join("src");
+        let first = new_package(&dir, "my_pkg");
+        let second = new_package(&dir, "other_pkg");
+        let existing = new_package(&dir, "my_pkg");
+        let package_xml = read_to_string(dir.join("my_pkg/package.xml"));
+        let config = read_to_string(dir.join(DEFAULT_CONFIG));
+        let subdirs = ["msg", "srv", "action"].map(|sub| dir.join("my_pkg").join(sub).is_dir());
+
+        // the sample message is generated
+        let out = tmp.join("generated");
+        let args = Args::new(["-i", dir.to_str().unwrap(), "-o", out.to_str().unwrap()]);
+        let generated = generate_workspace(&args.unwrap());
+        let example = out.join("my_pkg/msg/example.rs").exists();
+        remove_dir_all(&tmp).unwrap();
+
+        // the configuration file is created only once
+        assert!(first.unwrap());
+        assert!(!second.unwrap());
+        assert!(existing
+            .unwrap_err()
+            .to_string()
+            .ends_with("my_pkg already exists"));
+        assert!(package_xml.unwrap().contains("<name>my_pkg</name>"));
+        assert!(config.unwrap().contains("\"default\""));
+        assert_eq!(subdirs, [true; 3]);
+        generated.unwrap();
+        assert!(example);
+    }
+}
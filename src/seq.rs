@@ -0,0 +1,258 @@
+//! Sequences of messages shared by the files of a package.
+//!
+//! `--seq-macro` generates `{package}/seq.rs` defining `impl_seq!`,
+//! which each generated file invokes instead of repeating the implementation of `{Type}Seq`.
+//! This shrinks the generated code and makes fixes of sequences a one-place change.
+//! The macro is expanded in the generated file, so `size_t` and the C functions are resolved there.
+//!
+//! `--typed-seq` generates `{package}/seq.rs` defining `TypedSeq<T, N>` instead,
+//! and `{Type}Seq<N>` is an alias of it.
+//! `TypedSeq` calls the C functions through the associated constants of `SeqElement` implemented by each message,
+//! so it is compiled once per package rather than once per type.
+
+/// How `{Type}Seq` is implemented.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SeqImpl {
+    /// Expanded in each generated file.
+    #[default]
+    Expanded,
+
+    /// Expanded by `impl_seq!` of `seq.rs`.
+    Macro,
+
+    /// Alias of `TypedSeq` of `seq.rs`.
+    Typed,
+}
+
+/// Generate the content of `seq.rs`.
+pub fn gen_seq_module(seq_impl: SeqImpl) -> String {
+    let (doc, module) = if seq_impl == SeqImpl::Typed {
+        ("`TypedSeq` of messages", TYPED_SEQ_MODULE)
+    } else {
+        (
+            "`impl_seq!` implementing a message and its sequence",
+            SEQ_MODULE,
+        )
+    };
+
+    format!(
+        "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+//! {doc}.
+
+{module}"
+    )
+}
+
+/// Invocation of `impl_seq!` for `type_name`, whose C functions are `{c_name}__init` and so on.
+/// Generated files are `{package}/(msg|srv)/{file}.rs`, so the macro is in `super::super::seq`.
+/// `defaults` are statements setting fields of `msg` to their default values.
+pub fn gen_impl_seq(c_name: &str, type_name: &str, defaults: &[String]) -> String {
+    let defaults = if defaults.is_empty() {
+        String::new()
+    } else {
+        format!(",\n    |msg| {{ {} }}", defaults.join(" "))
+    };
+    format!(
+        "
+super::super::seq::impl_seq!(
+    {type_name},
+    {type_name}SeqRaw,
+    {type_name}Seq,
+    {c_name}__init,
+    {c_name}__fini,
+    {c_name}__Sequence__init,
+    {c_name}__Sequence__fini{defaults}
+);
+"
+    )
+}
+
+/// `{type_name}Seq` as an alias of `TypedSeq`, and `SeqElement` calling `{c_name}__init` and so on.
+pub fn gen_typed_seq(c_name: &str, type_name: &str) -> String {
+    format!(
+        "
+type {type_name}SeqRaw = super::super::seq::SeqRaw<{type_name}>;
+
+unsafe impl super::super::seq::SeqElement for {type_name} {{
+    const INIT: unsafe extern \"C\" fn(*mut Self) -> bool = {c_name}__init;
+    const SEQ_INIT: unsafe extern \"C\" fn(*mut {type_name}SeqRaw, usize) -> bool = {c_name}__Sequence__init;
+    const SEQ_FINI: unsafe extern \"C\" fn(*mut {type_name}SeqRaw) = {c_name}__Sequence__fini;
+}}
+
+/// Sequence of {type_name}.
+/// `N` is the maximum number of elements.
+/// If `N` is `0`, the size is unlimited.
+pub type {type_name}Seq<const N: usize> = super::super::seq::TypedSeq<{type_name}, N>;
+"
+    )
+}
+
+/// `seq.rs` of `--seq-macro`, which is a file to be compiled by tests.
+const SEQ_MODULE: &str = include_str!("seq/impl_seq.rs");
+
+/// `seq.rs` of `--typed-seq`, which is a file to be compiled by tests.
+const TYPED_SEQ_MODULE: &str = include_str!("seq/typed_seq.rs");
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    thread_local! {
+        /// The number of sequences allocated by the mock C functions.
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Mock of `{package}__msg__{Type}__Sequence__init` allocating `size` elements, whose capacity is the same.
+    fn seq_init<T>(size: usize, elem: impl Fn() -> T) -> (*mut T, usize) {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        let elems: Box<[T]> = (0..size).map(|_| elem()).collect();
+        (Box::into_raw(elems) as *mut T, size)
+    }
+
+    /// Mock of `{package}__msg__{Type}__Sequence__fini`, which finalizes all the elements of the capacity.
+    unsafe fn seq_fini<T>(data: *mut T, capacity: usize) {
+        if !data.is_null() {
+            drop(Box::from_raw(core::ptr::slice_from_raw_parts_mut(
+                data, capacity,
+            )));
+        }
+    }
+
+    /// `seq.rs` of `--seq-macro` expanded for a message whose C functions are mocked.
+    #[allow(dead_code, non_camel_case_types, unused_imports)]
+    mod macro_seq {
+        include!("seq/impl_seq.rs");
+
+        type size_t = usize;
+
+        #[derive(Debug)]
+        pub struct Elem {
+            pub value: i32,
+        }
+
+        unsafe extern "C" fn init(msg: *mut Elem) -> bool {
+            (*msg).value = 7;
+            true
+        }
+
+        unsafe extern "C" fn fini(_msg: *mut Elem) {}
+
+        unsafe extern "C" fn seq_init(seq: *mut ElemSeqRaw, size: usize) -> bool {
+            let (data, capacity) = super::seq_init(size, || Elem { value: 7 });
+            (*seq).data = data;
+            (*seq).size = size;
+            (*seq).capacity = capacity;
+            true
+        }
+
+        unsafe extern "C" fn seq_fini(seq: *mut ElemSeqRaw) {
+            super::seq_fini((*seq).data, (*seq).capacity);
+        }
+
+        impl_seq!(Elem, ElemSeqRaw, ElemSeq, init, fini, seq_init, seq_fini);
+
+        pub fn capacity<const N: usize>(seq: &ElemSeq<N>) -> usize {
+            seq.capacity
+        }
+    }
+
+    /// `seq.rs` of `--typed-seq`.
+    #[allow(dead_code)]
+    mod typed_seq {
+        include!("seq/typed_seq.rs");
+    }
+
+    #[derive(Debug)]
+    struct TypedElem {
+        value: i32,
+    }
+
+    unsafe extern "C" fn typed_init(msg: *mut TypedElem) -> bool {
+        (*msg).value = 7;
+        true
+    }
+
+    unsafe extern "C" fn typed_seq_init(
+        seq: *mut typed_seq::SeqRaw<TypedElem>,
+        size: usize,
+    ) -> bool {
+        let (data, capacity) = seq_init(size, || TypedElem { value: 7 });
+        (*seq).data = data;
+        (*seq).size = size;
+        (*seq).capacity = capacity;
+        true
+    }
+
+    unsafe extern "C" fn typed_seq_fini(seq: *mut typed_seq::SeqRaw<TypedElem>) {
+        seq_fini((*seq).data, (*seq).capacity);
+    }
+
+    unsafe impl typed_seq::SeqElement for TypedElem {
+        const INIT: unsafe extern "C" fn(*mut Self) -> bool = typed_init;
+        const SEQ_INIT: unsafe extern "C" fn(*mut typed_seq::SeqRaw<Self>, usize) -> bool =
+            typed_seq_init;
+        const SEQ_FINI: unsafe extern "C" fn(*mut typed_seq::SeqRaw<Self>) = typed_seq_fini;
+    }
+
+    /// Check `resize()` and `push()` of `$seq`, whose elements are `$elem` initialized to 7.
+    macro_rules! check_resize {
+        ($seq:ident, $elem:ident, $capacity:expr) => {{
+            let values = |seq: &$seq<4>| seq.iter().map(|e| e.value).collect::<Vec<_>>();
+            let allocations = || ALLOCATIONS.with(|n| n.get());
+
+            let mut seq = $seq::<4>::new(2).unwrap();
+            seq.as_slice_mut()[0].value = 1;
+            seq.as_slice_mut()[1].value = 2;
+            let base = allocations();
+
+            // shrinking keeps the capacity
+            assert!(seq.resize(1));
+            assert_eq!(values(&seq), [1]);
+            assert_eq!($capacity(&seq), 2);
+
+            // growing within the capacity initializes the elements again
+            assert!(seq.resize(2));
+            assert_eq!(values(&seq), [1, 7]);
+            assert_eq!(allocations(), base);
+
+            // growing beyond the capacity reallocates twice as much, bounded by N
+            assert!(seq.push($elem { value: 3 }).is_ok());
+            assert_eq!(values(&seq), [1, 7, 3]);
+            assert_eq!($capacity(&seq), 4);
+            assert_eq!(allocations(), base + 1);
+            assert!(seq.push($elem { value: 4 }).is_ok());
+            assert_eq!(allocations(), base + 1);
+
+            // N bounds the size
+            assert!(!seq.resize(5));
+            let Err(elem) = seq.push($elem { value: 5 }) else {
+                panic!("pushed beyond N");
+            };
+            assert_eq!(elem.value, 5);
+            assert_eq!(values(&seq), [1, 7, 3, 4]);
+
+            // an unlimited sequence grows from null
+            let mut seq = $seq::<0>::null();
+            for (value, capacity) in [(1, 1), (2, 2), (3, 4), (4, 4), (5, 8)] {
+                assert!(seq.push($elem { value }).is_ok());
+                assert_eq!($capacity(&seq), capacity);
+            }
+            assert_eq!(
+                seq.iter().map(|e| e.value).collect::<Vec<_>>(),
+                [1, 2, 3, 4, 5]
+            );
+        }};
+    }
+
+    #[test]
+    fn test_macro_seq_resize() {
+        use macro_seq::{capacity, Elem, ElemSeq};
+        check_resize!(ElemSeq, Elem, capacity);
+    }
+
+    #[test]
+    fn test_typed_seq_resize() {
+        type Seq<const N: usize> = typed_seq::TypedSeq<TypedElem, N>;
+        check_resize!(Seq, TypedElem, |seq: &Seq<_>| seq.capacity);
+    }
+}
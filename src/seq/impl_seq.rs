@@ -0,0 +1,208 @@
+/// Implement `new()`, `Default` and `Drop` of `$ty`, and define `$raw` and `$seq`.
+/// `$defaults` sets fields of `$msg` to the default values of the definition.
+macro_rules! impl_seq {
+    ($ty:ident, $raw:ident, $seq:ident, $init:ident, $fini:ident, $seq_init:ident, $seq_fini:ident $(, |$msg:ident| $defaults:block)?) => {
+        impl $ty {
+            /// Create a message initialized by the C function.
+            /// Returns `None` if the initialization fails.
+            #[must_use]
+            pub fn new() -> Option<Self> {
+                let mut msg: Self = unsafe { core::mem::MaybeUninit::zeroed().assume_init() };
+                if unsafe { $init(&mut msg) } {
+                    $(
+                        let $msg = &mut msg;
+                        $defaults
+                    )?
+                    Some(msg)
+                } else {
+                    None
+                }
+            }
+        }
+
+        impl Default for $ty {
+            /// Same as `new()` but panics if the initialization fails.
+            fn default() -> Self {
+                Self::new().expect(concat!("failed to initialize ", stringify!($ty)))
+            }
+        }
+
+        impl Drop for $ty {
+            fn drop(&mut self) {
+                unsafe { $fini(self) };
+            }
+        }
+
+        #[repr(C)]
+        #[derive(Debug)]
+        struct $raw {
+            data: *mut $ty,
+            size: size_t,
+            capacity: size_t,
+        }
+
+        #[doc = concat!("Sequence of ", stringify!($ty), ".")]
+        /// `N` is the maximum number of elements.
+        /// If `N` is `0`, the size is unlimited.
+        #[repr(C)]
+        #[derive(Debug)]
+        pub struct $seq<const N: usize> {
+            data: *mut $ty,
+            size: size_t,
+            capacity: size_t,
+        }
+
+        impl<const N: usize> $seq<N> {
+            /// Create a sequence.
+            /// `N` represents the maximum number of elements.
+            /// If `N` is `0`, the sequence is unlimited.
+            #[must_use]
+            pub fn new(size: usize) -> Option<Self> {
+                if N != 0 && size > N {
+                    // the size exceeds in the maximum number
+                    return None;
+                }
+
+                let mut msg: $raw = unsafe { core::mem::MaybeUninit::zeroed().assume_init() };
+                if unsafe { $seq_init(&mut msg, size) } {
+                    Some(Self {data: msg.data, size: msg.size, capacity: msg.capacity })
+                } else {
+                    None
+                }
+            }
+
+            #[must_use]
+            pub fn null() -> Self {
+                let msg: $raw = unsafe { core::mem::MaybeUninit::zeroed().assume_init() };
+                Self {data: msg.data, size: msg.size, capacity: msg.capacity }
+            }
+
+            pub fn as_slice(&self) -> &[$ty] {
+                if self.data.is_null() {
+                    &[]
+                } else {
+                    let s = unsafe { core::slice::from_raw_parts(self.data, self.size as _) };
+                    s
+                }
+            }
+
+            pub fn as_slice_mut(&mut self) -> &mut [$ty] {
+                if self.data.is_null() {
+                    &mut []
+                } else {
+                    let s = unsafe { core::slice::from_raw_parts_mut(self.data, self.size as _) };
+                    s
+                }
+            }
+
+            pub fn iter(&self) -> core::slice::Iter<'_, $ty> {
+                self.as_slice().iter()
+            }
+
+            pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, $ty> {
+                self.as_slice_mut().iter_mut()
+            }
+
+            pub fn len(&self) -> usize {
+                self.as_slice().len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            /// Resize the sequence.
+            #[doc = concat!("New elements are initialized by `", stringify!($ty), "::new()`.")]
+            /// Return `false` if `size` exceeds `N` or the allocation fails.
+            #[must_use = "the sequence is not resized if `false` is returned"]
+            pub fn resize(&mut self, size: usize) -> bool {
+                if N != 0 && size > N {
+                    // the size exceeds in the maximum number
+                    return false;
+                }
+
+                let len = self.len();
+                if size <= self.capacity as usize {
+                    if size > len {
+                        // elements beyond the size are still initialized, so just reset them
+                        let s = unsafe { core::slice::from_raw_parts_mut(self.data, size) };
+                        for elem in s[len..].iter_mut() {
+                            let Some(msg) = $ty::new() else {
+                                return false;
+                            };
+                            *elem = msg;
+                        }
+                    }
+                    self.size = size as _;
+                    return true;
+                }
+
+                // reallocate with extra capacity to push elements in amortized constant time
+                let mut capacity = size.max(self.capacity as usize * 2);
+                if N != 0 {
+                    capacity = capacity.min(N);
+                }
+
+                let Some(mut seq) = Self::new(capacity) else {
+                    return false;
+                };
+                for (dst, src) in seq.as_slice_mut().iter_mut().zip(self.as_slice_mut()) {
+                    core::mem::swap(dst, src);
+                }
+                seq.size = size as _;
+
+                // the old sequence is finalized by drop
+                core::mem::swap(self, &mut seq);
+                true
+            }
+
+            /// Append an element to the end of the sequence.
+            /// Return the element back if the sequence is full or the allocation fails.
+            pub fn push(&mut self, value: $ty) -> Result<(), $ty> {
+                let len = self.len();
+                if !self.resize(len + 1) {
+                    return Err(value);
+                }
+                self.as_slice_mut()[len] = value;
+                Ok(())
+            }
+        }
+
+        impl<'a, const N: usize> IntoIterator for &'a $seq<N> {
+            type Item = &'a $ty;
+            type IntoIter = core::slice::Iter<'a, $ty>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.iter()
+            }
+        }
+
+        impl<'a, const N: usize> IntoIterator for &'a mut $seq<N> {
+            type Item = &'a mut $ty;
+            type IntoIter = core::slice::IterMut<'a, $ty>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.iter_mut()
+            }
+        }
+
+        impl<const N: usize> Default for $seq<N> {
+            /// Same as `null()`.
+            fn default() -> Self {
+                Self::null()
+            }
+        }
+
+        impl<const N: usize> Drop for $seq<N> {
+            fn drop(&mut self) {
+                let mut msg = $raw{data: self.data, size: self.size, capacity: self.capacity};
+                unsafe { $seq_fini(&mut msg) };
+            }
+        }
+
+        unsafe impl<const N: usize> Send for $seq<N> {}
+        unsafe impl<const N: usize> Sync for $seq<N> {}
+    };
+}
+
+pub(crate) use impl_seq;
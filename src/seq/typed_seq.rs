@@ -0,0 +1,184 @@
+/// The same layout as sequences of rosidl.
+#[repr(C)]
+#[derive(Debug)]
+pub struct SeqRaw<T> {
+    pub(super) data: *mut T,
+    pub(super) size: usize,
+    pub(super) capacity: usize,
+}
+
+/// Message whose sequences are allocated by the C functions of rosidl.
+///
+/// # Safety
+///
+/// The functions must be the ones generated by rosidl for the message.
+pub unsafe trait SeqElement: Sized {
+    /// `{package}__msg__{Type}__init`
+    const INIT: unsafe extern "C" fn(*mut Self) -> bool;
+
+    /// `{package}__msg__{Type}__Sequence__init`
+    const SEQ_INIT: unsafe extern "C" fn(*mut SeqRaw<Self>, usize) -> bool;
+
+    /// `{package}__msg__{Type}__Sequence__fini`
+    const SEQ_FINI: unsafe extern "C" fn(*mut SeqRaw<Self>);
+}
+
+/// Sequence of `T`.
+/// `N` is the maximum number of elements.
+/// If `N` is `0`, the size is unlimited.
+#[repr(C)]
+#[derive(Debug)]
+pub struct TypedSeq<T: SeqElement, const N: usize> {
+    pub(super) data: *mut T,
+    pub(super) size: usize,
+    pub(super) capacity: usize,
+}
+
+impl<T: SeqElement, const N: usize> TypedSeq<T, N> {
+    /// Create a sequence.
+    /// `N` represents the maximum number of elements.
+    /// If `N` is `0`, the sequence is unlimited.
+    #[must_use]
+    pub fn new(size: usize) -> Option<Self> {
+        if N != 0 && size > N {
+            // the size exceeds in the maximum number
+            return None;
+        }
+
+        let mut msg: SeqRaw<T> = unsafe { core::mem::MaybeUninit::zeroed().assume_init() };
+        if unsafe { (T::SEQ_INIT)(&mut msg, size) } {
+            Some(Self { data: msg.data, size: msg.size, capacity: msg.capacity })
+        } else {
+            None
+        }
+    }
+
+    #[must_use]
+    pub fn null() -> Self {
+        Self { data: core::ptr::null_mut(), size: 0, capacity: 0 }
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        if self.data.is_null() {
+            &[]
+        } else {
+            unsafe { core::slice::from_raw_parts(self.data, self.size) }
+        }
+    }
+
+    pub fn as_slice_mut(&mut self) -> &mut [T] {
+        if self.data.is_null() {
+            &mut []
+        } else {
+            unsafe { core::slice::from_raw_parts_mut(self.data, self.size) }
+        }
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    pub fn iter_mut(&mut self) -> core::slice::IterMut<'_, T> {
+        self.as_slice_mut().iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Resize the sequence.
+    /// New elements are initialized by `T::INIT`.
+    /// Return `false` if `size` exceeds `N` or the allocation fails.
+    #[must_use = "the sequence is not resized if `false` is returned"]
+    pub fn resize(&mut self, size: usize) -> bool {
+        if N != 0 && size > N {
+            // the size exceeds in the maximum number
+            return false;
+        }
+
+        let len = self.len();
+        if size <= self.capacity {
+            if size > len {
+                // elements beyond the size are still initialized, so just reset them
+                let s = unsafe { core::slice::from_raw_parts_mut(self.data, size) };
+                for elem in s[len..].iter_mut() {
+                    let mut msg: T = unsafe { core::mem::MaybeUninit::zeroed().assume_init() };
+                    if !unsafe { (T::INIT)(&mut msg) } {
+                        return false;
+                    }
+                    *elem = msg;
+                }
+            }
+            self.size = size;
+            return true;
+        }
+
+        // reallocate with extra capacity to push elements in amortized constant time
+        let mut capacity = size.max(self.capacity * 2);
+        if N != 0 {
+            capacity = capacity.min(N);
+        }
+
+        let Some(mut seq) = Self::new(capacity) else {
+            return false;
+        };
+        for (dst, src) in seq.as_slice_mut().iter_mut().zip(self.as_slice_mut()) {
+            core::mem::swap(dst, src);
+        }
+        seq.size = size;
+
+        // the old sequence is finalized by drop
+        core::mem::swap(self, &mut seq);
+        true
+    }
+
+    /// Append an element to the end of the sequence.
+    /// Return the element back if the sequence is full or the allocation fails.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        let len = self.len();
+        if !self.resize(len + 1) {
+            return Err(value);
+        }
+        self.as_slice_mut()[len] = value;
+        Ok(())
+    }
+}
+
+impl<'a, T: SeqElement, const N: usize> IntoIterator for &'a TypedSeq<T, N> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: SeqElement, const N: usize> IntoIterator for &'a mut TypedSeq<T, N> {
+    type Item = &'a mut T;
+    type IntoIter = core::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T: SeqElement, const N: usize> Default for TypedSeq<T, N> {
+    /// Same as `null()`.
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T: SeqElement, const N: usize> Drop for TypedSeq<T, N> {
+    fn drop(&mut self) {
+        let mut msg = SeqRaw { data: self.data, size: self.size, capacity: self.capacity };
+        unsafe { (T::SEQ_FINI)(&mut msg) };
+    }
+}
+
+unsafe impl<T: SeqElement, const N: usize> Send for TypedSeq<T, N> {}
+unsafe impl<T: SeqElement, const N: usize> Sync for TypedSeq<T, N> {}
@@ -0,0 +1,105 @@
+//! Serialization by serde.
+//!
+//! Message structs derive `serde::Serialize` and `serde::Deserialize`,
+//! which require the `serde` feature of safe_drive for `RosString` and sequences of primitive types.
+//! Sequences of generated types are serialized as sequences of their elements.
+
+use crate::generator::FallibleApi;
+use convert_case::{Case, Casing};
+use std::{borrow::Cow, collections::VecDeque};
+
+/// Derive attribute of generated structs.
+pub fn gen_derive(serde: bool) -> &'static str {
+    if serde {
+        "#[derive(Debug, serde::Serialize, serde::Deserialize)]"
+    } else {
+        "#[derive(Debug)]"
+    }
+}
+
+/// Generate `impl Serialize` and `impl Deserialize` for `{type_name}Seq<N>`.
+pub fn gen_seq_serde(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    type_name: &str,
+    fallible_api: FallibleApi,
+) {
+    let to_option = fallible_api.to_option();
+    let impl_str = format!(
+        "
+impl<const N: usize> serde::Serialize for {type_name}Seq<N> {{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {{
+        serializer.collect_seq(self.iter())
+    }}
+}}
+
+impl<'de, const N: usize> serde::Deserialize<'de> for {type_name}Seq<N> {{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {{
+        let elems = Vec::<{type_name}>::deserialize(deserializer)?;
+        let mut seq = Self::new(elems.len()){to_option}.ok_or_else(|| {{
+            <D::Error as serde::de::Error>::custom(format!(
+                \"failed to allocate {type_name}Seq<{{N}}> of {{}} elements\",
+                elems.len()
+            ))
+        }})?;
+        for (dst, src) in seq.iter_mut().zip(elems) {{
+            *dst = src;
+        }}
+        Ok(seq)
+    }}
+}}
+"
+    );
+
+    lines.push_back(impl_str.into());
+}
+
+/// Generate tests which serialize the default values of `type_names` to JSON and deserialize them back.
+/// Messages are compared by `PartialEq`, and the others are compared by serializing them again.
+pub fn gen_round_trip_tests(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    msg_types: &[String],
+    other_types: &[String],
+) {
+    let mut tests = String::new();
+
+    for type_name in msg_types.iter() {
+        let snake = type_name.to_case(Case::Snake);
+        tests.push_str(&format!(
+            "
+    #[test]
+    fn round_trip_{snake}() {{
+        let msg = {type_name}::new().unwrap();
+        let json = serde_json::to_string(&msg).unwrap();
+        let back: {type_name} = serde_json::from_str(&json).unwrap();
+        assert!(msg == back, \"{{json}}\");
+    }}
+"
+        ));
+    }
+
+    for type_name in other_types.iter() {
+        let snake = type_name.to_case(Case::Snake);
+        tests.push_str(&format!(
+            "
+    #[test]
+    fn round_trip_{snake}() {{
+        let msg = {type_name}::new().unwrap();
+        let json = serde_json::to_string(&msg).unwrap();
+        let back: {type_name} = serde_json::from_str(&json).unwrap();
+        assert_eq!(json, serde_json::to_string(&back).unwrap());
+    }}
+"
+        ));
+    }
+
+    lines.push_back(
+        format!(
+            "
+#[cfg(test)]
+mod serde_tests {{
+    use super::*;
+{tests}}}"
+        )
+        .into(),
+    );
+}
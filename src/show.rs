@@ -0,0 +1,105 @@
+//! Fully expanded definitions like `ros2 interface show`.
+//!
+//! Each field of a message type is followed by the definition of the type indented by a tab,
+//! so that the output shows what the generator actually saw after resolving `pkg/Type` across input directories.
+
+use crate::{
+    cst::{parse_cst, LineKind},
+    generator::gen_primitives,
+    parser::{Expr, TypeName},
+};
+use std::error::Error;
+
+/// Expand the definition of `contents`, which is in `pkg`.
+/// `find(pkg, type_name)` returns the contents of the .msg file of `pkg/msg/type_name`.
+pub fn expand<'a>(
+    pkg: &str,
+    contents: &str,
+    find: &impl Fn(&str, &str) -> Option<&'a str>,
+) -> Result<String, Box<dyn Error>> {
+    let mut result = String::new();
+    let mut stack = Vec::new();
+    expand_rec(&mut result, pkg, contents, find, &mut stack, 0)?;
+    Ok(result)
+}
+
+fn expand_rec<'a>(
+    result: &mut String,
+    pkg: &str,
+    contents: &str,
+    find: &impl Fn(&str, &str) -> Option<&'a str>,
+    stack: &mut Vec<String>,
+    depth: usize,
+) -> Result<(), Box<dyn Error>> {
+    let indent = "\t".repeat(depth);
+    let cst = parse_cst(contents)?;
+
+    for line in cst.lines.iter() {
+        let text = line.to_string();
+        let text = text.trim();
+
+        // empty lines are kept only at the top level
+        if text.is_empty() {
+            if depth == 0 {
+                result.push('\n');
+            }
+            continue;
+        }
+        result.push_str(&format!("{indent}{text}\n"));
+
+        let LineKind::Definition(definition) = &line.kind else {
+            continue;
+        };
+
+        let Expr::Variable { type_name, .. } = &definition.expr else {
+            continue;
+        };
+
+        let (scope, type_name) = match type_name {
+            TypeName::Type { type_name, .. } if gen_primitives(type_name).is_none() => {
+                (pkg, type_name.as_str())
+            }
+            TypeName::ScopedType {
+                scope, type_name, ..
+            } => (scope.as_str(), type_name.as_str()),
+            _ => continue,
+        };
+
+        let key = format!("{scope}/{type_name}");
+        if stack.contains(&key) {
+            return Err(format!("{key} is defined recursively").into());
+        }
+
+        let Some(nested) = find(scope, type_name) else {
+            result.push_str(&format!("{indent}\t# {key} is not found\n"));
+            continue;
+        };
+
+        stack.push(key);
+        expand_rec(result, scope, nested, find, stack, depth + 1)?;
+        stack.pop();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+
+    #[test]
+    fn test_expand() {
+        let find = |pkg: &str, type_name: &str| match (pkg, type_name) {
+            ("geometry_msgs", "Point") => Some("# position\nfloat64 x\n\nfloat64 y\n"),
+            ("my_pkg", "Inner") => Some("geometry_msgs/Point[] points\nother_msgs/Missing m\n"),
+            ("my_pkg", "Loop") => Some("Loop l\n"),
+            _ => None,
+        };
+
+        let expanded = expand("my_pkg", "# top\nint32 a\n\nInner inner # c\n", &find).unwrap();
+        let expected = "# top\nint32 a\n\nInner inner # c\n\tgeometry_msgs/Point[] points\n\t\t# position\n\t\tfloat64 x\n\t\tfloat64 y\n\tother_msgs/Missing m\n\t\t# other_msgs/Missing is not found\n";
+        assert_eq!(expanded, expected);
+
+        assert!(expand("my_pkg", "Loop l\n", &find).is_err());
+    }
+}
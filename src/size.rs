@@ -0,0 +1,336 @@
+//! Sizes of messages for bandwidth budgeting and shared-memory eligibility.
+//!
+//! - The C struct size is the size of the struct of rosidl on 64-bit targets,
+//!   where strings and sequences are `{data, size, capacity}` of 24 bytes.
+//! - The maximum CDR size is the size of XCDR1 including the encapsulation header,
+//!   computed in the same way as `max_serialized_size` of rosidl_typesupport_fastrtps.
+//!   It is not defined if any field is unbounded, and such fields are reported instead.
+
+use crate::{
+    generator::gen_primitives,
+    parser::{parse_msg, ArrayInfo, Expr, TypeName, ValueType},
+};
+use nom::Finish;
+use std::error::Error;
+
+/// Size of strings and sequences of rosidl, `{data, size, capacity}`.
+const SEQ_SIZE: usize = 24;
+
+/// Size of the encapsulation header of CDR.
+const ENCAPSULATION_SIZE: usize = 4;
+
+/// Sizes of a message.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Sizes {
+    /// Size of the C struct.
+    pub c_size: usize,
+
+    /// Alignment of the C struct.
+    pub c_align: usize,
+
+    /// Maximum size of the serialized message, or `None` if the message is unbounded.
+    pub max_cdr_size: Option<usize>,
+
+    /// Paths of unbounded fields, such as `points` and `header.frame_id`.
+    pub unbounded: Vec<String>,
+}
+
+/// Compute sizes of the message of `exprs`, which is in `pkg`.
+/// `find(pkg, type_name)` returns the contents of the .msg file of `pkg/msg/type_name`.
+pub fn sizes<'a>(
+    pkg: &str,
+    exprs: &[Expr],
+    find: &impl Fn(&str, &str) -> Option<&'a str>,
+) -> Result<Sizes, Box<dyn Error>> {
+    let mut sizes = Sizes {
+        c_size: 0,
+        c_align: 1,
+        max_cdr_size: None,
+        unbounded: Vec::new(),
+    };
+
+    let mut cdr_offset = Some(0);
+    let mut stack = Vec::new();
+    visit(
+        pkg,
+        exprs,
+        find,
+        "",
+        &mut sizes,
+        &mut cdr_offset,
+        &mut stack,
+    )?;
+
+    sizes.max_cdr_size = cdr_offset.map(|offset| offset + ENCAPSULATION_SIZE);
+    Ok(sizes)
+}
+
+/// Compute the C layout of `exprs` into `sizes`, and add the CDR size to `cdr_offset`.
+fn visit<'a>(
+    pkg: &str,
+    exprs: &[Expr],
+    find: &impl Fn(&str, &str) -> Option<&'a str>,
+    prefix: &str,
+    sizes: &mut Sizes,
+    cdr_offset: &mut Option<usize>,
+    stack: &mut Vec<String>,
+) -> Result<(), Box<dyn Error>> {
+    let mut c_size = 0;
+    let mut c_align = 1;
+
+    for expr in exprs.iter() {
+        let Expr::Variable {
+            type_name,
+            var_name,
+            value: None | Some(ValueType::Default(_)),
+            ..
+        } = expr
+        else {
+            continue;
+        };
+
+        let path = format!("{prefix}{var_name}");
+
+        // C layout and CDR size of an element
+        let (elem_size, elem_align, array_info) = match type_name {
+            TypeName::Type {
+                type_name,
+                array_info,
+            } if gen_primitives(type_name).is_some() => {
+                let size = primitive_size(type_name);
+                (size, size, array_info)
+            }
+            TypeName::String(array_info) => {
+                push_unbounded(sizes, &path);
+                (SEQ_SIZE, 8, array_info)
+            }
+            TypeName::LimitedString { array_info, .. } => (SEQ_SIZE, 8, array_info),
+            TypeName::Type {
+                type_name,
+                array_info,
+            } => {
+                let nested = nested_sizes(pkg, type_name, find, &path, sizes, stack)?;
+                (nested.c_size, nested.c_align, array_info)
+            }
+            TypeName::ScopedType {
+                scope,
+                type_name,
+                array_info,
+            } => {
+                let nested = nested_sizes(scope, type_name, find, &path, sizes, stack)?;
+                (nested.c_size, nested.c_align, array_info)
+            }
+        };
+
+        // C layout
+        let (size, align) = match array_info {
+            ArrayInfo::NotArray => (elem_size, elem_align),
+            ArrayInfo::Static(n) => (elem_size * n, elem_align),
+            ArrayInfo::Dynamic | ArrayInfo::Limited(_) => (SEQ_SIZE, 8),
+        };
+        c_size = align_to(c_size, align) + size;
+        c_align = c_align.max(align);
+
+        // CDR
+        let count = match array_info {
+            ArrayInfo::NotArray => 1,
+            ArrayInfo::Static(n) => *n,
+            ArrayInfo::Limited(n) => {
+                add_cdr(cdr_offset, 4, 4);
+                *n
+            }
+            ArrayInfo::Dynamic => {
+                push_unbounded(sizes, &path);
+                *cdr_offset = None;
+                0
+            }
+        };
+
+        for _ in 0..count {
+            match type_name {
+                TypeName::Type { type_name, .. } if gen_primitives(type_name).is_some() => {
+                    let size = primitive_size(type_name);
+                    add_cdr(cdr_offset, size, size);
+                }
+                TypeName::String(_) => {
+                    *cdr_offset = None;
+                }
+                TypeName::LimitedString { size, .. } => {
+                    add_cdr(cdr_offset, 4, 4);
+                    add_cdr(cdr_offset, size + 1, 1);
+                }
+                TypeName::Type { type_name, .. } => {
+                    cdr_nested(pkg, type_name, find, cdr_offset)?;
+                }
+                TypeName::ScopedType {
+                    scope, type_name, ..
+                } => {
+                    cdr_nested(scope, type_name, find, cdr_offset)?;
+                }
+            }
+
+            if cdr_offset.is_none() {
+                break;
+            }
+        }
+    }
+
+    // empty structs have `structure_needs_at_least_one_member`, which is also serialized
+    if c_size == 0 {
+        add_cdr(cdr_offset, 1, 1);
+    }
+    sizes.c_size = align_to(c_size.max(1), c_align);
+    sizes.c_align = c_align;
+    Ok(())
+}
+
+/// C layout of `scope/type_name`. Unbounded fields of the type are added to `sizes` with `path`.
+fn nested_sizes<'a>(
+    scope: &str,
+    type_name: &str,
+    find: &impl Fn(&str, &str) -> Option<&'a str>,
+    path: &str,
+    sizes: &mut Sizes,
+    stack: &mut Vec<String>,
+) -> Result<Sizes, Box<dyn Error>> {
+    let key = format!("{scope}/{type_name}");
+    if stack.contains(&key) {
+        return Err(format!("{key} is defined recursively").into());
+    }
+
+    let exprs = parse_nested(scope, type_name, find)?;
+
+    let mut nested = Sizes {
+        c_size: 0,
+        c_align: 1,
+        max_cdr_size: None,
+        unbounded: Vec::new(),
+    };
+
+    // CDR is computed by `cdr_nested()` because the padding depends on the offset
+    stack.push(key);
+    visit(
+        scope,
+        &exprs,
+        find,
+        &format!("{path}."),
+        &mut nested,
+        &mut None,
+        stack,
+    )?;
+    stack.pop();
+
+    for field in nested.unbounded.iter() {
+        push_unbounded(sizes, field);
+    }
+
+    Ok(nested)
+}
+
+/// Add the CDR size of `scope/type_name` to `cdr_offset`.
+fn cdr_nested<'a>(
+    scope: &str,
+    type_name: &str,
+    find: &impl Fn(&str, &str) -> Option<&'a str>,
+    cdr_offset: &mut Option<usize>,
+) -> Result<(), Box<dyn Error>> {
+    let exprs = parse_nested(scope, type_name, find)?;
+    let mut sizes = Sizes {
+        c_size: 0,
+        c_align: 1,
+        max_cdr_size: None,
+        unbounded: Vec::new(),
+    };
+
+    // recursion is detected by `nested_sizes()` before this is called
+    visit(
+        scope,
+        &exprs,
+        find,
+        "",
+        &mut sizes,
+        cdr_offset,
+        &mut Vec::new(),
+    )
+}
+
+/// Parse `scope/type_name`.
+/// `builtin_interfaces/Time` and `Duration` are built in if they are not found.
+fn parse_nested<'a>(
+    scope: &str,
+    type_name: &str,
+    find: &impl Fn(&str, &str) -> Option<&'a str>,
+) -> Result<Vec<Expr>, Box<dyn Error>> {
+    let contents = match (find(scope, type_name), scope, type_name) {
+        (Some(contents), _, _) => contents,
+        (None, "builtin_interfaces", "Time" | "Duration") => "int32 sec\nuint32 nanosec\n",
+        (None, _, _) => return Err(format!("{scope}/{type_name} is not found").into()),
+    };
+
+    let (_, exprs) = parse_msg(contents)
+        .finish()
+        .map_err(|_| format!("{scope}/{type_name}: failed to parse"))?;
+    Ok(exprs)
+}
+
+fn push_unbounded(sizes: &mut Sizes, path: &str) {
+    if !sizes.unbounded.iter().any(|p| p == path) {
+        sizes.unbounded.push(path.to_string());
+    }
+}
+
+fn add_cdr(cdr_offset: &mut Option<usize>, size: usize, align: usize) {
+    if let Some(offset) = cdr_offset {
+        *offset = align_to(*offset, align) + size;
+    }
+}
+
+fn align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+fn primitive_size(type_name: &str) -> usize {
+    match type_name {
+        "int16" | "uint16" => 2,
+        "int32" | "uint32" | "float32" => 4,
+        "int64" | "uint64" | "float64" => 8,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sizes;
+    use crate::parser::parse_msg;
+    use nom::Finish;
+
+    #[test]
+    fn test_sizes() {
+        let find = |pkg: &str, type_name: &str| match (pkg, type_name) {
+            ("geometry_msgs", "Point") => Some("float64 x\nfloat64 y\nfloat64 z\n"),
+            ("my_pkg", "Named") => Some("string name\n"),
+            _ => None,
+        };
+
+        // bool + padding + Point[2] + uint8[<=3] + string<=4
+        let (_, exprs) = parse_msg("bool a\ngeometry_msgs/Point[2] p\nuint8[<=3] b\nstring<=4 s\n")
+            .finish()
+            .unwrap();
+        let s = sizes("my_pkg", &exprs, &find).unwrap();
+        assert_eq!((s.c_size, s.c_align), (8 + 48 + 24 + 24, 8));
+        assert_eq!(s.max_cdr_size, Some(4 + 8 + 48 + 4 + 3 + 1 + 4 + 5));
+        assert!(s.unbounded.is_empty());
+
+        let (_, exprs) = parse_msg("int32[] a\nNamed n\nbuiltin_interfaces/Time t\n")
+            .finish()
+            .unwrap();
+        let s = sizes("my_pkg", &exprs, &find).unwrap();
+        assert_eq!(s.c_size, 24 + 24 + 8);
+        assert_eq!(s.max_cdr_size, None);
+        assert_eq!(s.unbounded, ["a", "n.name"]);
+
+        let (_, exprs) = parse_msg("").finish().unwrap();
+        let s = sizes("my_pkg", &exprs, &find).unwrap();
+        assert_eq!((s.c_size, s.max_cdr_size), (1, Some(4 + 1)));
+    }
+}
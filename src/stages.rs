@@ -0,0 +1,73 @@
+//! Build order of generated crates.
+//!
+//! Packages are divided into stages, where packages of each stage depend only on packages of previous stages,
+//! so that huge interface sets can be generated and built stage by stage.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+};
+
+/// Divide packages into stages by `libs`, which are packages referred from each package.
+/// Packages which are not generated are ignored, and circular dependencies are errors.
+pub fn stages(
+    libs: &BTreeMap<String, BTreeSet<String>>,
+) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+    let mut stages: Vec<Vec<String>> = Vec::new();
+    let mut done: BTreeSet<String> = BTreeSet::new();
+
+    while done.len() < libs.len() {
+        let stage: Vec<String> = libs
+            .iter()
+            .filter(|(pkg, _)| !done.contains(*pkg))
+            .filter(|(pkg, deps)| {
+                deps.iter()
+                    .all(|dep| dep == *pkg || !libs.contains_key(dep) || done.contains(dep))
+            })
+            .map(|(pkg, _)| pkg.clone())
+            .collect();
+
+        if stage.is_empty() {
+            let rest: Vec<&str> = libs
+                .keys()
+                .filter(|pkg| !done.contains(*pkg))
+                .map(|pkg| pkg.as_str())
+                .collect();
+            return Err(format!("circular dependencies among {}", rest.join(", ")).into());
+        }
+
+        done.extend(stage.iter().cloned());
+        stages.push(stage);
+    }
+
+    Ok(stages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stages;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    fn libs(deps: &[(&str, &[&str])]) -> BTreeMap<String, BTreeSet<String>> {
+        deps.iter()
+            .map(|(pkg, deps)| {
+                let deps = deps.iter().map(|dep| dep.to_string()).collect();
+                (pkg.to_string(), deps)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stages() {
+        let result = stages(&libs(&[
+            ("a", &["a", "std_msgs"]),
+            ("b", &["a"]),
+            ("c", &["a", "b"]),
+            ("d", &[]),
+        ]))
+        .unwrap();
+        assert_eq!(result, [vec!["a", "d"], vec!["b"], vec!["c"]]);
+
+        assert!(stages(&libs(&[("a", &["b"]), ("b", &["a"]), ("c", &[])])).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! Complexity of interface packages for interface review.
+
+use crate::{
+    generator::gen_primitives,
+    parser::{parse_msg, parse_srv, ArrayInfo, Expr, TypeName, ValueType},
+};
+use nom::Finish;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+};
+
+/// Statistics of a package.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub messages: usize,
+    pub services: usize,
+
+    /// Maximum depth of nested message types.
+    /// Messages having only primitive and string fields have depth 0.
+    pub max_depth: usize,
+
+    /// Number of unbounded strings and sequences in messages and services of the package.
+    pub unbounded: usize,
+
+    /// Packages referred by the package.
+    pub external: BTreeSet<String>,
+}
+
+/// Add the definition of `pkg/(msg|srv)/type_name` to `stats`.
+/// `find(pkg, type_name)` returns the contents of the .msg file of `pkg/msg/type_name`.
+pub fn add<'a>(
+    stats: &mut Stats,
+    pkg: &str,
+    ext: &str,
+    contents: &str,
+    find: &impl Fn(&str, &str) -> Option<&'a str>,
+) -> Result<(), Box<dyn Error>> {
+    let exprs = if ext == "msg" {
+        stats.messages += 1;
+        parse_msg(contents)
+            .finish()
+            .map_err(|_| "failed to parse")?
+            .1
+    } else {
+        stats.services += 1;
+        let (_, (mut req, resp)) = parse_srv(contents)
+            .finish()
+            .map_err(|_| "failed to parse")?;
+        req.extend(resp);
+        req
+    };
+
+    for (scope, _, array_info) in fields(pkg, &exprs) {
+        if scope != pkg {
+            stats.external.insert(scope.to_string());
+        }
+        if matches!(array_info, ArrayInfo::Dynamic) {
+            stats.unbounded += 1;
+        }
+    }
+
+    // unbounded strings, which are not counted twice if they are in unbounded sequences
+    stats.unbounded += exprs
+        .iter()
+        .filter(|expr| {
+            matches!(
+                expr,
+                Expr::Variable {
+                    type_name: TypeName::String(
+                        ArrayInfo::NotArray | ArrayInfo::Static(_) | ArrayInfo::Limited(_)
+                    ),
+                    value: None | Some(ValueType::Default(_)),
+                    ..
+                }
+            )
+        })
+        .count();
+
+    let depth = depth(pkg, &exprs, find, &mut Vec::new(), &mut BTreeMap::new())?;
+    stats.max_depth = stats.max_depth.max(depth);
+
+    Ok(())
+}
+
+/// Depth of nested message types of `exprs`.
+/// Types which are not found are regarded as messages having depth 0.
+fn depth<'a>(
+    pkg: &str,
+    exprs: &[Expr],
+    find: &impl Fn(&str, &str) -> Option<&'a str>,
+    stack: &mut Vec<String>,
+    memo: &mut BTreeMap<String, usize>,
+) -> Result<usize, Box<dyn Error>> {
+    let mut max = 0;
+    for (scope, type_name, _) in fields(pkg, exprs) {
+        let Some(type_name) = type_name else {
+            continue;
+        };
+
+        let key = format!("{scope}/{type_name}");
+        let nested = if let Some(depth) = memo.get(&key) {
+            *depth
+        } else if stack.contains(&key) {
+            return Err(format!("{key} is defined recursively").into());
+        } else {
+            let depth = match find(scope, type_name) {
+                Some(contents) => {
+                    let (_, exprs) = parse_msg(contents)
+                        .finish()
+                        .map_err(|_| format!("{key}: failed to parse"))?;
+                    stack.push(key.clone());
+                    let depth = depth(scope, &exprs, find, stack, memo)?;
+                    stack.pop();
+                    depth
+                }
+                None => 0,
+            };
+            memo.insert(key, depth);
+            depth
+        };
+
+        max = max.max(nested + 1);
+    }
+
+    Ok(max)
+}
+
+/// Package, message type if not primitive, and array information of fields.
+fn fields<'a>(
+    pkg: &'a str,
+    exprs: &'a [Expr],
+) -> impl Iterator<Item = (&'a str, Option<&'a str>, &'a ArrayInfo)> {
+    exprs.iter().filter_map(move |expr| {
+        let Expr::Variable {
+            type_name,
+            value: None | Some(ValueType::Default(_)),
+            ..
+        } = expr
+        else {
+            return None;
+        };
+
+        match type_name {
+            TypeName::Type {
+                type_name,
+                array_info,
+            } => {
+                let type_name = Some(type_name.as_str()).filter(|t| gen_primitives(t).is_none());
+                Some((pkg, type_name, array_info))
+            }
+            TypeName::ScopedType {
+                scope,
+                type_name,
+                array_info,
+            } => Some((scope.as_str(), Some(type_name.as_str()), array_info)),
+            TypeName::String(array_info) | TypeName::LimitedString { array_info, .. } => {
+                Some((pkg, None, array_info))
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add, Stats};
+
+    #[test]
+    fn test_stats() {
+        let find = |pkg: &str, type_name: &str| match (pkg, type_name) {
+            ("geometry_msgs", "Pose") => Some("geometry_msgs/Point position\n"),
+            ("geometry_msgs", "Point") => Some("float64 x\n"),
+            ("my_pkg", "Inner") => Some("geometry_msgs/Pose[] poses\n"),
+            _ => None,
+        };
+
+        let mut stats = Stats::default();
+        add(
+            &mut stats,
+            "my_pkg",
+            "msg",
+            "Inner inner\nstring s\nint32[] a\nint32 C = 1\n",
+            &find,
+        )
+        .unwrap();
+        add(
+            &mut stats,
+            "my_pkg",
+            "srv",
+            "string<=3 a\n---\nstd_msgs/Header h\n",
+            &find,
+        )
+        .unwrap();
+
+        assert_eq!((stats.messages, stats.services), (1, 1));
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(stats.unbounded, 2);
+        assert_eq!(stats.external.into_iter().collect::<Vec<_>>(), ["std_msgs"]);
+    }
+}
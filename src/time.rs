@@ -0,0 +1,297 @@
+//! Conversions of `builtin_interfaces/Time` and `builtin_interfaces/Duration` fields.
+//!
+//! `builtin_interfaces` represents seconds by `int32`, so conversions from `std` or `chrono`
+//! can overflow (the year-2038 problem).
+//! Generated setters return `false` instead of silently wrapping around.
+
+use crate::parser::{ArrayInfo, Expr, TypeName, ValueType};
+use std::{borrow::Cow, collections::VecDeque};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TimeType {
+    Time,
+    Duration,
+}
+
+/// Generate conversion helpers for `builtin_interfaces/Time` and `builtin_interfaces/Duration` fields.
+/// Helpers of `chrono` are generated if `chrono` is true.
+/// Helpers of `std::time::SystemTime` are not generated if `no_std` is true.
+pub fn gen_time_conversions(
+    lines: &mut VecDeque<Cow<'_, str>>,
+    struct_name: &str,
+    exprs: &[Expr],
+    chrono: bool,
+    no_std: bool,
+) {
+    let mut fields = Vec::new();
+    for expr in exprs.iter() {
+        if let Expr::Variable {
+            type_name:
+                TypeName::ScopedType {
+                    scope,
+                    type_name,
+                    array_info: ArrayInfo::NotArray,
+                },
+            var_name,
+            value: None | Some(ValueType::Default(_)),
+            ..
+        } = expr
+        {
+            if scope != "builtin_interfaces" {
+                continue;
+            }
+
+            match type_name.as_str() {
+                "Time" => fields.push((var_name.as_str(), TimeType::Time)),
+                "Duration" => fields.push((var_name.as_str(), TimeType::Duration)),
+                _ => (),
+            }
+        }
+    }
+
+    if fields.is_empty() {
+        return;
+    }
+
+    let mut methods = Vec::new();
+    for (var_name, ty) in fields.iter() {
+        let field = crate::mangle(var_name);
+        match ty {
+            TimeType::Time => {
+                if !no_std {
+                    methods.push(gen_std_time(var_name, &field));
+                }
+                if chrono {
+                    methods.push(gen_chrono_time(var_name, &field));
+                }
+            }
+            TimeType::Duration => {
+                methods.push(gen_std_duration(var_name, &field));
+                if chrono {
+                    methods.push(gen_chrono_duration(var_name, &field));
+                }
+            }
+        }
+    }
+
+    if methods.is_empty() {
+        return;
+    }
+
+    let methods = methods.concat();
+    lines.push_back(format!("\nimpl {struct_name} {{{methods}\n}}").into());
+}
+
+fn gen_std_time(var_name: &str, field: &str) -> String {
+    format!(
+        "
+    /// `{var_name}` as `std::time::SystemTime`.
+    /// Returns `None` if `{var_name}.nanosec` is out of range.
+    pub fn {var_name}_as_system_time(&self) -> Option<std::time::SystemTime> {{
+        if self.{field}.nanosec >= 1_000_000_000 {{
+            return None;
+        }}
+
+        let nanosec = std::time::Duration::from_nanos(self.{field}.nanosec as u64);
+        let sec = std::time::Duration::from_secs(self.{field}.sec.unsigned_abs() as u64);
+        if self.{field}.sec >= 0 {{
+            std::time::UNIX_EPOCH.checked_add(sec)?.checked_add(nanosec)
+        }} else {{
+            std::time::UNIX_EPOCH.checked_sub(sec)?.checked_add(nanosec)
+        }}
+    }}
+
+    /// Set `{var_name}` by `std::time::SystemTime`.
+    /// Returns `false` if `time` cannot be represented by `builtin_interfaces/Time`,
+    /// which happens after the year 2038.
+    #[must_use = \"the field is not set if `false` is returned\"]
+    pub fn set_{var_name}_from_system_time(&mut self, time: std::time::SystemTime) -> bool {{
+        let (sec, nanosec) = match time.duration_since(std::time::UNIX_EPOCH) {{
+            Ok(d) => (d.as_secs() as i128, d.subsec_nanos()),
+            Err(e) => {{
+                // before the epoch
+                let d = e.duration();
+                if d.subsec_nanos() == 0 {{
+                    (-(d.as_secs() as i128), 0)
+                }} else {{
+                    (-(d.as_secs() as i128) - 1, 1_000_000_000 - d.subsec_nanos())
+                }}
+            }}
+        }};
+
+        if let Ok(sec) = i32::try_from(sec) {{
+            self.{field}.sec = sec;
+            self.{field}.nanosec = nanosec;
+            true
+        }} else {{
+            false
+        }}
+    }}
+"
+    )
+}
+
+fn gen_std_duration(var_name: &str, field: &str) -> String {
+    format!(
+        "
+    /// `{var_name}` as `std::time::Duration`.
+    /// Returns `None` if `{var_name}` is negative or `{var_name}.nanosec` is out of range.
+    pub fn {var_name}_as_duration(&self) -> Option<core::time::Duration> {{
+        if self.{field}.sec < 0 || self.{field}.nanosec >= 1_000_000_000 {{
+            return None;
+        }}
+
+        Some(core::time::Duration::new(self.{field}.sec as u64, self.{field}.nanosec))
+    }}
+
+    /// Set `{var_name}` by `std::time::Duration`.
+    /// Returns `false` if the seconds of `duration` exceed `i32::MAX`.
+    #[must_use = \"the field is not set if `false` is returned\"]
+    pub fn set_{var_name}_from_duration(&mut self, duration: core::time::Duration) -> bool {{
+        if let Ok(sec) = i32::try_from(duration.as_secs()) {{
+            self.{field}.sec = sec;
+            self.{field}.nanosec = duration.subsec_nanos();
+            true
+        }} else {{
+            false
+        }}
+    }}
+"
+    )
+}
+
+fn gen_chrono_time(var_name: &str, field: &str) -> String {
+    format!(
+        "
+    /// `{var_name}` as `chrono::DateTime<chrono::Utc>`.
+    /// Returns `None` if `{var_name}.nanosec` is out of range.
+    pub fn {var_name}_as_chrono(&self) -> Option<chrono::DateTime<chrono::Utc>> {{
+        if self.{field}.nanosec >= 1_000_000_000 {{
+            return None;
+        }}
+
+        chrono::DateTime::from_timestamp(self.{field}.sec as i64, self.{field}.nanosec)
+    }}
+
+    /// Set `{var_name}` by `chrono::DateTime<chrono::Utc>`.
+    /// Returns `false` if `time` cannot be represented by `builtin_interfaces/Time`,
+    /// which happens after the year 2038.
+    #[must_use = \"the field is not set if `false` is returned\"]
+    pub fn set_{var_name}_from_chrono(&mut self, time: chrono::DateTime<chrono::Utc>) -> bool {{
+        if let Ok(sec) = i32::try_from(time.timestamp()) {{
+            self.{field}.sec = sec;
+            self.{field}.nanosec = time.timestamp_subsec_nanos();
+            true
+        }} else {{
+            false
+        }}
+    }}
+"
+    )
+}
+
+fn gen_chrono_duration(var_name: &str, field: &str) -> String {
+    format!(
+        "
+    /// `{var_name}` as `chrono::TimeDelta`.
+    /// Returns `None` if `{var_name}.nanosec` is out of range.
+    pub fn {var_name}_as_chrono(&self) -> Option<chrono::TimeDelta> {{
+        chrono::TimeDelta::new(self.{field}.sec as i64, self.{field}.nanosec)
+    }}
+
+    /// Set `{var_name}` by `chrono::TimeDelta`.
+    /// Returns `false` if the seconds of `duration` are out of the range of `i32`.
+    #[must_use = \"the field is not set if `false` is returned\"]
+    pub fn set_{var_name}_from_chrono(&mut self, duration: chrono::TimeDelta) -> bool {{
+        // nanoseconds must be positive
+        let (sec, nanosec) = if duration.subsec_nanos() < 0 {{
+            (duration.num_seconds() - 1, duration.subsec_nanos() + 1_000_000_000)
+        }} else {{
+            (duration.num_seconds(), duration.subsec_nanos())
+        }};
+
+        if let Ok(sec) = i32::try_from(sec) {{
+            self.{field}.sec = sec;
+            self.{field}.nanosec = nanosec as u32;
+            true
+        }} else {{
+            false
+        }}
+    }}
+"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::gen_time_conversions;
+    use crate::parser;
+    use nom::Finish;
+    use std::collections::VecDeque;
+
+    const MOCKS: &str = "
+use std::time::{Duration, UNIX_EPOCH};
+
+#[derive(Default)]
+pub struct Time {
+    pub sec: i32,
+    pub nanosec: u32,
+}
+
+#[derive(Default)]
+pub struct Msg {
+    pub stamp: Time,
+    pub timeout: Time,
+}
+";
+
+    const TESTS: &str = "
+#[test]
+fn test_duration() {
+    let mut msg = Msg::default();
+    let max = Duration::new(i32::MAX as u64, 5);
+    assert!(msg.set_timeout_from_duration(max));
+    assert_eq!(msg.timeout_as_duration(), Some(max));
+
+    // the field is kept if the seconds overflow
+    assert!(!msg.set_timeout_from_duration(Duration::from_secs(i32::MAX as u64 + 1)));
+    assert!(!msg.set_timeout_from_duration(Duration::MAX));
+    assert_eq!((msg.timeout.sec, msg.timeout.nanosec), (i32::MAX, 5));
+
+    msg.timeout.sec = -1;
+    assert_eq!(msg.timeout_as_duration(), None);
+}
+
+#[test]
+fn test_system_time() {
+    let mut msg = Msg::default();
+    let max = UNIX_EPOCH + Duration::from_secs(i32::MAX as u64);
+    assert!(msg.set_stamp_from_system_time(max));
+    assert_eq!(msg.stamp_as_system_time(), Some(max));
+    assert!(!msg.set_stamp_from_system_time(max + Duration::from_secs(1)));
+    assert_eq!(msg.stamp.sec, i32::MAX);
+
+    // nanoseconds are positive before the epoch
+    let before = UNIX_EPOCH - Duration::new(1, 1);
+    assert!(msg.set_stamp_from_system_time(before));
+    assert_eq!((msg.stamp.sec, msg.stamp.nanosec), (-2, 999_999_999));
+    assert_eq!(msg.stamp_as_system_time(), Some(before));
+
+    let min = UNIX_EPOCH - Duration::from_secs(i32::MIN.unsigned_abs() as u64);
+    assert!(msg.set_stamp_from_system_time(min));
+    assert!(!msg.set_stamp_from_system_time(min - Duration::from_secs(1)));
+    assert_eq!(msg.stamp.sec, i32::MIN);
+}
+";
+
+    #[test]
+    fn test_overflow() {
+        let input = "builtin_interfaces/Time stamp\nbuiltin_interfaces/Duration timeout\n";
+        let (_, exprs) = parser::parse_msg(input).finish().unwrap();
+        let mut lines = VecDeque::new();
+        gen_time_conversions(&mut lines, "Msg", &exprs, false, false);
+
+        let code: String = lines.iter().map(|l| format!("{l}\n")).collect();
+        crate::tests::run_generated_tests("time", &format!("{MOCKS}{code}{TESTS}"));
+    }
+}
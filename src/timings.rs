@@ -0,0 +1,157 @@
+//! Durations of phases of generation reported by `--timings`.
+//!
+//! Phases are `walk` collecting definitions, `parse`, `generate` and `write`,
+//! and `parse`, `generate` and `write` are also recorded for each package.
+//! The report is JSON, or folded stacks like `ros2msg_to_rs;parse;my_module 1234` in microseconds,
+//! which flamegraph tools such as inferno read.
+
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// Format of the report of `--timings`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TimingsFormat {
+    #[default]
+    Json,
+
+    /// Folded stacks for flamegraphs.
+    Folded,
+}
+
+#[derive(Debug, Default)]
+pub struct Timings {
+    /// Total duration of each phase in the order of recording.
+    phases: Vec<(&'static str, Duration)>,
+
+    /// Duration of each phase of each package.
+    packages: BTreeMap<String, BTreeMap<&'static str, Duration>>,
+}
+
+impl Timings {
+    /// Add `duration` to `phase`, and to `phase` of `package` if it is `Some`.
+    pub fn add(&mut self, package: Option<&str>, phase: &'static str, duration: Duration) {
+        match self.phases.iter_mut().find(|(name, _)| *name == phase) {
+            Some((_, total)) => *total += duration,
+            None => self.phases.push((phase, duration)),
+        }
+
+        if let Some(package) = package {
+            *self
+                .packages
+                .entry(package.to_string())
+                .or_default()
+                .entry(phase)
+                .or_default() += duration;
+        }
+    }
+
+    /// Run `f` and add its duration.
+    pub fn time<T>(
+        &mut self,
+        package: Option<&str>,
+        phase: &'static str,
+        f: impl FnOnce() -> T,
+    ) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.add(package, phase, start.elapsed());
+        result
+    }
+
+    pub fn report(&self, format: TimingsFormat) -> String {
+        match format {
+            TimingsFormat::Json => self.to_json(),
+            TimingsFormat::Folded => self.to_folded(),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct Report<'a> {
+            total_us: u64,
+            phases: Vec<Phase<'a>>,
+            packages: BTreeMap<&'a str, BTreeMap<&'a str, u64>>,
+        }
+
+        #[derive(Serialize)]
+        struct Phase<'a> {
+            name: &'a str,
+            us: u64,
+        }
+
+        let report = Report {
+            total_us: micros(self.phases.iter().map(|(_, d)| *d).sum()),
+            phases: self
+                .phases
+                .iter()
+                .map(|(name, d)| Phase {
+                    name,
+                    us: micros(*d),
+                })
+                .collect(),
+            packages: self
+                .packages
+                .iter()
+                .map(|(pkg, phases)| {
+                    let phases = phases.iter().map(|(name, d)| (*name, micros(*d))).collect();
+                    (pkg.as_str(), phases)
+                })
+                .collect(),
+        };
+
+        let mut json = serde_json::to_string_pretty(&report).unwrap();
+        json.push('\n');
+        json
+    }
+
+    /// Durations of packages are under their phases,
+    /// and the rest of each phase, such as writing `mod.rs`, is the phase itself.
+    fn to_folded(&self) -> String {
+        let mut folded = String::new();
+        for (phase, total) in self.phases.iter() {
+            let mut rest = *total;
+            for (pkg, phases) in self.packages.iter() {
+                if let Some(d) = phases.get(phase) {
+                    folded.push_str(&format!("ros2msg_to_rs;{phase};{pkg} {}\n", micros(*d)));
+                    rest = rest.saturating_sub(*d);
+                }
+            }
+            if !rest.is_zero() {
+                folded.push_str(&format!("ros2msg_to_rs;{phase} {}\n", micros(rest)));
+            }
+        }
+        folded
+    }
+}
+
+fn micros(d: Duration) -> u64 {
+    d.as_micros() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Timings, TimingsFormat};
+    use std::time::Duration;
+
+    #[test]
+    fn test_report() {
+        let mut timings = Timings::default();
+        timings.add(None, "walk", Duration::from_micros(5));
+        timings.add(Some("a"), "parse", Duration::from_micros(10));
+        timings.add(Some("b"), "parse", Duration::from_micros(20));
+        timings.add(Some("a"), "parse", Duration::from_micros(1));
+        timings.add(None, "write", Duration::from_micros(3));
+
+        assert_eq!(
+            timings.report(TimingsFormat::Folded),
+            "ros2msg_to_rs;walk 5\nros2msg_to_rs;parse;a 11\nros2msg_to_rs;parse;b 20\nros2msg_to_rs;write 3\n"
+        );
+
+        let json = timings.report(TimingsFormat::Json);
+        assert!(json.contains("\"total_us\": 39"));
+        assert!(json.contains("\"a\": {\n      \"parse\": 11\n    }"));
+    }
+}
@@ -0,0 +1,74 @@
+//! Versions of packages generated side by side.
+//!
+//! `--versioned-input v1=old/src` generates packages in `old/src` as modules `{package}_v1`,
+//! whose references to packages of the same version are resolved to the modules of the version.
+//! Identical messages of consecutive versions have the same layouts, so they are moved by `From` in both directions
+//! without copying, which are written to `{package}_{version}/conversions.rs`, so that interfaces can be migrated gradually.
+//! Changed messages are not generated in versions, because they would call the C functions of `-i`,
+//! so messages are never converted field by field.
+
+use crate::{
+    generator::Renames,
+    parser::{Expr, TypeName},
+};
+
+/// Replace packages of scoped types in `exprs` by their modules.
+pub fn rename_scopes(exprs: &mut [Expr], renames: &Renames) {
+    for expr in exprs.iter_mut() {
+        if let Expr::Variable {
+            type_name: TypeName::ScopedType { scope, .. },
+            ..
+        } = expr
+        {
+            *scope = renames.package(scope).to_string();
+        }
+    }
+}
+
+/// Generate `conversions.rs` of `cur`, which converts identical messages `type_names` between `prev` and `cur`.
+pub fn gen_conversions(prev: &str, cur: &str, type_names: &[String]) -> String {
+    let prev = crate::mangle_module(prev);
+    let mut code = format!(
+        "// This file was automatically generated by ros2msg_to_rs (https://github.com/tier4/ros2msg_to_rs).
+//! Conversions between messages of `{prev}` and `{cur}`.
+//!
+//! Identical messages have the same layouts, so they are converted without copying.
+"
+    );
+
+    for type_name in type_names.iter() {
+        let prev_type = format!("super::super::{prev}::msg::{type_name}");
+        let cur_type = format!("super::msg::{type_name}");
+        for (from, to) in [(&prev_type, &cur_type), (&cur_type, &prev_type)] {
+            code.push_str(&format!(
+                "
+impl From<{from}> for {to} {{
+    fn from(msg: {from}) -> Self {{
+        // the memory is released by the same C functions of the identical layout
+        let msg = core::mem::ManuallyDrop::new(msg);
+        unsafe {{ core::ptr::read(&*msg as *const {from} as *const Self) }}
+    }}
+}}
+"
+            ));
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::gen_conversions;
+
+    #[test]
+    fn test_conversions() {
+        let code = gen_conversions("my_pkg_v1", "my_pkg_v2", &["Point".to_string()]);
+        assert!(code
+            .contains("impl From<super::super::my_pkg_v1::msg::Point> for super::msg::Point {\n"));
+        assert!(code
+            .contains("impl From<super::msg::Point> for super::super::my_pkg_v1::msg::Point {\n"));
+        assert!(code.contains(
+            "        unsafe { core::ptr::read(&*msg as *const super::msg::Point as *const Self) }\n"
+        ));
+    }
+}
@@ -0,0 +1,151 @@
+//! Named warnings controlled by `--allow`, `--warn` (`-W`) and `--deny`.
+//!
+//! Each warning is printed with its name, e.g. `Warning: ... [builtin-time-2038]`.
+//! Denied warnings are printed as errors, and generation fails after all files are processed.
+
+use crate::{
+    context,
+    diagnostics::{self, Message, Severity},
+};
+use std::{collections::BTreeMap, error::Error};
+
+/// Kinds of warnings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Warning {
+    /// builtin_interfaces/Time and Duration have `int32 sec`, which overflows in 2038.
+    #[value(name = "builtin-time-2038")]
+    BuiltinTime2038,
+
+    /// Type and field names which do not follow the naming convention of ROS 2.
+    NamingConvention,
+
+    /// The same `pkg/Type` is defined in several input directories.
+    DuplicateDefinitions,
+
+    /// Generated fields, constants or methods collide.
+    NameCollisions,
+
+    /// A generated crate refers to a package which is not generated.
+    MissingDependencies,
+
+    /// A generated type refers to a type skipped by the configuration file.
+    SkippedTypes,
+
+    /// Layouts of messages are overridden by the configuration file, so they differ from the C structs.
+    LayoutOverrides,
+
+    /// Messages of different packages are structurally identical, so one can be an alias of the other.
+    /// This is allowed by default.
+    IdenticalDefinitions,
+
+    /// Default values of fields do not match their types, so the fields are left to the C initializer.
+    InvalidDefaults,
+
+    /// Messages have no owned mirrors of `--owned` because they have fields of types not generated by ros2msg_to_rs.
+    UnmirroredMessages,
+
+    /// Definitions of `--versioned-input` differ from the ones of `-i`, whose C functions they would call,
+    /// so they are not generated.
+    VersionedChanges,
+}
+
+/// How a warning is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// Return the levels of warnings to be set to the context.
+/// Levels are applied in the order of `allow`, `warn` and `deny`, so `deny` takes precedence.
+pub fn levels(allow: &[Warning], warn: &[Warning], deny: &[Warning]) -> BTreeMap<Warning, Level> {
+    let mut levels = BTreeMap::new();
+
+    for (warnings, level) in [
+        (allow, Level::Allow),
+        (warn, Level::Warn),
+        (deny, Level::Deny),
+    ] {
+        for warning in warnings.iter() {
+            levels.insert(*warning, level);
+        }
+    }
+
+    levels
+}
+
+fn level(warning: Warning) -> Level {
+    let default = match warning {
+        Warning::IdenticalDefinitions => Level::Allow,
+        _ => Level::Warn,
+    };
+    context::with(|c| c.levels.get(&warning).copied().unwrap_or(default))
+}
+
+/// Return true if the warning is reported, to skip checks of allowed warnings.
+pub fn enabled(warning: Warning) -> bool {
+    level(warning) != Level::Allow
+}
+
+/// Report a warning according to its level.
+pub fn warn(warning: Warning, msg: &str) {
+    let level = match level(warning) {
+        Level::Allow => return,
+        Level::Warn => Severity::Warning,
+        Level::Deny => {
+            context::with(|c| c.denied += 1);
+            Severity::Error
+        }
+    };
+
+    diagnostics::report(Message::Diagnostic {
+        level,
+        code: Some(&name(warning)),
+        message: msg,
+        file: None,
+    });
+}
+
+/// Return an error if any denied warning has been reported.
+pub fn check_denied() -> Result<(), Box<dyn Error>> {
+    match context::with(|c| c.denied) {
+        0 => Ok(()),
+        n => Err(format!("{n} denied warning(s) reported").into()),
+    }
+}
+
+/// Return the warning if `name` does not follow the naming convention of ROS 2.
+/// Types are `CamelCase`, fields are `snake_case` and constants are `UPPER_CASE`.
+pub fn check_naming(kind: &str, name: &str) -> Option<String> {
+    let mut chars = name.chars();
+    let first = chars.next()?;
+
+    let ok = match kind {
+        "type" => first.is_ascii_uppercase() && chars.all(|c| c.is_ascii_alphanumeric()),
+        "field" => {
+            first.is_ascii_lowercase()
+                && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+                && !name.contains("__")
+                && !name.ends_with('_')
+        }
+        _ => {
+            first.is_ascii_uppercase()
+                && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        }
+    };
+
+    if ok {
+        None
+    } else {
+        Some(format!(
+            "{kind} name `{name}` does not follow the naming convention"
+        ))
+    }
+}
+
+fn name(warning: Warning) -> String {
+    clap::ValueEnum::to_possible_value(&warning)
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}